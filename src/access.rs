@@ -0,0 +1,475 @@
+use std::fmt;
+use std::ops::{BitAnd, BitOr, BitOrAssign, Not, Sub};
+use std::vec;
+
+use ::model::*;
+use ::module::{ModuleDependency, ModuleDescriptor, PackageExport};
+use ::result::{ParseError, ParseResult};
+
+// Declares a set of access flags. Flags are listed in the order their Java
+// keywords are written; flags without a keyword have an empty one.
+macro_rules! access_flags {
+    ($(#[$meta:meta])* pub struct $name:ident { $($flag:ident = $mask:expr, $keyword:expr;)* }) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+        pub struct $name {
+            bits: u16
+        }
+
+        impl $name {
+            $(pub const $flag: $name = $name { bits: $mask };)*
+
+            const FLAGS: &'static [($name, &'static str, &'static str)] = &[$(($name::$flag, stringify!($flag), $keyword)),*];
+
+            /// Keeps every bit, including those that have no meaning here.
+            pub fn from_bits(bits: u16) -> $name {
+                $name { bits: bits }
+            }
+
+            pub fn bits(&self) -> u16 {
+                self.bits
+            }
+
+            pub fn empty() -> $name {
+                $name { bits: 0 }
+            }
+
+            pub fn all() -> $name {
+                $name { bits: 0 $(| $mask)* }
+            }
+
+            /// The set bits that are not defined for this kind of flags.
+            pub fn unknown_bits(&self) -> u16 {
+                self.bits & !$name::all().bits
+            }
+
+            pub fn is_empty(&self) -> bool {
+                self.bits == 0
+            }
+
+            pub fn contains(&self, other: $name) -> bool {
+                self.bits & other.bits == other.bits
+            }
+
+            pub fn intersects(&self, other: $name) -> bool {
+                self.bits & other.bits != 0
+            }
+
+            pub fn insert(&mut self, other: $name) {
+                self.bits |= other.bits;
+            }
+
+            pub fn remove(&mut self, other: $name) {
+                self.bits &= !other.bits;
+            }
+
+            /// The defined flags that are set, one at a time.
+            pub fn iter(&self) -> vec::IntoIter<$name> {
+                $name::FLAGS.iter()
+                    .filter(|&&(flag, _, _)| self.contains(flag))
+                    .map(|&(flag, _, _)| flag)
+                    .collect::<Vec<$name>>()
+                    .into_iter()
+            }
+
+            /// The JVMS names of the set flags without the `ACC_` prefix, by
+            /// increasing bit as in the tables of the JVMS.
+            pub fn names(&self) -> Vec<&'static str> {
+                let mut flags: Vec<&($name, &'static str, &'static str)> = $name::FLAGS.iter()
+                    .filter(|&&(flag, _, _)| self.contains(flag))
+                    .collect();
+                flags.sort_by_key(|&&(flag, _, _)| flag.bits);
+                flags.iter().map(|&&(_, name, _)| name).collect()
+            }
+
+            /// The Java keywords of the set flags, e.g. `public static final`.
+            pub fn keywords(&self) -> Vec<&'static str> {
+                $name::FLAGS.iter()
+                    .filter(|&&(flag, _, keyword)| !keyword.is_empty() && self.contains(flag))
+                    .map(|&(_, _, keyword)| keyword)
+                    .collect()
+            }
+        }
+
+        impl BitOr for $name {
+            type Output = $name;
+            fn bitor(self, other: $name) -> $name {
+                $name { bits: self.bits | other.bits }
+            }
+        }
+
+        impl BitOrAssign for $name {
+            fn bitor_assign(&mut self, other: $name) {
+                self.bits |= other.bits;
+            }
+        }
+
+        impl BitAnd for $name {
+            type Output = $name;
+            fn bitand(self, other: $name) -> $name {
+                $name { bits: self.bits & other.bits }
+            }
+        }
+
+        impl Sub for $name {
+            type Output = $name;
+            fn sub(self, other: $name) -> $name {
+                $name { bits: self.bits & !other.bits }
+            }
+        }
+
+        impl Not for $name {
+            type Output = $name;
+            fn not(self) -> $name {
+                $name { bits: !self.bits & $name::all().bits }
+            }
+        }
+
+        impl IntoIterator for $name {
+            type Item = $name;
+            type IntoIter = vec::IntoIter<$name>;
+            fn into_iter(self) -> vec::IntoIter<$name> {
+                self.iter()
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+                fmt.write_str(&self.keywords().join(" "))
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+                let mut names: Vec<String> = $name::FLAGS.iter()
+                    .filter(|&&(flag, _, _)| self.contains(flag))
+                    .map(|&(_, name, _)| name.to_string())
+                    .collect();
+                if self.unknown_bits() != 0 {
+                    names.push(format!("{:#06x}", self.unknown_bits()));
+                }
+                write!(fmt, "{}({})", stringify!($name), names.join(" | "))
+            }
+        }
+    }
+}
+
+access_flags! {
+    /// Flags of a class, JVMS 4.1.
+    pub struct ClassAccess {
+        PUBLIC = 0x0001, "public";
+        ABSTRACT = 0x0400, "abstract";
+        FINAL = 0x0010, "final";
+        SUPER = 0x0020, "";
+        INTERFACE = 0x0200, "";
+        SYNTHETIC = 0x1000, "";
+        ANNOTATION = 0x2000, "";
+        ENUM = 0x4000, "";
+        MODULE = 0x8000, "";
+    }
+}
+
+access_flags! {
+    /// Flags of a field, JVMS 4.5.
+    pub struct FieldAccess {
+        PUBLIC = 0x0001, "public";
+        PROTECTED = 0x0004, "protected";
+        PRIVATE = 0x0002, "private";
+        STATIC = 0x0008, "static";
+        FINAL = 0x0010, "final";
+        TRANSIENT = 0x0080, "transient";
+        VOLATILE = 0x0040, "volatile";
+        SYNTHETIC = 0x1000, "";
+        ENUM = 0x4000, "";
+    }
+}
+
+access_flags! {
+    /// Flags of a method, JVMS 4.6.
+    pub struct MethodAccess {
+        PUBLIC = 0x0001, "public";
+        PROTECTED = 0x0004, "protected";
+        PRIVATE = 0x0002, "private";
+        ABSTRACT = 0x0400, "abstract";
+        STATIC = 0x0008, "static";
+        FINAL = 0x0010, "final";
+        SYNCHRONIZED = 0x0020, "synchronized";
+        NATIVE = 0x0100, "native";
+        STRICT = 0x0800, "strictfp";
+        BRIDGE = 0x0040, "";
+        VARARGS = 0x0080, "";
+        SYNTHETIC = 0x1000, "";
+    }
+}
+
+access_flags! {
+    /// Flags of an entry in the `InnerClasses` attribute, JVMS 4.7.6.
+    pub struct InnerClassAccess {
+        PUBLIC = 0x0001, "public";
+        PROTECTED = 0x0004, "protected";
+        PRIVATE = 0x0002, "private";
+        ABSTRACT = 0x0400, "abstract";
+        STATIC = 0x0008, "static";
+        FINAL = 0x0010, "final";
+        INTERFACE = 0x0200, "";
+        SYNTHETIC = 0x1000, "";
+        ANNOTATION = 0x2000, "";
+        ENUM = 0x4000, "";
+    }
+}
+
+access_flags! {
+    /// Flags of an entry in the `MethodParameters` attribute, JVMS 4.7.24.
+    pub struct ParameterAccess {
+        FINAL = 0x0010, "final";
+        SYNTHETIC = 0x1000, "";
+        MANDATED = 0x8000, "";
+    }
+}
+
+access_flags! {
+    /// Flags of a module declaration, JVMS 4.7.25.
+    pub struct ModuleAccess {
+        OPEN = 0x0020, "open";
+        SYNTHETIC = 0x1000, "";
+        MANDATED = 0x8000, "";
+    }
+}
+
+access_flags! {
+    /// Flags of a `requires` directive.
+    pub struct RequiresAccess {
+        TRANSITIVE = 0x0020, "transitive";
+        STATIC_PHASE = 0x0040, "static";
+        SYNTHETIC = 0x1000, "";
+        MANDATED = 0x8000, "";
+    }
+}
+
+access_flags! {
+    /// Flags of an `exports` or `opens` directive.
+    pub struct ExportsAccess {
+        SYNTHETIC = 0x1000, "";
+        MANDATED = 0x8000, "";
+    }
+}
+
+fn illegal<T: fmt::Debug>(flags: T, reason: &str) -> ParseResult<()> {
+    Result::Err(ParseError::Format(format!("illegal access flags {:?}: {}", flags, reason)))
+}
+
+fn visibility_count(public: bool, protected: bool, private: bool) -> usize {
+    [public, protected, private].iter().filter(|&&set| set).count()
+}
+
+impl ClassAccess {
+    /// Checks the combination rules of JVMS 4.1.
+    pub fn check(&self) -> ParseResult<()> {
+        if self.contains(ClassAccess::MODULE) {
+            if *self != ClassAccess::MODULE {
+                return illegal(*self, "a module may have no other flags");
+            }
+        } else if self.contains(ClassAccess::INTERFACE) {
+            if !self.contains(ClassAccess::ABSTRACT) {
+                return illegal(*self, "an interface must be abstract");
+            }
+            if self.intersects(ClassAccess::FINAL | ClassAccess::SUPER | ClassAccess::ENUM) {
+                return illegal(*self, "an interface may not be final, super or enum");
+            }
+        } else {
+            if self.contains(ClassAccess::ANNOTATION) {
+                return illegal(*self, "an annotation must be an interface");
+            }
+            if self.contains(ClassAccess::FINAL | ClassAccess::ABSTRACT) {
+                return illegal(*self, "a class may not be both final and abstract");
+            }
+        }
+        Result::Ok(())
+    }
+}
+
+impl FieldAccess {
+    /// Checks the combination rules of JVMS 4.5 for a field of a class or,
+    /// if `in_interface`, of an interface.
+    pub fn check(&self, in_interface: bool) -> ParseResult<()> {
+        if visibility_count(self.contains(FieldAccess::PUBLIC), self.contains(FieldAccess::PROTECTED),
+                self.contains(FieldAccess::PRIVATE)) > 1 {
+            return illegal(*self, "at most one of public, protected and private may be set");
+        }
+        if self.contains(FieldAccess::FINAL | FieldAccess::VOLATILE) {
+            return illegal(*self, "a field may not be both final and volatile");
+        }
+        if in_interface {
+            if !self.contains(FieldAccess::PUBLIC | FieldAccess::STATIC | FieldAccess::FINAL) {
+                return illegal(*self, "an interface field must be public, static and final");
+            }
+            if !(*self - FieldAccess::PUBLIC - FieldAccess::STATIC - FieldAccess::FINAL - FieldAccess::SYNTHETIC).is_empty() {
+                return illegal(*self, "an interface field may only be public, static, final and synthetic");
+            }
+        }
+        Result::Ok(())
+    }
+}
+
+impl MethodAccess {
+    /// Checks the combination rules of JVMS 4.6 for a method of a class or,
+    /// if `in_interface`, of an interface, in a class file of `major_version`.
+    /// Class initializers are exempt and instance initializers have further
+    /// rules, both of which `Class::check_access` takes into account.
+    pub fn check(&self, in_interface: bool, major_version: u16) -> ParseResult<()> {
+        let public = self.contains(MethodAccess::PUBLIC);
+        let private = self.contains(MethodAccess::PRIVATE);
+        if visibility_count(public, self.contains(MethodAccess::PROTECTED), private) > 1 {
+            return illegal(*self, "at most one of public, protected and private may be set");
+        }
+        if in_interface {
+            if major_version < 52 {
+                if !self.contains(MethodAccess::PUBLIC | MethodAccess::ABSTRACT) {
+                    return illegal(*self, "an interface method must be public and abstract before version 52");
+                }
+            } else if public == private {
+                return illegal(*self, "an interface method must be either public or private");
+            }
+            if self.intersects(MethodAccess::PROTECTED | MethodAccess::FINAL | MethodAccess::SYNCHRONIZED | MethodAccess::NATIVE) {
+                return illegal(*self, "an interface method may not be protected, final, synchronized or native");
+            }
+        }
+        if self.contains(MethodAccess::ABSTRACT) {
+            let mut forbidden = MethodAccess::PRIVATE | MethodAccess::STATIC | MethodAccess::FINAL
+                    | MethodAccess::SYNCHRONIZED | MethodAccess::NATIVE;
+            if major_version >= 46 && major_version <= 60 {
+                forbidden |= MethodAccess::STRICT;
+            }
+            if self.intersects(forbidden) {
+                return illegal(*self, "an abstract method may not be private, static, final, synchronized, native or strictfp");
+            }
+        }
+        Result::Ok(())
+    }
+}
+
+impl InnerClassAccess {
+    /// Checks the combination rules of JVMS 4.1 as they apply to a nested class.
+    pub fn check(&self) -> ParseResult<()> {
+        if visibility_count(self.contains(InnerClassAccess::PUBLIC), self.contains(InnerClassAccess::PROTECTED),
+                self.contains(InnerClassAccess::PRIVATE)) > 1 {
+            return illegal(*self, "at most one of public, protected and private may be set");
+        }
+        if self.contains(InnerClassAccess::INTERFACE) {
+            if !self.contains(InnerClassAccess::ABSTRACT) {
+                return illegal(*self, "an interface must be abstract");
+            }
+            if self.intersects(InnerClassAccess::FINAL | InnerClassAccess::ENUM) {
+                return illegal(*self, "an interface may not be final or enum");
+            }
+        } else {
+            if self.contains(InnerClassAccess::ANNOTATION) {
+                return illegal(*self, "an annotation must be an interface");
+            }
+            if self.contains(InnerClassAccess::FINAL | InnerClassAccess::ABSTRACT) {
+                return illegal(*self, "a class may not be both final and abstract");
+            }
+        }
+        Result::Ok(())
+    }
+}
+
+impl Class {
+    pub fn access(&self) -> ClassAccess {
+        ClassAccess::from_bits(self.access_flags)
+    }
+
+    /// Checks the access flags of the class, its fields, methods and nested
+    /// classes for illegal combinations.
+    pub fn check_access(&self) -> ParseResult<()> {
+        let access = self.access();
+        try!(access.check());
+        let in_interface = access.contains(ClassAccess::INTERFACE);
+        for field in &self.fields {
+            try!(field.access().check(in_interface));
+        }
+        for method in &self.methods {
+            let name = try!(method.name(&self.constant_pool));
+            let access = method.access();
+            if name == "<clinit>" {
+                continue;
+            }
+            if name == "<init>" {
+                let allowed = MethodAccess::PUBLIC | MethodAccess::PROTECTED | MethodAccess::PRIVATE
+                        | MethodAccess::VARARGS | MethodAccess::STRICT | MethodAccess::SYNTHETIC;
+                if !(access - allowed).is_empty() {
+                    return illegal(access, "an instance initializer may only be public, protected, private, varargs, strictfp and synthetic");
+                }
+            }
+            try!(access.check(in_interface, self.major_version));
+        }
+        for attribute in &self.attributes {
+            if let Attribute::InnerClasses { ref classes } = *attribute {
+                for inner_class in classes {
+                    try!(inner_class.access().check());
+                }
+            }
+        }
+        Result::Ok(())
+    }
+}
+
+impl Field {
+    pub fn access(&self) -> FieldAccess {
+        FieldAccess::from_bits(self.access_flags)
+    }
+}
+
+impl Method {
+    pub fn access(&self) -> MethodAccess {
+        MethodAccess::from_bits(self.access_flags)
+    }
+}
+
+impl InnerClass {
+    pub fn access(&self) -> InnerClassAccess {
+        InnerClassAccess::from_bits(self.inner_class_access_flags)
+    }
+}
+
+impl MethodParameter {
+    pub fn access(&self) -> ParameterAccess {
+        ParameterAccess::from_bits(self.access_flags)
+    }
+}
+
+impl ModuleRequires {
+    pub fn access(&self) -> RequiresAccess {
+        RequiresAccess::from_bits(self.requires_flags)
+    }
+}
+
+impl ModuleExports {
+    pub fn access(&self) -> ExportsAccess {
+        ExportsAccess::from_bits(self.exports_flags)
+    }
+}
+
+impl ModuleOpens {
+    pub fn access(&self) -> ExportsAccess {
+        ExportsAccess::from_bits(self.opens_flags)
+    }
+}
+
+impl ModuleDescriptor {
+    pub fn access(&self) -> ModuleAccess {
+        ModuleAccess::from_bits(self.flags)
+    }
+}
+
+impl ModuleDependency {
+    pub fn access(&self) -> RequiresAccess {
+        RequiresAccess::from_bits(self.flags)
+    }
+}
+
+impl PackageExport {
+    pub fn access(&self) -> ExportsAccess {
+        ExportsAccess::from_bits(self.flags)
+    }
+}
@@ -0,0 +1,2049 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use ::descriptor::MethodDescriptor;
+use ::encode::{encode_code, relocate_code};
+use ::java_string::JavaString;
+use ::model::*;
+use ::pool::ConstantPool;
+use ::result::{ParseError, ParseResult};
+use ::signature::{ClassSignature, ClassTypeSignature};
+
+/// Assembles a class from text in the format printed by `disassemble`, so that
+/// a disassembled class reads back as the class it came from. As in javap's
+/// output, the structure is given by indentation.
+///
+/// Classes written by hand may take some liberties with the format:
+///
+/// * The constant pool may be left out or be incomplete. Wherever javap prints
+///   an index such as `#12` followed by a comment, the text of the comment may
+///   be written instead, e.g. `invokevirtual Method java/io/PrintStream.println:(I)V`.
+///   The constant is added to the pool unless an equal one exists.
+/// * Instructions may be labelled with names instead of offsets, and a label
+///   may stand on a line of its own. Branches, exception tables and debug
+///   tables refer to labels, and the code is laid out anew.
+/// * Lines that only repeat what other lines say, such as the counts of
+///   interfaces, fields and methods and `args_size`, may be left out. So may
+///   `flags`, which then follow from the modifiers, and the versions, which
+///   default to Java 8.
+///
+/// Stack map frames are taken as written. Some details do not survive
+/// disassembly: inner class flags that have no keyword, trailing spaces in
+/// strings and unpaired surrogates.
+pub fn assemble(source: &str) -> ParseResult<Class> {
+    let nodes = tree(source);
+    let mut assembler = Assembler { pool: ConstantPool::default(), this_class: 0, line: 0 };
+    for node in nodes.iter().filter(|node| node.text == "Constant pool:") {
+        try!(assembler.constant_pool(node));
+    }
+    assembler.class(&nodes)
+}
+
+static MODIFIERS: [(&str, u16); 11] = [
+    ("public", 0x0001), ("private", 0x0002), ("protected", 0x0004), ("static", 0x0008), ("final", 0x0010),
+    ("synchronized", 0x0020), ("volatile", 0x0040), ("transient", 0x0080), ("native", 0x0100),
+    ("abstract", 0x0400), ("strictfp", 0x0800)
+];
+
+// Each name stands for the same bit wherever it is used.
+static FLAG_NAMES: [(&str, u16); 21] = [
+    ("ACC_PUBLIC", 0x0001), ("ACC_PRIVATE", 0x0002), ("ACC_PROTECTED", 0x0004), ("ACC_STATIC", 0x0008),
+    ("ACC_FINAL", 0x0010), ("ACC_SUPER", 0x0020), ("ACC_SYNCHRONIZED", 0x0020), ("ACC_VOLATILE", 0x0040),
+    ("ACC_BRIDGE", 0x0040), ("ACC_TRANSIENT", 0x0080), ("ACC_VARARGS", 0x0080), ("ACC_NATIVE", 0x0100),
+    ("ACC_INTERFACE", 0x0200), ("ACC_ABSTRACT", 0x0400), ("ACC_STRICT", 0x0800), ("ACC_SYNTHETIC", 0x1000),
+    ("ACC_ANNOTATION", 0x2000), ("ACC_ENUM", 0x4000), ("ACC_MODULE", 0x8000), ("ACC_MANDATED", 0x8000),
+    ("ACC_OPEN", 0x0020)
+];
+
+static REFERENCE_KINDS: [&str; 10] = [
+    "REF_unknown", "REF_getField", "REF_getStatic", "REF_putField", "REF_putStatic", "REF_invokeVirtual",
+    "REF_invokeStatic", "REF_invokeSpecial", "REF_newInvokeSpecial", "REF_invokeInterface"
+];
+
+// Lines about the class file rather than the class, as javap prints them.
+static FILE_LINES: [&str; 5] = ["Classfile ", "Last modified ", "MD5 checksum ", "SHA-256 checksum ", "Compiled from "];
+
+static CODE_ATTRIBUTES: [&str; 7] = [
+    "Exception table", "LineNumberTable", "LocalVariableTable", "LocalVariableTypeTable", "StackMapTable",
+    "RuntimeVisibleTypeAnnotations", "RuntimeInvisibleTypeAnnotations"
+];
+
+// A line of the source and the lines indented below it.
+struct Node<'s> {
+    number: usize,
+    text: &'s str,
+    // The text with its trailing spaces, which belong to strings.
+    line: &'s str,
+    children: Vec<Node<'s>>
+}
+
+impl<'s> Node<'s> {
+    // The lines below this one in order, whatever their indentation.
+    fn descendants(&self) -> Vec<&Node<'s>> {
+        let mut lines = Vec::new();
+        for child in &self.children {
+            lines.push(child);
+            lines.extend(child.descendants());
+        }
+        lines
+    }
+}
+
+fn tree(source: &str) -> Vec<Node> {
+    let mut roots = Vec::new();
+    let mut open: Vec<(usize, Node)> = Vec::new();
+    for (i, line) in source.lines().enumerate() {
+        let text = line.trim();
+        if text.is_empty() {
+            continue;
+        }
+        let mut indent = line.len() - line.trim_left().len();
+        // javap indents the dump of an attribute it does not know by two more
+        // spaces than its neighbours.
+        if is_unknown_attribute(text) {
+            indent = indent.saturating_sub(2);
+        }
+        close(&mut open, &mut roots, indent);
+        open.push((indent, Node { number: i + 1, text: text, line: line.trim_left(), children: Vec::new() }));
+    }
+    close(&mut open, &mut roots, 0);
+    roots
+}
+
+// Closes the open nodes indented by at least `indent`.
+fn close<'s>(open: &mut Vec<(usize, Node<'s>)>, roots: &mut Vec<Node<'s>>, indent: usize) {
+    while open.last().map_or(false, |&(open_indent, _)| open_indent >= indent) {
+        let (_, node) = open.pop().unwrap();
+        match open.last_mut() {
+            Option::Some(&mut (_, ref mut parent)) => parent.children.push(node),
+            Option::None => roots.push(node)
+        }
+    }
+}
+
+fn is_unknown_attribute(text: &str) -> bool {
+    match text.find(": length = 0x") {
+        Option::Some(i) => i > 0 && !text[..i].contains(' ')
+            && text[i + 13..].chars().all(|c| c.is_ascii_hexdigit()),
+        Option::None => false
+    }
+}
+
+fn is_code_attribute(text: &str) -> bool {
+    let name = text.split(':').next().unwrap_or("");
+    CODE_ATTRIBUTES.contains(&name) || is_unknown_attribute(text)
+}
+
+// The first word and the rest, both without surrounding spaces.
+fn split_word(text: &str) -> (&str, &str) {
+    let text = text.trim();
+    match text.find(char::is_whitespace) {
+        Option::Some(i) => (&text[..i], text[i..].trim_left()),
+        Option::None => (text, "")
+    }
+}
+
+fn strip_prefix<'a>(text: &'a str, prefix: &str) -> Option<&'a str> {
+    if text.starts_with(prefix) {
+        Option::Some(&text[prefix.len()..])
+    } else {
+        Option::None
+    }
+}
+
+fn strip_suffix<'a>(text: &'a str, suffix: &str) -> Option<&'a str> {
+    if text.ends_with(suffix) {
+        Option::Some(&text[..text.len() - suffix.len()])
+    } else {
+        Option::None
+    }
+}
+
+fn split_key(text: &str) -> (&str, &str) {
+    match text.find(':') {
+        Option::Some(i) => (text[..i].trim(), text[i + 1..].trim()),
+        Option::None => (text, "")
+    }
+}
+
+fn strip_comment(text: &str) -> &str {
+    match text.find("//") {
+        Option::Some(i) => text[..i].trim(),
+        Option::None => text.trim()
+    }
+}
+
+fn modifier(word: &str) -> Option<u16> {
+    MODIFIERS.iter().find(|&&(keyword, _)| keyword == word).map(|&(_, flag)| flag)
+}
+
+// The flags of the leading modifiers of a declaration.
+fn modifiers(declaration: &str) -> u16 {
+    declaration.split_whitespace()
+        .take_while(|&word| word == "default" || modifier(word).is_some())
+        .map(|word| modifier(word).unwrap_or(0))
+        .fold(0, |flags, flag| flags | flag)
+}
+
+fn internal_name(java_name: &str) -> String {
+    java_name.replace('.', "/")
+}
+
+fn binary_name(class_type: &ClassTypeSignature) -> String {
+    let mut name = class_type.package.clone();
+    if !name.is_empty() {
+        name.push('/');
+    }
+    name.push_str(&class_type.class.name);
+    for inner in &class_type.inner {
+        name.push('$');
+        name.push_str(&inner.name);
+    }
+    name
+}
+
+// The name of a method as javap declares it: constructors are named after the
+// class and have no return type, and the static initializer is `{}`.
+fn method_name(declaration: &str) -> String {
+    let open = match declaration.find('(') {
+        Option::Some(open) => open,
+        Option::None => { return "<clinit>".to_string(); }
+    };
+    let before = declaration[..open].trim_right();
+    let start = before.rfind(' ').map_or(0, |i| i + 1);
+    let name = &before[start..];
+    if name.starts_with('<') {
+        return name.to_string();
+    }
+    let mut prefix = before[..start].trim();
+    loop {
+        let (word, rest) = split_word(prefix);
+        if word.is_empty() || (word != "default" && modifier(word).is_none()) {
+            break;
+        }
+        prefix = rest;
+    }
+    if prefix.starts_with('<') {
+        let mut depth = 0;
+        for (i, c) in prefix.char_indices() {
+            match c {
+                '<' => depth += 1,
+                '>' => depth -= 1,
+                _ => {}
+            }
+            if depth == 0 {
+                prefix = prefix[i + 1..].trim();
+                break;
+            }
+        }
+    }
+    if prefix.is_empty() { "<init>".to_string() } else { name.to_string() }
+}
+
+// Undoes the escapes of strings in javap's output.
+fn unescape(text: &str) -> JavaString {
+    let mut units: Vec<u16> = Vec::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Option::Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buffer = [0; 2];
+            units.extend_from_slice(c.encode_utf16(&mut buffer));
+            continue;
+        }
+        let unit = match chars.peek().cloned() {
+            Option::Some('t') => Option::Some(0x09),
+            Option::Some('n') => Option::Some(0x0a),
+            Option::Some('r') => Option::Some(0x0d),
+            Option::Some('b') => Option::Some(0x08),
+            Option::Some('f') => Option::Some(0x0c),
+            Option::Some(c @ '"') | Option::Some(c @ '\'') | Option::Some(c @ '\\') => Option::Some(c as u16),
+            Option::Some('u') => {
+                let hex: String = chars.clone().skip(1).take(4).collect();
+                match u16::from_str_radix(&hex, 16) {
+                    Result::Ok(unit) if hex.len() == 4 => {
+                        for _ in 0..4 {
+                            chars.next();
+                        }
+                        Option::Some(unit)
+                    },
+                    _ => Option::None
+                }
+            },
+            _ => Option::None
+        };
+        match unit {
+            Option::Some(unit) => {
+                chars.next();
+                units.push(unit);
+            },
+            Option::None => units.push('\\' as u16)
+        }
+    }
+    JavaString::from_utf16(&units)
+}
+
+// Undoes the quotes javap puts around names that are not valid binary names.
+fn unquote(name: &str) -> String {
+    if name.len() < 2 || !name.starts_with('"') || !name.ends_with('"') {
+        return name.to_string();
+    }
+    let mut unquoted = String::with_capacity(name.len());
+    let mut chars = name[1..name.len() - 1].chars();
+    while let Option::Some(c) = chars.next() {
+        if c != '\\' {
+            unquoted.push(c);
+            continue;
+        }
+        match chars.next() {
+            Option::Some('n') => unquoted.push('\n'),
+            Option::Some('t') => unquoted.push('\t'),
+            Option::Some(c) => unquoted.push(c),
+            Option::None => unquoted.push('\\')
+        }
+    }
+    unquoted
+}
+
+// Splits off a possibly quoted name that ends at `.` or `:`.
+fn name_part(text: &str) -> (String, &str) {
+    if text.starts_with('"') {
+        let mut escaped = false;
+        for (i, c) in text.char_indices().skip(1) {
+            match c {
+                '\\' if !escaped => escaped = true,
+                '"' if !escaped => { return (unquote(&text[..i + 1]), &text[i + 1..]); },
+                _ => escaped = false
+            }
+        }
+    }
+    let end = text.find(|c| c == '.' || c == ':').unwrap_or(text.len());
+    (text[..end].to_string(), &text[end..])
+}
+
+// Splits `[class.]name:descriptor` as javap prints member references.
+fn member_parts(text: &str) -> Option<(Option<String>, String, String)> {
+    let (first, rest) = name_part(text);
+    let (class_name, name, rest) = match strip_prefix(rest, ".") {
+        Option::Some(rest) => {
+            let (name, rest) = name_part(rest);
+            (Option::Some(first), name, rest)
+        },
+        Option::None => (Option::None, first, rest)
+    };
+    strip_prefix(rest, ":").map(|descriptor| (class_name, name, descriptor.to_string()))
+}
+
+fn parse_float(text: &str) -> Option<f64> {
+    match text {
+        "NaN" => Option::Some(::std::f64::NAN),
+        "Infinity" => Option::Some(::std::f64::INFINITY),
+        "-Infinity" => Option::Some(::std::f64::NEG_INFINITY),
+        _ => text.parse().ok()
+    }
+}
+
+// Reads the `#n` indices of annotations as javap prints them unresolved.
+struct Cursor<'t> {
+    rest: &'t str
+}
+
+impl<'t> Cursor<'t> {
+    fn eat(&mut self, c: char) -> bool {
+        if self.rest.starts_with(c) {
+            self.rest = &self.rest[c.len_utf8()..];
+            true
+        } else {
+            false
+        }
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.rest.chars().next();
+        if let Option::Some(c) = c {
+            self.rest = &self.rest[c.len_utf8()..];
+        }
+        c
+    }
+
+    fn index(&mut self) -> Option<u16> {
+        if !self.eat('#') {
+            return Option::None;
+        }
+        let end = self.rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(self.rest.len());
+        let index = self.rest[..end].parse().ok();
+        self.rest = &self.rest[end..];
+        index
+    }
+
+    fn annotation(&mut self) -> Option<Annotation> {
+        let type_index = match self.index() {
+            Option::Some(type_index) => type_index,
+            Option::None => { return Option::None; }
+        };
+        if !self.eat('(') {
+            return Option::None;
+        }
+        let mut element_value_pairs = Vec::new();
+        if !self.eat(')') {
+            loop {
+                let element_name_index = match self.index() {
+                    Option::Some(index) => index,
+                    Option::None => { return Option::None; }
+                };
+                if !self.eat('=') {
+                    return Option::None;
+                }
+                let value = match self.element_value() {
+                    Option::Some(value) => value,
+                    Option::None => { return Option::None; }
+                };
+                element_value_pairs.push(ElementValuePair { element_name_index: element_name_index, value: value });
+                if !self.eat(',') {
+                    break;
+                }
+            }
+            if !self.eat(')') {
+                return Option::None;
+            }
+        }
+        Option::Some(Annotation { type_index: type_index, element_value_pairs: element_value_pairs })
+    }
+
+    fn element_value(&mut self) -> Option<ElementValue> {
+        let value = match self.next() {
+            Option::Some('e') => {
+                let type_name_index = match self.index() {
+                    Option::Some(index) => index,
+                    Option::None => { return Option::None; }
+                };
+                if !self.eat('.') {
+                    return Option::None;
+                }
+                let const_name_index = match self.index() {
+                    Option::Some(index) => index,
+                    Option::None => { return Option::None; }
+                };
+                ElementValue::EnumConstant { type_name_index: type_name_index, const_name_index: const_name_index }
+            },
+            Option::Some('c') => ElementValue::Class { class_info_index: match self.index() {
+                Option::Some(index) => index,
+                Option::None => { return Option::None; }
+            } },
+            Option::Some('@') => match self.annotation() {
+                Option::Some(annotation) => ElementValue::Annotation(annotation),
+                Option::None => { return Option::None; }
+            },
+            Option::Some('[') => {
+                let mut values = Vec::new();
+                if !self.eat(']') {
+                    loop {
+                        match self.element_value() {
+                            Option::Some(value) => values.push(value),
+                            Option::None => { return Option::None; }
+                        }
+                        if !self.eat(',') {
+                            break;
+                        }
+                    }
+                    if !self.eat(']') {
+                        return Option::None;
+                    }
+                }
+                ElementValue::Array(values)
+            },
+            Option::Some(tag) if "BCDFIJSZs".contains(tag) => match self.index() {
+                Option::Some(index) => ElementValue::Constant { tag: tag, const_value_index: index },
+                Option::None => { return Option::None; }
+            },
+            _ => { return Option::None; }
+        };
+        Option::Some(value)
+    }
+}
+
+// Where the labels of a method's code end up. Until the code is laid out,
+// instructions are identified by their position in the listing.
+struct Labels {
+    names: HashMap<String, usize>,
+    // Labels that are offsets, as in javap's listings, in ascending order.
+    numbers: Vec<(u32, usize)>,
+    count: usize,
+    // The offset of every instruction and of the end of the code.
+    pcs: Vec<u32>
+}
+
+impl Labels {
+    fn position(&self, label: &str) -> Option<usize> {
+        match self.names.get(label) {
+            Option::Some(&position) => Option::Some(position),
+            Option::None => label.parse().ok().and_then(|number| {
+                self.numbers.binary_search_by_key(&number, |&(n, _)| n).ok().map(|i| self.numbers[i].1)
+            })
+        }
+    }
+
+    // Offsets that are not labels keep their distance to the label before.
+    fn offset(&self, offset: u32) -> u32 {
+        match self.numbers.binary_search_by_key(&offset, |&(n, _)| n) {
+            Result::Ok(i) => self.pcs[self.numbers[i].1],
+            Result::Err(0) => offset,
+            Result::Err(i) => {
+                let (number, position) = self.numbers[i - 1];
+                self.pcs[position] + (offset - number)
+            }
+        }
+    }
+
+    fn pc(&self, label: &str) -> Option<u32> {
+        match self.names.get(label) {
+            Option::Some(&position) => Option::Some(self.pcs[position]),
+            Option::None => label.parse().ok().map(|offset| self.offset(offset))
+        }
+    }
+}
+
+type CodeLine<'n, 's> = (&'n Node<'s>, Option<&'s str>, Option<&'s str>);
+
+// Splits lines of code into labels and instructions. Lines indented below an
+// instruction are read as if they were not, except for the cases of switches.
+fn code_lines<'n, 's>(node: &'n Node<'s>, lines: &mut Vec<CodeLine<'n, 's>>) {
+    let (first, rest) = split_word(node.text);
+    let (label, instruction) = match strip_suffix(first, ":") {
+        Option::Some(label) if !label.is_empty() => (Option::Some(label), if rest.is_empty() { Option::None } else { Option::Some(rest) }),
+        _ => (Option::None, Option::Some(node.text))
+    };
+    lines.push((node, label, instruction));
+    let switch = instruction.map_or(false, |text| text.starts_with("tableswitch") || text.starts_with("lookupswitch"));
+    if !switch {
+        for child in &node.children {
+            code_lines(child, lines);
+        }
+    }
+}
+
+struct Declaration {
+    flags: u16,
+    name: String,
+    is_module: bool,
+    is_generic: bool,
+    super_name: Option<String>,
+    interfaces: Vec<String>
+}
+
+struct Assembler {
+    pool: ConstantPool,
+    this_class: u16,
+    // The line being assembled, for error messages.
+    line: usize
+}
+
+impl Assembler {
+    fn error<T>(&self, message: &str) -> ParseResult<T> {
+        Result::Err(ParseError::Format(format!("line {}: {}", self.line, message)))
+    }
+
+    fn number<T: FromStr>(&self, text: &str) -> ParseResult<T> {
+        match text.trim().parse() {
+            Result::Ok(number) => Result::Ok(number),
+            Result::Err(_) => self.error(&format!("invalid number {}", text.trim()))
+        }
+    }
+
+    fn hex(&self, text: &str) -> ParseResult<u16> {
+        let text = text.trim();
+        match u16::from_str_radix(strip_prefix(text, "0x").unwrap_or(text), 16) {
+            Result::Ok(number) => Result::Ok(number),
+            Result::Err(_) => self.error(&format!("invalid hexadecimal number {}", text))
+        }
+    }
+
+    // The index of an equal constant, added to the pool if there is none.
+    fn constant(&mut self, info: ConstantPoolInfo) -> ParseResult<u16> {
+        if let Option::Some(i) = self.pool.iter().position(|entry| *entry == info) {
+            return Result::Ok(i as u16 + 1);
+        }
+        match self.pool.push(info) {
+            Result::Ok(index) => Result::Ok(index),
+            Result::Err(_) => self.error("too many constants")
+        }
+    }
+
+    fn utf8(&mut self, string: &str) -> ParseResult<u16> {
+        self.constant(ConstantPoolInfo::Utf8(JavaString::from(string)))
+    }
+
+    fn class_constant(&mut self, name: &str) -> ParseResult<u16> {
+        let name_index = try!(self.utf8(name));
+        self.constant(ConstantPoolInfo::Class(name_index))
+    }
+
+    fn name_and_type(&mut self, name: &str, descriptor: &str) -> ParseResult<u16> {
+        let name_index = try!(self.utf8(name));
+        let descriptor_index = try!(self.utf8(descriptor));
+        self.constant(ConstantPoolInfo::NameAndType(name_index, descriptor_index))
+    }
+
+    // An index written as `#n`, ignoring what follows it.
+    fn index(&self, text: &str) -> ParseResult<u16> {
+        match strip_prefix(strip_comment(text), "#") {
+            Option::Some(index) => self.number(split_word(index).0),
+            Option::None => self.error(&format!("expected a constant pool index but found {}", text.trim()))
+        }
+    }
+
+    // A constant given by index or as javap shows it in comments, e.g. `class
+    // java/lang/Object` or `String hello`.
+    fn constant_reference(&mut self, text: &str) -> ParseResult<u16> {
+        let text = text.trim();
+        if text.starts_with('#') {
+            return self.index(text);
+        }
+        let (tag, value) = match text.find(' ') {
+            Option::Some(i) => (&text[..i], &text[i + 1..]),
+            Option::None => (text, "")
+        };
+        let info = match tag {
+            "Utf8" => ConstantPoolInfo::Utf8(unescape(value)),
+            "int" => ConstantPoolInfo::Integer(try!(self.number(value))),
+            "float" => match parse_float(value.trim_right_matches('f')) {
+                Option::Some(value) => ConstantPoolInfo::Float(value as f32),
+                Option::None => { return self.error(&format!("invalid float {}", value)); }
+            },
+            "long" => ConstantPoolInfo::Long(try!(self.number(value.trim_right_matches('l')))),
+            "double" => match parse_float(value.trim_right_matches('d')) {
+                Option::Some(value) => ConstantPoolInfo::Double(value),
+                Option::None => { return self.error(&format!("invalid double {}", value)); }
+            },
+            "class" => { return self.class_constant(&unquote(value)); },
+            "String" => {
+                let string_index = try!(self.constant(ConstantPoolInfo::Utf8(unescape(value))));
+                ConstantPoolInfo::String(string_index)
+            },
+            "Field" | "Method" | "InterfaceMethod" => { return self.member_reference(tag, value); },
+            "NameAndType" => match member_parts(value) {
+                Option::Some((Option::None, name, descriptor)) => { return self.name_and_type(&name, &descriptor); },
+                _ => { return self.error(&format!("invalid name and type {}", value)); }
+            },
+            "MethodHandle" => { return self.method_handle(value); },
+            "MethodType" => ConstantPoolInfo::MethodType(try!(self.utf8(value))),
+            "Dynamic" | "InvokeDynamic" => {
+                let (bootstrap, name_and_type) = match strip_prefix(value, "#").and_then(|v| v.find(':').map(|i| v.split_at(i))) {
+                    Option::Some((bootstrap, name_and_type)) => (bootstrap, &name_and_type[1..]),
+                    Option::None => { return self.error(&format!("invalid dynamic constant {}", value)); }
+                };
+                let bootstrap_index = try!(self.number(bootstrap));
+                let name_and_type_index = match member_parts(name_and_type) {
+                    Option::Some((Option::None, name, descriptor)) => try!(self.name_and_type(&name, &descriptor)),
+                    _ => { return self.error(&format!("invalid dynamic constant {}", value)); }
+                };
+                if tag == "Dynamic" {
+                    ConstantPoolInfo::Dynamic(bootstrap_index, name_and_type_index)
+                } else {
+                    ConstantPoolInfo::InvokeDynamic(bootstrap_index, name_and_type_index)
+                }
+            },
+            "Module" => ConstantPoolInfo::Module(try!(self.utf8(&unquote(value)))),
+            "Package" => ConstantPoolInfo::Package(try!(self.utf8(&unquote(value)))),
+            _ => { return self.error(&format!("unknown constant {}", text)); }
+        };
+        self.constant(info)
+    }
+
+    // References without a class name are to members of this class.
+    fn member_reference(&mut self, tag: &str, text: &str) -> ParseResult<u16> {
+        let (class_name, name, descriptor) = match member_parts(text) {
+            Option::Some(parts) => parts,
+            Option::None => { return self.error(&format!("invalid member reference {}", text)); }
+        };
+        let class_index = match class_name {
+            Option::Some(class_name) => try!(self.class_constant(&class_name)),
+            Option::None => self.this_class
+        };
+        let name_and_type_index = try!(self.name_and_type(&name, &descriptor));
+        self.constant(match tag {
+            "Field" => ConstantPoolInfo::Fieldref(class_index, name_and_type_index),
+            "Method" => ConstantPoolInfo::Methodref(class_index, name_and_type_index),
+            _ => ConstantPoolInfo::InterfaceMethodref(class_index, name_and_type_index)
+        })
+    }
+
+    fn method_handle(&mut self, text: &str) -> ParseResult<u16> {
+        let (kind, reference) = split_word(text);
+        let kind = match REFERENCE_KINDS.iter().position(|&name| name == kind) {
+            Option::Some(kind) if kind > 0 => kind as u8,
+            _ => { return self.error(&format!("unknown reference kind {}", kind)); }
+        };
+        let tag = match kind {
+            1...4 => "Field",
+            9 => "InterfaceMethod",
+            _ => "Method"
+        };
+        let mut reference_index = try!(self.member_reference(tag, reference));
+        // invokestatic and invokespecial handles may refer to interface methods
+        // as well, which javap does not tell apart.
+        if kind == 6 || kind == 7 {
+            if let ConstantPoolInfo::Methodref(class_index, name_and_type_index) = *try!(self.pool.get(reference_index)) {
+                let interface_method = ConstantPoolInfo::InterfaceMethodref(class_index, name_and_type_index);
+                if let Option::Some(i) = self.pool.iter().position(|entry| *entry == interface_method) {
+                    reference_index = i as u16 + 1;
+                }
+            }
+        }
+        self.constant(ConstantPoolInfo::MethodHandle(kind, reference_index))
+    }
+
+    fn constant_pool(&mut self, node: &Node) -> ParseResult<()> {
+        for entry in &node.children {
+            self.line = entry.number;
+            let (index, rest) = match entry.text.find(" = ") {
+                Option::Some(i) => (&entry.text[..i], &entry.text[i + 3..]),
+                Option::None => { return self.error("expected a constant pool entry"); }
+            };
+            let index = try!(self.index(index));
+            if index as usize != self.pool.len() + 1 {
+                return self.error(&format!("expected constant #{}", self.pool.len() + 1));
+            }
+            let tag = rest.split(' ').next().unwrap_or("");
+            let operands = strip_comment(&rest[tag.len()..]);
+            let info = match tag {
+                "Utf8" => {
+                    // The value starts in the column after the padded tag, so
+                    // that leading spaces belong to it.
+                    let value = &entry.line[entry.text.len() - rest.len() + tag.len()..];
+                    let spaces = value.len() - value.trim_left_matches(' ').len();
+                    let column = if 19 - tag.len() <= spaces { 19 - tag.len() } else { spaces };
+                    ConstantPoolInfo::Utf8(unescape(&value[column..]))
+                },
+                "Integer" => ConstantPoolInfo::Integer(try!(self.number(operands))),
+                "Float" => match parse_float(operands.trim_right_matches('f')) {
+                    Option::Some(value) => ConstantPoolInfo::Float(value as f32),
+                    Option::None => { return self.error(&format!("invalid float {}", operands)); }
+                },
+                "Long" => ConstantPoolInfo::Long(try!(self.number(operands.trim_right_matches('l')))),
+                "Double" => match parse_float(operands.trim_right_matches('d')) {
+                    Option::Some(value) => ConstantPoolInfo::Double(value),
+                    Option::None => { return self.error(&format!("invalid double {}", operands)); }
+                },
+                "Class" => ConstantPoolInfo::Class(try!(self.index(operands))),
+                "String" => ConstantPoolInfo::String(try!(self.index(operands))),
+                "MethodType" => ConstantPoolInfo::MethodType(try!(self.index(operands))),
+                "Module" => ConstantPoolInfo::Module(try!(self.index(operands))),
+                "Package" => ConstantPoolInfo::Package(try!(self.index(operands))),
+                "Fieldref" | "Methodref" | "InterfaceMethodref" => {
+                    let (class_index, name_and_type_index) = try!(self.index_pair(operands, '.'));
+                    match tag {
+                        "Fieldref" => ConstantPoolInfo::Fieldref(class_index, name_and_type_index),
+                        "Methodref" => ConstantPoolInfo::Methodref(class_index, name_and_type_index),
+                        _ => ConstantPoolInfo::InterfaceMethodref(class_index, name_and_type_index)
+                    }
+                },
+                "NameAndType" => {
+                    let (name_index, descriptor_index) = try!(self.index_pair(operands, ':'));
+                    ConstantPoolInfo::NameAndType(name_index, descriptor_index)
+                },
+                "MethodHandle" => {
+                    let (kind, reference) = operands.split_at(operands.find(':').unwrap_or(0));
+                    ConstantPoolInfo::MethodHandle(try!(self.number(kind)), try!(self.index(&reference[1..])))
+                },
+                "Dynamic" | "InvokeDynamic" => {
+                    let (bootstrap_index, name_and_type_index) = try!(self.index_pair(operands, ':'));
+                    if tag == "Dynamic" {
+                        ConstantPoolInfo::Dynamic(bootstrap_index, name_and_type_index)
+                    } else {
+                        ConstantPoolInfo::InvokeDynamic(bootstrap_index, name_and_type_index)
+                    }
+                },
+                _ => { return self.error(&format!("unknown constant type {}", tag)); }
+            };
+            if self.pool.push(info).is_err() {
+                return self.error("too many constants");
+            }
+        }
+        Result::Ok(())
+    }
+
+    fn index_pair(&self, text: &str, separator: char) -> ParseResult<(u16, u16)> {
+        match text.find(separator) {
+            Option::Some(i) => Result::Ok((try!(self.index(&text[..i])), try!(self.index(&text[i + 1..])))),
+            Option::None => self.error(&format!("expected two indices separated by {} but found {}", separator, text))
+        }
+    }
+
+    fn class(mut self, nodes: &[Node]) -> ParseResult<Class> {
+        let mut declaration = Option::None;
+        let mut properties = Vec::new();
+        let mut members = Vec::new();
+        let mut attribute_nodes = Vec::new();
+        for node in nodes {
+            let text = node.text;
+            if text == "Constant pool:" || text == "}" || FILE_LINES.iter().any(|prefix| text.starts_with(prefix)) {
+                continue;
+            } else if text == "{" {
+                members.extend(node.children.iter());
+            } else if is_class_property(text) {
+                properties.push(node);
+            } else if declaration.is_none() {
+                declaration = Option::Some(node);
+                properties.extend(node.children.iter());
+            } else {
+                attribute_nodes.push(node);
+            }
+        }
+        let declaration = match declaration {
+            Option::Some(node) => {
+                self.line = node.number;
+                try!(self.declaration(node.text))
+            },
+            Option::None => { return self.error("missing class declaration"); }
+        };
+
+        let mut minor_version = 0;
+        let mut major_version = 52;
+        let mut access_flags = declaration.flags;
+        let mut this_class = Option::None;
+        let mut super_class = Option::None;
+        for node in properties {
+            self.line = node.number;
+            let (key, value) = split_key(node.text);
+            match key {
+                "minor version" => minor_version = try!(self.number(value)),
+                "major version" => major_version = try!(self.number(value)),
+                "flags" => access_flags = try!(self.flags(value)),
+                "this_class" => this_class = Option::Some(try!(self.class_reference(value))),
+                "super_class" => super_class = Option::Some(try!(self.class_reference(value))),
+                _ => {}
+            }
+        }
+        self.this_class = match this_class {
+            Option::Some(this_class) => this_class,
+            Option::None => try!(self.class_constant(&declaration.name))
+        };
+
+        let mut fields = Vec::new();
+        let mut methods = Vec::new();
+        for node in members {
+            self.line = node.number;
+            let member = node.text.trim_right_matches(';');
+            if member.contains('(') || member.ends_with("{}") {
+                methods.push(try!(self.method(node, member)));
+            } else {
+                fields.push(try!(self.field(node, member)));
+            }
+        }
+        let attributes = try!(self.attributes(&attribute_nodes, Option::None));
+
+        // Generic supertypes are declared as javap shows them, so the names
+        // are taken from the signature.
+        let signature = match attributes.iter().filter_map(|attribute| match *attribute {
+            Attribute::Signature { signature_index } => Option::Some(signature_index),
+            _ => Option::None
+        }).next() {
+            Option::Some(index) if declaration.is_generic => {
+                Option::Some(try!(ClassSignature::parse(&try!(self.pool.utf8_string(index)))))
+            },
+            _ => Option::None
+        };
+        let (super_name, interface_names) = match signature {
+            Option::Some(ref signature) => {
+                (Option::Some(binary_name(&signature.superclass)), signature.interfaces.iter().map(binary_name).collect())
+            },
+            Option::None => (declaration.super_name.clone(), declaration.interfaces.clone())
+        };
+        let super_class = match super_class {
+            Option::Some(super_class) => super_class,
+            Option::None if declaration.is_module => 0,
+            Option::None => try!(self.class_constant(super_name.as_ref().map_or("java/lang/Object", |name| name.as_str())))
+        };
+        let mut interfaces = Vec::with_capacity(interface_names.len());
+        for name in interface_names {
+            interfaces.push(try!(self.class_constant(&name)));
+        }
+
+        Result::Ok(Class {
+            magic: 0xCAFEBABE,
+            minor_version: minor_version,
+            major_version: major_version,
+            constant_pool: self.pool,
+            access_flags: access_flags,
+            this_class: self.this_class,
+            super_class: super_class,
+            interfaces: interfaces,
+            fields: fields,
+            methods: methods,
+            attributes: attributes
+        })
+    }
+
+    fn declaration(&self, text: &str) -> ParseResult<Declaration> {
+        let mut flags = 0;
+        let mut rest = text;
+        let kind = loop {
+            let (word, remainder) = split_word(rest);
+            rest = remainder;
+            match word {
+                "class" | "interface" | "module" => break word,
+                "open" => {},
+                _ => match modifier(word) {
+                    Option::Some(flag) => flags |= flag,
+                    Option::None => { return self.error(&format!("expected a class declaration but found {}", text)); }
+                }
+            }
+        };
+        let end = rest.find(|c| c == ' ' || c == '<').unwrap_or(rest.len());
+        let (name, supertypes) = rest.split_at(end);
+        let mut declaration = Declaration {
+            flags: flags,
+            name: internal_name(name),
+            is_module: kind == "module",
+            is_generic: supertypes.contains('<'),
+            super_name: Option::None,
+            interfaces: Vec::new()
+        };
+        match kind {
+            "module" => {
+                declaration.flags = 0x8000;
+                declaration.name = "module-info".to_string();
+                return Result::Ok(declaration);
+            },
+            "interface" => declaration.flags |= 0x0600,
+            _ => declaration.flags |= 0x0020
+        }
+        if declaration.is_generic {
+            return Result::Ok(declaration);
+        }
+        let mut extends = Vec::new();
+        let mut implements = Vec::new();
+        let mut in_implements = Option::None;
+        for word in supertypes.split_whitespace() {
+            match word {
+                "extends" => in_implements = Option::Some(false),
+                "implements" => in_implements = Option::Some(true),
+                _ => {
+                    let list = match in_implements {
+                        Option::Some(false) => &mut extends,
+                        Option::Some(true) => &mut implements,
+                        Option::None => { return self.error(&format!("unexpected {} in class declaration", word)); }
+                    };
+                    list.extend(word.split(',').filter(|name| !name.is_empty()).map(internal_name));
+                }
+            }
+        }
+        if kind == "interface" {
+            declaration.interfaces = extends;
+        } else {
+            declaration.super_name = extends.into_iter().next();
+            declaration.interfaces = implements;
+        }
+        Result::Ok(declaration)
+    }
+
+    // Flags as `(0x0021) ACC_PUBLIC, ACC_SUPER`, where the names are only
+    // read if the number is left out.
+    fn flags(&self, text: &str) -> ParseResult<u16> {
+        if let Option::Some(hex) = strip_prefix(text, "(0x") {
+            return self.hex(&hex[..hex.find(')').unwrap_or(hex.len())]);
+        }
+        let mut flags = 0;
+        for name in text.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+            flags |= match FLAG_NAMES.iter().find(|&&(flag_name, _)| flag_name == name) {
+                Option::Some(&(_, flag)) => flag,
+                Option::None if name.starts_with("0x") => try!(self.hex(name)),
+                Option::None => { return self.error(&format!("unknown flag {}", name)); }
+            };
+        }
+        Result::Ok(flags)
+    }
+
+    fn class_reference(&mut self, text: &str) -> ParseResult<u16> {
+        if text.starts_with('#') {
+            self.index(text)
+        } else {
+            self.class_constant(&unquote(text))
+        }
+    }
+
+    fn field(&mut self, node: &Node, declaration: &str) -> ParseResult<Field> {
+        let name = declaration.rsplit(' ').next().unwrap_or("");
+        let name_index = try!(self.utf8(name));
+        let (access_flags, descriptor_index, attributes) = try!(self.member(node, declaration));
+        Result::Ok(Field {
+            access_flags: access_flags,
+            name_index: name_index,
+            descriptor_index: descriptor_index,
+            attributes: attributes
+        })
+    }
+
+    fn method(&mut self, node: &Node, declaration: &str) -> ParseResult<Method> {
+        let name_index = try!(self.utf8(&method_name(declaration)));
+        let (access_flags, descriptor_index, attributes) = try!(self.member(node, declaration));
+        Result::Ok(Method {
+            access_flags: access_flags,
+            name_index: name_index,
+            descriptor_index: descriptor_index,
+            attributes: attributes
+        })
+    }
+
+    fn member(&mut self, node: &Node, declaration: &str) -> ParseResult<(u16, u16, Vec<Attribute>)> {
+        let mut access_flags = modifiers(declaration);
+        let mut descriptor_index = Option::None;
+        let mut attribute_nodes = Vec::new();
+        for child in &node.children {
+            self.line = child.number;
+            if let Option::Some(descriptor) = strip_prefix(child.text, "descriptor:") {
+                descriptor_index = Option::Some(try!(self.utf8(descriptor.trim())));
+            } else if let Option::Some(flags) = strip_prefix(child.text, "flags:") {
+                access_flags = try!(self.flags(flags.trim()));
+            } else {
+                attribute_nodes.push(child);
+            }
+        }
+        let descriptor_index = match descriptor_index {
+            Option::Some(descriptor_index) => descriptor_index,
+            Option::None => {
+                self.line = node.number;
+                return self.error("missing descriptor");
+            }
+        };
+        let attributes = try!(self.attributes(&attribute_nodes, Option::None));
+        Result::Ok((access_flags, descriptor_index, attributes))
+    }
+
+    fn attributes(&mut self, nodes: &[&Node], labels: Option<&Labels>) -> ParseResult<Vec<Attribute>> {
+        let mut attributes = Vec::with_capacity(nodes.len());
+        for node in nodes {
+            attributes.push(try!(self.attribute(node, labels)));
+        }
+        Result::Ok(attributes)
+    }
+
+    fn attribute(&mut self, node: &Node, labels: Option<&Labels>) -> ParseResult<Attribute> {
+        self.line = node.number;
+        let (name, value) = split_key(node.text);
+        // The writer looks up the names of the attributes it knows.
+        let name_index = try!(self.utf8(name));
+        if is_unknown_attribute(node.text) {
+            let mut info = Vec::new();
+            for line in node.descendants() {
+                self.line = line.number;
+                for byte in line.text.split_whitespace() {
+                    info.push(try!(self.hex(byte)) as u8);
+                }
+            }
+            return Result::Ok(Attribute::Unknown { name_index: name_index, info: info });
+        }
+        let attribute = match name {
+            "ConstantValue" => Attribute::ConstantValue { constvalue_index: try!(self.constant_reference(value)) },
+            "Code" => try!(self.code(node)),
+            "StackMapTable" => {
+                let mut frames = Vec::with_capacity(node.children.len());
+                for frame in &node.children {
+                    frames.push(try!(self.stack_map_frame(frame, labels)));
+                }
+                Attribute::StackMapTable(frames)
+            },
+            "Exceptions" => {
+                let mut exception_index_table = Vec::new();
+                for line in node.descendants() {
+                    self.line = line.number;
+                    let names = strip_prefix(line.text, "throws").unwrap_or(line.text);
+                    for name in names.split(',').map(str::trim).filter(|name| !name.is_empty()) {
+                        exception_index_table.push(try!(self.class_constant(&internal_name(name))));
+                    }
+                }
+                Attribute::Exceptions { exception_index_table: exception_index_table }
+            },
+            "InnerClasses" => {
+                let mut classes = Vec::with_capacity(node.children.len());
+                for line in &node.children {
+                    self.line = line.number;
+                    classes.push(try!(self.inner_class(line.text)));
+                }
+                Attribute::InnerClasses { classes: classes }
+            },
+            "EnclosingMethod" => {
+                let (class_index, method_index) = try!(self.index_pair(strip_comment(value), '.'));
+                Attribute::EnclosingMethod { class_index: class_index, method_index: method_index }
+            },
+            "Synthetic" => Attribute::Synthetic,
+            "Deprecated" => Attribute::Deprecated,
+            "Signature" => Attribute::Signature { signature_index: if value.starts_with('#') {
+                try!(self.index(value))
+            } else {
+                try!(self.utf8(value))
+            } },
+            "SourceFile" => {
+                let source_file = strip_prefix(value, "\"").and_then(|v| strip_suffix(v, "\"")).unwrap_or(value);
+                Attribute::SourceFile { sourcefile_index: try!(self.utf8(source_file)) }
+            },
+            "SourceDebugExtension" => {
+                let mut text = String::new();
+                for line in node.descendants() {
+                    text.push_str(line.text);
+                    text.push('\n');
+                }
+                Attribute::SourceDebugExtension(JavaString::from(text).into_modified_utf8())
+            },
+            "LineNumberTable" => {
+                let mut line_numbers = Vec::with_capacity(node.children.len());
+                for line in &node.children {
+                    self.line = line.number;
+                    let (line_number, pc) = split_key(strip_prefix(line.text, "line").unwrap_or(line.text));
+                    line_numbers.push(LineNumber {
+                        start_pc: try!(self.pc(labels, pc)),
+                        line_number: try!(self.number(line_number))
+                    });
+                }
+                Attribute::LineNumberTable(line_numbers)
+            },
+            "LocalVariableTable" => Attribute::LocalVariableTable(try!(self.local_variables(node, labels))),
+            "LocalVariableTypeTable" => Attribute::LocalVariableTypeTable(try!(self.local_variables(node, labels))),
+            "RuntimeVisibleAnnotations" => Attribute::RuntimeVisibleAnnotations(try!(self.annotations(node))),
+            "RuntimeInvisibleAnnotations" => Attribute::RuntimeInvisibleAnnotations(try!(self.annotations(node))),
+            "RuntimeVisibleParameterAnnotations" | "RuntimeInvisibleParameterAnnotations" => {
+                let mut parameters = Vec::with_capacity(node.children.len());
+                for parameter in &node.children {
+                    parameters.push(try!(self.annotations(parameter)));
+                }
+                if name == "RuntimeVisibleParameterAnnotations" {
+                    Attribute::RuntimeVisibleParameterAnnotations(parameters)
+                } else {
+                    Attribute::RuntimeInvisibleParameterAnnotations(parameters)
+                }
+            },
+            "RuntimeVisibleTypeAnnotations" | "RuntimeInvisibleTypeAnnotations" => {
+                let mut annotations = Vec::with_capacity(node.children.len());
+                for line in &node.children {
+                    self.line = line.number;
+                    annotations.push(try!(self.type_annotation(line.text, labels)));
+                }
+                if name == "RuntimeVisibleTypeAnnotations" {
+                    Attribute::RuntimeVisibleTypeAnnotations(annotations)
+                } else {
+                    Attribute::RuntimeInvisibleTypeAnnotations(annotations)
+                }
+            },
+            "AnnotationDefault" => {
+                let default_value = node.children.first().map_or("", |line| {
+                    strip_prefix(line.text, "default_value:").unwrap_or(line.text).trim()
+                });
+                let mut cursor = Cursor { rest: default_value };
+                match cursor.element_value() {
+                    Option::Some(ref element_value) if cursor.rest.is_empty() => {
+                        Attribute::AnnotationDefault { element_value: element_value.clone() }
+                    },
+                    _ => { return self.error(&format!("invalid default value {}", default_value)); }
+                }
+            },
+            "BootstrapMethods" => {
+                let mut bootstrap_methods = Vec::with_capacity(node.children.len());
+                for line in &node.children {
+                    self.line = line.number;
+                    let method_ref = try!(self.constant_reference(split_key(line.text).1));
+                    let mut arguments = Vec::new();
+                    for argument in line.children.iter().flat_map(|child| child.children.iter()) {
+                        self.line = argument.number;
+                        arguments.push(try!(self.constant_reference(argument.text)));
+                    }
+                    bootstrap_methods.push(BootstrapMethod { method_ref: method_ref, arguments: arguments });
+                }
+                Attribute::BootstrapMethods(bootstrap_methods)
+            },
+            "MethodParameters" => {
+                let mut parameters = Vec::new();
+                for line in node.descendants() {
+                    self.line = line.number;
+                    if line.text.starts_with("Name ") && line.text.ends_with("Flags") {
+                        continue;
+                    }
+                    parameters.push(try!(self.method_parameter(line.text)));
+                }
+                Attribute::MethodParameters(parameters)
+            },
+            "Module" => try!(self.module(node)),
+            "ModulePackages" => Attribute::ModulePackages { package_index: try!(self.references(node)) },
+            "ModuleMainClass" => Attribute::ModuleMainClass { main_class_index: try!(self.constant_reference(value)) },
+            "NestHost" => Attribute::NestHost { host_class_index: try!(self.constant_reference(value)) },
+            "NestMembers" => Attribute::NestMembers { classes: try!(self.classes(node)) },
+            "PermittedSubclasses" => Attribute::PermittedSubclasses { classes: try!(self.classes(node)) },
+            "Record" => {
+                let mut components = Vec::with_capacity(node.children.len());
+                for component in &node.children {
+                    components.push(try!(self.record_component(component)));
+                }
+                Attribute::Record(components)
+            },
+            "ModuleTarget" | "ModuleResolution" | "ModuleHashes" => {
+                Attribute::Unknown { name_index: name_index, info: try!(self.jdk_attribute(name, node)) }
+            },
+            _ => { return self.error(&format!("unknown attribute {}", node.text)); }
+        };
+        Result::Ok(attribute)
+    }
+
+    fn pc(&self, labels: Option<&Labels>, label: &str) -> ParseResult<u16> {
+        let label = label.trim();
+        let pc = match labels {
+            Option::Some(labels) => labels.pc(label),
+            Option::None => label.parse().ok()
+        };
+        match pc {
+            Option::Some(pc) if pc <= 0xffff => Result::Ok(pc as u16),
+            _ => self.error(&format!("unknown label {}", label))
+        }
+    }
+
+    fn offset(&self, labels: Option<&Labels>, offset: u16) -> ParseResult<u16> {
+        let pc = labels.map_or(offset as u32, |labels| labels.offset(offset as u32));
+        if pc > 0xffff {
+            return self.error(&format!("offset {} is out of range", pc));
+        }
+        Result::Ok(pc as u16)
+    }
+
+    fn code(&mut self, node: &Node) -> ParseResult<Attribute> {
+        let mut limits = Option::None;
+        let mut lines = Vec::new();
+        let mut tables = Vec::new();
+        for child in &node.children {
+            if child.text.starts_with("stack=") {
+                self.line = child.number;
+                limits = Option::Some(try!(self.limits(child.text)));
+                for line in &child.children {
+                    code_lines(line, &mut lines);
+                }
+            } else if is_code_attribute(child.text) {
+                tables.push(child);
+            } else {
+                code_lines(child, &mut lines);
+            }
+        }
+        let (max_stack, max_locals) = match limits {
+            Option::Some(limits) => limits,
+            Option::None => {
+                self.line = node.number;
+                return self.error("missing stack and locals");
+            }
+        };
+
+        let mut labels = Labels { names: HashMap::new(), numbers: Vec::new(), count: 0, pcs: Vec::new() };
+        for &(line, label, instruction) in &lines {
+            self.line = line.number;
+            if let Option::Some(label) = label {
+                if labels.position(label).is_some() {
+                    return self.error(&format!("duplicate label {}", label));
+                }
+                match label.parse() {
+                    Result::Ok(number) => labels.numbers.push((number, labels.count)),
+                    Result::Err(_) => { labels.names.insert(label.to_string(), labels.count); }
+                }
+            }
+            if instruction.is_some() {
+                labels.count += 1;
+            }
+        }
+        labels.numbers.sort();
+
+        // Until the code is laid out, branch offsets count instructions.
+        let mut code = Vec::with_capacity(labels.count);
+        for &(line, _, instruction) in &lines {
+            if let Option::Some(text) = instruction {
+                self.line = line.number;
+                let position = code.len();
+                let instruction = try!(self.instruction(text, line, position, &labels));
+                code.push((position as u32, instruction));
+            }
+        }
+        let length = try!(encode_code(&code)).len() as u32;
+        let (code, _) = try!(relocate_code(&code));
+        labels.pcs = code.iter().map(|&(pc, _)| pc).collect();
+        labels.pcs.push(length);
+
+        let mut exception_table = Vec::new();
+        let mut attribute_nodes = Vec::new();
+        for table in tables {
+            if table.text != "Exception table:" {
+                attribute_nodes.push(table);
+                continue;
+            }
+            for row in table.descendants() {
+                self.line = row.number;
+                if row.text.starts_with("from") {
+                    continue;
+                }
+                exception_table.push(try!(self.exception(row.text, &labels)));
+            }
+        }
+        let attributes = try!(self.attributes(&attribute_nodes, Option::Some(&labels)));
+        Result::Ok(Attribute::Code {
+            max_stack: max_stack,
+            max_locals: max_locals,
+            code: code,
+            exception_table: exception_table,
+            attributes: attributes
+        })
+    }
+
+    fn limits(&self, text: &str) -> ParseResult<(u16, u16)> {
+        let mut max_stack = Option::None;
+        let mut max_locals = Option::None;
+        for setting in text.split(',') {
+            let (key, value) = match setting.find('=') {
+                Option::Some(i) => (setting[..i].trim(), &setting[i + 1..]),
+                Option::None => { return self.error(&format!("expected key=value but found {}", setting.trim())); }
+            };
+            match key {
+                "stack" => max_stack = Option::Some(try!(self.number(value))),
+                "locals" => max_locals = Option::Some(try!(self.number(value))),
+                _ => {}
+            }
+        }
+        match (max_stack, max_locals) {
+            (Option::Some(max_stack), Option::Some(max_locals)) => Result::Ok((max_stack, max_locals)),
+            _ => self.error("missing stack and locals")
+        }
+    }
+
+    fn exception(&mut self, text: &str, labels: &Labels) -> ParseResult<Exception> {
+        let mut words = text.split_whitespace();
+        let mut pcs = [0; 3];
+        for pc in pcs.iter_mut() {
+            *pc = try!(self.pc(Option::Some(labels), words.next().unwrap_or("")));
+        }
+        let catch_type = match words.next() {
+            Option::Some("any") => 0,
+            Option::Some("Class") => {
+                let name: Vec<&str> = words.collect();
+                try!(self.class_constant(&unquote(&name.join(" "))))
+            },
+            _ => { return self.error(&format!("expected a catch type in {}", text)); }
+        };
+        Result::Ok(Exception { start_pc: pcs[0], end_pc: pcs[1], handler_pc: pcs[2], catch_type: catch_type })
+    }
+
+    // The offset from the instruction at `position` to a label, counted in
+    // instructions.
+    fn branch(&self, label: &str, position: usize, labels: &Labels) -> ParseResult<i32> {
+        let label = label.trim();
+        match labels.position(label) {
+            Option::Some(target) if target < labels.count => Result::Ok(target as i32 - position as i32),
+            Option::Some(_) => self.error(&format!("label {} is not followed by an instruction", label)),
+            Option::None => self.error(&format!("unknown label {}", label))
+        }
+    }
+
+    fn short_branch(&self, label: &str, position: usize, labels: &Labels) -> ParseResult<i16> {
+        let offset = try!(self.branch(label, position, labels));
+        if offset < i16::min_value() as i32 || offset > i16::max_value() as i32 {
+            return self.error(&format!("branch to {} does not fit into 16 bits", label));
+        }
+        Result::Ok(offset as i16)
+    }
+
+    // A constant pool operand with an optional count, e.g. `#4,  1`.
+    fn counted_operand(&mut self, operands: &str) -> ParseResult<(u16, Option<u8>)> {
+        let text = if operands.starts_with('#') { strip_comment(operands) } else { operands };
+        if let Option::Some(i) = text.rfind(',') {
+            if let Result::Ok(count) = text[i + 1..].trim().parse() {
+                return Result::Ok((try!(self.constant_reference(&text[..i])), Option::Some(count)));
+            }
+        }
+        Result::Ok((try!(self.constant_reference(text)), Option::None))
+    }
+
+    fn instruction(&mut self, text: &str, node: &Node, position: usize, labels: &Labels) -> ParseResult<Instruction> {
+        use ::model::Instruction::*;
+
+        let (mnemonic, operands) = split_word(text);
+        macro_rules! simple {
+            ($($name:ident),*) => {
+                match mnemonic {
+                    $(stringify!($name) => Option::Some($name),)*
+                    "return" => Option::Some(return_),
+                    _ => Option::None
+                }
+            }
+        }
+        macro_rules! local {
+            ($narrow:ident, $wide:ident) => {{
+                let index: u16 = try!(self.number(operands));
+                if index > 0xff { $wide(index) } else { $narrow(index as u8) }
+            }}
+        }
+
+        let simple = simple!(aaload, aastore, aconst_null, aload_0, aload_1, aload_2, aload_3, areturn, arraylength,
+            astore_0, astore_1, astore_2, astore_3, athrow, baload, bastore, caload, castore, d2f, d2i, d2l, dadd,
+            daload, dastore, dcmpg, dcmpl, dconst_0, dconst_1, ddiv, dload_0, dload_1, dload_2, dload_3, dmul, dneg,
+            drem, dreturn, dstore_0, dstore_1, dstore_2, dstore_3, dsub, dup, dup_x1, dup_x2, dup2, dup2_x1, dup2_x2,
+            f2d, f2i, f2l, fadd, faload, fastore, fcmpg, fcmpl, fconst_0, fconst_1, fconst_2, fdiv, fload_0, fload_1,
+            fload_2, fload_3, fmul, fneg, frem, freturn, fstore_0, fstore_1, fstore_2, fstore_3, fsub, i2b, i2c, i2d,
+            i2f, i2l, i2s, iadd, iaload, iand, iastore, iconst_m1, iconst_0, iconst_1, iconst_2, iconst_3, iconst_4,
+            iconst_5, idiv, iload_0, iload_1, iload_2, iload_3, imul, ineg, ior, irem, ireturn, ishl, ishr, istore_0,
+            istore_1, istore_2, istore_3, isub, iushr, ixor, l2d, l2f, l2i, ladd, laload, land, lastore, lcmp,
+            lconst_0, lconst_1, ldiv, lload_0, lload_1, lload_2, lload_3, lmul, lneg, lor, lrem, lreturn, lshl, lshr,
+            lstore_0, lstore_1, lstore_2, lstore_3, lsub, lushr, lxor, monitorenter, monitorexit, nop, pop, pop2,
+            saload, sastore, swap);
+        if let Option::Some(instruction) = simple {
+            if !operands.is_empty() {
+                return self.error(&format!("{} takes no operands", mnemonic));
+            }
+            return Result::Ok(instruction);
+        }
+
+        let instruction = match mnemonic {
+            "aload" => local!(aload, aload_w),
+            "astore" => local!(astore, astore_w),
+            "dload" => local!(dload, dload_w),
+            "dstore" => local!(dstore, dstore_w),
+            "fload" => local!(fload, fload_w),
+            "fstore" => local!(fstore, fstore_w),
+            "iload" => local!(iload, iload_w),
+            "istore" => local!(istore, istore_w),
+            "lload" => local!(lload, lload_w),
+            "lstore" => local!(lstore, lstore_w),
+            "ret" => local!(ret, ret_w),
+            "aload_w" => aload_w(try!(self.number(operands))),
+            "astore_w" => astore_w(try!(self.number(operands))),
+            "dload_w" => dload_w(try!(self.number(operands))),
+            "dstore_w" => dstore_w(try!(self.number(operands))),
+            "fload_w" => fload_w(try!(self.number(operands))),
+            "fstore_w" => fstore_w(try!(self.number(operands))),
+            "iload_w" => iload_w(try!(self.number(operands))),
+            "istore_w" => istore_w(try!(self.number(operands))),
+            "lload_w" => lload_w(try!(self.number(operands))),
+            "lstore_w" => lstore_w(try!(self.number(operands))),
+            "ret_w" => ret_w(try!(self.number(operands))),
+            "iinc" | "iinc_w" => {
+                let (index, value) = match operands.find(',') {
+                    Option::Some(i) => (&operands[..i], &operands[i + 1..]),
+                    Option::None => { return self.error("expected a local variable and an increment"); }
+                };
+                let index: u16 = try!(self.number(index));
+                let value: i16 = try!(self.number(value));
+                if mnemonic == "iinc_w" || index > 0xff || value < i8::min_value() as i16 || value > i8::max_value() as i16 {
+                    iinc_w(index, value)
+                } else {
+                    iinc(index as u8, value as i8)
+                }
+            },
+            "bipush" => bipush(try!(self.number(operands))),
+            "sipush" => sipush(try!(self.number(operands))),
+            "goto" => goto(try!(self.short_branch(operands, position, labels))),
+            "if_acmpeq" => if_acmpeq(try!(self.short_branch(operands, position, labels))),
+            "if_acmpne" => if_acmpne(try!(self.short_branch(operands, position, labels))),
+            "if_icmpeq" => if_icmpeq(try!(self.short_branch(operands, position, labels))),
+            "if_icmpne" => if_icmpne(try!(self.short_branch(operands, position, labels))),
+            "if_icmplt" => if_icmplt(try!(self.short_branch(operands, position, labels))),
+            "if_icmpge" => if_icmpge(try!(self.short_branch(operands, position, labels))),
+            "if_icmpgt" => if_icmpgt(try!(self.short_branch(operands, position, labels))),
+            "if_icmple" => if_icmple(try!(self.short_branch(operands, position, labels))),
+            "ifeq" => ifeq(try!(self.short_branch(operands, position, labels))),
+            "ifne" => ifne(try!(self.short_branch(operands, position, labels))),
+            "iflt" => iflt(try!(self.short_branch(operands, position, labels))),
+            "ifge" => ifge(try!(self.short_branch(operands, position, labels))),
+            "ifgt" => ifgt(try!(self.short_branch(operands, position, labels))),
+            "ifle" => ifle(try!(self.short_branch(operands, position, labels))),
+            "ifnonnull" => ifnonnull(try!(self.short_branch(operands, position, labels))),
+            "ifnull" => ifnull(try!(self.short_branch(operands, position, labels))),
+            "jsr" => jsr(try!(self.short_branch(operands, position, labels))),
+            "goto_w" => goto_w(try!(self.branch(operands, position, labels))),
+            "jsr_w" => jsr_w(try!(self.branch(operands, position, labels))),
+            "ldc" => {
+                let index = try!(self.constant_reference(operands));
+                if index > 0xff {
+                    return self.error(&format!("constant #{} needs ldc_w", index));
+                }
+                ldc(index as u8)
+            },
+            "anewarray" => anewarray(try!(self.constant_reference(operands))),
+            "checkcast" => checkcast(try!(self.constant_reference(operands))),
+            "getfield" => getfield(try!(self.constant_reference(operands))),
+            "getstatic" => getstatic(try!(self.constant_reference(operands))),
+            "instanceof" => instanceof(try!(self.constant_reference(operands))),
+            "invokespecial" => invokespecial(try!(self.constant_reference(operands))),
+            "invokestatic" => invokestatic(try!(self.constant_reference(operands))),
+            "invokevirtual" => invokevirtual(try!(self.constant_reference(operands))),
+            "ldc_w" => ldc_w(try!(self.constant_reference(operands))),
+            "ldc2_w" => ldc2_w(try!(self.constant_reference(operands))),
+            "new" => new(try!(self.constant_reference(operands))),
+            "putfield" => putfield(try!(self.constant_reference(operands))),
+            "putstatic" => putstatic(try!(self.constant_reference(operands))),
+            "invokedynamic" => invokedynamic(try!(self.counted_operand(operands)).0),
+            "invokeinterface" => {
+                let (index, count) = try!(self.counted_operand(operands));
+                let count = match count {
+                    Option::Some(count) => count,
+                    Option::None => {
+                        let descriptor = try!(self.pool.member_ref(index)).descriptor;
+                        try!(MethodDescriptor::parse(&descriptor)).parameter_slots() as u8 + 1
+                    }
+                };
+                invokeinterface(index, count)
+            },
+            "multianewarray" => match try!(self.counted_operand(operands)) {
+                (index, Option::Some(dimensions)) => multianewarray(index, dimensions),
+                (_, Option::None) => { return self.error("expected a class and the number of dimensions"); }
+            },
+            "newarray" => newarray(match operands {
+                "boolean" => ArrayType::Boolean,
+                "char" => ArrayType::Char,
+                "float" => ArrayType::Float,
+                "double" => ArrayType::Double,
+                "byte" => ArrayType::Byte,
+                "short" => ArrayType::Short,
+                "int" => ArrayType::Int,
+                "long" => ArrayType::Long,
+                _ => { return self.error(&format!("unknown array type {}", operands)); }
+            }),
+            "tableswitch" => {
+                let (default, cases) = try!(self.switch(node, position, labels));
+                // Without cases the range is only in the comment.
+                let low = match cases.first() {
+                    Option::Some(&(low, _)) => low,
+                    Option::None => {
+                        let range = operands.split("//").nth(1).unwrap_or("");
+                        range.split_whitespace().next().and_then(|low| low.parse().ok()).unwrap_or(0)
+                    }
+                };
+                for (i, &(key, _)) in cases.iter().enumerate() {
+                    if key as i64 != low as i64 + i as i64 {
+                        return self.error("the cases of a tableswitch must be consecutive");
+                    }
+                }
+                let offsets: Vec<i32> = cases.iter().map(|&(_, offset)| offset).collect();
+                tableswitch(default, low, offsets.into_boxed_slice())
+            },
+            "lookupswitch" => {
+                let (default, cases) = try!(self.switch(node, position, labels));
+                lookupswitch(default, cases.into_boxed_slice())
+            },
+            _ => { return self.error(&format!("unknown instruction {}", mnemonic)); }
+        };
+        Result::Ok(instruction)
+    }
+
+    fn switch(&mut self, node: &Node, position: usize, labels: &Labels) -> ParseResult<(i32, Vec<(i32, i32)>)> {
+        let mut default = Option::None;
+        let mut cases = Vec::new();
+        for line in node.descendants() {
+            self.line = line.number;
+            if line.text == "}" {
+                continue;
+            }
+            let (key, label) = split_key(line.text);
+            let offset = try!(self.branch(label, position, labels));
+            if key == "default" {
+                default = Option::Some(offset);
+            } else {
+                cases.push((try!(self.number(key)), offset));
+            }
+        }
+        self.line = node.number;
+        match default {
+            Option::Some(default) => Result::Ok((default, cases)),
+            Option::None => self.error("missing default")
+        }
+    }
+
+    fn local_variables(&mut self, node: &Node, labels: Option<&Labels>) -> ParseResult<Vec<LocalVariable>> {
+        let mut variables = Vec::new();
+        for row in node.descendants() {
+            self.line = row.number;
+            if row.text.starts_with("Start ") {
+                continue;
+            }
+            let words: Vec<&str> = row.text.split_whitespace().collect();
+            if words.len() != 5 {
+                return self.error("expected start, length, slot, name and signature");
+            }
+            let start_pc = try!(self.pc(labels, words[0]));
+            // The end is the label of the length column or as far from the
+            // start as in the listing.
+            let end_pc = match (words[0].parse::<u32>(), words[1].parse::<u32>()) {
+                (Result::Ok(start), Result::Ok(length)) => try!(self.pc(labels, &(start + length).to_string())),
+                (Result::Err(_), Result::Ok(length)) => start_pc + try!(self.number::<u16>(&length.to_string())),
+                _ => try!(self.pc(labels, words[1]))
+            };
+            if end_pc < start_pc {
+                return self.error("local variable ends before it starts");
+            }
+            let name_index = try!(self.constant(ConstantPoolInfo::Utf8(unescape(words[3]))));
+            let descriptor_or_signature_index = try!(self.constant(ConstantPoolInfo::Utf8(unescape(words[4]))));
+            variables.push(LocalVariable {
+                start_pc: start_pc,
+                length: end_pc - start_pc,
+                name_index: name_index,
+                descriptor_or_signature_index: descriptor_or_signature_index,
+                index: try!(self.number(words[2]))
+            });
+        }
+        Result::Ok(variables)
+    }
+
+    fn stack_map_frame(&mut self, node: &Node, labels: Option<&Labels>) -> ParseResult<StackMapFrame> {
+        self.line = node.number;
+        let frame_type: u8 = match strip_prefix(node.text, "frame_type =") {
+            Option::Some(rest) => try!(self.number(split_word(rest).0)),
+            Option::None => { return self.error("expected frame_type"); }
+        };
+        let mut offset_delta = Option::None;
+        let mut locals = Vec::new();
+        let mut stack = Vec::new();
+        for line in &node.children {
+            self.line = line.number;
+            let (key, value) = match line.text.find('=') {
+                Option::Some(i) => (line.text[..i].trim(), line.text[i + 1..].trim()),
+                Option::None => { return self.error("expected offset_delta, locals or stack"); }
+            };
+            match key {
+                "offset_delta" => offset_delta = Option::Some(try!(self.number(value))),
+                "locals" => locals = try!(self.verification_types(value, labels)),
+                "stack" => stack = try!(self.verification_types(value, labels)),
+                _ => { return self.error(&format!("unknown frame entry {}", key)); }
+            }
+        }
+        self.line = node.number;
+        let offset_delta = match (frame_type, offset_delta) {
+            (0...127, _) => 0,
+            (_, Option::Some(offset_delta)) => offset_delta,
+            (_, Option::None) => { return self.error("missing offset_delta"); }
+        };
+        let single = |stack: Vec<VerificationType>| if stack.len() == 1 { stack.into_iter().next() } else { Option::None };
+        let frame = match frame_type {
+            0...63 => StackMapFrame::SameFrame { offset_delta: frame_type },
+            64...127 => match single(stack) {
+                Option::Some(stack) => StackMapFrame::SameLocals1StackItemFrame { offset_delta: frame_type - 64, stack: stack },
+                Option::None => { return self.error("expected one stack item"); }
+            },
+            247 => match single(stack) {
+                Option::Some(stack) => StackMapFrame::SameLocals1StackItemFrameExtended { offset_delta: offset_delta, stack: stack },
+                Option::None => { return self.error("expected one stack item"); }
+            },
+            248...250 => StackMapFrame::ChopFrame { offset_delta: offset_delta, chopped: 251 - frame_type },
+            251 => StackMapFrame::SameFrameExtended { offset_delta: offset_delta },
+            252...254 if locals.len() == frame_type as usize - 251 => {
+                StackMapFrame::AppendFrame { offset_delta: offset_delta, locals: locals }
+            },
+            252...254 => { return self.error(&format!("expected {} locals", frame_type - 251)); },
+            255 => StackMapFrame::FullFrame { offset_delta: offset_delta, locals: locals, stack: stack },
+            _ => { return self.error(&format!("reserved frame type {}", frame_type)); }
+        };
+        Result::Ok(frame)
+    }
+
+    fn verification_types(&mut self, text: &str, labels: Option<&Labels>) -> ParseResult<Vec<VerificationType>> {
+        let list = match strip_prefix(text, "[").and_then(|text| strip_suffix(text, "]")) {
+            Option::Some(list) => list,
+            Option::None => { return self.error(&format!("expected a list of types but found {}", text)); }
+        };
+        let mut types = Vec::new();
+        for item in list.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+            types.push(match item {
+                "top" => VerificationType::Top,
+                "int" => VerificationType::Integer,
+                "float" => VerificationType::Float,
+                "double" => VerificationType::Double,
+                "long" => VerificationType::Long,
+                "null" => VerificationType::Null,
+                "this" => VerificationType::UninitializedThis,
+                _ => match strip_prefix(item, "uninitialized ") {
+                    Option::Some(label) => VerificationType::UninitializedVariable { offset: try!(self.pc(labels, label)) },
+                    Option::None => VerificationType::Object { index: try!(self.constant_reference(item)) }
+                }
+            });
+        }
+        Result::Ok(types)
+    }
+
+    fn inner_class(&mut self, text: &str) -> ParseResult<InnerClass> {
+        let text = strip_comment(text).trim_right_matches(';');
+        let mut inner_class = InnerClass {
+            inner_class_info_index: 0,
+            outer_class_info_index: 0,
+            inner_name_index: 0,
+            inner_class_access_flags: 0
+        };
+        let mut words = text.split_whitespace();
+        while let Option::Some(word) = words.next() {
+            if word == "of" {
+                inner_class.outer_class_info_index = try!(self.index(words.next().unwrap_or("")));
+            } else if let Option::Some(index) = strip_suffix(word, "=") {
+                inner_class.inner_name_index = try!(self.index(index));
+            } else if word.starts_with('#') {
+                inner_class.inner_class_info_index = try!(self.index(word));
+            } else {
+                inner_class.inner_class_access_flags |= match modifier(word) {
+                    Option::Some(flag) => flag,
+                    Option::None => { return self.error(&format!("unknown modifier {}", word)); }
+                };
+            }
+        }
+        Result::Ok(inner_class)
+    }
+
+    // Annotations as `0: #12(#13=I#14)`; the resolved lines below are ignored.
+    fn annotations(&mut self, node: &Node) -> ParseResult<Vec<Annotation>> {
+        let mut annotations = Vec::with_capacity(node.children.len());
+        for line in &node.children {
+            self.line = line.number;
+            let mut cursor = Cursor { rest: split_key(line.text).1 };
+            match cursor.annotation() {
+                Option::Some(ref annotation) if cursor.rest.is_empty() => annotations.push(annotation.clone()),
+                _ => { return self.error(&format!("invalid annotation {}", line.text)); }
+            }
+        }
+        Result::Ok(annotations)
+    }
+
+    fn type_annotation(&mut self, text: &str, labels: Option<&Labels>) -> ParseResult<TypeAnnotation> {
+        let mut cursor = Cursor { rest: split_key(text).1 };
+        let annotation = match cursor.annotation() {
+            Option::Some(annotation) => annotation,
+            Option::None => { return self.error(&format!("invalid type annotation {}", text)); }
+        };
+        if !cursor.eat(':') {
+            return self.error(&format!("invalid type annotation {}", text));
+        }
+        let position = cursor.rest.trim();
+        let (target, mut rest) = match position.find(',') {
+            Option::Some(i) => (&position[..i], &position[i + 1..]),
+            Option::None => (position, "")
+        };
+        let mut values = HashMap::new();
+        let mut local_variables = Vec::new();
+        let mut path = Vec::new();
+        loop {
+            rest = rest.trim_left_matches(|c| c == ',' || c == ' ');
+            if rest.is_empty() {
+                break;
+            }
+            if let Option::Some(ranges) = strip_prefix(rest, "{") {
+                let end = ranges.find('}').unwrap_or(ranges.len());
+                for range in ranges[..end].split(';') {
+                    let mut fields = HashMap::new();
+                    for field in range.split(',') {
+                        let (key, value) = split_at_equals(field);
+                        fields.insert(key, try!(self.number::<u16>(value)));
+                    }
+                    match (fields.get("start_pc"), fields.get("length"), fields.get("index")) {
+                        (Option::Some(&start_pc), Option::Some(&length), Option::Some(&index)) => {
+                            let start = try!(self.offset(labels, start_pc));
+                            let end = try!(self.offset(labels, start_pc.saturating_add(length)));
+                            local_variables.push(LocalVariableTarget { start_pc: start, length: end - start, index: index });
+                        },
+                        _ => { return self.error(&format!("invalid local variable range {}", range)); }
+                    }
+                }
+                rest = ranges.get(end + 1..).unwrap_or("");
+            } else if let Option::Some(location) = strip_prefix(rest, "location=[") {
+                let end = location.find(']').unwrap_or(location.len());
+                for element in location[..end].split(',').map(str::trim).filter(|e| !e.is_empty()) {
+                    path.push(match element {
+                        "ARRAY" => PathElement { kind: TypePathKind::Array, argument_index: 0 },
+                        "INNER_TYPE" => PathElement { kind: TypePathKind::Nested, argument_index: 0 },
+                        "WILDCARD" => PathElement { kind: TypePathKind::WildcardBound, argument_index: 0 },
+                        _ => match strip_prefix(element, "TYPE_ARGUMENT(").and_then(|e| strip_suffix(e, ")")) {
+                            Option::Some(index) => PathElement {
+                                kind: TypePathKind::TypeArgument,
+                                argument_index: try!(self.number(index))
+                            },
+                            Option::None => { return self.error(&format!("unknown type path element {}", element)); }
+                        }
+                    });
+                }
+                rest = location.get(end + 1..).unwrap_or("");
+            } else {
+                let end = rest.find(',').unwrap_or(rest.len());
+                let (key, value) = split_at_equals(&rest[..end]);
+                values.insert(key, try!(self.number::<u16>(value)));
+                rest = &rest[end..];
+            }
+        }
+
+        let value = |key: &str| values.get(key).cloned().unwrap_or(0);
+        let (target_type, target_info) = match target {
+            "CLASS_TYPE_PARAMETER" => (TargetType::Type, TargetInfo::TypeParameter { index: value("param_index") as u8 }),
+            "METHOD_TYPE_PARAMETER" => (TargetType::Method, TargetInfo::TypeParameter { index: value("param_index") as u8 }),
+            "CLASS_EXTENDS" => (TargetType::Supertype, TargetInfo::Supertype { index: value("type_index") }),
+            "CLASS_TYPE_PARAMETER_BOUND" | "METHOD_TYPE_PARAMETER_BOUND" => {
+                let target_type = if target == "CLASS_TYPE_PARAMETER_BOUND" { TargetType::TypeBound } else { TargetType::MethodBound };
+                let info = TargetInfo::TypeParameterBound {
+                    index: value("param_index") as u8,
+                    bound_index: value("bound_index") as u8
+                };
+                (target_type, info)
+            },
+            "FIELD" => (TargetType::Field, TargetInfo::Empty),
+            "METHOD_RETURN" => (TargetType::MethodReturnType, TargetInfo::Empty),
+            "METHOD_RECEIVER" => (TargetType::ReceiverType, TargetInfo::Empty),
+            "METHOD_FORMAL_PARAMETER" => {
+                (TargetType::Parameter, TargetInfo::MethodFormalParameter { index: value("param_index") as u8 })
+            },
+            "THROWS" => (TargetType::Throws, TargetInfo::Throws { type_index: value("type_index") }),
+            "LOCAL_VARIABLE" => (TargetType::LocalVariableDeclaration, TargetInfo::Localvar(local_variables)),
+            "RESOURCE_VARIABLE" => (TargetType::ResourceVariableDeclaration, TargetInfo::Localvar(local_variables)),
+            "EXCEPTION_PARAMETER" => {
+                (TargetType::ExceptionParameterDeclaration, TargetInfo::Catch { exception_table_index: value("exception_index") })
+            },
+            "INSTANCEOF" | "NEW" | "CONSTRUCTOR_REFERENCE" | "METHOD_REFERENCE" => {
+                let target_type = match target {
+                    "INSTANCEOF" => TargetType::Instanceof,
+                    "NEW" => TargetType::New,
+                    "CONSTRUCTOR_REFERENCE" => TargetType::MethodReferenceNew,
+                    _ => TargetType::MethodReference
+                };
+                (target_type, TargetInfo::Offset(try!(self.offset(labels, value("offset")))))
+            },
+            "CAST" | "CONSTRUCTOR_INVOCATION_TYPE_ARGUMENT" | "METHOD_INVOCATION_TYPE_ARGUMENT"
+                    | "CONSTRUCTOR_REFERENCE_TYPE_ARGUMENT" | "METHOD_REFERENCE_TYPE_ARGUMENT" => {
+                let target_type = match target {
+                    "CAST" => TargetType::Cast,
+                    "CONSTRUCTOR_INVOCATION_TYPE_ARGUMENT" => TargetType::ConstructorArgument,
+                    "METHOD_INVOCATION_TYPE_ARGUMENT" => TargetType::MethodArgument,
+                    "CONSTRUCTOR_REFERENCE_TYPE_ARGUMENT" => TargetType::MethodReferenceNewArgument,
+                    _ => TargetType::MethodReferenceArgument
+                };
+                let info = TargetInfo::TypeArgument {
+                    offset: try!(self.offset(labels, value("offset"))),
+                    index: value("type_index") as u8
+                };
+                (target_type, info)
+            },
+            _ => { return self.error(&format!("unknown type annotation target {}", target)); }
+        };
+        Result::Ok(TypeAnnotation {
+            target_type: target_type,
+            target_info: target_info,
+            type_path: TypePath { path: path },
+            type_index: annotation.type_index,
+            element_value_pairs: annotation.element_value_pairs
+        })
+    }
+
+    // A row of `MethodParameters`, the name padded to 31 characters and then
+    // the flags.
+    fn method_parameter(&mut self, text: &str) -> ParseResult<MethodParameter> {
+        let split = text.char_indices().nth(31).map(|(i, _)| i).and_then(|i| {
+            if text[i..].split_whitespace().all(|word| word == "final" || word == "mandated" || word == "synthetic") {
+                Option::Some(i)
+            } else {
+                Option::None
+            }
+        });
+        let (name, flags) = match split {
+            Option::Some(i) => (text[..i].trim_right(), &text[i..]),
+            Option::None => {
+                // Short names leave the flags after the padding.
+                let mut name = text;
+                let mut flags = "";
+                while let Option::Some(i) = name.rfind(' ') {
+                    match &name[i + 1..] {
+                        "final" | "mandated" | "synthetic" => {
+                            flags = &text[i..];
+                            name = name[..i].trim_right();
+                        },
+                        _ => break
+                    }
+                }
+                (name, flags)
+            }
+        };
+        let mut access_flags = 0;
+        for word in flags.split_whitespace() {
+            access_flags |= match word {
+                "final" => 0x0010,
+                "synthetic" => 0x1000,
+                _ => 0x8000
+            };
+        }
+        let name_index = if name == "<no name>" {
+            0
+        } else {
+            try!(self.constant(ConstantPoolInfo::Utf8(unescape(name))))
+        };
+        Result::Ok(MethodParameter { name_index: name_index, access_flags: access_flags })
+    }
+
+    fn module(&mut self, node: &Node) -> ParseResult<Attribute> {
+        if node.children.len() != 7 {
+            return self.error("expected the module, its version and the counts of requires, exports, opens, uses and provides");
+        }
+        let sections = &node.children;
+        self.line = sections[0].number;
+        let (module_name_index, module_flags) = try!(self.index_and_flags(sections[0].text));
+        self.line = sections[1].number;
+        let module_version_index = try!(self.index(sections[1].text));
+
+        let mut requires = Vec::new();
+        for entry in sections[2].children.chunks(2) {
+            self.line = entry[0].number;
+            let (requires_index, requires_flags) = try!(self.index_and_flags(entry[0].text));
+            let requires_version_index = match entry.get(1) {
+                Option::Some(version) => {
+                    self.line = version.number;
+                    try!(self.index(version.text))
+                },
+                Option::None => { return self.error("missing version"); }
+            };
+            requires.push(ModuleRequires {
+                requires_index: requires_index,
+                requires_flags: requires_flags,
+                requires_version_index: requires_version_index
+            });
+        }
+        let mut exports = Vec::new();
+        for entry in &sections[3].children {
+            let (exports_index, exports_flags, exports_to_index) = try!(self.module_package(entry));
+            exports.push(ModuleExports { exports_index: exports_index, exports_flags: exports_flags, exports_to_index: exports_to_index });
+        }
+        let mut opens = Vec::new();
+        for entry in &sections[4].children {
+            let (opens_index, opens_flags, opens_to_index) = try!(self.module_package(entry));
+            opens.push(ModuleOpens { opens_index: opens_index, opens_flags: opens_flags, opens_to_index: opens_to_index });
+        }
+        let uses_index = try!(self.references(&sections[5]));
+        let mut provides = Vec::new();
+        for entry in &sections[6].children {
+            self.line = entry.number;
+            provides.push(ModuleProvides {
+                provides_index: try!(self.index(entry.text)),
+                provides_with_index: try!(self.references(entry))
+            });
+        }
+        Result::Ok(Attribute::Module {
+            module_name_index: module_name_index,
+            module_flags: module_flags,
+            module_version_index: module_version_index,
+            requires: requires,
+            exports: exports,
+            opens: opens,
+            uses_index: uses_index,
+            provides: provides
+        })
+    }
+
+    fn module_package(&mut self, node: &Node) -> ParseResult<(u16, u16, Vec<u16>)> {
+        self.line = node.number;
+        let (index, flags) = try!(self.index_and_flags(node.text));
+        Result::Ok((index, flags, try!(self.references(node))))
+    }
+
+    // `#n,flags` with the flags in hex.
+    fn index_and_flags(&self, text: &str) -> ParseResult<(u16, u16)> {
+        let text = strip_comment(text);
+        match text.find(',') {
+            Option::Some(i) => Result::Ok((try!(self.index(&text[..i])), try!(self.hex(&text[i + 1..])))),
+            Option::None => self.error(&format!("expected an index and flags but found {}", text))
+        }
+    }
+
+    fn references(&mut self, node: &Node) -> ParseResult<Vec<u16>> {
+        let mut indices = Vec::with_capacity(node.children.len());
+        for line in &node.children {
+            self.line = line.number;
+            indices.push(try!(self.constant_reference(line.text)));
+        }
+        Result::Ok(indices)
+    }
+
+    fn classes(&mut self, node: &Node) -> ParseResult<Vec<u16>> {
+        let mut classes = Vec::with_capacity(node.children.len());
+        for line in &node.children {
+            self.line = line.number;
+            classes.push(try!(self.class_reference(line.text)));
+        }
+        Result::Ok(classes)
+    }
+
+    fn record_component(&mut self, node: &Node) -> ParseResult<RecordComponent> {
+        self.line = node.number;
+        let name = node.text.trim_right_matches(';').rsplit(' ').next().unwrap_or("");
+        let name_index = try!(self.utf8(name));
+        let mut descriptor_index = Option::None;
+        let mut attribute_nodes = Vec::new();
+        for child in &node.children {
+            match strip_prefix(child.text, "descriptor:") {
+                Option::Some(descriptor) => descriptor_index = Option::Some(try!(self.utf8(descriptor.trim()))),
+                Option::None => attribute_nodes.push(child)
+            }
+        }
+        let descriptor_index = match descriptor_index {
+            Option::Some(descriptor_index) => descriptor_index,
+            Option::None => { return self.error("missing descriptor"); }
+        };
+        Result::Ok(RecordComponent {
+            name_index: name_index,
+            descriptor_index: descriptor_index,
+            attributes: try!(self.attributes(&attribute_nodes, Option::None))
+        })
+    }
+
+    // The contents of the module attributes that javap knows from the JDK's
+    // own tools, in the class file format.
+    fn jdk_attribute(&mut self, name: &str, node: &Node) -> ParseResult<Vec<u8>> {
+        let mut info = Vec::new();
+        let lines = node.descendants();
+        match name {
+            "ModuleTarget" | "ModuleResolution" => {
+                let line = match lines.first() {
+                    Option::Some(line) => line,
+                    Option::None => { return self.error(&format!("missing contents of {}", name)); }
+                };
+                self.line = line.number;
+                let value = match strip_prefix(line.text, "target_platform:") {
+                    Option::Some(index) => try!(self.index(index.trim())),
+                    Option::None => try!(self.hex(strip_comment(line.text)))
+                };
+                push_u16(&mut info, value);
+            },
+            _ => {
+                let mut lines = lines.into_iter();
+                for _ in 0..2 {
+                    let line = match lines.next() {
+                        Option::Some(line) => line,
+                        Option::None => { return self.error("missing algorithm and count of hashes"); }
+                    };
+                    self.line = line.number;
+                    let value = match strip_prefix(line.text, "algorithm:") {
+                        Option::Some(index) => try!(self.index(index.trim())),
+                        Option::None => try!(self.number(strip_comment(line.text)))
+                    };
+                    push_u16(&mut info, value);
+                }
+                while let Option::Some(module) = lines.next() {
+                    self.line = module.number;
+                    push_u16(&mut info, try!(self.index(module.text)));
+                    let hash = match (lines.next(), lines.next()) {
+                        (Option::Some(_), Option::Some(hash)) => hash,
+                        _ => { return self.error("missing hash"); }
+                    };
+                    self.line = hash.number;
+                    let hex = strip_prefix(hash.text, "hash: [").and_then(|hex| strip_suffix(hex, "]")).unwrap_or("");
+                    push_u16(&mut info, (hex.len() / 2) as u16);
+                    for i in (0..hex.len() / 2).map(|i| i * 2) {
+                        info.push(try!(self.hex(hex.get(i..i + 2).unwrap_or(""))) as u8);
+                    }
+                }
+            }
+        }
+        Result::Ok(info)
+    }
+}
+
+fn is_class_property(text: &str) -> bool {
+    ["minor version:", "major version:", "flags:", "this_class:", "super_class:", "interfaces:"].iter()
+        .any(|prefix| text.starts_with(prefix))
+}
+
+fn split_at_equals(text: &str) -> (&str, &str) {
+    match text.find('=') {
+        Option::Some(i) => (text[..i].trim(), text[i + 1..].trim()),
+        Option::None => (text.trim(), "")
+    }
+}
+
+fn push_u16(bytes: &mut Vec<u8>, value: u16) {
+    bytes.push((value >> 8) as u8);
+    bytes.push(value as u8);
+}
@@ -0,0 +1,288 @@
+use std::ops::Range;
+
+use ::model::{Exception, Instruction};
+use ::model::Instruction::*;
+use ::result::{ParseError, ParseResult};
+
+/// The basic blocks of a method's code and the edges between them.
+///
+/// Blocks are numbered in code order, so block 0 is the entry. A block starts
+/// at the first instruction, at every branch, switch and `jsr` target, at every
+/// exception handler and at the bounds of every protected range, and after
+/// every instruction that does not simply fall through.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ControlFlowGraph {
+    pub blocks: Vec<BasicBlock>
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BasicBlock {
+    pub start_pc: u32,
+    /// The indices of the block's instructions in the code.
+    pub instructions: Range<usize>,
+    pub successors: Vec<Edge>,
+    pub predecessors: Vec<Edge>
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Edge {
+    pub from: usize,
+    pub to: usize,
+    pub kind: EdgeKind
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EdgeKind {
+    /// To the next instruction, including when a conditional branch is not
+    /// taken.
+    FallThrough,
+    /// A `goto` or `goto_w`.
+    Jump,
+    /// A conditional branch that is taken.
+    Branch,
+    /// A case or the default of a `tableswitch` or `lookupswitch`, in that
+    /// order. Cases that go to the same block share an edge.
+    Switch,
+    /// A `jsr` or `jsr_w` to its subroutine.
+    Subroutine,
+    /// A `ret` to the instruction after a `jsr` that called its subroutine.
+    SubroutineReturn,
+    /// To the handler of the exception table entry with this index.
+    Exception(usize)
+}
+
+// What an instruction that ends a block does, with the indices of its
+// targets.
+enum Flow {
+    Next,
+    Branch(usize),
+    Jump(usize),
+    Switch(Vec<usize>),
+    Subroutine(usize),
+    Return,
+    Ret
+}
+
+impl ControlFlowGraph {
+    /// Builds the graph of the code and exception table of a `Code`
+    /// attribute. Branches must target the start of an instruction, and the
+    /// code must be in ascending order of pc as decoded.
+    ///
+    /// The subroutine of a `ret` is found by following the edges from each
+    /// `jsr` target, stepping over nested `jsr`s. A `ret` that is not reached
+    /// that way has no successors, and neither does falling off the end of the
+    /// code.
+    pub fn new(code: &[(u32, Instruction)], exception_table: &[Exception]) -> ParseResult<ControlFlowGraph> {
+        if code.is_empty() {
+            return Result::Ok(ControlFlowGraph { blocks: Vec::new() });
+        }
+        let index_of = |pc: u32| code.binary_search_by_key(&pc, |&(pc, _)| pc).ok();
+        let target = |pc: u32, target: u32| {
+            index_of(target).ok_or_else(|| {
+                let message = format!("branch at {} targets {} which is not the start of an instruction", pc, target);
+                ParseError::Decode(message)
+            })
+        };
+        // Protected ranges may end after the last instruction, at the end of
+        // the code.
+        let range_bound = |pc: u32, end: bool| {
+            match index_of(pc) {
+                Option::Some(index) => Result::Ok(index),
+                Option::None if end && pc > code[code.len() - 1].0 => Result::Ok(code.len()),
+                Option::None => {
+                    let message = format!("exception table refers to {} which is not the start of an instruction", pc);
+                    Result::Err(ParseError::Decode(message))
+                }
+            }
+        };
+
+        let mut leaders = vec![false; code.len() + 1];
+        leaders[0] = true;
+        let mut flows = Vec::with_capacity(code.len());
+        for (i, &(pc, ref instruction)) in code.iter().enumerate() {
+            let mut targets = Vec::new();
+            for to in try!(instruction.branch_targets(pc)) {
+                let to = try!(target(pc, to));
+                leaders[to] = true;
+                targets.push(to);
+            }
+            if instruction.ends_block() {
+                leaders[i + 1] = true;
+            }
+            flows.push(match *instruction {
+                goto(_) | goto_w(_) => Flow::Jump(targets[0]),
+                jsr(_) | jsr_w(_) => Flow::Subroutine(targets[0]),
+                tableswitch(_, _, _) | lookupswitch(_, _) => Flow::Switch(targets),
+                ret(_) | ret_w(_) => Flow::Ret,
+                _ if instruction.is_branch() => Flow::Branch(targets[0]),
+                _ if !instruction.falls_through() => Flow::Return,
+                _ => Flow::Next
+            });
+        }
+        let mut ranges = Vec::with_capacity(exception_table.len());
+        for exception in exception_table {
+            let start = try!(range_bound(exception.start_pc as u32, false));
+            let end = try!(range_bound(exception.end_pc as u32, true));
+            let handler = try!(range_bound(exception.handler_pc as u32, false));
+            leaders[start] = true;
+            leaders[end] = true;
+            leaders[handler] = true;
+            ranges.push((start, end, handler));
+        }
+
+        let mut blocks = Vec::new();
+        let mut block_of = Vec::with_capacity(code.len());
+        for i in 0..code.len() {
+            if leaders[i] {
+                blocks.push(BasicBlock {
+                    start_pc: code[i].0,
+                    instructions: i..i,
+                    successors: Vec::new(),
+                    predecessors: Vec::new()
+                });
+            }
+            let block = blocks.len() - 1;
+            blocks[block].instructions.end = i + 1;
+            block_of.push(block);
+        }
+
+        let mut edges = Vec::new();
+        let mut rets = Vec::new();
+        for (from, block) in blocks.iter().enumerate() {
+            let last = block.instructions.end - 1;
+            let mut edge = |to: usize, kind: EdgeKind| edges.push(Edge { from: from, to: block_of[to], kind: kind });
+            match flows[last] {
+                Flow::Next if last + 1 < code.len() => edge(last + 1, EdgeKind::FallThrough),
+                Flow::Next | Flow::Return => {},
+                Flow::Branch(to) => {
+                    if last + 1 < code.len() {
+                        edge(last + 1, EdgeKind::FallThrough);
+                    }
+                    edge(to, EdgeKind::Branch);
+                },
+                Flow::Jump(to) => edge(to, EdgeKind::Jump),
+                Flow::Subroutine(to) => edge(to, EdgeKind::Subroutine),
+                Flow::Switch(ref switch_targets) => {
+                    let mut targets: Vec<usize> = Vec::with_capacity(switch_targets.len());
+                    for &to in switch_targets {
+                        if !targets.iter().any(|&other| block_of[other] == block_of[to]) {
+                            targets.push(to);
+                        }
+                    }
+                    for to in targets {
+                        edge(to, EdgeKind::Switch);
+                    }
+                },
+                Flow::Ret => rets.push(from)
+            }
+        }
+        for (k, &(start, end, handler)) in ranges.iter().enumerate() {
+            for (from, block) in blocks.iter().enumerate() {
+                if start <= block.instructions.start && block.instructions.start < end {
+                    edges.push(Edge { from: from, to: block_of[handler], kind: EdgeKind::Exception(k) });
+                }
+            }
+        }
+
+        // Connect each ret to the return sites of the subroutines that reach
+        // it.
+        let mut subroutine_edges = Vec::new();
+        if !rets.is_empty() {
+            let mut successors = vec![Vec::new(); blocks.len()];
+            for edge in &edges {
+                successors[edge.from].push(*edge);
+            }
+            let mut calls: Vec<(usize, Vec<usize>)> = Vec::new();
+            for edge in edges.iter().filter(|edge| edge.kind == EdgeKind::Subroutine) {
+                let return_site = blocks[edge.from].instructions.end;
+                let entry = match calls.iter().position(|&(entry, _)| entry == edge.to) {
+                    Option::Some(entry) => entry,
+                    Option::None => {
+                        calls.push((edge.to, Vec::new()));
+                        calls.len() - 1
+                    }
+                };
+                if return_site < code.len() {
+                    calls[entry].1.push(block_of[return_site]);
+                }
+            }
+            for &(entry, ref return_sites) in &calls {
+                let mut visited = vec![false; blocks.len()];
+                let mut stack = vec![entry];
+                while let Option::Some(block) = stack.pop() {
+                    if visited[block] {
+                        continue;
+                    }
+                    visited[block] = true;
+                    let last = blocks[block].instructions.end - 1;
+                    for edge in &successors[block] {
+                        match (&flows[last], edge.kind) {
+                            (_, EdgeKind::Exception(_)) => stack.push(edge.to),
+                            (&Flow::Subroutine(_), _) => {},
+                            _ => stack.push(edge.to)
+                        }
+                    }
+                    match flows[last] {
+                        Flow::Subroutine(_) if last + 1 < code.len() => stack.push(block_of[last + 1]),
+                        Flow::Ret => {
+                            for &to in return_sites {
+                                let edge = Edge { from: block, to: to, kind: EdgeKind::SubroutineReturn };
+                                if !subroutine_edges.contains(&edge) {
+                                    subroutine_edges.push(edge);
+                                }
+                            }
+                        },
+                        _ => {}
+                    }
+                }
+            }
+        }
+        edges.extend(subroutine_edges);
+
+        for edge in edges {
+            blocks[edge.from].successors.push(edge);
+            blocks[edge.to].predecessors.push(edge);
+        }
+        Result::Ok(ControlFlowGraph { blocks: blocks })
+    }
+
+    /// The block containing the instruction at `pc`.
+    pub fn block_at(&self, pc: u32) -> Option<usize> {
+        match self.blocks.binary_search_by_key(&pc, |block| block.start_pc) {
+            Result::Ok(block) => Option::Some(block),
+            Result::Err(0) => Option::None,
+            Result::Err(block) => Option::Some(block - 1)
+        }
+    }
+
+    /// The blocks in reverse postorder of a depth-first search from the entry,
+    /// following all edges. Unreachable blocks are left out.
+    pub fn reverse_postorder(&self) -> Vec<usize> {
+        let mut order = Vec::with_capacity(self.blocks.len());
+        if self.blocks.is_empty() {
+            return order;
+        }
+        let mut visited = vec![false; self.blocks.len()];
+        let mut stack = vec![(0, 0)];
+        visited[0] = true;
+        while !stack.is_empty() {
+            let top = stack.len() - 1;
+            let (block, next) = stack[top];
+            match self.blocks[block].successors.get(next) {
+                Option::Some(edge) => {
+                    stack[top].1 += 1;
+                    if !visited[edge.to] {
+                        visited[edge.to] = true;
+                        stack.push((edge.to, 0));
+                    }
+                },
+                Option::None => {
+                    order.push(block);
+                    stack.pop();
+                }
+            }
+        }
+        order.reverse();
+        order
+    }
+}
@@ -0,0 +1,516 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use ::model::{Attribute, Exception, Instruction, LineNumber, LocalVariable, LocalVariableTarget, TargetInfo, TypeAnnotation};
+use ::model::Instruction::*;
+use ::opcode::Opcode;
+use ::result::{ParseError, ParseResult};
+
+/// A position in a `CodeBody`, before the instruction that follows it or at
+/// the end of the code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Label(u32);
+
+/// The code of a method with labels in place of offsets, so that instructions
+/// can be inserted and removed without breaking branches, exception ranges or
+/// debug information.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CodeBody {
+    pub max_stack: u16,
+    pub max_locals: u16,
+    pub elements: Vec<CodeElement>,
+    pub exception_table: Vec<LabeledException>,
+    pub line_numbers: Vec<LabeledLineNumber>,
+    pub local_variables: Vec<LabeledLocalVariable>,
+    pub local_variable_types: Vec<LabeledLocalVariable>,
+    /// The other attributes of the code. The offsets of type annotations move
+    /// along with the labels placed at them, and annotations whose labels are
+    /// no longer placed are dropped.
+    pub attributes: Vec<Attribute>,
+    // The StackMapTable is kept only while the code lowers to exactly what it
+    // was, since its offsets and frames do not survive editing;
+    // `compute_frames` computes a new one.
+    stack_map: Option<StackMap>,
+    // The label at each pc of the lifted code that something referred to.
+    original_labels: BTreeMap<u32, Label>,
+    next_label: u32
+}
+
+// A StackMapTable along with the code and exception table it describes.
+#[derive(Debug, Clone, PartialEq)]
+struct StackMap {
+    table: Attribute,
+    code: Vec<(u32, Instruction)>,
+    exception_table: Vec<Exception>
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CodeElement {
+    /// Marks the position of a label. A label is placed exactly once.
+    Label(Label),
+    /// Any instruction other than a branch or switch.
+    Instruction(Instruction),
+    /// A conditional branch, `goto`, `goto_w`, `jsr` or `jsr_w` to a label.
+    /// `goto` and `jsr` become `goto_w` and `jsr_w` when the label is too far
+    /// away for a 16 bit offset, and a conditional branch then becomes the
+    /// opposite branch over a `goto_w`.
+    Branch(Opcode, Label),
+    TableSwitch { default: Label, low: i32, targets: Vec<Label> },
+    LookupSwitch { default: Label, pairs: Vec<(i32, Label)> }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LabeledException {
+    pub start: Label,
+    pub end: Label,
+    pub handler: Label,
+    pub catch_type: u16
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LabeledLineNumber {
+    pub start: Label,
+    pub line_number: u16
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LabeledLocalVariable {
+    pub start: Label,
+    pub end: Label,
+    pub name_index: u16,
+    pub descriptor_or_signature_index: u16,
+    pub index: u16
+}
+
+impl CodeBody {
+    pub fn new(max_stack: u16, max_locals: u16) -> CodeBody {
+        CodeBody {
+            max_stack: max_stack,
+            max_locals: max_locals,
+            elements: Vec::new(),
+            exception_table: Vec::new(),
+            line_numbers: Vec::new(),
+            local_variables: Vec::new(),
+            local_variable_types: Vec::new(),
+            attributes: Vec::new(),
+            stack_map: Option::None,
+            original_labels: BTreeMap::new(),
+            next_label: 0
+        }
+    }
+
+    /// Lifts a `Code` attribute, placing a label at every pc that a branch,
+    /// switch, exception table entry, line number, local variable or type
+    /// annotation refers to. Labels are numbered in the order of their pcs.
+    pub fn from_attribute(attribute: &Attribute) -> ParseResult<CodeBody> {
+        let (max_stack, max_locals, code, exception_table, attributes) = match *attribute {
+            Attribute::Code { max_stack, max_locals, ref code, ref exception_table, ref attributes } => {
+                (max_stack, max_locals, code, exception_table, attributes)
+            },
+            ref other => return Result::Err(ParseError::Format(format!("expected a Code attribute but got {:?}", other)))
+        };
+        let end = code.last().map_or(0, |&(pc, ref instruction)| pc + instruction.length(pc));
+        let starts: BTreeSet<u32> = code.iter().map(|&(pc, _)| pc).collect();
+        // Ranges may end at the end of the code, but nothing may start there.
+        let check = |pc: u32, what: &str, may_end: bool| {
+            if starts.contains(&pc) || (may_end && pc == end) {
+                Result::Ok(pc)
+            } else {
+                Result::Err(ParseError::Decode(format!("{} refers to {} which is not the start of an instruction", what, pc)))
+            }
+        };
+
+        let mut pcs = BTreeSet::new();
+        for &(pc, ref instruction) in code {
+            for target in try!(instruction.branch_targets(pc)) {
+                pcs.insert(try!(check(target, &format!("branch at {}", pc), false)));
+            }
+        }
+        for exception in exception_table {
+            pcs.insert(try!(check(exception.start_pc as u32, "exception table", false)));
+            pcs.insert(try!(check(exception.end_pc as u32, "exception table", true)));
+            pcs.insert(try!(check(exception.handler_pc as u32, "exception table", false)));
+        }
+        for attribute in attributes {
+            match *attribute {
+                Attribute::LineNumberTable(ref line_numbers) => {
+                    for line_number in line_numbers {
+                        pcs.insert(try!(check(line_number.start_pc as u32, "line number table", false)));
+                    }
+                },
+                Attribute::LocalVariableTable(ref variables) | Attribute::LocalVariableTypeTable(ref variables) => {
+                    for variable in variables {
+                        pcs.insert(try!(check(variable.start_pc as u32, "local variable table", true)));
+                        pcs.insert(try!(check(variable.start_pc as u32 + variable.length as u32, "local variable table", true)));
+                    }
+                },
+                Attribute::RuntimeVisibleTypeAnnotations(ref annotations)
+                        | Attribute::RuntimeInvisibleTypeAnnotations(ref annotations) => {
+                    for annotation in annotations {
+                        match annotation.target_info {
+                            TargetInfo::Offset(offset) | TargetInfo::TypeArgument { offset, .. } => {
+                                pcs.insert(try!(check(offset as u32, "type annotation", false)));
+                            },
+                            TargetInfo::Localvar(ref targets) => {
+                                for target in targets {
+                                    pcs.insert(try!(check(target.start_pc as u32, "type annotation", true)));
+                                    pcs.insert(try!(check(target.start_pc as u32 + target.length as u32, "type annotation", true)));
+                                }
+                            },
+                            _ => {}
+                        }
+                    }
+                },
+                _ => {}
+            }
+        }
+
+        let mut body = CodeBody::new(max_stack, max_locals);
+        let labels: HashMap<u32, Label> = pcs.into_iter().map(|pc| (pc, body.new_label())).collect();
+        body.original_labels = labels.iter().map(|(&pc, &label)| (pc, label)).collect();
+        let label = |pc: u32| labels[&pc];
+        for &(pc, ref instruction) in code {
+            if let Option::Some(&label) = labels.get(&pc) {
+                body.elements.push(CodeElement::Label(label));
+            }
+            let targets = try!(instruction.branch_targets(pc));
+            let element = match *instruction {
+                tableswitch(_, low, _) => CodeElement::TableSwitch {
+                    default: label(targets[targets.len() - 1]),
+                    low: low,
+                    targets: targets[..targets.len() - 1].iter().map(|&target| label(target)).collect()
+                },
+                lookupswitch(_, ref pairs) => CodeElement::LookupSwitch {
+                    default: label(targets[targets.len() - 1]),
+                    pairs: pairs.iter().zip(targets.iter()).map(|(&(key, _), &target)| (key, label(target))).collect()
+                },
+                _ if instruction.is_branch() => CodeElement::Branch(instruction.opcode(), label(targets[0])),
+                _ => CodeElement::Instruction(instruction.clone())
+            };
+            body.elements.push(element);
+        }
+        if let Option::Some(&label) = labels.get(&end) {
+            body.elements.push(CodeElement::Label(label));
+        }
+
+        body.exception_table = exception_table.iter().map(|exception| LabeledException {
+            start: label(exception.start_pc as u32),
+            end: label(exception.end_pc as u32),
+            handler: label(exception.handler_pc as u32),
+            catch_type: exception.catch_type
+        }).collect();
+        let lift_variables = |variables: &[LocalVariable]| -> Vec<LabeledLocalVariable> {
+            variables.iter().map(|variable| LabeledLocalVariable {
+                start: label(variable.start_pc as u32),
+                end: label(variable.start_pc as u32 + variable.length as u32),
+                name_index: variable.name_index,
+                descriptor_or_signature_index: variable.descriptor_or_signature_index,
+                index: variable.index
+            }).collect()
+        };
+        for attribute in attributes {
+            match *attribute {
+                Attribute::LineNumberTable(ref line_numbers) => {
+                    body.line_numbers.extend(line_numbers.iter().map(|line_number| LabeledLineNumber {
+                        start: label(line_number.start_pc as u32),
+                        line_number: line_number.line_number
+                    }));
+                },
+                Attribute::LocalVariableTable(ref variables) => body.local_variables.extend(lift_variables(variables)),
+                Attribute::LocalVariableTypeTable(ref variables) => body.local_variable_types.extend(lift_variables(variables)),
+                Attribute::StackMapTable(_) => {
+                    body.stack_map = Option::Some(StackMap {
+                        table: attribute.clone(),
+                        code: code.clone(),
+                        exception_table: exception_table.clone()
+                    });
+                },
+                ref other => body.attributes.push(other.clone())
+            }
+        }
+        Result::Ok(body)
+    }
+
+    /// A label that is not yet placed.
+    pub fn new_label(&mut self) -> Label {
+        let label = Label(self.next_label);
+        self.next_label += 1;
+        label
+    }
+
+    /// The index of the element that places a label.
+    pub fn position(&self, label: Label) -> Option<usize> {
+        self.elements.iter().position(|element| *element == CodeElement::Label(label))
+    }
+
+    /// Lowers the body back to a `Code` attribute with offsets, widening the
+    /// branches whose labels are too far away. The debug tables come first
+    /// among the attributes and are left out when empty, followed by the
+    /// original `StackMapTable` if the code and exception table are unchanged.
+    pub fn to_attribute(&self) -> ParseResult<Attribute> {
+        let mut wide: Vec<bool> = self.elements.iter().map(|element| match *element {
+            CodeElement::Branch(Opcode::goto_w, _) | CodeElement::Branch(Opcode::jsr_w, _) => true,
+            _ => false
+        }).collect();
+        // Widening only makes the code longer, so this ends once every branch
+        // that is still narrow fits.
+        let (pcs, positions) = loop {
+            let (pcs, positions) = try!(self.layout(&wide));
+            let mut widened = false;
+            for (i, element) in self.elements.iter().enumerate() {
+                if let CodeElement::Branch(_, label) = *element {
+                    let offset = try!(position(&positions, label)) as i64 - pcs[i] as i64;
+                    if !wide[i] && (offset < i16::min_value() as i64 || offset > i16::max_value() as i64) {
+                        wide[i] = true;
+                        widened = true;
+                    }
+                }
+            }
+            if !widened {
+                break (pcs, positions);
+            }
+        };
+        let end = pcs[pcs.len() - 1];
+        if end > 65535 {
+            return Result::Err(ParseError::Encode(format!("code length {} exceeds 65535 bytes", end)));
+        }
+        let offset = |label: Label, pc: u32| position(&positions, label).map(|target| target as i64 - pc as i64);
+        let pc_of = |label: Label| position(&positions, label).map(|pc| pc as u16);
+
+        let mut code = Vec::with_capacity(self.elements.len());
+        for (i, element) in self.elements.iter().enumerate() {
+            let pc = pcs[i];
+            match *element {
+                CodeElement::Label(_) => {},
+                CodeElement::Instruction(ref instruction) => {
+                    if instruction.is_branch() {
+                        let message = format!("{} at {} must be a branch to a label", instruction.mnemonic(), pc);
+                        return Result::Err(ParseError::Encode(message));
+                    }
+                    code.push((pc, instruction.clone()));
+                },
+                CodeElement::Branch(opcode, label) => {
+                    let offset = try!(offset(label, pc));
+                    match opcode {
+                        Opcode::goto | Opcode::goto_w if wide[i] => code.push((pc, goto_w(offset as i32))),
+                        Opcode::jsr | Opcode::jsr_w if wide[i] => code.push((pc, jsr_w(offset as i32))),
+                        _ if wide[i] => {
+                            code.push((pc, try!(branch(opposite(opcode), 8))));
+                            code.push((pc + 3, goto_w((offset - 3) as i32)));
+                        },
+                        _ => code.push((pc, try!(branch(opcode, offset as i16))))
+                    }
+                },
+                CodeElement::TableSwitch { default, low, ref targets } => {
+                    let mut offsets = Vec::with_capacity(targets.len());
+                    for &target in targets {
+                        offsets.push(try!(offset(target, pc)) as i32);
+                    }
+                    code.push((pc, tableswitch(try!(offset(default, pc)) as i32, low, offsets.into_boxed_slice())));
+                },
+                CodeElement::LookupSwitch { default, ref pairs } => {
+                    let mut offsets = Vec::with_capacity(pairs.len());
+                    for &(key, target) in pairs {
+                        offsets.push((key, try!(offset(target, pc)) as i32));
+                    }
+                    code.push((pc, lookupswitch(try!(offset(default, pc)) as i32, offsets.into_boxed_slice())));
+                }
+            }
+        }
+
+        let mut exception_table = Vec::with_capacity(self.exception_table.len());
+        for exception in &self.exception_table {
+            exception_table.push(Exception {
+                start_pc: try!(pc_of(exception.start)),
+                end_pc: try!(pc_of(exception.end)),
+                handler_pc: try!(pc_of(exception.handler)),
+                catch_type: exception.catch_type
+            });
+        }
+        let lower_variables = |variables: &[LabeledLocalVariable]| -> ParseResult<Vec<LocalVariable>> {
+            let mut lowered = Vec::with_capacity(variables.len());
+            for variable in variables {
+                let start = try!(pc_of(variable.start));
+                let end = try!(pc_of(variable.end));
+                if end < start {
+                    let message = format!("local variable {} ends at {} before its start at {}", variable.index, end, start);
+                    return Result::Err(ParseError::Encode(message));
+                }
+                lowered.push(LocalVariable {
+                    start_pc: start,
+                    length: end - start,
+                    name_index: variable.name_index,
+                    descriptor_or_signature_index: variable.descriptor_or_signature_index,
+                    index: variable.index
+                });
+            }
+            Result::Ok(lowered)
+        };
+        let mut attributes = Vec::with_capacity(self.attributes.len() + 3);
+        if !self.line_numbers.is_empty() {
+            let mut line_numbers = Vec::with_capacity(self.line_numbers.len());
+            for line_number in &self.line_numbers {
+                line_numbers.push(LineNumber { start_pc: try!(pc_of(line_number.start)), line_number: line_number.line_number });
+            }
+            attributes.push(Attribute::LineNumberTable(line_numbers));
+        }
+        if !self.local_variables.is_empty() {
+            attributes.push(Attribute::LocalVariableTable(try!(lower_variables(&self.local_variables))));
+        }
+        if !self.local_variable_types.is_empty() {
+            attributes.push(Attribute::LocalVariableTypeTable(try!(lower_variables(&self.local_variable_types))));
+        }
+        if let Option::Some(ref stack_map) = self.stack_map {
+            if stack_map.code == code && stack_map.exception_table == exception_table {
+                attributes.push(stack_map.table.clone());
+            }
+        }
+        for attribute in &self.attributes {
+            match *attribute {
+                Attribute::RuntimeVisibleTypeAnnotations(ref annotations) => {
+                    let annotations = self.relocate_type_annotations(annotations, &positions, exception_table.len());
+                    if !annotations.is_empty() {
+                        attributes.push(Attribute::RuntimeVisibleTypeAnnotations(annotations));
+                    }
+                },
+                Attribute::RuntimeInvisibleTypeAnnotations(ref annotations) => {
+                    let annotations = self.relocate_type_annotations(annotations, &positions, exception_table.len());
+                    if !annotations.is_empty() {
+                        attributes.push(Attribute::RuntimeInvisibleTypeAnnotations(annotations));
+                    }
+                },
+                ref other => attributes.push(other.clone())
+            }
+        }
+
+        Result::Ok(Attribute::Code {
+            max_stack: self.max_stack,
+            max_locals: self.max_locals,
+            code: code,
+            exception_table: exception_table,
+            attributes: attributes
+        })
+    }
+
+    // Moves the offsets of type annotations to the new pcs of the labels that
+    // were placed at them. An annotation is dropped if one of its labels is
+    // no longer placed, or if its exception table entry is gone.
+    fn relocate_type_annotations(&self, annotations: &[TypeAnnotation], positions: &BTreeMap<Label, u32>,
+            exception_count: usize) -> Vec<TypeAnnotation> {
+        let pc = |offset: u32| {
+            self.original_labels.get(&offset).and_then(|label| positions.get(label)).map(|&pc| pc as u16)
+        };
+        let mut relocated = Vec::with_capacity(annotations.len());
+        for annotation in annotations {
+            let target_info = match annotation.target_info {
+                TargetInfo::Offset(offset) => pc(offset as u32).map(TargetInfo::Offset),
+                TargetInfo::TypeArgument { offset, index } => {
+                    pc(offset as u32).map(|offset| TargetInfo::TypeArgument { offset: offset, index: index })
+                },
+                TargetInfo::Localvar(ref targets) => {
+                    let mut moved = Vec::with_capacity(targets.len());
+                    for target in targets {
+                        match (pc(target.start_pc as u32), pc(target.start_pc as u32 + target.length as u32)) {
+                            (Option::Some(start), Option::Some(end)) if start <= end => {
+                                moved.push(LocalVariableTarget { start_pc: start, length: end - start, index: target.index });
+                            },
+                            _ => {}
+                        }
+                    }
+                    if moved.len() == targets.len() { Option::Some(TargetInfo::Localvar(moved)) } else { Option::None }
+                },
+                TargetInfo::Catch { exception_table_index } if exception_table_index as usize >= exception_count => Option::None,
+                ref other => Option::Some(other.clone())
+            };
+            if let Option::Some(target_info) = target_info {
+                relocated.push(TypeAnnotation { target_info: target_info, ..annotation.clone() });
+            }
+        }
+        relocated
+    }
+
+    // The pc of every element, followed by the length of the code, and the
+    // pc of every label, given which branches are wide.
+    fn layout(&self, wide: &[bool]) -> ParseResult<(Vec<u32>, BTreeMap<Label, u32>)> {
+        let mut pcs = Vec::with_capacity(self.elements.len() + 1);
+        let mut positions = BTreeMap::new();
+        let mut pc = 0u32;
+        for (i, element) in self.elements.iter().enumerate() {
+            pcs.push(pc);
+            let padding = 3 - pc % 4;
+            pc += match *element {
+                CodeElement::Label(label) => {
+                    if positions.insert(label, pc).is_some() {
+                        return Result::Err(ParseError::Encode(format!("{:?} is placed more than once", label)));
+                    }
+                    0
+                },
+                CodeElement::Instruction(ref instruction) => instruction.length(pc),
+                CodeElement::Branch(Opcode::goto, _) | CodeElement::Branch(Opcode::goto_w, _)
+                        | CodeElement::Branch(Opcode::jsr, _) | CodeElement::Branch(Opcode::jsr_w, _) if wide[i] => 5,
+                CodeElement::Branch(_, _) if wide[i] => 8,
+                CodeElement::Branch(_, _) => 3,
+                CodeElement::TableSwitch { ref targets, .. } => 13 + padding + 4 * targets.len() as u32,
+                CodeElement::LookupSwitch { ref pairs, .. } => 9 + padding + 8 * pairs.len() as u32
+            };
+        }
+        pcs.push(pc);
+        Result::Ok((pcs, positions))
+    }
+}
+
+fn position(positions: &BTreeMap<Label, u32>, label: Label) -> ParseResult<u32> {
+    match positions.get(&label) {
+        Option::Some(&pc) => Result::Ok(pc),
+        Option::None => Result::Err(ParseError::Encode(format!("{:?} is not placed", label)))
+    }
+}
+
+// The instruction of a narrow branch with an opcode.
+fn branch(opcode: Opcode, offset: i16) -> ParseResult<Instruction> {
+    let instruction = match opcode {
+        Opcode::ifeq => ifeq(offset),
+        Opcode::ifne => ifne(offset),
+        Opcode::iflt => iflt(offset),
+        Opcode::ifge => ifge(offset),
+        Opcode::ifgt => ifgt(offset),
+        Opcode::ifle => ifle(offset),
+        Opcode::if_icmpeq => if_icmpeq(offset),
+        Opcode::if_icmpne => if_icmpne(offset),
+        Opcode::if_icmplt => if_icmplt(offset),
+        Opcode::if_icmpge => if_icmpge(offset),
+        Opcode::if_icmpgt => if_icmpgt(offset),
+        Opcode::if_icmple => if_icmple(offset),
+        Opcode::if_acmpeq => if_acmpeq(offset),
+        Opcode::if_acmpne => if_acmpne(offset),
+        Opcode::ifnull => ifnull(offset),
+        Opcode::ifnonnull => ifnonnull(offset),
+        Opcode::goto => goto(offset),
+        Opcode::jsr => jsr(offset),
+        _ => return Result::Err(ParseError::Encode(format!("{} is not a branch to a label", opcode)))
+    };
+    Result::Ok(instruction)
+}
+
+// The conditional branch taken exactly when one with this opcode is not, or
+// the opcode itself for anything else.
+fn opposite(opcode: Opcode) -> Opcode {
+    match opcode {
+        Opcode::ifeq => Opcode::ifne,
+        Opcode::ifne => Opcode::ifeq,
+        Opcode::iflt => Opcode::ifge,
+        Opcode::ifge => Opcode::iflt,
+        Opcode::ifgt => Opcode::ifle,
+        Opcode::ifle => Opcode::ifgt,
+        Opcode::if_icmpeq => Opcode::if_icmpne,
+        Opcode::if_icmpne => Opcode::if_icmpeq,
+        Opcode::if_icmplt => Opcode::if_icmpge,
+        Opcode::if_icmpge => Opcode::if_icmplt,
+        Opcode::if_icmpgt => Opcode::if_icmple,
+        Opcode::if_icmple => Opcode::if_icmpgt,
+        Opcode::if_acmpeq => Opcode::if_acmpne,
+        Opcode::if_acmpne => Opcode::if_acmpeq,
+        Opcode::ifnull => Opcode::ifnonnull,
+        Opcode::ifnonnull => Opcode::ifnull,
+        other => other
+    }
+}
@@ -0,0 +1,312 @@
+use std::collections::BTreeSet;
+
+use ::cfg::{ControlFlowGraph, EdgeKind};
+use ::model::{Exception, Instruction};
+use ::model::Instruction::*;
+use ::result::ParseResult;
+
+/// The values of a data-flow analysis, ordered so that joining two values
+/// gives one at least as large as both.
+pub trait Lattice: Clone + PartialEq {
+    /// Joins another value into this one, returning whether it changed.
+    fn join(&mut self, other: &Self) -> bool;
+}
+
+/// Sets join by union.
+impl<T: Ord + Clone> Lattice for BTreeSet<T> {
+    fn join(&mut self, other: &Self) -> bool {
+        let length = self.len();
+        self.extend(other.iter().cloned());
+        self.len() != length
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    /// Values flow from the entry along the edges of the code.
+    Forward,
+    /// Values flow from the exits against the edges of the code.
+    Backward
+}
+
+/// A data-flow problem over the instructions of a method's code.
+pub trait DataFlowAnalysis {
+    type Value: Lattice;
+
+    fn direction(&self) -> Direction;
+
+    /// The value on entry to the method for a forward analysis, or after
+    /// every instruction that leaves it for a backward one.
+    fn boundary(&self) -> Self::Value;
+
+    /// The least value, which instructions that are never reached keep.
+    fn bottom(&self) -> Self::Value;
+
+    /// The value after an instruction given the value before it, or the
+    /// value before it given the value after it for a backward analysis.
+    fn transfer(&self, pc: u32, instruction: &Instruction, value: &Self::Value) -> Self::Value;
+
+    /// The value passed to an exception handler from an instruction it
+    /// protects, given the value before the instruction in either direction,
+    /// since an instruction that throws has no other effect. It is the value
+    /// itself by default.
+    fn exception(&self, exception: &Exception, value: &Self::Value) -> Self::Value {
+        let _ = exception;
+        value.clone()
+    }
+}
+
+/// The solution of a data-flow analysis: the values before and after every
+/// instruction, indexed like the code.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataFlow<V> {
+    pub before: Vec<V>,
+    pub after: Vec<V>
+}
+
+impl<V: Lattice> DataFlow<V> {
+    /// Solves an analysis over the code and exception table of a `Code`
+    /// attribute by iterating until no value changes.
+    ///
+    /// Instructions go to the same places as in the `ControlFlowGraph`: a
+    /// `jsr` goes to its subroutine and a `ret` to the instructions after the
+    /// `jsr`s that reach it. Every instruction in a protected range goes to
+    /// its handler.
+    pub fn new<A>(analysis: &A, code: &[(u32, Instruction)], exception_table: &[Exception]) -> ParseResult<DataFlow<V>>
+            where A: DataFlowAnalysis<Value = V> {
+        let graph = try!(ControlFlowGraph::new(code, exception_table));
+        let count = code.len();
+        let mut successors = vec![Vec::new(); count];
+        let mut handlers = vec![Vec::new(); count];
+        for block in &graph.blocks {
+            let last = block.instructions.end - 1;
+            for i in block.instructions.clone() {
+                if i < last {
+                    successors[i].push(i + 1);
+                }
+            }
+            for edge in &block.successors {
+                let to = graph.blocks[edge.to].instructions.start;
+                match edge.kind {
+                    EdgeKind::Exception(k) => {
+                        for i in block.instructions.clone() {
+                            handlers[i].push((to, k));
+                        }
+                    },
+                    _ => successors[last].push(to)
+                }
+            }
+        }
+
+        let mut before = vec![analysis.bottom(); count];
+        let mut after = vec![analysis.bottom(); count];
+        let mut visited = vec![false; count];
+        match analysis.direction() {
+            Direction::Forward => {
+                let mut work = Vec::new();
+                if count > 0 {
+                    before[0].join(&analysis.boundary());
+                    work.push(0);
+                }
+                while let Option::Some(i) = work.pop() {
+                    visited[i] = true;
+                    after[i] = analysis.transfer(code[i].0, &code[i].1, &before[i]);
+                    for &successor in &successors[i] {
+                        if before[successor].join(&after[i]) || !visited[successor] {
+                            work.push(successor);
+                        }
+                    }
+                    for &(handler, k) in &handlers[i] {
+                        let value = analysis.exception(&exception_table[k], &before[i]);
+                        if before[handler].join(&value) || !visited[handler] {
+                            work.push(handler);
+                        }
+                    }
+                }
+            },
+            Direction::Backward => {
+                let mut predecessors = vec![Vec::new(); count];
+                let mut protected = vec![Vec::new(); count];
+                for i in 0..count {
+                    for &successor in &successors[i] {
+                        predecessors[successor].push(i);
+                    }
+                    for &(handler, _) in &handlers[i] {
+                        protected[handler].push(i);
+                    }
+                    if successors[i].is_empty() {
+                        after[i].join(&analysis.boundary());
+                    }
+                }
+                let mut work: Vec<usize> = (0..count).collect();
+                while let Option::Some(i) = work.pop() {
+                    let mut value = analysis.transfer(code[i].0, &code[i].1, &after[i]);
+                    for &(handler, k) in &handlers[i] {
+                        value.join(&analysis.exception(&exception_table[k], &before[handler]));
+                    }
+                    if !before[i].join(&value) && visited[i] {
+                        continue;
+                    }
+                    visited[i] = true;
+                    for &predecessor in &predecessors[i] {
+                        if after[predecessor].join(&before[i]) || !visited[predecessor] {
+                            work.push(predecessor);
+                        }
+                    }
+                    work.extend(protected[i].iter().cloned());
+                }
+            }
+        }
+        Result::Ok(DataFlow { before: before, after: after })
+    }
+}
+
+/// A local variable that an instruction reads or writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LocalAccess {
+    pub index: u16,
+    /// 2 for longs and doubles, which take two slots.
+    pub size: u16,
+    pub reads: bool,
+    pub writes: bool
+}
+
+impl Instruction {
+    /// The local variable the instruction loads, stores, increments or
+    /// returns to the address in, if any.
+    pub fn local_access(&self) -> Option<LocalAccess> {
+        let access = |index: u16, size: u16, reads: bool, writes: bool| {
+            Option::Some(LocalAccess { index: index, size: size, reads: reads, writes: writes })
+        };
+        match *self {
+            iload(index) | fload(index) | aload(index) | ret(index) => access(index as u16, 1, true, false),
+            lload(index) | dload(index) => access(index as u16, 2, true, false),
+            iload_w(index) | fload_w(index) | aload_w(index) | ret_w(index) => access(index, 1, true, false),
+            lload_w(index) | dload_w(index) => access(index, 2, true, false),
+            iload_0 | fload_0 | aload_0 => access(0, 1, true, false),
+            iload_1 | fload_1 | aload_1 => access(1, 1, true, false),
+            iload_2 | fload_2 | aload_2 => access(2, 1, true, false),
+            iload_3 | fload_3 | aload_3 => access(3, 1, true, false),
+            lload_0 | dload_0 => access(0, 2, true, false),
+            lload_1 | dload_1 => access(1, 2, true, false),
+            lload_2 | dload_2 => access(2, 2, true, false),
+            lload_3 | dload_3 => access(3, 2, true, false),
+            istore(index) | fstore(index) | astore(index) => access(index as u16, 1, false, true),
+            lstore(index) | dstore(index) => access(index as u16, 2, false, true),
+            istore_w(index) | fstore_w(index) | astore_w(index) => access(index, 1, false, true),
+            lstore_w(index) | dstore_w(index) => access(index, 2, false, true),
+            istore_0 | fstore_0 | astore_0 => access(0, 1, false, true),
+            istore_1 | fstore_1 | astore_1 => access(1, 1, false, true),
+            istore_2 | fstore_2 | astore_2 => access(2, 1, false, true),
+            istore_3 | fstore_3 | astore_3 => access(3, 1, false, true),
+            lstore_0 | dstore_0 => access(0, 2, false, true),
+            lstore_1 | dstore_1 => access(1, 2, false, true),
+            lstore_2 | dstore_2 => access(2, 2, false, true),
+            lstore_3 | dstore_3 => access(3, 2, false, true),
+            iinc(index, _) => access(index as u16, 1, true, true),
+            iinc_w(index, _) => access(index, 1, true, true),
+            _ => Option::None
+        }
+    }
+}
+
+// The slots of the local variable, which may run past the last one in
+// invalid code.
+fn slots(access: &LocalAccess) -> Vec<u16> {
+    (access.index as u32..access.index as u32 + access.size as u32).filter(|&slot| slot <= 0xffff).map(|slot| slot as u16).collect()
+}
+
+/// The local variable slots whose values may still be read, by slot index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LiveLocals;
+
+impl DataFlowAnalysis for LiveLocals {
+    type Value = BTreeSet<u16>;
+
+    fn direction(&self) -> Direction {
+        Direction::Backward
+    }
+
+    fn boundary(&self) -> BTreeSet<u16> {
+        BTreeSet::new()
+    }
+
+    fn bottom(&self) -> BTreeSet<u16> {
+        BTreeSet::new()
+    }
+
+    fn transfer(&self, _: u32, instruction: &Instruction, live: &BTreeSet<u16>) -> BTreeSet<u16> {
+        let mut live = live.clone();
+        if let Option::Some(access) = instruction.local_access() {
+            if access.reads {
+                live.extend(slots(&access));
+            } else {
+                for slot in slots(&access) {
+                    live.remove(&slot);
+                }
+            }
+        }
+        live
+    }
+}
+
+/// A value stored in a local variable slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Definition {
+    pub local: u16,
+    /// The pc of the instruction that stores the value, or none for `this`
+    /// and the parameters.
+    pub pc: Option<u32>
+}
+
+/// The values stored in local variable slots that may still be there, as the
+/// set of their definitions.
+///
+/// Storing over either half of a long or double stored in the code kills both
+/// halves. The parameters are not typed, so each of their slots is a separate
+/// definition: after `istore_1` over a long parameter in slots 1 and 2, the
+/// parameter definition of slot 2 still reaches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReachingDefinitions {
+    /// The number of slots that hold `this` and the parameters on entry.
+    pub parameter_slots: u16
+}
+
+impl DataFlowAnalysis for ReachingDefinitions {
+    type Value = BTreeSet<Definition>;
+
+    fn direction(&self) -> Direction {
+        Direction::Forward
+    }
+
+    fn boundary(&self) -> BTreeSet<Definition> {
+        (0..self.parameter_slots).map(|local| Definition { local: local, pc: Option::None }).collect()
+    }
+
+    fn bottom(&self) -> BTreeSet<Definition> {
+        BTreeSet::new()
+    }
+
+    fn transfer(&self, pc: u32, instruction: &Instruction, definitions: &BTreeSet<Definition>) -> BTreeSet<Definition> {
+        match instruction.local_access() {
+            Option::Some(ref access) if access.writes => {
+                let slots = slots(access);
+                // A long or double defines two slots at the same pc, and
+                // overwriting either half destroys the whole value.
+                let overwritten: BTreeSet<u32> = definitions.iter()
+                    .filter(|definition| slots.contains(&definition.local))
+                    .filter_map(|definition| definition.pc)
+                    .collect();
+                let mut definitions: BTreeSet<Definition> = definitions.iter()
+                    .filter(|definition| {
+                        !slots.contains(&definition.local) && !definition.pc.map_or(false, |pc| overwritten.contains(&pc))
+                    })
+                    .cloned()
+                    .collect();
+                definitions.extend(slots.iter().map(|&local| Definition { local: local, pc: Option::Some(pc) }));
+                definitions
+            },
+            _ => definitions.clone()
+        }
+    }
+}
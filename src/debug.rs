@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+
+use ::descriptor::FieldType;
+use ::model::{Attribute, Method};
+use ::pool::ConstantPool;
+use ::result::{ParseError, ParseResult};
+use ::signature::ReferenceTypeSignature;
+
+/// The line numbers and local variables of a `Code` attribute, for looking up
+/// the line of a pc or the variable in a slot.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DebugInfo {
+    // The start pc and line of every line number table entry, ordered by pc.
+    // Where several entries start at the same pc, only the first counts, as
+    // in the JVM.
+    lines: Vec<(u32, u16)>,
+    // The pc after the last instruction.
+    end: u32,
+    variables: Vec<Variable>
+}
+
+/// An entry of the `LocalVariableTable` along with the generic signature that
+/// the `LocalVariableTypeTable` gives for the same range and slot.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variable {
+    pub name: String,
+    pub slot: u16,
+    pub start_pc: u32,
+    /// The pc after the last one at which the variable has a value.
+    pub end_pc: u32,
+    pub descriptor: String,
+    pub signature: Option<String>
+}
+
+impl Variable {
+    pub fn field_type(&self) -> ParseResult<FieldType> {
+        FieldType::parse(&self.descriptor)
+    }
+
+    pub fn type_signature(&self) -> ParseResult<Option<ReferenceTypeSignature>> {
+        match self.signature {
+            Option::Some(ref signature) => ReferenceTypeSignature::parse(signature).map(Option::Some),
+            Option::None => Result::Ok(Option::None)
+        }
+    }
+}
+
+impl DebugInfo {
+    /// Collects the `LineNumberTable`, `LocalVariableTable` and
+    /// `LocalVariableTypeTable` attributes of a `Code` attribute. Entries of
+    /// the type table without a matching entry in the variable table are
+    /// ignored.
+    pub fn from_attribute(attribute: &Attribute, constant_pool: &ConstantPool) -> ParseResult<DebugInfo> {
+        let (code, attributes) = match *attribute {
+            Attribute::Code { ref code, ref attributes, .. } => (code, attributes),
+            ref other => return Result::Err(ParseError::Format(format!("expected a Code attribute but got {:?}", other)))
+        };
+        let mut lines = Vec::new();
+        let mut variables = Vec::new();
+        let mut signatures = HashMap::new();
+        for attribute in attributes {
+            match *attribute {
+                Attribute::LineNumberTable(ref line_numbers) => {
+                    lines.extend(line_numbers.iter().map(|line_number| (line_number.start_pc as u32, line_number.line_number)));
+                },
+                Attribute::LocalVariableTable(ref entries) => {
+                    for entry in entries {
+                        variables.push(Variable {
+                            name: try!(constant_pool.utf8_string(entry.name_index)),
+                            slot: entry.index,
+                            start_pc: entry.start_pc as u32,
+                            end_pc: entry.start_pc as u32 + entry.length as u32,
+                            descriptor: try!(constant_pool.utf8_string(entry.descriptor_or_signature_index)),
+                            signature: Option::None
+                        });
+                    }
+                },
+                Attribute::LocalVariableTypeTable(ref entries) => {
+                    for entry in entries {
+                        let signature = try!(constant_pool.utf8_string(entry.descriptor_or_signature_index));
+                        signatures.insert((entry.start_pc, entry.length, entry.index), signature);
+                    }
+                },
+                _ => {}
+            }
+        }
+        // The sort is stable, so the first of several entries at a pc stays
+        // in front.
+        lines.sort_by_key(|&(pc, _)| pc);
+        lines.dedup_by_key(|&mut (pc, _)| pc);
+        for variable in &mut variables {
+            let key = (variable.start_pc as u16, (variable.end_pc - variable.start_pc) as u16, variable.slot);
+            variable.signature = signatures.get(&key).cloned();
+        }
+        Result::Ok(DebugInfo {
+            lines: lines,
+            end: code.last().map_or(0, |&(pc, ref instruction)| pc + instruction.length(pc)),
+            variables: variables
+        })
+    }
+
+    /// The source line of the instruction at a pc: the line of the last entry
+    /// of the line number table that starts at or before it.
+    pub fn line_for_pc(&self, pc: u32) -> Option<u16> {
+        if pc >= self.end {
+            return Option::None;
+        }
+        match self.lines.binary_search_by_key(&pc, |&(start_pc, _)| start_pc) {
+            Result::Ok(index) => Option::Some(self.lines[index].1),
+            Result::Err(0) => Option::None,
+            Result::Err(index) => Option::Some(self.lines[index - 1].1)
+        }
+    }
+
+    /// The pcs at which the code of a line starts, in order. A line may have
+    /// several, such as the condition of a loop that javac moves to the end.
+    pub fn pcs_for_line(&self, line: u16) -> Vec<u32> {
+        self.lines.iter().filter(|&&(_, number)| number == line).map(|&(pc, _)| pc).collect()
+    }
+
+    /// The variable that a slot holds at a pc, if the tables name one.
+    pub fn variable_at(&self, slot: u16, pc: u32) -> Option<&Variable> {
+        self.variables.iter().find(|variable| variable.slot == slot && variable.start_pc <= pc && pc < variable.end_pc)
+    }
+
+    /// All variables, in the order of the `LocalVariableTable`.
+    pub fn variables(&self) -> &[Variable] {
+        &self.variables
+    }
+}
+
+impl Method {
+    /// The name of each parameter in the descriptor. Names come from the
+    /// `MethodParameters` attribute if it has one entry per parameter, and
+    /// otherwise, or where an entry has no name, from the variable in the
+    /// parameter's slot at pc 0. A parameter without either is `None`.
+    pub fn parameter_names(&self, constant_pool: &ConstantPool) -> ParseResult<Vec<Option<String>>> {
+        let descriptor = try!(self.method_descriptor(constant_pool));
+        let mut debug_info = Option::None;
+        let mut parameters = Option::None;
+        for attribute in &self.attributes {
+            match *attribute {
+                Attribute::Code { .. } => debug_info = Option::Some(try!(DebugInfo::from_attribute(attribute, constant_pool))),
+                Attribute::MethodParameters(ref entries) if entries.len() == descriptor.parameters.len() => parameters = Option::Some(entries),
+                _ => {}
+            }
+        }
+        let mut names = Vec::with_capacity(descriptor.parameters.len());
+        let mut slot = if self.is_static() { 0 } else { 1 };
+        for (i, parameter) in descriptor.parameters.iter().enumerate() {
+            let name = match parameters {
+                Option::Some(entries) => try!(constant_pool.optional_utf8(entries[i].name_index)),
+                Option::None => Option::None
+            };
+            names.push(name.or_else(|| {
+                debug_info.as_ref().and_then(|debug_info| debug_info.variable_at(slot, 0)).map(|variable| variable.name.clone())
+            }));
+            slot += parameter.size();
+        }
+        Result::Ok(names)
+    }
+}
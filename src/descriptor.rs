@@ -0,0 +1,294 @@
+use std::fmt;
+
+use ::result::{ParseError, ParseResult};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BaseType {
+    Byte,                               // B
+    Char,                               // C
+    Double,                             // D
+    Float,                              // F
+    Int,                                // I
+    Long,                               // J
+    Short,                              // S
+    Boolean                             // Z
+}
+
+impl BaseType {
+    pub fn from_char(c: char) -> Option<BaseType> {
+        match c {
+            'B' => Option::Some(BaseType::Byte),
+            'C' => Option::Some(BaseType::Char),
+            'D' => Option::Some(BaseType::Double),
+            'F' => Option::Some(BaseType::Float),
+            'I' => Option::Some(BaseType::Int),
+            'J' => Option::Some(BaseType::Long),
+            'S' => Option::Some(BaseType::Short),
+            'Z' => Option::Some(BaseType::Boolean),
+            _ => Option::None
+        }
+    }
+
+    pub fn descriptor_char(&self) -> char {
+        match *self {
+            BaseType::Byte => 'B',
+            BaseType::Char => 'C',
+            BaseType::Double => 'D',
+            BaseType::Float => 'F',
+            BaseType::Int => 'I',
+            BaseType::Long => 'J',
+            BaseType::Short => 'S',
+            BaseType::Boolean => 'Z'
+        }
+    }
+
+    pub fn java_name(&self) -> &'static str {
+        match *self {
+            BaseType::Byte => "byte",
+            BaseType::Char => "char",
+            BaseType::Double => "double",
+            BaseType::Float => "float",
+            BaseType::Int => "int",
+            BaseType::Long => "long",
+            BaseType::Short => "short",
+            BaseType::Boolean => "boolean"
+        }
+    }
+
+    /// The number of local variable slots, 2 for long and double.
+    pub fn size(&self) -> u16 {
+        match *self {
+            BaseType::Long | BaseType::Double => 2,
+            _ => 1
+        }
+    }
+}
+
+/// A field descriptor such as `I`, `Ljava/lang/String;` or `[[D`. Class names
+/// are kept in internal form.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FieldType {
+    Base(BaseType),
+    Object(String),
+    Array(Box<FieldType>)
+}
+
+impl FieldType {
+    pub fn parse(descriptor: &str) -> ParseResult<FieldType> {
+        let mut parser = Parser::new(descriptor);
+        let field_type = try!(parser.field_type());
+        try!(parser.end());
+        Result::Ok(field_type)
+    }
+
+    /// The number of local variable slots, 2 for long and double.
+    pub fn size(&self) -> u16 {
+        match *self {
+            FieldType::Base(base_type) => base_type.size(),
+            _ => 1
+        }
+    }
+
+    pub fn is_reference(&self) -> bool {
+        match *self {
+            FieldType::Base(_) => false,
+            _ => true
+        }
+    }
+
+    /// The number of array dimensions, 0 if this is not an array.
+    pub fn dimensions(&self) -> usize {
+        match *self {
+            FieldType::Array(ref component) => 1 + component.dimensions(),
+            _ => 0
+        }
+    }
+
+    /// Renders the type as in Java source, e.g. `java.lang.String[]`.
+    pub fn to_java_source(&self) -> String {
+        match *self {
+            FieldType::Base(base_type) => base_type.java_name().to_string(),
+            FieldType::Object(ref class_name) => class_name.replace('/', "."),
+            FieldType::Array(ref component) => format!("{}[]", component.to_java_source())
+        }
+    }
+}
+
+impl fmt::Display for FieldType {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FieldType::Base(base_type) => write!(fmt, "{}", base_type.descriptor_char()),
+            FieldType::Object(ref class_name) => write!(fmt, "L{};", class_name),
+            FieldType::Array(ref component) => write!(fmt, "[{}", component)
+        }
+    }
+}
+
+/// A method descriptor such as `([Ljava/lang/String;I)V`. A `return_type` of
+/// `None` stands for `void`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MethodDescriptor {
+    pub parameters: Vec<FieldType>,
+    pub return_type: Option<FieldType>
+}
+
+impl MethodDescriptor {
+    pub fn parse(descriptor: &str) -> ParseResult<MethodDescriptor> {
+        let mut parser = Parser::new(descriptor);
+        try!(parser.expect('('));
+        let mut parameters = Vec::new();
+        // Counted as parameters are read, since the sizes of a descriptor
+        // that fills a Utf8 constant overflow a u16.
+        let mut slots = 0;
+        while try!(parser.peek()) != ')' {
+            let parameter = try!(parser.field_type());
+            slots += parameter.size();
+            if slots > 255 {
+                let message = format!("method descriptor {} has more than 255 parameter slots", descriptor);
+                return Result::Err(ParseError::Format(message));
+            }
+            parameters.push(parameter);
+        }
+        try!(parser.expect(')'));
+        let return_type = if try!(parser.peek()) == 'V' {
+            parser.position += 1;
+            Option::None
+        } else {
+            Option::Some(try!(parser.field_type()))
+        };
+        try!(parser.end());
+        Result::Ok(MethodDescriptor {
+            parameters: parameters,
+            return_type: return_type
+        })
+    }
+
+    /// The number of local variable slots taken by the parameters, not
+    /// counting `this`.
+    pub fn parameter_slots(&self) -> u16 {
+        self.parameters.iter().map(FieldType::size).sum()
+    }
+
+    /// The number of operand stack slots taken by the return value.
+    pub fn return_slots(&self) -> u16 {
+        self.return_type.as_ref().map(FieldType::size).unwrap_or(0)
+    }
+
+    /// Renders the method as in Java source, e.g. `void main(java.lang.String[])`.
+    pub fn to_java_source(&self, name: &str) -> String {
+        let return_type = match self.return_type {
+            Option::Some(ref return_type) => return_type.to_java_source(),
+            Option::None => "void".to_string()
+        };
+        let parameters: Vec<String> = self.parameters.iter().map(FieldType::to_java_source).collect();
+        format!("{} {}({})", return_type, name, parameters.join(", "))
+    }
+}
+
+impl fmt::Display for MethodDescriptor {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(fmt, "("));
+        for parameter in &self.parameters {
+            try!(write!(fmt, "{}", parameter));
+        }
+        match self.return_type {
+            Option::Some(ref return_type) => write!(fmt, "){}", return_type),
+            Option::None => write!(fmt, ")V")
+        }
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    position: usize
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Parser<'a> {
+        Parser { input: input, position: 0 }
+    }
+
+    fn error<T>(&self, expected: &str) -> ParseResult<T> {
+        let found = match self.input[self.position..].chars().next() {
+            Option::Some(c) => format!("{:?}", c),
+            Option::None => "end of input".to_string()
+        };
+        let message = format!("invalid descriptor {:?} at position {}: expected {} but found {}",
+                self.input, self.position, expected, found);
+        Result::Err(ParseError::Format(message))
+    }
+
+    fn peek(&self) -> ParseResult<char> {
+        match self.input[self.position..].chars().next() {
+            Option::Some(c) => Result::Ok(c),
+            Option::None => self.error("a type")
+        }
+    }
+
+    fn expect(&mut self, c: char) -> ParseResult<()> {
+        if self.input[self.position..].starts_with(c) {
+            self.position += c.len_utf8();
+            Result::Ok(())
+        } else {
+            self.error(&format!("{:?}", c))
+        }
+    }
+
+    fn end(&self) -> ParseResult<()> {
+        if self.position == self.input.len() {
+            Result::Ok(())
+        } else {
+            self.error("end of input")
+        }
+    }
+
+    fn field_type(&mut self) -> ParseResult<FieldType> {
+        let start = self.position;
+        let mut dimensions = 0;
+        while self.input[self.position..].starts_with('[') {
+            if dimensions == 255 {
+                self.position = start;
+                return self.error("at most 255 array dimensions");
+            }
+            dimensions += 1;
+            self.position += 1;
+        }
+        let c = try!(self.peek());
+        let mut field_type = if let Option::Some(base_type) = BaseType::from_char(c) {
+            self.position += 1;
+            FieldType::Base(base_type)
+        } else if c == 'L' {
+            self.position += 1;
+            FieldType::Object(try!(self.class_name()))
+        } else {
+            return self.error("a field type");
+        };
+        for _ in 0..dimensions {
+            field_type = FieldType::Array(Box::new(field_type));
+        }
+        Result::Ok(field_type)
+    }
+
+    // Reads a binary class name up to and including the terminating ';'.
+    fn class_name(&mut self) -> ParseResult<String> {
+        let start = self.position;
+        let mut segment_start = true;
+        loop {
+            let c = match self.input[self.position..].chars().next() {
+                Option::Some(c) => c,
+                Option::None => { return self.error("';'"); }
+            };
+            match c {
+                ';' | '/' if segment_start => { return self.error("an identifier"); },
+                ';' => break,
+                '/' => segment_start = true,
+                '.' | '[' => { return self.error("a class name character"); },
+                _ => segment_start = false
+            }
+            self.position += c.len_utf8();
+        }
+        let class_name = self.input[start..self.position].to_string();
+        self.position += 1;
+        Result::Ok(class_name)
+    }
+}
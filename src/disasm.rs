@@ -0,0 +1,1563 @@
+use std::char;
+
+use ::access::{ClassAccess, ExportsAccess, InnerClassAccess, MethodAccess, ModuleAccess, ParameterAccess};
+use ::descriptor::{FieldType, MethodDescriptor};
+use ::java_string::JavaString;
+use ::model::*;
+use ::pool::ConstantPool;
+use ::result::{ParseError, ParseResult};
+use ::signature::{ClassSignature, MethodSignature, ReferenceTypeSignature};
+
+/// Renders a class in the format of `javap -c -v -p`, starting at the
+/// `Compiled from` line. The `Classfile`, `Last modified` and checksum lines
+/// describe the file rather than the class and are left out.
+pub fn disassemble(class: &Class) -> ParseResult<String> {
+    let mut disassembler = Disassembler {
+        class: class,
+        pool: &class.constant_pool,
+        out: Printer::new(),
+        method: Option::None
+    };
+    try!(disassembler.class());
+    Result::Ok(disassembler.out.output)
+}
+
+// javap writes modifiers in this order rather than the one in which the
+// access flag types list their keywords.
+static MODIFIER_ORDER: [&str; 11] = [
+    "public", "private", "protected", "static", "final", "synchronized", "volatile", "transient", "native",
+    "abstract", "strictfp"
+];
+
+static REFERENCE_KINDS: [&str; 10] = [
+    "REF_unknown", "REF_getField", "REF_getStatic", "REF_putField", "REF_putStatic", "REF_invokeVirtual",
+    "REF_invokeStatic", "REF_invokeSpecial", "REF_newInvokeSpecial", "REF_invokeInterface"
+];
+
+fn modifiers(keywords: &[&str]) -> Vec<&'static str> {
+    MODIFIER_ORDER.iter().filter(|modifier| keywords.contains(modifier)).cloned().collect()
+}
+
+// Known flags by increasing bit, then any unknown bits in hex, highest first.
+fn flag_names(flags: u16, names: Vec<&str>, unknown_bits: u16) -> String {
+    let mut names: Vec<String> = names.iter().map(|name| format!("ACC_{}", name)).collect();
+    for bit in (0..16).rev() {
+        if unknown_bits & (1 << bit) != 0 {
+            names.push(format!("0x{:x}", 1 << bit));
+        }
+    }
+    format!("flags: (0x{:04x}) {}", flags, names.join(", "))
+}
+
+// Mimics javap's line writer: spaces are held back until something else is
+// printed on the line, so trailing spaces vanish, and indentation is added
+// when the first character of a line is printed.
+struct Printer {
+    output: String,
+    line: String,
+    line_width: usize,
+    pending_spaces: usize,
+    indent: usize
+}
+
+impl Printer {
+    fn new() -> Printer {
+        Printer { output: String::new(), line: String::new(), line_width: 0, pending_spaces: 0, indent: 0 }
+    }
+
+    fn print(&mut self, text: &str) {
+        for c in text.chars() {
+            match c {
+                ' ' => self.pending_spaces += 1,
+                '\n' => self.newline(),
+                _ => {
+                    if self.line.is_empty() {
+                        for _ in 0..self.indent * 2 {
+                            self.line.push(' ');
+                        }
+                        self.line_width = self.indent * 2;
+                    }
+                    for _ in 0..self.pending_spaces {
+                        self.line.push(' ');
+                    }
+                    self.line_width += self.pending_spaces + 1;
+                    self.pending_spaces = 0;
+                    self.line.push(c);
+                }
+            }
+        }
+    }
+
+    fn println(&mut self, text: &str) {
+        self.print(text);
+        self.newline();
+    }
+
+    fn newline(&mut self) {
+        self.pending_spaces = 0;
+        self.output.push_str(&self.line);
+        self.output.push('\n');
+        self.line.clear();
+        self.line_width = 0;
+    }
+
+    // Pads to the comment column, 40 characters past the indentation.
+    fn tab(&mut self) {
+        let column = self.indent * 2 + 40;
+        self.pending_spaces += if column <= self.line_width { 1 } else { column - self.line_width };
+    }
+
+    fn indent(&mut self, levels: isize) {
+        self.indent = (self.indent as isize + levels) as usize;
+    }
+}
+
+struct Disassembler<'a> {
+    class: &'a Class,
+    pool: &'a ConstantPool,
+    out: Printer,
+    method: Option<&'a Method>
+}
+
+impl<'a> Disassembler<'a> {
+    fn class(&mut self) -> ParseResult<()> {
+        let class = self.class;
+        self.out.indent(1);
+        for attribute in &class.attributes {
+            if let Attribute::SourceFile { sourcefile_index } = *attribute {
+                let source_file = try!(self.pool.utf8(sourcefile_index)).to_string_lossy();
+                self.out.println(&format!("Compiled from \"{}\"", source_file));
+            }
+        }
+        self.out.indent(-1);
+
+        let access = class.access();
+        let is_interface = access.contains(ClassAccess::INTERFACE);
+        let keywords = if is_interface { (access - ClassAccess::ABSTRACT).keywords() } else { access.keywords() };
+        for modifier in modifiers(&keywords) {
+            self.out.print(modifier);
+            self.out.print(" ");
+        }
+        if access.contains(ClassAccess::MODULE) {
+            try!(self.module_declaration());
+        } else {
+            self.out.print(if is_interface { "interface " } else { "class " });
+            self.out.print(&java_name(&try!(class.name())));
+            try!(self.class_supertypes());
+        }
+        self.out.println("");
+
+        self.out.indent(1);
+        self.out.println(&format!("minor version: {}", class.minor_version));
+        self.out.println(&format!("major version: {}", class.major_version));
+        self.out.println(&flag_names(access.bits(), access.names(), access.unknown_bits()));
+        for &(label, index) in &[("this_class", class.this_class), ("super_class", class.super_class)] {
+            self.out.print(&format!("{}: #{}", label, index));
+            if index != 0 {
+                self.out.tab();
+                let value = try!(self.constant_string(index));
+                self.out.print(&format!("// {}", value));
+            }
+            self.out.println("");
+        }
+        self.out.println(&format!("interfaces: {}, fields: {}, methods: {}, attributes: {}",
+                class.interfaces.len(), class.fields.len(), class.methods.len(), class.attributes.len()));
+        self.out.indent(-1);
+        try!(self.constant_pool());
+
+        self.out.println("{");
+        self.out.indent(1);
+        for field in &class.fields {
+            try!(self.field(field));
+        }
+        for (i, method) in class.methods.iter().enumerate() {
+            if i > 0 {
+                self.out.println("");
+            }
+            try!(self.method(method));
+        }
+        self.out.indent(-1);
+        self.out.println("}");
+        self.attributes(&class.attributes)
+    }
+
+    fn module_declaration(&mut self) -> ParseResult<()> {
+        for attribute in &self.class.attributes {
+            if let Attribute::Module { module_name_index, module_flags, module_version_index, .. } = *attribute {
+                let name = match *try!(self.pool.get(module_name_index)) {
+                    ConstantPoolInfo::Module(_) => try!(self.pool.module_name(module_name_index)),
+                    _ => try!(self.pool.utf8_string(module_name_index))
+                };
+                if ModuleAccess::from_bits(module_flags).contains(ModuleAccess::OPEN) {
+                    self.out.print("open ");
+                }
+                self.out.print("module ");
+                self.out.print(&java_name(&name));
+                if module_version_index != 0 {
+                    self.out.print("@");
+                    self.out.print(&try!(self.pool.utf8(module_version_index)).to_string_lossy());
+                }
+                return Result::Ok(());
+            }
+        }
+        self.out.print("class ");
+        self.out.print(&java_name(&try!(self.class.name())));
+        Result::Ok(())
+    }
+
+    fn class_supertypes(&mut self) -> ParseResult<()> {
+        let class = self.class;
+        let is_interface = class.access().contains(ClassAccess::INTERFACE);
+        let signature = match class.attributes.iter().filter_map(|attribute| match *attribute {
+            Attribute::Signature { signature_index } => Option::Some(signature_index),
+            _ => Option::None
+        }).next() {
+            Option::Some(index) => try!(ClassSignature::parse(&try!(self.pool.utf8_string(index)))),
+            Option::None => {
+                if !is_interface && class.super_class != 0 {
+                    let super_name = java_name(&try!(self.pool.class_name(class.super_class)));
+                    if super_name != "java.lang.Object" {
+                        self.out.print(" extends ");
+                        self.out.print(&super_name);
+                    }
+                }
+                for (i, name) in try!(class.interface_names()).iter().enumerate() {
+                    self.out.print(if i > 0 { "," } else if is_interface { " extends " } else { " implements " });
+                    self.out.print(&java_name(name));
+                }
+                return Result::Ok(());
+            }
+        };
+        // javap reads a signature without type parameters or interfaces as a
+        // plain superclass type.
+        if signature.type_parameters.is_empty() && signature.interfaces.is_empty() {
+            self.out.print(" extends ");
+            self.out.print(&signature.superclass.to_java_source());
+            return Result::Ok(());
+        }
+        let type_parameters: Vec<String> = signature.type_parameters.iter().map(|p| p.to_java_source()).collect();
+        let interfaces: Vec<String> = signature.interfaces.iter().map(|i| i.to_java_source()).collect();
+        if !type_parameters.is_empty() {
+            self.out.print(&format!("<{}>", type_parameters.join(", ")));
+        }
+        if !is_interface {
+            self.out.print(" extends ");
+            self.out.print(&signature.superclass.to_java_source());
+        }
+        if !interfaces.is_empty() {
+            self.out.print(if is_interface { " extends " } else { " implements " });
+            self.out.print(&interfaces.join(", "));
+        }
+        Result::Ok(())
+    }
+
+    fn constant_pool(&mut self) -> ParseResult<()> {
+        self.out.println("Constant pool:");
+        self.out.indent(1);
+        let width = (self.pool.len() + 1).to_string().len() + 1;
+        for (i, info) in self.pool.iter().enumerate() {
+            let index = i as u16 + 1;
+            let tag = match *info {
+                ConstantPoolInfo::Utf8(_) => "Utf8",
+                ConstantPoolInfo::Integer(_) => "Integer",
+                ConstantPoolInfo::Float(_) => "Float",
+                ConstantPoolInfo::Long(_) => "Long",
+                ConstantPoolInfo::Double(_) => "Double",
+                ConstantPoolInfo::Class(_) => "Class",
+                ConstantPoolInfo::String(_) => "String",
+                ConstantPoolInfo::Fieldref(_, _) => "Fieldref",
+                ConstantPoolInfo::Methodref(_, _) => "Methodref",
+                ConstantPoolInfo::InterfaceMethodref(_, _) => "InterfaceMethodref",
+                ConstantPoolInfo::NameAndType(_, _) => "NameAndType",
+                ConstantPoolInfo::MethodHandle(_, _) => "MethodHandle",
+                ConstantPoolInfo::MethodType(_) => "MethodType",
+                ConstantPoolInfo::Dynamic(_, _) => "Dynamic",
+                ConstantPoolInfo::InvokeDynamic(_, _) => "InvokeDynamic",
+                ConstantPoolInfo::Module(_) => "Module",
+                ConstantPoolInfo::Package(_) => "Package",
+                ConstantPoolInfo::Invalid => { continue; }
+            };
+            self.out.print(&format!("{:>width$} = {:<18} ", format!("#{}", index), tag, width = width));
+            let operands = match *info {
+                ConstantPoolInfo::Utf8(_) | ConstantPoolInfo::Integer(_) | ConstantPoolInfo::Float(_)
+                        | ConstantPoolInfo::Long(_) | ConstantPoolInfo::Double(_) | ConstantPoolInfo::Invalid => {
+                    let value = try!(self.constant_string(index));
+                    self.out.println(&value);
+                    continue;
+                },
+                ConstantPoolInfo::Class(name_index) | ConstantPoolInfo::String(name_index)
+                        | ConstantPoolInfo::MethodType(name_index) | ConstantPoolInfo::Module(name_index)
+                        | ConstantPoolInfo::Package(name_index) => format!("#{}", name_index),
+                ConstantPoolInfo::Fieldref(class_index, name_and_type_index)
+                        | ConstantPoolInfo::Methodref(class_index, name_and_type_index)
+                        | ConstantPoolInfo::InterfaceMethodref(class_index, name_and_type_index) => {
+                    format!("#{}.#{}", class_index, name_and_type_index)
+                },
+                ConstantPoolInfo::NameAndType(name_index, descriptor_index) => {
+                    format!("#{}:#{}", name_index, descriptor_index)
+                },
+                ConstantPoolInfo::MethodHandle(kind, reference_index) => format!("{}:#{}", kind, reference_index),
+                ConstantPoolInfo::Dynamic(bootstrap_index, name_and_type_index)
+                        | ConstantPoolInfo::InvokeDynamic(bootstrap_index, name_and_type_index) => {
+                    format!("#{}:#{}", bootstrap_index, name_and_type_index)
+                }
+            };
+            self.out.print(&operands);
+            self.out.tab();
+            let value = try!(self.constant_string(index));
+            match *info {
+                ConstantPoolInfo::MethodType(_) => self.out.println(&format!("//  {}", value)),
+                _ => self.out.println(&format!("// {}", value))
+            }
+        }
+        self.out.indent(-1);
+        Result::Ok(())
+    }
+
+    // The value of a constant as javap shows it in comments.
+    fn constant_string(&self, index: u16) -> ParseResult<String> {
+        let value = match *try!(self.pool.get(index)) {
+            ConstantPoolInfo::Utf8(ref string) => escape_utf8(&try!(string.to_utf16())),
+            ConstantPoolInfo::Integer(value) => value.to_string(),
+            ConstantPoolInfo::Float(value) => format!("{}f", java_float(value)),
+            ConstantPoolInfo::Long(value) => format!("{}l", value),
+            ConstantPoolInfo::Double(value) => format!("{}d", java_double(value)),
+            ConstantPoolInfo::Class(name_index) | ConstantPoolInfo::Module(name_index)
+                    | ConstantPoolInfo::Package(name_index) => {
+                check_name(&try!(self.pool.utf8(name_index)).to_string_lossy())
+            },
+            ConstantPoolInfo::String(string_index) => escape_utf8(&try!(try!(self.pool.utf8(string_index)).to_utf16())),
+            ConstantPoolInfo::MethodType(descriptor_index) => {
+                try!(self.pool.utf8(descriptor_index)).to_string_lossy().into_owned()
+            },
+            ConstantPoolInfo::Fieldref(_, _) | ConstantPoolInfo::Methodref(_, _) | ConstantPoolInfo::InterfaceMethodref(_, _) => {
+                try!(self.member_string(index))
+            },
+            ConstantPoolInfo::NameAndType(name_index, descriptor_index) => {
+                format!("{}:{}", check_name(&try!(self.pool.utf8(name_index)).to_string_lossy()),
+                        try!(self.pool.utf8(descriptor_index)).to_string_lossy())
+            },
+            ConstantPoolInfo::MethodHandle(kind, reference_index) => {
+                let kind = REFERENCE_KINDS.get(kind as usize).cloned().unwrap_or("REF_unknown");
+                format!("{} {}", kind, try!(self.member_string(reference_index)))
+            },
+            ConstantPoolInfo::Dynamic(bootstrap_index, name_and_type_index)
+                    | ConstantPoolInfo::InvokeDynamic(bootstrap_index, name_and_type_index) => {
+                let (name, descriptor) = try!(self.pool.name_and_type(name_and_type_index));
+                format!("#{}:{}:{}", bootstrap_index, check_name(&name), descriptor)
+            },
+            ConstantPoolInfo::Invalid => {
+                return Result::Err(ParseError::Format(format!("constant pool index {} is not a constant", index)));
+            }
+        };
+        Result::Ok(value)
+    }
+
+    // A field or method reference as `class.name:descriptor`. The operands are
+    // resolved by type, so that a reference to an entry of the wrong type,
+    // such as itself, is an error rather than endless recursion.
+    fn member_string(&self, index: u16) -> ParseResult<String> {
+        let member = try!(self.pool.member_ref(index));
+        Result::Ok(format!("{}.{}:{}", check_name(&member.class_name), check_name(&member.name), member.descriptor))
+    }
+
+    // A tagged constant as in instruction comments, e.g. `Method
+    // java/lang/Object."<init>":()V`. References to members of this class
+    // leave out the class name.
+    fn constant_reference(&self, index: u16) -> ParseResult<String> {
+        if index == 0 {
+            return Result::Ok("#0".to_string());
+        }
+        let info = try!(self.pool.get(index));
+        let tag = match *info {
+            ConstantPoolInfo::Utf8(_) => "Utf8",
+            ConstantPoolInfo::Integer(_) => "int",
+            ConstantPoolInfo::Float(_) => "float",
+            ConstantPoolInfo::Long(_) => "long",
+            ConstantPoolInfo::Double(_) => "double",
+            ConstantPoolInfo::Class(_) => "class",
+            ConstantPoolInfo::String(_) => "String",
+            ConstantPoolInfo::Fieldref(_, _) => "Field",
+            ConstantPoolInfo::Methodref(_, _) => "Method",
+            ConstantPoolInfo::InterfaceMethodref(_, _) => "InterfaceMethod",
+            ConstantPoolInfo::NameAndType(_, _) => "NameAndType",
+            ConstantPoolInfo::MethodHandle(_, _) => "MethodHandle",
+            ConstantPoolInfo::MethodType(_) => "MethodType",
+            ConstantPoolInfo::Dynamic(_, _) => "Dynamic",
+            ConstantPoolInfo::InvokeDynamic(_, _) => "InvokeDynamic",
+            ConstantPoolInfo::Module(_) => "Module",
+            ConstantPoolInfo::Package(_) => "Package",
+            ConstantPoolInfo::Invalid => "Invalid"
+        };
+        let value = match *info {
+            ConstantPoolInfo::Fieldref(class_index, name_and_type_index)
+                    | ConstantPoolInfo::Methodref(class_index, name_and_type_index)
+                    | ConstantPoolInfo::InterfaceMethodref(class_index, name_and_type_index)
+                    if class_index == self.class.this_class => {
+                let (name, descriptor) = try!(self.pool.name_and_type(name_and_type_index));
+                format!("{}:{}", check_name(&name), descriptor)
+            },
+            _ => try!(self.constant_string(index))
+        };
+        Result::Ok(format!("{} {}", tag, value))
+    }
+
+    fn field(&mut self, field: &Field) -> ParseResult<()> {
+        let access = field.access();
+        for modifier in modifiers(&access.keywords()) {
+            self.out.print(modifier);
+            self.out.print(" ");
+        }
+        let field_type = match try!(field.signature(self.pool)) {
+            Option::Some(signature) => signature.to_java_source(),
+            Option::None => try!(field.field_type(self.pool)).to_java_source()
+        };
+        self.out.print(&field_type);
+        self.out.print(" ");
+        self.out.print(&try!(field.name(self.pool)));
+        self.out.println(";");
+        self.out.indent(1);
+        let descriptor = try!(self.pool.utf8(field.descriptor_index)).to_string_lossy();
+        self.out.println(&format!("descriptor: {}", descriptor));
+        self.out.println(&flag_names(access.bits(), access.names(), access.unknown_bits()));
+        try!(self.attributes(&field.attributes));
+        self.out.indent(-1);
+        self.out.println("");
+        Result::Ok(())
+    }
+
+    fn method(&mut self, method: &'a Method) -> ParseResult<()> {
+        self.method = Option::Some(method);
+        let class = self.class;
+        let access = method.access();
+        let name = try!(method.name(self.pool));
+        let descriptor = try!(method.method_descriptor(self.pool));
+        let signature = try!(method.signature(self.pool));
+
+        let mut method_modifiers = modifiers(&access.keywords());
+        let default_methods = class.major_version >= 52;
+        if class.access().contains(ClassAccess::INTERFACE) && !access.contains(MethodAccess::ABSTRACT) && name != "<clinit>"
+                && default_methods && !access.intersects(MethodAccess::STATIC | MethodAccess::PRIVATE) {
+            method_modifiers.push("default");
+        }
+        for modifier in method_modifiers {
+            self.out.print(modifier);
+            self.out.print(" ");
+        }
+        let (parameters, return_type) = match signature {
+            Option::Some(ref signature) => {
+                if !signature.type_parameters.is_empty() {
+                    let type_parameters: Vec<String> = signature.type_parameters.iter().map(|p| p.to_java_source()).collect();
+                    self.out.print(&format!("<{}> ", type_parameters.join(", ")));
+                }
+                signature_types(signature)
+            },
+            Option::None => descriptor_types(&descriptor)
+        };
+        let mut parameters = format!("({})", parameters.join(", "));
+        if access.contains(MethodAccess::VARARGS) {
+            if let Option::Some(i) = parameters.rfind("[]") {
+                parameters = format!("{}...{}", &parameters[..i], &parameters[i + 2..]);
+            }
+        }
+        match name.as_str() {
+            "<init>" => {
+                self.out.print(&java_name(&try!(class.name())));
+                self.out.print(&parameters);
+            },
+            "<clinit>" => self.out.print("{}"),
+            _ => {
+                self.out.print(&return_type);
+                self.out.print(" ");
+                self.out.print(&name);
+                self.out.print(&parameters);
+            }
+        }
+        for attribute in &method.attributes {
+            if let Attribute::Exceptions { ref exception_index_table } = *attribute {
+                self.out.print(" throws ");
+                let generic_throws = signature.as_ref().map(|s| s.throws.as_slice()).unwrap_or(&[]);
+                if !generic_throws.is_empty() {
+                    let throws: Vec<String> = generic_throws.iter().map(internal_source).collect();
+                    self.out.print(&throws.join(", "));
+                } else {
+                    for (i, &index) in exception_index_table.iter().enumerate() {
+                        if i > 0 {
+                            self.out.print(", ");
+                        }
+                        self.out.print(&java_name(&try!(self.pool.class_name(index))));
+                    }
+                }
+            }
+        }
+        self.out.println(";");
+        self.out.indent(1);
+        let raw_descriptor = try!(self.pool.utf8(method.descriptor_index)).to_string_lossy();
+        self.out.println(&format!("descriptor: {}", raw_descriptor));
+        self.out.println(&flag_names(access.bits(), access.names(), access.unknown_bits()));
+        try!(self.attributes(&method.attributes));
+        self.out.indent(-1);
+        self.method = Option::None;
+        Result::Ok(())
+    }
+
+    fn attributes(&mut self, attributes: &[Attribute]) -> ParseResult<()> {
+        for attribute in attributes {
+            try!(self.attribute(attribute));
+        }
+        Result::Ok(())
+    }
+
+    fn attribute(&mut self, attribute: &Attribute) -> ParseResult<()> {
+        match *attribute {
+            Attribute::ConstantValue { constvalue_index } => {
+                let value = try!(self.constant_reference(constvalue_index));
+                self.out.println(&format!("ConstantValue: {}", value));
+            },
+            Attribute::Code { max_stack, max_locals, ref code, ref exception_table, ref attributes } => {
+                self.out.println("Code:");
+                self.out.indent(1);
+                let args_size = match self.method {
+                    Option::Some(method) => {
+                        let parameters = try!(method.method_descriptor(self.pool)).parameters.len();
+                        if method.is_static() { parameters } else { parameters + 1 }
+                    },
+                    Option::None => 0
+                };
+                self.out.println(&format!("stack={}, locals={}, args_size={}", max_stack, max_locals, args_size));
+                for &(pc, ref instruction) in code {
+                    try!(self.instruction(pc, instruction));
+                }
+                try!(self.exception_table(exception_table));
+                try!(self.attributes(attributes));
+                self.out.indent(-1);
+            },
+            Attribute::StackMapTable(ref frames) => {
+                self.out.println(&format!("StackMapTable: number_of_entries = {}", frames.len()));
+                self.out.indent(1);
+                for frame in frames {
+                    try!(self.stack_map_frame(frame));
+                }
+                self.out.indent(-1);
+            },
+            Attribute::Exceptions { ref exception_index_table } => {
+                self.out.println("Exceptions:");
+                self.out.indent(1);
+                let mut names = Vec::with_capacity(exception_index_table.len());
+                for &index in exception_index_table {
+                    names.push(java_name(&try!(self.pool.class_name(index))));
+                }
+                self.out.println(&format!("throws {}", names.join(", ")));
+                self.out.indent(-1);
+            },
+            Attribute::InnerClasses { ref classes } => {
+                if !classes.is_empty() {
+                    self.out.println("InnerClasses:");
+                    self.out.indent(1);
+                }
+                for inner_class in classes {
+                    try!(self.inner_class(inner_class));
+                }
+                if !classes.is_empty() {
+                    self.out.indent(-1);
+                }
+            },
+            Attribute::EnclosingMethod { class_index, method_index } => {
+                self.out.print(&format!("EnclosingMethod: #{}.#{}", class_index, method_index));
+                self.out.tab();
+                self.out.print(&format!("// {}", java_name(&try!(self.pool.class_name(class_index)))));
+                if method_index != 0 {
+                    let (name, _) = try!(self.pool.name_and_type(method_index));
+                    self.out.print(&format!(".{}", name));
+                }
+                self.out.println("");
+            },
+            Attribute::Synthetic => self.out.println("Synthetic: true"),
+            Attribute::Signature { signature_index } => {
+                self.out.print(&format!("Signature: #{}", signature_index));
+                self.out.tab();
+                let signature = try!(self.pool.utf8(signature_index)).to_string_lossy();
+                self.out.println(&format!("// {}", signature));
+            },
+            Attribute::SourceFile { sourcefile_index } => {
+                let source_file = try!(self.pool.utf8(sourcefile_index)).to_string_lossy();
+                self.out.println(&format!("SourceFile: \"{}\"", source_file));
+            },
+            Attribute::SourceDebugExtension(ref data) => {
+                self.out.println("SourceDebugExtension:");
+                self.out.indent(1);
+                let text = JavaString::from_modified_utf8(data.clone());
+                for line in text.to_string_lossy().split(&['\r', '\n'][..]).filter(|l| !l.is_empty()) {
+                    self.out.println(line);
+                }
+                self.out.indent(-1);
+            },
+            Attribute::LineNumberTable(ref line_numbers) => {
+                self.out.println("LineNumberTable:");
+                self.out.indent(1);
+                for line_number in line_numbers {
+                    self.out.println(&format!("line {}: {}", line_number.line_number, line_number.start_pc));
+                }
+                self.out.indent(-1);
+            },
+            Attribute::LocalVariableTable(ref variables) => {
+                try!(self.local_variables("LocalVariableTable:", variables));
+            },
+            Attribute::LocalVariableTypeTable(ref variables) => {
+                try!(self.local_variables("LocalVariableTypeTable:", variables));
+            },
+            Attribute::Deprecated => self.out.println("Deprecated: true"),
+            Attribute::RuntimeVisibleAnnotations(ref annotations) => {
+                try!(self.annotations("RuntimeVisibleAnnotations:", annotations));
+            },
+            Attribute::RuntimeInvisibleAnnotations(ref annotations) => {
+                try!(self.annotations("RuntimeInvisibleAnnotations:", annotations));
+            },
+            Attribute::RuntimeVisibleParameterAnnotations(ref parameters) => {
+                try!(self.parameter_annotations("RuntimeVisibleParameterAnnotations:", parameters));
+            },
+            Attribute::RuntimeInvisibleParameterAnnotations(ref parameters) => {
+                try!(self.parameter_annotations("RuntimeInvisibleParameterAnnotations:", parameters));
+            },
+            Attribute::RuntimeVisibleTypeAnnotations(ref annotations) => {
+                try!(self.type_annotations("RuntimeVisibleTypeAnnotations:", annotations));
+            },
+            Attribute::RuntimeInvisibleTypeAnnotations(ref annotations) => {
+                try!(self.type_annotations("RuntimeInvisibleTypeAnnotations:", annotations));
+            },
+            Attribute::AnnotationDefault { ref element_value } => {
+                self.out.println("AnnotationDefault:");
+                self.out.indent(1);
+                self.out.print("default_value: ");
+                try!(self.element_value(element_value, false));
+                self.out.println("");
+                self.out.indent(1);
+                try!(self.element_value(element_value, true));
+                self.out.indent(-2);
+                self.out.println("");
+            },
+            Attribute::BootstrapMethods(ref bootstrap_methods) => {
+                self.out.println("BootstrapMethods:");
+                for (i, bootstrap_method) in bootstrap_methods.iter().enumerate() {
+                    self.out.indent(1);
+                    let method_ref = try!(self.constant_string(bootstrap_method.method_ref));
+                    self.out.println(&format!("{}: #{} {}", i, bootstrap_method.method_ref, method_ref));
+                    self.out.indent(1);
+                    self.out.println("Method arguments:");
+                    self.out.indent(1);
+                    for &argument in &bootstrap_method.arguments {
+                        let value = try!(self.constant_string(argument));
+                        self.out.println(&format!("#{} {}", argument, value));
+                    }
+                    self.out.indent(-3);
+                }
+            },
+            Attribute::MethodParameters(ref parameters) => {
+                self.out.println("MethodParameters:");
+                self.out.indent(1);
+                self.out.println(&format!("{:<31}{}", "Name", "Flags"));
+                for parameter in parameters {
+                    let name = if parameter.name_index == 0 {
+                        "<no name>".to_string()
+                    } else {
+                        try!(self.constant_string(parameter.name_index))
+                    };
+                    let access = parameter.access();
+                    let flags: Vec<String> = [access & ParameterAccess::FINAL, access & ParameterAccess::MANDATED,
+                            access & ParameterAccess::SYNTHETIC].iter().flat_map(ParameterAccess::names).map(str::to_lowercase).collect();
+                    self.out.println(&format!("{:<31}{}", name, flags.join(" ")));
+                }
+                self.out.indent(-1);
+            },
+            Attribute::Module { module_name_index, module_flags, module_version_index, ref requires, ref exports,
+                    ref opens, ref uses_index, ref provides } => {
+                self.out.println("Module:");
+                self.out.indent(1);
+                self.out.print(&format!("#{},{:x}", module_name_index, module_flags));
+                self.out.tab();
+                let constant = try!(self.constant_string(module_name_index));
+                self.out.print(&format!("// {}", constant));
+                let access = ModuleAccess::from_bits(module_flags);
+                self.module_flags([access & ModuleAccess::OPEN, access & ModuleAccess::MANDATED,
+                        access & ModuleAccess::SYNTHETIC].iter().flat_map(ModuleAccess::names).collect());
+                try!(self.module_version(module_version_index));
+
+                self.out.print(&requires.len().to_string());
+                self.out.tab();
+                self.out.println("// requires");
+                self.out.indent(1);
+                for entry in requires {
+                    self.out.print(&format!("#{},{:x}", entry.requires_index, entry.requires_flags));
+                    self.out.tab();
+                    let constant = try!(self.constant_string(entry.requires_index));
+                    self.out.print(&format!("// {}", constant));
+                    self.module_flags(entry.access().names());
+                    try!(self.module_version(entry.requires_version_index));
+                }
+                self.out.indent(-1);
+
+                self.out.print(&exports.len().to_string());
+                self.out.tab();
+                self.out.println("// exports");
+                self.out.indent(1);
+                for entry in exports {
+                    try!(self.module_package(entry.exports_index, entry.exports_flags, &entry.exports_to_index));
+                }
+                self.out.indent(-1);
+
+                self.out.print(&opens.len().to_string());
+                self.out.tab();
+                self.out.println("// opens");
+                self.out.indent(1);
+                for entry in opens {
+                    try!(self.module_package(entry.opens_index, entry.opens_flags, &entry.opens_to_index));
+                }
+                self.out.indent(-1);
+
+                self.out.print(&uses_index.len().to_string());
+                self.out.tab();
+                self.out.println("// uses");
+                self.out.indent(1);
+                for &index in uses_index {
+                    self.out.print(&format!("#{}", index));
+                    self.out.tab();
+                    let constant = try!(self.constant_string(index));
+                    self.out.println(&format!("// {}", constant));
+                }
+                self.out.indent(-1);
+
+                self.out.print(&provides.len().to_string());
+                self.out.tab();
+                self.out.println("// provides");
+                self.out.indent(1);
+                for entry in provides {
+                    self.out.print(&format!("#{}", entry.provides_index));
+                    self.out.tab();
+                    let service = try!(self.constant_string(entry.provides_index));
+                    self.out.println(&format!("// {} with ... {}", service, entry.provides_with_index.len()));
+                    self.out.indent(1);
+                    for &index in &entry.provides_with_index {
+                        self.out.print(&format!("#{}", index));
+                        self.out.tab();
+                        let constant = try!(self.constant_string(index));
+                        self.out.println(&format!("// ... with {}", constant));
+                    }
+                    self.out.indent(-1);
+                }
+                self.out.indent(-1);
+                self.out.indent(-1);
+            },
+            Attribute::ModulePackages { ref package_index } => {
+                self.out.println("ModulePackages: ");
+                self.out.indent(1);
+                for &index in package_index {
+                    self.out.print(&format!("#{}", index));
+                    self.out.tab();
+                    self.out.println(&format!("// {}", java_name(&try!(self.pool.package_name(index)))));
+                }
+                self.out.indent(-1);
+            },
+            Attribute::ModuleMainClass { main_class_index } => {
+                self.out.print(&format!("ModuleMainClass: #{}", main_class_index));
+                self.out.tab();
+                self.out.println(&format!("// {}", java_name(&try!(self.pool.class_name(main_class_index)))));
+            },
+            Attribute::NestHost { host_class_index } => {
+                let host = try!(self.constant_reference(host_class_index));
+                self.out.println(&format!("NestHost: {}", host));
+            },
+            Attribute::NestMembers { ref classes } => {
+                try!(self.class_list("NestMembers:", classes));
+            },
+            Attribute::Record(ref components) => {
+                self.out.println("Record:");
+                self.out.indent(1);
+                for component in components {
+                    try!(self.record_component(component));
+                }
+                self.out.indent(-1);
+            },
+            Attribute::PermittedSubclasses { ref classes } => {
+                try!(self.class_list("PermittedSubclasses:", classes));
+            },
+            Attribute::Unknown { name_index, ref info } => {
+                let name = try!(self.pool.utf8(name_index)).to_string_lossy();
+                if try!(self.jdk_attribute(&name, info)) {
+                    return Result::Ok(());
+                }
+                self.out.println(&format!("  {}: length = 0x{:x}", name, info.len()));
+                self.out.print("   ");
+                for (i, byte) in info.iter().enumerate() {
+                    self.out.print(&format!("{:02x}", byte));
+                    if i % 16 == 15 {
+                        self.out.println("");
+                        self.out.print("   ");
+                    } else {
+                        self.out.print(" ");
+                    }
+                }
+                self.out.println("");
+            }
+        }
+        Result::Ok(())
+    }
+
+    // javap also knows the attributes that the JDK's own tools add to
+    // module-info. Returns false if `name` is not one of them or the data is
+    // malformed, in which case the caller dumps the bytes.
+    fn jdk_attribute(&mut self, name: &str, info: &[u8]) -> ParseResult<bool> {
+        let u2 = |i: usize| if i + 2 <= info.len() { Option::Some((info[i] as u16) << 8 | info[i + 1] as u16) } else { Option::None };
+        match name {
+            "ModuleTarget" if info.len() == 2 => {
+                let platform_index = u2(0).unwrap_or(0);
+                self.out.println("ModuleTarget:");
+                self.out.indent(1);
+                self.out.print(&format!("target_platform: #{}", platform_index));
+                if platform_index != 0 {
+                    self.out.tab();
+                    let platform = try!(self.constant_string(platform_index));
+                    self.out.print(&format!("// {}", platform));
+                }
+                self.out.println("");
+                self.out.indent(-1);
+            },
+            "ModuleResolution" if info.len() == 2 => {
+                let flags = u2(0).unwrap_or(0);
+                self.out.println("ModuleResolution:");
+                self.out.indent(1);
+                self.out.print(&format!("{:x}", flags));
+                self.out.tab();
+                self.out.print("// ");
+                for &(mask, flag) in &[(0x0001, " DO_NOT_RESOLVE_BY_DEFAULT"), (0x0002, " WARN_DEPRECATED"),
+                        (0x0004, " WARN_DEPRECATED_FOR_REMOVAL"), (0x0008, " WARN_INCUBATING")] {
+                    if flags & mask != 0 {
+                        self.out.print(flag);
+                    }
+                }
+                self.out.println("");
+                self.out.indent(-1);
+            },
+            "ModuleHashes" => {
+                let mut hashes = Vec::new();
+                let (algorithm_index, count) = match (u2(0), u2(2)) {
+                    (Option::Some(algorithm_index), Option::Some(count)) => (algorithm_index, count),
+                    _ => { return Result::Ok(false); }
+                };
+                let mut i = 4;
+                for _ in 0..count {
+                    let (module_index, length) = match (u2(i), u2(i + 2)) {
+                        (Option::Some(module_index), Option::Some(length)) => (module_index, length as usize),
+                        _ => { return Result::Ok(false); }
+                    };
+                    if i + 4 + length > info.len() {
+                        return Result::Ok(false);
+                    }
+                    hashes.push((module_index, &info[i + 4..i + 4 + length]));
+                    i += 4 + length;
+                }
+                if i != info.len() {
+                    return Result::Ok(false);
+                }
+                self.out.println("ModuleHashes:");
+                self.out.indent(1);
+                self.out.print(&format!("algorithm: #{}", algorithm_index));
+                self.out.tab();
+                let algorithm = try!(self.constant_string(algorithm_index));
+                self.out.println(&format!("// {}", algorithm));
+                self.out.print(&count.to_string());
+                self.out.tab();
+                self.out.println("// hashes");
+                for (module_index, hash) in hashes {
+                    self.out.print(&format!("#{}", module_index));
+                    self.out.tab();
+                    let module_name = try!(self.pool.module_name(module_index));
+                    self.out.println(&format!("// {}", module_name));
+                    self.out.println(&format!("hash_length: {}", hash.len()));
+                    let hex: Vec<String> = hash.iter().map(|byte| format!("{:02x}", byte)).collect();
+                    self.out.println(&format!("hash: [{}]", hex.concat()));
+                }
+                self.out.indent(-1);
+            },
+            _ => { return Result::Ok(false); }
+        }
+        Result::Ok(true)
+    }
+
+    fn instruction(&mut self, pc: u32, instruction: &Instruction) -> ParseResult<()> {
+        use ::model::Instruction::*;
+
+        self.out.print(&format!("{:4}: {:<13} ", pc, instruction.mnemonic()));
+        let branch = |offset: i32| (pc as i64 + offset as i64).to_string();
+        match *instruction {
+            aload(index) | astore(index) | dload(index) | dstore(index) | fload(index) | fstore(index)
+                    | iload(index) | istore(index) | lload(index) | lstore(index) | ret(index) => {
+                self.out.print(&index.to_string());
+            },
+            aload_w(index) | astore_w(index) | dload_w(index) | dstore_w(index) | fload_w(index)
+                    | fstore_w(index) | iload_w(index) | istore_w(index) | lload_w(index) | lstore_w(index)
+                    | ret_w(index) => {
+                self.out.print(&index.to_string());
+            },
+            iinc(index, value) => self.out.print(&format!("{}, {}", index, value)),
+            iinc_w(index, value) => self.out.print(&format!("{}, {}", index, value)),
+            bipush(value) => self.out.print(&value.to_string()),
+            sipush(value) => self.out.print(&value.to_string()),
+            goto(offset) | if_acmpeq(offset) | if_acmpne(offset) | if_icmpeq(offset) | if_icmpne(offset)
+                    | if_icmplt(offset) | if_icmpge(offset) | if_icmpgt(offset) | if_icmple(offset)
+                    | ifeq(offset) | ifne(offset) | iflt(offset) | ifge(offset) | ifgt(offset) | ifle(offset)
+                    | ifnonnull(offset) | ifnull(offset) | jsr(offset) => {
+                self.out.print(&branch(offset as i32));
+            },
+            goto_w(offset) | jsr_w(offset) => self.out.print(&branch(offset)),
+            ldc(index) => try!(self.constant_operand(index as u16, "")),
+            anewarray(index) | checkcast(index) | getfield(index) | getstatic(index) | instanceof(index)
+                    | invokespecial(index) | invokestatic(index) | invokevirtual(index) | ldc_w(index)
+                    | ldc2_w(index) | new(index) | putfield(index) | putstatic(index) => {
+                try!(self.constant_operand(index, ""));
+            },
+            invokedynamic(index) => try!(self.constant_operand(index, ",  0")),
+            invokeinterface(index, count) => try!(self.constant_operand(index, &format!(",  {}", count))),
+            multianewarray(index, dimensions) => try!(self.constant_operand(index, &format!(",  {}", dimensions))),
+            newarray(ref array_type) => {
+                let name = match *array_type {
+                    ArrayType::Boolean => "boolean",
+                    ArrayType::Char => "char",
+                    ArrayType::Float => "float",
+                    ArrayType::Double => "double",
+                    ArrayType::Byte => "byte",
+                    ArrayType::Short => "short",
+                    ArrayType::Int => "int",
+                    ArrayType::Long => "long"
+                };
+                self.out.print(&format!(" {}", name));
+            },
+            tableswitch(default, low, ref offsets) => {
+                let high = low as i64 + offsets.len() as i64 - 1;
+                self.out.print(&format!("{{ // {} to {}", low, high));
+                self.out.indent(3);
+                for (i, &offset) in offsets.iter().enumerate() {
+                    self.out.print(&format!("\n{:12}: {}", low as i64 + i as i64, branch(offset)));
+                }
+                self.out.print(&format!("\n     default: {}\n}}", branch(default)));
+                self.out.indent(-3);
+            },
+            lookupswitch(default, ref pairs) => {
+                self.out.print(&format!("{{ // {}", pairs.len()));
+                self.out.indent(3);
+                for &(key, offset) in pairs.iter() {
+                    self.out.print(&format!("\n{:12}: {}", key, branch(offset)));
+                }
+                self.out.print(&format!("\n     default: {}\n}}", branch(default)));
+                self.out.indent(-3);
+            },
+            _ => {}
+        }
+        self.out.println("");
+        Result::Ok(())
+    }
+
+    fn constant_operand(&mut self, index: u16, suffix: &str) -> ParseResult<()> {
+        self.out.print(&format!("#{}{}", index, suffix));
+        self.out.tab();
+        let reference = try!(self.constant_reference(index));
+        self.out.print(&format!("// {}", reference));
+        Result::Ok(())
+    }
+
+    fn exception_table(&mut self, exception_table: &[Exception]) -> ParseResult<()> {
+        if exception_table.is_empty() {
+            return Result::Ok(());
+        }
+        self.out.println("Exception table:");
+        self.out.indent(1);
+        self.out.println(" from    to  target type");
+        for exception in exception_table {
+            self.out.print(&format!("{:6}{:6}{:6}   ", exception.start_pc, exception.end_pc, exception.handler_pc));
+            if exception.catch_type == 0 {
+                self.out.println("any");
+            } else {
+                let catch_type = try!(self.constant_string(exception.catch_type));
+                self.out.println(&format!("Class {}", catch_type));
+            }
+        }
+        self.out.indent(-1);
+        Result::Ok(())
+    }
+
+    fn local_variables(&mut self, header: &str, variables: &[LocalVariable]) -> ParseResult<()> {
+        self.out.println(header);
+        self.out.indent(1);
+        self.out.println("Start  Length  Slot  Name   Signature");
+        for variable in variables {
+            let name = try!(self.constant_string(variable.name_index));
+            let descriptor = try!(self.constant_string(variable.descriptor_or_signature_index));
+            self.out.println(&format!("{:5} {:7} {:5} {:>5}   {}",
+                    variable.start_pc, variable.length, variable.index, name, descriptor));
+        }
+        self.out.indent(-1);
+        Result::Ok(())
+    }
+
+    fn stack_map_frame(&mut self, frame: &StackMapFrame) -> ParseResult<()> {
+        match *frame {
+            StackMapFrame::SameFrame { .. } => {
+                self.out.println(&format!("frame_type = {} /* same */", frame.frame_type()));
+            },
+            StackMapFrame::SameLocals1StackItemFrame { ref stack, .. } => {
+                self.out.println(&format!("frame_type = {} /* same_locals_1_stack_item */", frame.frame_type()));
+                self.out.indent(1);
+                try!(self.verification_types("stack", &[stack.clone()]));
+                self.out.indent(-1);
+            },
+            StackMapFrame::SameLocals1StackItemFrameExtended { offset_delta, ref stack } => {
+                self.out.println("frame_type = 247 /* same_locals_1_stack_item_frame_extended */");
+                self.out.indent(1);
+                self.out.println(&format!("offset_delta = {}", offset_delta));
+                try!(self.verification_types("stack", &[stack.clone()]));
+                self.out.indent(-1);
+            },
+            StackMapFrame::ChopFrame { offset_delta, .. } => {
+                self.out.println(&format!("frame_type = {} /* chop */", frame.frame_type()));
+                self.out.indent(1);
+                self.out.println(&format!("offset_delta = {}", offset_delta));
+                self.out.indent(-1);
+            },
+            StackMapFrame::SameFrameExtended { offset_delta } => {
+                self.out.println("frame_type = 251 /* same_frame_extended */");
+                self.out.indent(1);
+                self.out.println(&format!("offset_delta = {}", offset_delta));
+                self.out.indent(-1);
+            },
+            StackMapFrame::AppendFrame { offset_delta, ref locals } => {
+                self.out.println(&format!("frame_type = {} /* append */", frame.frame_type()));
+                self.out.indent(1);
+                self.out.println(&format!("offset_delta = {}", offset_delta));
+                try!(self.verification_types("locals", locals));
+                self.out.indent(-1);
+            },
+            StackMapFrame::FullFrame { offset_delta, ref locals, ref stack } => {
+                self.out.println("frame_type = 255 /* full_frame */");
+                self.out.indent(1);
+                self.out.println(&format!("offset_delta = {}", offset_delta));
+                try!(self.verification_types("locals", locals));
+                try!(self.verification_types("stack", stack));
+                self.out.indent(-1);
+            }
+        }
+        Result::Ok(())
+    }
+
+    fn verification_types(&mut self, name: &str, types: &[VerificationType]) -> ParseResult<()> {
+        self.out.print(&format!("{} = [", name));
+        for (i, verification_type) in types.iter().enumerate() {
+            let text = match *verification_type {
+                VerificationType::Top => "top".to_string(),
+                VerificationType::Integer => "int".to_string(),
+                VerificationType::Float => "float".to_string(),
+                VerificationType::Double => "double".to_string(),
+                VerificationType::Long => "long".to_string(),
+                VerificationType::Null => "null".to_string(),
+                VerificationType::UninitializedThis => "this".to_string(),
+                VerificationType::Object { index } => try!(self.constant_reference(index)),
+                VerificationType::UninitializedVariable { offset } => format!("uninitialized {}", offset)
+            };
+            self.out.print(&format!(" {}", text));
+            self.out.print(if i == types.len() - 1 { " " } else { "," });
+        }
+        self.out.println("]");
+        Result::Ok(())
+    }
+
+    fn inner_class(&mut self, inner_class: &InnerClass) -> ParseResult<()> {
+        let access = inner_class.access();
+        let access = if access.contains(InnerClassAccess::INTERFACE) { access - InnerClassAccess::ABSTRACT } else { access };
+        for modifier in modifiers(&access.keywords()) {
+            self.out.print(&format!("{} ", modifier));
+        }
+        if inner_class.inner_name_index != 0 {
+            self.out.print(&format!("#{}= ", inner_class.inner_name_index));
+        }
+        self.out.print(&format!("#{}", inner_class.inner_class_info_index));
+        if inner_class.outer_class_info_index != 0 {
+            self.out.print(&format!(" of #{}", inner_class.outer_class_info_index));
+        }
+        self.out.print(";");
+        self.out.tab();
+        self.out.print("// ");
+        if inner_class.inner_name_index != 0 {
+            let inner_name = try!(self.pool.utf8(inner_class.inner_name_index)).to_string_lossy();
+            self.out.print(&format!("{}=", inner_name));
+        }
+        let inner = try!(self.constant_reference(inner_class.inner_class_info_index));
+        self.out.print(&inner);
+        if inner_class.outer_class_info_index != 0 {
+            let outer = try!(self.constant_reference(inner_class.outer_class_info_index));
+            self.out.print(&format!(" of {}", outer));
+        }
+        self.out.println("");
+        Result::Ok(())
+    }
+
+    fn class_list(&mut self, header: &str, classes: &[u16]) -> ParseResult<()> {
+        self.out.println(header);
+        self.out.indent(1);
+        for &index in classes {
+            let name = try!(self.constant_string(index));
+            self.out.println(&name);
+        }
+        self.out.indent(-1);
+        Result::Ok(())
+    }
+
+    fn record_component(&mut self, component: &RecordComponent) -> ParseResult<()> {
+        let signature = component.attributes.iter().filter_map(|attribute| match *attribute {
+            Attribute::Signature { signature_index } => Option::Some(signature_index),
+            _ => Option::None
+        }).next();
+        let component_type = match signature {
+            Option::Some(index) => try!(ReferenceTypeSignature::parse(&try!(self.pool.utf8_string(index)))).to_java_source(),
+            Option::None => try!(FieldType::parse(&try!(self.pool.utf8_string(component.descriptor_index)))).to_java_source()
+        };
+        let name = try!(self.pool.utf8(component.name_index)).to_string_lossy();
+        self.out.println(&format!("{} {};", component_type, name));
+        self.out.indent(1);
+        let descriptor = try!(self.pool.utf8(component.descriptor_index)).to_string_lossy();
+        self.out.println(&format!("descriptor: {}", descriptor));
+        try!(self.attributes(&component.attributes));
+        self.out.println("");
+        self.out.indent(-1);
+        Result::Ok(())
+    }
+
+    fn module_flags(&mut self, names: Vec<&str>) {
+        for name in names {
+            self.out.print(&format!(" ACC_{}", name));
+        }
+        self.out.println("");
+    }
+
+    fn module_version(&mut self, version_index: u16) -> ParseResult<()> {
+        self.out.print(&format!("#{}", version_index));
+        if version_index != 0 {
+            self.out.tab();
+            let version = try!(self.constant_string(version_index));
+            self.out.print(&format!("// {}", version));
+        }
+        self.out.println("");
+        Result::Ok(())
+    }
+
+    fn module_package(&mut self, index: u16, flags: u16, to_index: &[u16]) -> ParseResult<()> {
+        self.out.print(&format!("#{},{:x}", index, flags));
+        self.out.tab();
+        let constant = try!(self.constant_string(index));
+        self.out.print(&format!("// {}", constant));
+        let access = ExportsAccess::from_bits(flags);
+        for name in [access & ExportsAccess::MANDATED, access & ExportsAccess::SYNTHETIC].iter().flat_map(ExportsAccess::names) {
+            self.out.print(&format!(" ACC_{}", name));
+        }
+        if to_index.is_empty() {
+            self.out.println("");
+            return Result::Ok(());
+        }
+        self.out.println(&format!(" to ... {}", to_index.len()));
+        self.out.indent(1);
+        for &to in to_index {
+            self.out.print(&format!("#{}", to));
+            self.out.tab();
+            let constant = try!(self.constant_string(to));
+            self.out.println(&format!("// ... to {}", constant));
+        }
+        self.out.indent(-1);
+        Result::Ok(())
+    }
+
+    fn annotations(&mut self, header: &str, annotations: &[Annotation]) -> ParseResult<()> {
+        self.out.println(header);
+        self.out.indent(1);
+        for (i, annotation) in annotations.iter().enumerate() {
+            self.out.print(&format!("{}: ", i));
+            try!(self.annotation(annotation.type_index, &annotation.element_value_pairs, false));
+            self.out.println("");
+            self.out.indent(1);
+            try!(self.annotation(annotation.type_index, &annotation.element_value_pairs, true));
+            self.out.indent(-1);
+            self.out.println("");
+        }
+        self.out.indent(-1);
+        Result::Ok(())
+    }
+
+    fn parameter_annotations(&mut self, header: &str, parameters: &[Vec<Annotation>]) -> ParseResult<()> {
+        self.out.println(header);
+        self.out.indent(1);
+        for (i, annotations) in parameters.iter().enumerate() {
+            self.out.println(&format!("parameter {}: ", i));
+            self.out.indent(1);
+            for (j, annotation) in annotations.iter().enumerate() {
+                self.out.print(&format!("{}: ", j));
+                try!(self.annotation(annotation.type_index, &annotation.element_value_pairs, false));
+                self.out.println("");
+                self.out.indent(1);
+                try!(self.annotation(annotation.type_index, &annotation.element_value_pairs, true));
+                self.out.indent(-1);
+                self.out.println("");
+            }
+            self.out.indent(-1);
+        }
+        self.out.indent(-1);
+        Result::Ok(())
+    }
+
+    fn type_annotations(&mut self, header: &str, annotations: &[TypeAnnotation]) -> ParseResult<()> {
+        self.out.println(header);
+        self.out.indent(1);
+        for (i, annotation) in annotations.iter().enumerate() {
+            self.out.print(&format!("{}: ", i));
+            try!(self.annotation(annotation.type_index, &annotation.element_value_pairs, false));
+            self.out.print(": ");
+            self.out.print(&type_annotation_position(annotation));
+            self.out.println("");
+            self.out.indent(1);
+            try!(self.annotation(annotation.type_index, &annotation.element_value_pairs, true));
+            self.out.indent(-1);
+            self.out.println("");
+        }
+        self.out.indent(-1);
+        Result::Ok(())
+    }
+
+    // With `resolve` unset, constant pool indices are shown as `#n`.
+    fn annotation(&mut self, type_index: u16, pairs: &[ElementValuePair], resolve: bool) -> ParseResult<()> {
+        let type_name = if resolve {
+            self.pool.utf8_string(type_index).ok()
+                .and_then(|descriptor| FieldType::parse(&descriptor).ok())
+                .map(|field_type| field_type.to_java_source())
+        } else {
+            Option::None
+        };
+        self.out.print(&type_name.unwrap_or_else(|| format!("#{}", type_index)));
+        if resolve {
+            if !pairs.is_empty() {
+                self.out.println("(");
+                self.out.indent(1);
+            }
+            for pair in pairs {
+                try!(self.element_value_pair(pair, true));
+                self.out.println("");
+            }
+            if !pairs.is_empty() {
+                self.out.indent(-1);
+                self.out.print(")");
+            }
+        } else {
+            self.out.print("(");
+            for (i, pair) in pairs.iter().enumerate() {
+                if i > 0 {
+                    self.out.print(",");
+                }
+                try!(self.element_value_pair(pair, false));
+            }
+            self.out.print(")");
+        }
+        Result::Ok(())
+    }
+
+    fn element_value_pair(&mut self, pair: &ElementValuePair, resolve: bool) -> ParseResult<()> {
+        let name = try!(self.index(pair.element_name_index, resolve));
+        self.out.print(&format!("{}=", name));
+        self.element_value(&pair.value, resolve)
+    }
+
+    fn index(&self, index: u16, resolve: bool) -> ParseResult<String> {
+        if resolve {
+            self.constant_string(index)
+        } else {
+            Result::Ok(format!("#{}", index))
+        }
+    }
+
+    fn element_value(&mut self, value: &ElementValue, resolve: bool) -> ParseResult<()> {
+        match *value {
+            ElementValue::Constant { tag, const_value_index } => {
+                if !resolve {
+                    self.out.print(&format!("{}#{}", tag, const_value_index));
+                    return Result::Ok(());
+                }
+                let text = match tag {
+                    'B' => format!("(byte) {}", try!(self.constant_string(const_value_index))),
+                    'S' => format!("(short) {}", try!(self.constant_string(const_value_index))),
+                    'D' | 'F' | 'I' | 'J' => try!(self.constant_string(const_value_index)),
+                    's' => format!("\"{}\"", try!(self.constant_string(const_value_index))),
+                    'C' | 'Z' => {
+                        let value = match *try!(self.pool.get(const_value_index)) {
+                            ConstantPoolInfo::Integer(value) => value,
+                            ref other => {
+                                let message = format!("expected integer at index {} but got {:?}", const_value_index, other);
+                                return Result::Err(ParseError::Format(message));
+                            }
+                        };
+                        if tag == 'Z' {
+                            (if value == 0 { "false" } else { "true" }).to_string()
+                        } else {
+                            let c = char::decode_utf16(Option::Some(value as u16)).next()
+                                .and_then(|c| c.ok()).unwrap_or(char::REPLACEMENT_CHARACTER);
+                            format!("'{}'", c)
+                        }
+                    },
+                    _ => format!("{}#{}", tag, const_value_index)
+                };
+                self.out.print(&text);
+            },
+            ElementValue::EnumConstant { type_name_index, const_name_index } => {
+                if resolve {
+                    let text = format!("{}.{}", try!(self.constant_string(type_name_index)),
+                            try!(self.constant_string(const_name_index)));
+                    self.out.print(&text);
+                } else {
+                    self.out.print(&format!("e#{}.#{}", type_name_index, const_name_index));
+                }
+            },
+            ElementValue::Class { class_info_index } => {
+                if resolve {
+                    let text = format!("class {}", try!(self.constant_string(class_info_index)));
+                    self.out.print(&text);
+                } else {
+                    self.out.print(&format!("c#{}", class_info_index));
+                }
+            },
+            ElementValue::Annotation(ref annotation) => {
+                self.out.print("@");
+                try!(self.annotation(annotation.type_index, &annotation.element_value_pairs, resolve));
+            },
+            ElementValue::Array(ref values) => {
+                self.out.print("[");
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        self.out.print(",");
+                    }
+                    try!(self.element_value(value, resolve));
+                }
+                self.out.print("]");
+            }
+        }
+        Result::Ok(())
+    }
+}
+
+fn type_annotation_position(annotation: &TypeAnnotation) -> String {
+    let target = match annotation.target_type {
+        TargetType::Type => "CLASS_TYPE_PARAMETER",
+        TargetType::Method => "METHOD_TYPE_PARAMETER",
+        TargetType::Supertype => "CLASS_EXTENDS",
+        TargetType::TypeBound => "CLASS_TYPE_PARAMETER_BOUND",
+        TargetType::MethodBound => "METHOD_TYPE_PARAMETER_BOUND",
+        TargetType::Field => "FIELD",
+        TargetType::MethodReturnType => "METHOD_RETURN",
+        TargetType::ReceiverType => "METHOD_RECEIVER",
+        TargetType::Parameter => "METHOD_FORMAL_PARAMETER",
+        TargetType::Throws => "THROWS",
+        TargetType::LocalVariableDeclaration => "LOCAL_VARIABLE",
+        TargetType::ResourceVariableDeclaration => "RESOURCE_VARIABLE",
+        TargetType::ExceptionParameterDeclaration => "EXCEPTION_PARAMETER",
+        TargetType::Instanceof => "INSTANCEOF",
+        TargetType::New => "NEW",
+        TargetType::MethodReferenceNew => "CONSTRUCTOR_REFERENCE",
+        TargetType::MethodReference => "METHOD_REFERENCE",
+        TargetType::Cast => "CAST",
+        TargetType::ConstructorArgument => "CONSTRUCTOR_INVOCATION_TYPE_ARGUMENT",
+        TargetType::MethodArgument => "METHOD_INVOCATION_TYPE_ARGUMENT",
+        TargetType::MethodReferenceNewArgument => "CONSTRUCTOR_REFERENCE_TYPE_ARGUMENT",
+        TargetType::MethodReferenceArgument => "METHOD_REFERENCE_TYPE_ARGUMENT"
+    };
+    let mut position = target.to_string();
+    match annotation.target_info {
+        TargetInfo::TypeParameter { index } => position.push_str(&format!(", param_index={}", index)),
+        TargetInfo::Supertype { index } => position.push_str(&format!(", type_index={}", index)),
+        TargetInfo::TypeParameterBound { index, bound_index } => {
+            position.push_str(&format!(", param_index={}, bound_index={}", index, bound_index));
+        },
+        TargetInfo::Empty => {},
+        TargetInfo::MethodFormalParameter { index } => position.push_str(&format!(", param_index={}", index)),
+        TargetInfo::Throws { type_index } => position.push_str(&format!(", type_index={}", type_index)),
+        TargetInfo::Localvar(ref targets) => {
+            let targets: Vec<String> = targets.iter().map(|target| {
+                format!("start_pc={}, length={}, index={}", target.start_pc, target.length, target.index)
+            }).collect();
+            position.push_str(&format!(", {{{}}}", targets.join("; ")));
+        },
+        TargetInfo::Catch { exception_table_index } => {
+            position.push_str(&format!(", exception_index={}", exception_table_index));
+        },
+        TargetInfo::Offset(offset) => position.push_str(&format!(", offset={}", offset)),
+        TargetInfo::TypeArgument { offset, index } => {
+            position.push_str(&format!(", offset={}, type_index={}", offset, index));
+        }
+    }
+    if !annotation.type_path.path.is_empty() {
+        let path: Vec<String> = annotation.type_path.path.iter().map(|element| match element.kind {
+            TypePathKind::Array => "ARRAY".to_string(),
+            TypePathKind::Nested => "INNER_TYPE".to_string(),
+            TypePathKind::WildcardBound => "WILDCARD".to_string(),
+            TypePathKind::TypeArgument => format!("TYPE_ARGUMENT({})", element.argument_index)
+        }).collect();
+        position.push_str(&format!(", location=[{}]", path.join(", ")));
+    }
+    position
+}
+
+fn descriptor_types(descriptor: &MethodDescriptor) -> (Vec<String>, String) {
+    let parameters = descriptor.parameters.iter().map(FieldType::to_java_source).collect();
+    let return_type = match descriptor.return_type {
+        Option::Some(ref return_type) => return_type.to_java_source(),
+        Option::None => "void".to_string()
+    };
+    (parameters, return_type)
+}
+
+fn signature_types(signature: &MethodSignature) -> (Vec<String>, String) {
+    let parameters = signature.parameters.iter().map(|p| p.to_java_source()).collect();
+    let return_type = match signature.return_type {
+        Option::Some(ref return_type) => return_type.to_java_source(),
+        Option::None => "void".to_string()
+    };
+    (parameters, return_type)
+}
+
+// javap prints generic thrown types with the class name in internal form.
+fn internal_source(signature: &ReferenceTypeSignature) -> String {
+    match *signature {
+        ReferenceTypeSignature::Class(ref class_type) => {
+            let mut source = class_type.package.clone();
+            if !source.is_empty() {
+                source.push('/');
+            }
+            source.push_str(&class_type.class.to_java_source());
+            for inner in &class_type.inner {
+                source.push('.');
+                source.push_str(&inner.to_java_source());
+            }
+            source
+        },
+        _ => signature.to_java_source()
+    }
+}
+
+fn java_name(name: &str) -> String {
+    name.replace('/', ".")
+}
+
+// Quotes names that are not valid binary names, as javap does.
+fn check_name(name: &str) -> String {
+    if name.is_empty() {
+        return "\"\"".to_string();
+    }
+    let mut previous = '/';
+    for c in name.chars() {
+        let start = c.is_alphabetic() || c == '$' || c == '_';
+        let part = start || c.is_numeric() || is_identifier_ignorable(c);
+        if (previous == '/' && !start) || (c != '/' && !part) {
+            let escaped = name.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n").replace('\t', "\\t");
+            return format!("\"{}\"", escaped);
+        }
+        previous = c;
+    }
+    name.to_string()
+}
+
+fn is_identifier_ignorable(c: char) -> bool {
+    let c = c as u32;
+    c <= 0x08 || (c >= 0x0e && c <= 0x1b) || (c >= 0x7f && c <= 0x9f)
+}
+
+fn escape_utf8(units: &[u16]) -> String {
+    let mut escaped = String::with_capacity(units.len());
+    for c in char::decode_utf16(units.iter().cloned()) {
+        // javap's output encoder turns unpaired surrogates into '?'.
+        let c = c.unwrap_or('?');
+        match c {
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\u{8}' => escaped.push_str("\\b"),
+            '\u{c}' => escaped.push_str("\\f"),
+            '"' => escaped.push_str("\\\""),
+            '\'' => escaped.push_str("\\'"),
+            '\\' => escaped.push_str("\\\\"),
+            _ if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            _ => escaped.push(c)
+        }
+    }
+    escaped
+}
+
+// Floating point constants are printed as by `Double.toString` since Java 19:
+// the decimal closest to the value among the shortest ones, of at least two
+// significant digits, that read back as the same value. Older javap versions
+// sometimes print more digits.
+fn java_float(value: f32) -> String {
+    if value.is_finite() {
+        let precision = significant_digits(&format!("{:e}", value.abs()));
+        java_floating_point(value.is_sign_negative(), &format!("{:.*e}", precision - 1, value.abs()))
+    } else {
+        java_special(value as f64)
+    }
+}
+
+fn java_double(value: f64) -> String {
+    if value.is_finite() {
+        let precision = significant_digits(&format!("{:e}", value.abs()));
+        java_floating_point(value.is_sign_negative(), &format!("{:.*e}", precision - 1, value.abs()))
+    } else {
+        java_special(value)
+    }
+}
+
+fn significant_digits(scientific: &str) -> usize {
+    let digits = scientific.chars().take_while(|&c| c != 'e').filter(char::is_ascii_digit).count();
+    if digits < 2 { 2 } else { digits }
+}
+
+fn java_special(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value > 0.0 {
+        "Infinity".to_string()
+    } else {
+        "-Infinity".to_string()
+    }
+}
+
+// Converts Rust's scientific notation of a magnitude, e.g. `1.250e-4`, to the
+// notation of `Double.toString`: plain for magnitudes in [1e-3, 1e7) and
+// `1.25E-4` otherwise. The sign is passed separately since older versions of
+// Rust format negative zero without one.
+fn java_floating_point(negative: bool, scientific: &str) -> String {
+    let (mantissa, exponent) = scientific.split_at(scientific.find('e').unwrap_or(scientific.len()));
+    let exponent: i32 = exponent[1..].parse().unwrap_or(0);
+    let sign = if negative { "-" } else { "" };
+    let digits: String = mantissa.chars().filter(|&c| c != '.').collect();
+    let digits = match digits.trim_right_matches('0') {
+        "" => "0".to_string(),
+        trimmed => trimmed.to_string()
+    };
+    if digits == "0" {
+        return format!("{}0.0", sign);
+    }
+    if exponent >= -3 && exponent < 7 {
+        if exponent < 0 {
+            format!("{}0.{}{}", sign, "0".repeat((-exponent - 1) as usize), digits)
+        } else {
+            let point = exponent as usize + 1;
+            let mut digits = digits;
+            while digits.len() < point {
+                digits.push('0');
+            }
+            let fraction = if digits.len() > point { &digits[point..] } else { "0" };
+            format!("{}{}.{}", sign, &digits[..point], fraction)
+        }
+    } else {
+        let fraction = if digits.len() > 1 { &digits[1..] } else { "0" };
+        format!("{}{}.{}E{}", sign, &digits[..1], fraction, exponent)
+    }
+}
@@ -0,0 +1,225 @@
+use ::cfg::ControlFlowGraph;
+
+/// The dominator or post-dominator tree of a control-flow graph.
+///
+/// A block dominates another if every path from the entry to the other block
+/// passes through it, and post-dominates it if every path from the other block
+/// to an exit does. Exits are the blocks without successors, so paths that
+/// leave a method by an uncaught exception are not considered. Blocks that are
+/// not connected to the entry, or to an exit for post-dominators, are not in
+/// the tree.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DominatorTree {
+    // The immediate dominator of every block in the tree, where the number of
+    // blocks stands for a root above the entry or the exits.
+    parents: Vec<Option<usize>>,
+    children: Vec<Vec<usize>>,
+    // The interval of every block in a preorder walk of the tree, so that a
+    // dominates b if a's interval contains b's.
+    intervals: Vec<(usize, usize)>,
+    post: bool
+}
+
+impl DominatorTree {
+    /// The dominator tree, rooted at block 0.
+    pub fn new(graph: &ControlFlowGraph) -> DominatorTree {
+        let successors = graph.blocks.iter()
+            .map(|block| block.successors.iter().map(|edge| edge.to).collect())
+            .collect();
+        let roots = if graph.blocks.is_empty() { Vec::new() } else { vec![0] };
+        DominatorTree::compute(successors, &roots, false)
+    }
+
+    /// The post-dominator tree, whose roots are the exits.
+    pub fn post_dominators(graph: &ControlFlowGraph) -> DominatorTree {
+        let predecessors = graph.blocks.iter()
+            .map(|block| block.predecessors.iter().map(|edge| edge.from).collect())
+            .collect();
+        let exits: Vec<usize> = (0..graph.blocks.len()).filter(|&block| graph.blocks[block].successors.is_empty()).collect();
+        DominatorTree::compute(predecessors, &exits, true)
+    }
+
+    // The algorithm of Cooper, Harvey and Kennedy, "A Simple, Fast Dominance
+    // Algorithm", over the edges in `successors`.
+    fn compute(mut successors: Vec<Vec<usize>>, roots: &[usize], post: bool) -> DominatorTree {
+        let count = successors.len();
+        successors.push(roots.to_vec());
+        let order = reverse_postorder(&successors, count);
+        let mut numbers = vec![::std::usize::MAX; count + 1];
+        for (number, &node) in order.iter().enumerate() {
+            numbers[node] = number;
+        }
+        let mut predecessors = vec![Vec::new(); count + 1];
+        for &node in &order {
+            for &successor in &successors[node] {
+                predecessors[successor].push(node);
+            }
+        }
+
+        let mut idoms = vec![Option::None; count + 1];
+        idoms[count] = Option::Some(count);
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &node in order.iter().skip(1) {
+                let mut idom = Option::None;
+                for &predecessor in &predecessors[node] {
+                    if idoms[predecessor].is_none() {
+                        continue;
+                    }
+                    idom = Option::Some(match idom {
+                        Option::None => predecessor,
+                        Option::Some(mut other) => {
+                            let mut finger = predecessor;
+                            while finger != other {
+                                while numbers[finger] > numbers[other] {
+                                    finger = idoms[finger].unwrap();
+                                }
+                                while numbers[other] > numbers[finger] {
+                                    other = idoms[other].unwrap();
+                                }
+                            }
+                            finger
+                        }
+                    });
+                }
+                if idom != idoms[node] {
+                    idoms[node] = idom;
+                    changed = true;
+                }
+            }
+        }
+
+        idoms.truncate(count);
+        let mut children = vec![Vec::new(); count + 1];
+        for (node, &idom) in idoms.iter().enumerate() {
+            if let Option::Some(idom) = idom {
+                children[idom].push(node);
+            }
+        }
+        let mut intervals = vec![(0, 0); count + 1];
+        let mut next = 0;
+        let mut stack = vec![(count, 0)];
+        while !stack.is_empty() {
+            let top = stack.len() - 1;
+            let (node, child) = stack[top];
+            if child == 0 {
+                intervals[node].0 = next;
+                next += 1;
+            }
+            match children[node].get(child) {
+                Option::Some(&grandchild) => {
+                    stack[top].1 += 1;
+                    stack.push((grandchild, 0));
+                },
+                Option::None => {
+                    intervals[node].1 = next;
+                    stack.pop();
+                }
+            }
+        }
+        intervals.truncate(count);
+        DominatorTree { parents: idoms, children: children, intervals: intervals, post: post }
+    }
+
+    pub fn is_post_dominator_tree(&self) -> bool {
+        self.post
+    }
+
+    /// Whether the block is connected to the entry, or to an exit for
+    /// post-dominators.
+    pub fn contains(&self, block: usize) -> bool {
+        self.parents[block].is_some()
+    }
+
+    /// The entry, or the exits for post-dominators.
+    pub fn roots(&self) -> &[usize] {
+        &self.children[self.parents.len()]
+    }
+
+    /// The immediate dominator of a block, which is none for roots and for
+    /// blocks not in the tree.
+    pub fn immediate_dominator(&self, block: usize) -> Option<usize> {
+        match self.parents[block] {
+            Option::Some(parent) if parent != self.parents.len() => Option::Some(parent),
+            _ => Option::None
+        }
+    }
+
+    /// The blocks that the block immediately dominates.
+    pub fn children(&self, block: usize) -> &[usize] {
+        &self.children[block]
+    }
+
+    /// Whether `a` dominates `b`. Every block in the tree dominates itself.
+    pub fn dominates(&self, a: usize, b: usize) -> bool {
+        self.contains(a) && self.contains(b)
+            && self.intervals[a].0 <= self.intervals[b].0 && self.intervals[b].1 <= self.intervals[a].1
+    }
+
+    /// The dominance frontier of every block: the blocks where its dominance
+    /// ends, because they have another predecessor it does not dominate. For
+    /// post-dominators these are the blocks the block is control dependent on.
+    pub fn frontiers(&self, graph: &ControlFlowGraph) -> Vec<Vec<usize>> {
+        let root = self.parents.len();
+        let mut frontiers = vec![Vec::new(); root];
+        for block in 0..root {
+            let idom = match self.parents[block] {
+                Option::Some(idom) => idom,
+                Option::None => continue
+            };
+            let mut predecessors: Vec<usize> = if self.post {
+                graph.blocks[block].successors.iter().map(|edge| edge.to).collect()
+            } else {
+                graph.blocks[block].predecessors.iter().map(|edge| edge.from).collect()
+            };
+            predecessors.retain(|&predecessor| self.contains(predecessor));
+            if self.roots().contains(&block) {
+                predecessors.push(root);
+            }
+            if predecessors.len() < 2 {
+                continue;
+            }
+            for predecessor in predecessors {
+                let mut runner = predecessor;
+                while runner != idom && runner != root {
+                    if frontiers[runner].last() != Option::Some(&block) {
+                        frontiers[runner].push(block);
+                    }
+                    runner = self.parents[runner].unwrap();
+                }
+            }
+        }
+        for frontier in frontiers.iter_mut() {
+            frontier.sort();
+        }
+        frontiers
+    }
+}
+
+// The nodes reachable from `root` in reverse postorder.
+fn reverse_postorder(successors: &[Vec<usize>], root: usize) -> Vec<usize> {
+    let mut order = Vec::with_capacity(successors.len());
+    let mut visited = vec![false; successors.len()];
+    let mut stack = vec![(root, 0)];
+    visited[root] = true;
+    while !stack.is_empty() {
+        let top = stack.len() - 1;
+        let (node, next) = stack[top];
+        match successors[node].get(next) {
+            Option::Some(&successor) => {
+                stack[top].1 += 1;
+                if !visited[successor] {
+                    visited[successor] = true;
+                    stack.push((successor, 0));
+                }
+            },
+            Option::None => {
+                order.push(node);
+                stack.pop();
+            }
+        }
+    }
+    order.reverse();
+    order
+}
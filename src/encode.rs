@@ -0,0 +1,518 @@
+use ::model::{Exception, Instruction, LineNumber, LocalVariable, LocalVariableTarget, TargetInfo, TypeAnnotation};
+use ::model::Instruction::*;
+use ::model::ArrayType;
+use ::result::{ParseError, ParseResult};
+
+use std::collections::BTreeMap;
+
+/// Encodes instructions back to bytecode. The instructions are laid out anew with
+/// `relocate_code`, so branches stay intact when instructions change size.
+pub fn encode_code(code: &[(u32, Instruction)]) -> ParseResult<Vec<u8>> {
+    let (relocated, _) = try!(relocate_code(code));
+    let mut bytes = Vec::new();
+    for &(pc, ref instruction) in relocated.iter() {
+        try!(encode_instruction(&mut bytes, pc, instruction));
+    }
+    if bytes.len() > 65535 {
+        return Result::Err(ParseError::Encode(format!("code length {} exceeds 65535 bytes", bytes.len())));
+    }
+    Result::Ok(bytes)
+}
+
+/// Computes the offset every instruction has when the list is encoded and
+/// rewrites branch offsets accordingly. The returned `PcMap` moves the pcs of
+/// exception tables and debug attributes the same way, so that they stay
+/// consistent with the code.
+///
+/// The recorded offsets identify branch targets: a branch from an instruction
+/// recorded at `pc` with offset `o` jumps to the first instruction recorded at
+/// `pc + o`. Instructions inserted by a tool may therefore reuse the offset of a
+/// neighbour.
+pub fn relocate_code(code: &[(u32, Instruction)]) -> ParseResult<(Vec<(u32, Instruction)>, PcMap)> {
+    let mut new_pcs = Vec::with_capacity(code.len());
+    let mut bytes = Vec::new();
+    for &(_, ref instruction) in code.iter() {
+        let new_pc = bytes.len() as u32;
+        try!(encode_instruction(&mut bytes, new_pc, instruction));
+        new_pcs.push(new_pc);
+    }
+
+    let mut targets = BTreeMap::new();
+    for (&(pc, _), &new_pc) in code.iter().zip(new_pcs.iter()) {
+        targets.entry(pc).or_insert(new_pc);
+    }
+    let old_end = code.last().map_or(0, |&(pc, ref instruction)| pc + instruction.length(pc));
+    let map = PcMap {
+        unchanged: old_end == bytes.len() as u32 && code.iter().zip(new_pcs.iter()).all(|(&(pc, _), &new_pc)| pc == new_pc),
+        pcs: targets,
+        old_end: old_end,
+        new_end: bytes.len() as u32
+    };
+
+    let mut relocated = Vec::with_capacity(code.len());
+    for (&(pc, ref instruction), &new_pc) in code.iter().zip(new_pcs.iter()) {
+        let instruction = try!(map_branch_offsets(instruction, |offset| {
+            let target = pc as i64 + offset as i64;
+            match map.pcs.get(&(target as u32)) {
+                Option::Some(&new_target) if target >= 0 => Result::Ok(new_target as i64 - new_pc as i64),
+                _ => {
+                    let message = format!("branch at {} targets {} which is not the start of an instruction", pc, target);
+                    Result::Err(ParseError::Encode(message))
+                }
+            }
+        }));
+        relocated.push((new_pc, instruction));
+    }
+    Result::Ok((relocated, map))
+}
+
+/// Where `relocate_code` moved the instructions. A recorded pc maps to the new
+/// pc of the first instruction recorded at it, and the end of the recorded
+/// code to the end of the new code, so that ranges ending there keep covering
+/// the last instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PcMap {
+    unchanged: bool,
+    pcs: BTreeMap<u32, u32>,
+    old_end: u32,
+    new_end: u32
+}
+
+impl PcMap {
+    /// Whether every instruction stays at its recorded pc.
+    pub fn is_unchanged(&self) -> bool {
+        self.unchanged
+    }
+
+    pub fn pc(&self, pc: u32) -> Option<u32> {
+        match self.pcs.get(&pc) {
+            Option::Some(&new_pc) => Option::Some(new_pc),
+            Option::None if pc == self.old_end => Option::Some(self.new_end),
+            Option::None => Option::None
+        }
+    }
+
+    fn map(&self, pc: u32, what: &str) -> ParseResult<u16> {
+        match self.pc(pc) {
+            Option::Some(new_pc) if new_pc <= 0xffff => Result::Ok(new_pc as u16),
+            _ => Result::Err(ParseError::Encode(format!("{} refers to {} which is not the start of an instruction", what, pc)))
+        }
+    }
+
+    fn range(&self, start_pc: u16, length: u16, what: &str) -> ParseResult<(u16, u16)> {
+        let start = try!(self.map(start_pc as u32, what));
+        let end = try!(self.map(start_pc as u32 + length as u32, what));
+        if end < start {
+            return Result::Err(ParseError::Encode(format!("{} at {} ends before it starts after relocation", what, start_pc)));
+        }
+        Result::Ok((start, end - start))
+    }
+
+    pub fn exception(&self, exception: &Exception) -> ParseResult<Exception> {
+        let start_pc = try!(self.map(exception.start_pc as u32, "exception table"));
+        let end_pc = try!(self.map(exception.end_pc as u32, "exception table"));
+        Result::Ok(Exception {
+            start_pc: start_pc,
+            end_pc: end_pc,
+            handler_pc: try!(self.map(exception.handler_pc as u32, "exception table")),
+            catch_type: exception.catch_type
+        })
+    }
+
+    pub fn line_number(&self, line_number: &LineNumber) -> ParseResult<LineNumber> {
+        Result::Ok(LineNumber {
+            start_pc: try!(self.map(line_number.start_pc as u32, "line number table")),
+            line_number: line_number.line_number
+        })
+    }
+
+    pub fn local_variable(&self, variable: &LocalVariable) -> ParseResult<LocalVariable> {
+        let (start_pc, length) = try!(self.range(variable.start_pc, variable.length, "local variable table"));
+        Result::Ok(LocalVariable {
+            start_pc: start_pc,
+            length: length,
+            name_index: variable.name_index,
+            descriptor_or_signature_index: variable.descriptor_or_signature_index,
+            index: variable.index
+        })
+    }
+
+    /// Moves the offsets of a type annotation in a `Code` attribute. Other
+    /// targets are returned as they are.
+    pub fn type_annotation(&self, annotation: &TypeAnnotation) -> ParseResult<TypeAnnotation> {
+        let target_info = match annotation.target_info {
+            TargetInfo::Offset(offset) => TargetInfo::Offset(try!(self.map(offset as u32, "type annotation"))),
+            TargetInfo::TypeArgument { offset, index } => TargetInfo::TypeArgument {
+                offset: try!(self.map(offset as u32, "type annotation")),
+                index: index
+            },
+            TargetInfo::Localvar(ref targets) => {
+                let mut moved = Vec::with_capacity(targets.len());
+                for target in targets {
+                    let (start_pc, length) = try!(self.range(target.start_pc, target.length, "type annotation"));
+                    moved.push(LocalVariableTarget { start_pc: start_pc, length: length, index: target.index });
+                }
+                TargetInfo::Localvar(moved)
+            },
+            ref other => other.clone()
+        };
+        Result::Ok(TypeAnnotation { target_info: target_info, ..annotation.clone() })
+    }
+}
+
+fn map_branch_offsets<F>(instruction: &Instruction, mut f: F) -> ParseResult<Instruction>
+        where F: FnMut(i32) -> ParseResult<i64> {
+    fn short<F>(f: &mut F, offset: i16) -> ParseResult<i16> where F: FnMut(i32) -> ParseResult<i64> {
+        let new_offset = try!(f(offset as i32));
+        if new_offset < i16::min_value() as i64 || new_offset > i16::max_value() as i64 {
+            let message = format!("branch offset {} does not fit into 16 bits", new_offset);
+            return Result::Err(ParseError::Encode(message));
+        }
+        Result::Ok(new_offset as i16)
+    }
+    let mapped = match *instruction {
+        goto(offset) => goto(try!(short(&mut f, offset))),
+        if_acmpeq(offset) => if_acmpeq(try!(short(&mut f, offset))),
+        if_acmpne(offset) => if_acmpne(try!(short(&mut f, offset))),
+        if_icmpeq(offset) => if_icmpeq(try!(short(&mut f, offset))),
+        if_icmpne(offset) => if_icmpne(try!(short(&mut f, offset))),
+        if_icmplt(offset) => if_icmplt(try!(short(&mut f, offset))),
+        if_icmpge(offset) => if_icmpge(try!(short(&mut f, offset))),
+        if_icmpgt(offset) => if_icmpgt(try!(short(&mut f, offset))),
+        if_icmple(offset) => if_icmple(try!(short(&mut f, offset))),
+        ifeq(offset) => ifeq(try!(short(&mut f, offset))),
+        ifne(offset) => ifne(try!(short(&mut f, offset))),
+        iflt(offset) => iflt(try!(short(&mut f, offset))),
+        ifge(offset) => ifge(try!(short(&mut f, offset))),
+        ifgt(offset) => ifgt(try!(short(&mut f, offset))),
+        ifle(offset) => ifle(try!(short(&mut f, offset))),
+        ifnonnull(offset) => ifnonnull(try!(short(&mut f, offset))),
+        ifnull(offset) => ifnull(try!(short(&mut f, offset))),
+        jsr(offset) => jsr(try!(short(&mut f, offset))),
+        goto_w(offset) => goto_w(try!(f(offset)) as i32),
+        jsr_w(offset) => jsr_w(try!(f(offset)) as i32),
+        lookupswitch(default, ref pairs) => {
+            let default = try!(f(default)) as i32;
+            let mut new_pairs = Vec::with_capacity(pairs.len());
+            for &(match_, offset) in pairs.iter() {
+                new_pairs.push((match_, try!(f(offset)) as i32));
+            }
+            lookupswitch(default, new_pairs.into_boxed_slice())
+        }
+        tableswitch(default, low, ref offsets) => {
+            let default = try!(f(default)) as i32;
+            let mut new_offsets = Vec::with_capacity(offsets.len());
+            for &offset in offsets.iter() {
+                new_offsets.push(try!(f(offset)) as i32);
+            }
+            tableswitch(default, low, new_offsets.into_boxed_slice())
+        }
+        ref other => other.clone()
+    };
+    Result::Ok(mapped)
+}
+
+fn encode_instruction(bytes: &mut Vec<u8>, pc: u32, instruction: &Instruction) -> ParseResult<()> {
+    match *instruction {
+        aaload => write_u8(bytes, 0x32),
+        aastore => write_u8(bytes, 0x53),
+        aconst_null => write_u8(bytes, 0x01),
+        aload(index) => { write_u8(bytes, 0x19); write_u8(bytes, index); }
+        aload_0 => write_u8(bytes, 0x2a),
+        aload_1 => write_u8(bytes, 0x2b),
+        aload_2 => write_u8(bytes, 0x2c),
+        aload_3 => write_u8(bytes, 0x2d),
+        anewarray(index) => { write_u8(bytes, 0xbd); write_u16(bytes, index); }
+        areturn => write_u8(bytes, 0xb0),
+        arraylength => write_u8(bytes, 0xbe),
+        astore(index) => { write_u8(bytes, 0x3a); write_u8(bytes, index); }
+        astore_0 => write_u8(bytes, 0x4b),
+        astore_1 => write_u8(bytes, 0x4c),
+        astore_2 => write_u8(bytes, 0x4d),
+        astore_3 => write_u8(bytes, 0x4e),
+        athrow => write_u8(bytes, 0xbf),
+        baload => write_u8(bytes, 0x33),
+        bastore => write_u8(bytes, 0x54),
+        bipush(value) => { write_u8(bytes, 0x10); write_u8(bytes, value as u8); }
+        caload => write_u8(bytes, 0x34),
+        castore => write_u8(bytes, 0x55),
+        checkcast(index) => { write_u8(bytes, 0xc0); write_u16(bytes, index); }
+        d2f => write_u8(bytes, 0x90),
+        d2i => write_u8(bytes, 0x8e),
+        d2l => write_u8(bytes, 0x8f),
+        dadd => write_u8(bytes, 0x63),
+        daload => write_u8(bytes, 0x31),
+        dastore => write_u8(bytes, 0x52),
+        dcmpg => write_u8(bytes, 0x98),
+        dcmpl => write_u8(bytes, 0x97),
+        dconst_0 => write_u8(bytes, 0x0e),
+        dconst_1 => write_u8(bytes, 0x0f),
+        ddiv => write_u8(bytes, 0x6f),
+        dload(index) => { write_u8(bytes, 0x18); write_u8(bytes, index); }
+        dload_0 => write_u8(bytes, 0x26),
+        dload_1 => write_u8(bytes, 0x27),
+        dload_2 => write_u8(bytes, 0x28),
+        dload_3 => write_u8(bytes, 0x29),
+        dmul => write_u8(bytes, 0x6b),
+        dneg => write_u8(bytes, 0x77),
+        drem => write_u8(bytes, 0x73),
+        dreturn => write_u8(bytes, 0xaf),
+        dstore(index) => { write_u8(bytes, 0x39); write_u8(bytes, index); }
+        dstore_0 => write_u8(bytes, 0x47),
+        dstore_1 => write_u8(bytes, 0x48),
+        dstore_2 => write_u8(bytes, 0x49),
+        dstore_3 => write_u8(bytes, 0x4a),
+        dsub => write_u8(bytes, 0x67),
+        dup => write_u8(bytes, 0x59),
+        dup_x1 => write_u8(bytes, 0x5a),
+        dup_x2 => write_u8(bytes, 0x5b),
+        dup2 => write_u8(bytes, 0x5c),
+        dup2_x1 => write_u8(bytes, 0x5d),
+        dup2_x2 => write_u8(bytes, 0x5e),
+        f2d => write_u8(bytes, 0x8d),
+        f2i => write_u8(bytes, 0x8b),
+        f2l => write_u8(bytes, 0x8c),
+        fadd => write_u8(bytes, 0x62),
+        faload => write_u8(bytes, 0x30),
+        fastore => write_u8(bytes, 0x51),
+        fcmpg => write_u8(bytes, 0x96),
+        fcmpl => write_u8(bytes, 0x95),
+        fconst_0 => write_u8(bytes, 0x0b),
+        fconst_1 => write_u8(bytes, 0x0c),
+        fconst_2 => write_u8(bytes, 0x0d),
+        fdiv => write_u8(bytes, 0x6e),
+        fload(index) => { write_u8(bytes, 0x17); write_u8(bytes, index); }
+        fload_0 => write_u8(bytes, 0x22),
+        fload_1 => write_u8(bytes, 0x23),
+        fload_2 => write_u8(bytes, 0x24),
+        fload_3 => write_u8(bytes, 0x25),
+        fmul => write_u8(bytes, 0x6a),
+        fneg => write_u8(bytes, 0x76),
+        frem => write_u8(bytes, 0x72),
+        freturn => write_u8(bytes, 0xae),
+        fstore(index) => { write_u8(bytes, 0x38); write_u8(bytes, index); }
+        fstore_0 => write_u8(bytes, 0x43),
+        fstore_1 => write_u8(bytes, 0x44),
+        fstore_2 => write_u8(bytes, 0x45),
+        fstore_3 => write_u8(bytes, 0x46),
+        fsub => write_u8(bytes, 0x66),
+        getfield(index) => { write_u8(bytes, 0xb4); write_u16(bytes, index); }
+        getstatic(index) => { write_u8(bytes, 0xb2); write_u16(bytes, index); }
+        goto(offset) => { write_u8(bytes, 0xa7); write_u16(bytes, offset as u16); }
+        goto_w(offset) => { write_u8(bytes, 0xc8); write_u32(bytes, offset as u32); }
+        i2b => write_u8(bytes, 0x91),
+        i2c => write_u8(bytes, 0x92),
+        i2d => write_u8(bytes, 0x87),
+        i2f => write_u8(bytes, 0x86),
+        i2l => write_u8(bytes, 0x85),
+        i2s => write_u8(bytes, 0x93),
+        iadd => write_u8(bytes, 0x60),
+        iaload => write_u8(bytes, 0x2e),
+        iand => write_u8(bytes, 0x7e),
+        iastore => write_u8(bytes, 0x4f),
+        iconst_m1 => write_u8(bytes, 0x02),
+        iconst_0 => write_u8(bytes, 0x03),
+        iconst_1 => write_u8(bytes, 0x04),
+        iconst_2 => write_u8(bytes, 0x05),
+        iconst_3 => write_u8(bytes, 0x06),
+        iconst_4 => write_u8(bytes, 0x07),
+        iconst_5 => write_u8(bytes, 0x08),
+        idiv => write_u8(bytes, 0x6c),
+        if_acmpeq(offset) => { write_u8(bytes, 0xa5); write_u16(bytes, offset as u16); }
+        if_acmpne(offset) => { write_u8(bytes, 0xa6); write_u16(bytes, offset as u16); }
+        if_icmpeq(offset) => { write_u8(bytes, 0x9f); write_u16(bytes, offset as u16); }
+        if_icmpne(offset) => { write_u8(bytes, 0xa0); write_u16(bytes, offset as u16); }
+        if_icmplt(offset) => { write_u8(bytes, 0xa1); write_u16(bytes, offset as u16); }
+        if_icmpge(offset) => { write_u8(bytes, 0xa2); write_u16(bytes, offset as u16); }
+        if_icmpgt(offset) => { write_u8(bytes, 0xa3); write_u16(bytes, offset as u16); }
+        if_icmple(offset) => { write_u8(bytes, 0xa4); write_u16(bytes, offset as u16); }
+        ifeq(offset) => { write_u8(bytes, 0x99); write_u16(bytes, offset as u16); }
+        ifne(offset) => { write_u8(bytes, 0x9a); write_u16(bytes, offset as u16); }
+        iflt(offset) => { write_u8(bytes, 0x9b); write_u16(bytes, offset as u16); }
+        ifge(offset) => { write_u8(bytes, 0x9c); write_u16(bytes, offset as u16); }
+        ifgt(offset) => { write_u8(bytes, 0x9d); write_u16(bytes, offset as u16); }
+        ifle(offset) => { write_u8(bytes, 0x9e); write_u16(bytes, offset as u16); }
+        ifnonnull(offset) => { write_u8(bytes, 0xc7); write_u16(bytes, offset as u16); }
+        ifnull(offset) => { write_u8(bytes, 0xc6); write_u16(bytes, offset as u16); }
+        iinc(index, constant) => {
+            write_u8(bytes, 0x84);
+            write_u8(bytes, index);
+            write_u8(bytes, constant as u8);
+        }
+        iload(index) => { write_u8(bytes, 0x15); write_u8(bytes, index); }
+        iload_0 => write_u8(bytes, 0x1a),
+        iload_1 => write_u8(bytes, 0x1b),
+        iload_2 => write_u8(bytes, 0x1c),
+        iload_3 => write_u8(bytes, 0x1d),
+        imul => write_u8(bytes, 0x68),
+        ineg => write_u8(bytes, 0x74),
+        instanceof(index) => { write_u8(bytes, 0xc1); write_u16(bytes, index); }
+        invokedynamic(index) => {
+            write_u8(bytes, 0xba);
+            write_u16(bytes, index);
+            write_u16(bytes, 0);
+        }
+        invokeinterface(index, count) => {
+            write_u8(bytes, 0xb9);
+            write_u16(bytes, index);
+            write_u8(bytes, count);
+            write_u8(bytes, 0);
+        }
+        invokespecial(index) => { write_u8(bytes, 0xb7); write_u16(bytes, index); }
+        invokestatic(index) => { write_u8(bytes, 0xb8); write_u16(bytes, index); }
+        invokevirtual(index) => { write_u8(bytes, 0xb6); write_u16(bytes, index); }
+        ior => write_u8(bytes, 0x80),
+        irem => write_u8(bytes, 0x70),
+        ireturn => write_u8(bytes, 0xac),
+        ishl => write_u8(bytes, 0x78),
+        ishr => write_u8(bytes, 0x7a),
+        istore(index) => { write_u8(bytes, 0x36); write_u8(bytes, index); }
+        istore_0 => write_u8(bytes, 0x3b),
+        istore_1 => write_u8(bytes, 0x3c),
+        istore_2 => write_u8(bytes, 0x3d),
+        istore_3 => write_u8(bytes, 0x3e),
+        isub => write_u8(bytes, 0x64),
+        iushr => write_u8(bytes, 0x7c),
+        ixor => write_u8(bytes, 0x82),
+        jsr(offset) => { write_u8(bytes, 0xa8); write_u16(bytes, offset as u16); }
+        jsr_w(offset) => { write_u8(bytes, 0xc9); write_u32(bytes, offset as u32); }
+        l2d => write_u8(bytes, 0x8a),
+        l2f => write_u8(bytes, 0x89),
+        l2i => write_u8(bytes, 0x88),
+        ladd => write_u8(bytes, 0x61),
+        laload => write_u8(bytes, 0x2f),
+        land => write_u8(bytes, 0x7f),
+        lastore => write_u8(bytes, 0x50),
+        lcmp => write_u8(bytes, 0x94),
+        lconst_0 => write_u8(bytes, 0x09),
+        lconst_1 => write_u8(bytes, 0x0a),
+        ldc(index) => { write_u8(bytes, 0x12); write_u8(bytes, index); }
+        ldc_w(index) => { write_u8(bytes, 0x13); write_u16(bytes, index); }
+        ldc2_w(index) => { write_u8(bytes, 0x14); write_u16(bytes, index); }
+        ldiv => write_u8(bytes, 0x6d),
+        lload(index) => { write_u8(bytes, 0x16); write_u8(bytes, index); }
+        lload_0 => write_u8(bytes, 0x1e),
+        lload_1 => write_u8(bytes, 0x1f),
+        lload_2 => write_u8(bytes, 0x20),
+        lload_3 => write_u8(bytes, 0x21),
+        lmul => write_u8(bytes, 0x69),
+        lneg => write_u8(bytes, 0x75),
+        lookupswitch(default, ref pairs) => {
+            write_u8(bytes, 0xab);
+            write_padding(bytes);
+            write_u32(bytes, default as u32);
+            write_u32(bytes, pairs.len() as u32);
+            for &(match_, offset) in pairs.iter() {
+                write_u32(bytes, match_ as u32);
+                write_u32(bytes, offset as u32);
+            }
+        }
+        lor => write_u8(bytes, 0x81),
+        lrem => write_u8(bytes, 0x71),
+        lreturn => write_u8(bytes, 0xad),
+        lshl => write_u8(bytes, 0x79),
+        lshr => write_u8(bytes, 0x7b),
+        lstore(index) => { write_u8(bytes, 0x37); write_u8(bytes, index); }
+        lstore_0 => write_u8(bytes, 0x3f),
+        lstore_1 => write_u8(bytes, 0x40),
+        lstore_2 => write_u8(bytes, 0x41),
+        lstore_3 => write_u8(bytes, 0x42),
+        lsub => write_u8(bytes, 0x65),
+        lushr => write_u8(bytes, 0x7d),
+        lxor => write_u8(bytes, 0x83),
+        monitorenter => write_u8(bytes, 0xc2),
+        monitorexit => write_u8(bytes, 0xc3),
+        multianewarray(index, dimensions) => {
+            write_u8(bytes, 0xc5);
+            write_u16(bytes, index);
+            write_u8(bytes, dimensions);
+        }
+        new(index) => { write_u8(bytes, 0xbb); write_u16(bytes, index); }
+        newarray(ref atype) => {
+            let atype = match *atype {
+                ArrayType::Boolean => 4,
+                ArrayType::Char => 5,
+                ArrayType::Float => 6,
+                ArrayType::Double => 7,
+                ArrayType::Byte => 8,
+                ArrayType::Short => 9,
+                ArrayType::Int => 10,
+                ArrayType::Long => 11
+            };
+            write_u8(bytes, 0xbc);
+            write_u8(bytes, atype);
+        }
+        nop => write_u8(bytes, 0x00),
+        pop => write_u8(bytes, 0x57),
+        pop2 => write_u8(bytes, 0x58),
+        putfield(index) => { write_u8(bytes, 0xb5); write_u16(bytes, index); }
+        putstatic(index) => { write_u8(bytes, 0xb3); write_u16(bytes, index); }
+        ret(index) => { write_u8(bytes, 0xa9); write_u8(bytes, index); }
+        return_ => write_u8(bytes, 0xb1),
+        saload => write_u8(bytes, 0x35),
+        sastore => write_u8(bytes, 0x56),
+        sipush(value) => { write_u8(bytes, 0x11); write_u16(bytes, value as u16); }
+        swap => write_u8(bytes, 0x5f),
+        tableswitch(default, low, ref offsets) => {
+            if offsets.is_empty() {
+                return Result::Err(ParseError::Encode(format!("tableswitch at {} without jump offsets", pc)));
+            }
+            let high = low as i64 + offsets.len() as i64 - 1;
+            if high > i32::max_value() as i64 {
+                return Result::Err(ParseError::Encode(format!("tableswitch at {} has too many jump offsets", pc)));
+            }
+            write_u8(bytes, 0xaa);
+            write_padding(bytes);
+            write_u32(bytes, default as u32);
+            write_u32(bytes, low as u32);
+            write_u32(bytes, high as u32);
+            for &offset in offsets.iter() {
+                write_u32(bytes, offset as u32);
+            }
+        }
+        iload_w(index) => write_wide(bytes, 0x15, index),
+        fload_w(index) => write_wide(bytes, 0x17, index),
+        aload_w(index) => write_wide(bytes, 0x19, index),
+        lload_w(index) => write_wide(bytes, 0x16, index),
+        dload_w(index) => write_wide(bytes, 0x18, index),
+        istore_w(index) => write_wide(bytes, 0x36, index),
+        fstore_w(index) => write_wide(bytes, 0x38, index),
+        astore_w(index) => write_wide(bytes, 0x3a, index),
+        lstore_w(index) => write_wide(bytes, 0x37, index),
+        dstore_w(index) => write_wide(bytes, 0x39, index),
+        ret_w(index) => write_wide(bytes, 0xa9, index),
+        iinc_w(index, constant) => {
+            write_wide(bytes, 0x84, index);
+            write_u16(bytes, constant as u16);
+        }
+    }
+    Result::Ok(())
+}
+
+fn write_padding(bytes: &mut Vec<u8>) {
+    while bytes.len() % 4 != 0 {
+        bytes.push(0);
+    }
+}
+
+fn write_wide(bytes: &mut Vec<u8>, opcode: u8, index: u16) {
+    write_u8(bytes, 0xc4);
+    write_u8(bytes, opcode);
+    write_u16(bytes, index);
+}
+
+fn write_u32(bytes: &mut Vec<u8>, val: u32) {
+    bytes.push((val >> 24) as u8);
+    bytes.push((val >> 16) as u8);
+    bytes.push((val >> 8) as u8);
+    bytes.push(val as u8);
+}
+
+fn write_u16(bytes: &mut Vec<u8>, val: u16) {
+    bytes.push((val >> 8) as u8);
+    bytes.push(val as u8);
+}
+
+fn write_u8(bytes: &mut Vec<u8>, val: u8) {
+    bytes.push(val);
+}
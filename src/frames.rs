@@ -0,0 +1,926 @@
+use std::fmt;
+
+use ::cfg::{ControlFlowGraph, EdgeKind};
+use ::descriptor::{BaseType, FieldType, MethodDescriptor};
+use ::java_string::JavaString;
+use ::model::{ArrayType, Attribute, Class, ConstantPoolInfo, Exception, Instruction, Method, StackMapFrame, VerificationType};
+use ::model::Instruction::*;
+use ::pool::ConstantPool;
+use ::result::{ParseError, ParseResult};
+
+/// Answers the one question about classes other than the one being analysed
+/// that computing frames needs.
+pub trait ClassHierarchy {
+    /// The internal name of the most specific class that both classes, given
+    /// by internal name, are or extend. Interfaces count as
+    /// `java/lang/Object`, as they do for the verifier.
+    fn common_superclass(&self, a: &str, b: &str) -> ParseResult<String>;
+
+    /// Whether the class is an interface. The verifier accepts any object
+    /// where an interface is expected, as the JVM does. Computing frames does
+    /// not need this, and the default says no class is an interface.
+    fn is_interface(&self, name: &str) -> ParseResult<bool> {
+        let _ = name;
+        Result::Ok(false)
+    }
+}
+
+impl<F> ClassHierarchy for F where F: Fn(&str, &str) -> ParseResult<String> {
+    fn common_superclass(&self, a: &str, b: &str) -> ParseResult<String> {
+        self(a, b)
+    }
+}
+
+/// The `StackMapTable` of a method's code along with the limits found while
+/// computing it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComputedFrames {
+    pub frames: Vec<StackMapFrame>,
+    pub max_stack: u16,
+    pub max_locals: u16
+}
+
+impl Class {
+    /// Replaces the `StackMapTable` of every method with code by a computed
+    /// one, and updates `max_stack`. Classes before version 50 are left alone,
+    /// as the JVM ignores their stack maps.
+    pub fn compute_frames(&mut self, hierarchy: &ClassHierarchy) -> ParseResult<()> {
+        if self.major_version < 50 {
+            return Result::Ok(());
+        }
+        let class_name = try!(self.name());
+        for i in 0..self.methods.len() {
+            let computed = match try!(self.methods[i].compute_frames(&class_name, &mut self.constant_pool, hierarchy)) {
+                Option::Some(computed) => computed,
+                Option::None => continue
+            };
+            if !computed.frames.is_empty() && self.constant_pool.find_utf8("StackMapTable").is_none() {
+                try!(self.constant_pool.push(ConstantPoolInfo::Utf8(JavaString::from("StackMapTable"))));
+            }
+            for attribute in self.methods[i].attributes.iter_mut() {
+                if let Attribute::Code { ref mut max_stack, ref mut max_locals, ref mut attributes, .. } = *attribute {
+                    *max_stack = computed.max_stack;
+                    *max_locals = (*max_locals).max(computed.max_locals);
+                    let position = attributes.iter().position(|attribute| match *attribute {
+                        Attribute::StackMapTable(_) => true,
+                        _ => false
+                    });
+                    let frames = Attribute::StackMapTable(computed.frames.clone());
+                    match position {
+                        Option::Some(position) if computed.frames.is_empty() => { attributes.remove(position); },
+                        Option::Some(position) => attributes[position] = frames,
+                        Option::None if computed.frames.is_empty() => {},
+                        Option::None => attributes.push(frames)
+                    }
+                }
+            }
+        }
+        Result::Ok(())
+    }
+}
+
+impl Method {
+    /// Computes the `StackMapTable` of the method's code, adding the `Class`
+    /// constants it refers to the pool. Returns `None` if the method has no
+    /// code.
+    ///
+    /// Frames are inferred at every branch target, exception handler and
+    /// instruction following an unconditional transfer of control, merging the
+    /// types of all paths that reach them. Code that needs a frame but cannot
+    /// be reached has none to infer, so it is an error, as are `jsr` and
+    /// `ret`, which class files with stack maps may not contain.
+    pub fn compute_frames(&self, class_name: &str, constant_pool: &mut ConstantPool, hierarchy: &ClassHierarchy)
+            -> ParseResult<Option<ComputedFrames>> {
+        let (code, exception_table) = match self.attributes.iter().filter_map(|attribute| match *attribute {
+            Attribute::Code { ref code, ref exception_table, .. } => Option::Some((code, exception_table)),
+            _ => Option::None
+        }).next() {
+            Option::Some(code) => code,
+            Option::None => return Result::Ok(Option::None)
+        };
+        let name = try!(self.name(constant_pool));
+        let descriptor = try!(self.method_descriptor(constant_pool));
+        let initial = Frame::initial(class_name, self.is_static(), &name, &descriptor);
+        let inferred = {
+            let interpreter = Interpreter {
+                pool: constant_pool,
+                hierarchy: hierarchy,
+                class_name: class_name,
+                code: code
+            };
+            try!(interpreter.frames(&initial, exception_table))
+        };
+
+        let mut previous = entries(&initial.locals);
+        let mut previous_pc = Option::None;
+        let mut table = Vec::with_capacity(inferred.frames.len());
+        for (pc, frame) in inferred.frames {
+            let mut locals = entries(&frame.locals);
+            while locals.last() == Option::Some(&FrameType::Top) {
+                locals.pop();
+            }
+            let offset_delta = match previous_pc {
+                Option::Some(previous_pc) => pc - previous_pc - 1,
+                Option::None => pc
+            } as u16;
+            table.push(try!(compress(offset_delta, &previous, &locals, &entries(&frame.stack), constant_pool)));
+            previous = locals;
+            previous_pc = Option::Some(pc);
+        }
+        Result::Ok(Option::Some(ComputedFrames { frames: table, max_stack: inferred.max_stack, max_locals: inferred.max_locals }))
+    }
+
+    /// The frames of the `StackMapTable` of the method's code, each with the
+    /// pc it applies to and one type per local and stack slot. Empty if the
+    /// method has no code or no table.
+    pub fn stack_map_frames(&self, class_name: &str, constant_pool: &ConstantPool) -> ParseResult<Vec<(u32, Frame)>> {
+        let (code, table) = match self.attributes.iter().filter_map(|attribute| match *attribute {
+            Attribute::Code { ref code, ref attributes, .. } => Option::Some((code, attributes)),
+            _ => Option::None
+        }).next() {
+            Option::Some((code, attributes)) => match attributes.iter().filter_map(|attribute| match *attribute {
+                Attribute::StackMapTable(ref table) => Option::Some(table),
+                _ => Option::None
+            }).next() {
+                Option::Some(table) => (code, table),
+                Option::None => return Result::Ok(Vec::new())
+            },
+            Option::None => return Result::Ok(Vec::new())
+        };
+        let name = try!(self.name(constant_pool));
+        let descriptor = try!(self.method_descriptor(constant_pool));
+        let mut frame = Frame::initial(class_name, self.is_static(), &name, &descriptor);
+        let mut frames: Vec<(u32, Frame)> = Vec::with_capacity(table.len());
+        for entry in table {
+            let pc = frames.last().map_or(entry.offset_delta() as u32, |&(pc, _)| pc + entry.offset_delta() as u32 + 1);
+            if code.binary_search_by_key(&pc, |&(pc, _)| pc).is_err() {
+                let message = format!("stack map frame at {} is not at the start of an instruction", pc);
+                return Result::Err(ParseError::Decode(message));
+            }
+            frame = try!(frame.apply(entry, constant_pool, code));
+            frames.push((pc, frame.clone()));
+        }
+        Result::Ok(frames)
+    }
+}
+
+// Picks the smallest frame that turns the previous frame's locals into these.
+fn compress(offset_delta: u16, previous: &[FrameType], locals: &[FrameType], stack: &[FrameType], pool: &mut ConstantPool)
+        -> ParseResult<StackMapFrame> {
+    if locals == previous {
+        match stack.len() {
+            0 if offset_delta < 64 => return Result::Ok(StackMapFrame::SameFrame { offset_delta: offset_delta as u8 }),
+            0 => return Result::Ok(StackMapFrame::SameFrameExtended { offset_delta: offset_delta }),
+            1 => {
+                let stack = try!(verification_type(&stack[0], pool));
+                return Result::Ok(if offset_delta < 64 {
+                    StackMapFrame::SameLocals1StackItemFrame { offset_delta: offset_delta as u8, stack: stack }
+                } else {
+                    StackMapFrame::SameLocals1StackItemFrameExtended { offset_delta: offset_delta, stack: stack }
+                });
+            },
+            _ => {}
+        }
+    }
+    if stack.is_empty() {
+        if locals.len() > previous.len() && locals.len() - previous.len() <= 3 && locals[..previous.len()] == *previous {
+            let locals = try!(verification_types(&locals[previous.len()..], pool));
+            return Result::Ok(StackMapFrame::AppendFrame { offset_delta: offset_delta, locals: locals });
+        }
+        if previous.len() > locals.len() && previous.len() - locals.len() <= 3 && previous[..locals.len()] == *locals {
+            let chopped = (previous.len() - locals.len()) as u8;
+            return Result::Ok(StackMapFrame::ChopFrame { offset_delta: offset_delta, chopped: chopped });
+        }
+    }
+    Result::Ok(StackMapFrame::FullFrame {
+        offset_delta: offset_delta,
+        locals: try!(verification_types(locals, pool)),
+        stack: try!(verification_types(stack, pool))
+    })
+}
+
+// Drops the second slots of longs and doubles, which verification types
+// leave implicit.
+fn entries(slots: &[FrameType]) -> Vec<FrameType> {
+    let mut entries = Vec::with_capacity(slots.len());
+    let mut slot = 0;
+    while slot < slots.len() {
+        entries.push(slots[slot].clone());
+        slot += slots[slot].size();
+    }
+    entries
+}
+
+fn verification_types(entries: &[FrameType], pool: &mut ConstantPool) -> ParseResult<Vec<VerificationType>> {
+    let mut types = Vec::with_capacity(entries.len());
+    for entry in entries {
+        types.push(try!(verification_type(entry, pool)));
+    }
+    Result::Ok(types)
+}
+
+// Adds the class a type names to the pool if it is not there yet.
+fn verification_type(entry: &FrameType, pool: &mut ConstantPool) -> ParseResult<VerificationType> {
+    Result::Ok(match *entry {
+        FrameType::Top => VerificationType::Top,
+        FrameType::Integer => VerificationType::Integer,
+        FrameType::Float => VerificationType::Float,
+        FrameType::Long => VerificationType::Long,
+        FrameType::Double => VerificationType::Double,
+        FrameType::Null => VerificationType::Null,
+        FrameType::UninitializedThis => VerificationType::UninitializedThis,
+        FrameType::Uninitialized(pc) => VerificationType::UninitializedVariable { offset: pc as u16 },
+        FrameType::Reference(ref name) => VerificationType::Object { index: try!(class_constant(pool, name)) },
+        FrameType::ReturnAddress(_) => return Result::Err(ParseError::Decode("return address in a frame".to_string()))
+    })
+}
+
+fn class_constant(pool: &mut ConstantPool, name: &str) -> ParseResult<u16> {
+    let name_index = match pool.find_utf8(name) {
+        Option::Some(index) => index,
+        Option::None => try!(pool.push(ConstantPoolInfo::Utf8(JavaString::from(name))))
+    };
+    match pool.find_class(name_index) {
+        Option::Some(index) => Result::Ok(index),
+        Option::None => pool.push(ConstantPoolInfo::Class(name_index))
+    }
+}
+
+/// The type of a local variable or operand stack slot as the verifier sees it.
+/// Longs and doubles take two slots, the second of which is `Top`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FrameType {
+    Top,
+    Integer,
+    Float,
+    Long,
+    Double,
+    Null,
+    UninitializedThis,
+    /// An object created by the `new` at this pc whose constructor has not
+    /// been called yet.
+    Uninitialized(u32),
+    /// A class by internal name or an array by descriptor, as in a `Class`
+    /// constant.
+    Reference(String),
+    /// The address pushed by a `jsr` to the subroutine at this pc. Only code
+    /// without stack maps has these.
+    ReturnAddress(u32)
+}
+
+impl FrameType {
+    /// The type of a value of a field type, where the integral types smaller
+    /// than `int` and `boolean` are `Integer`.
+    pub fn of(field_type: &FieldType) -> FrameType {
+        match *field_type {
+            FieldType::Base(BaseType::Long) => FrameType::Long,
+            FieldType::Base(BaseType::Double) => FrameType::Double,
+            FieldType::Base(BaseType::Float) => FrameType::Float,
+            FieldType::Base(_) => FrameType::Integer,
+            FieldType::Object(ref name) => FrameType::Reference(name.clone()),
+            FieldType::Array(_) => FrameType::Reference(field_type.to_string())
+        }
+    }
+
+    /// The type of the value a `ldc` of the constant at the index pushes.
+    pub fn of_constant(pool: &ConstantPool, index: u16) -> ParseResult<FrameType> {
+        let class = |name: &str| Result::Ok(FrameType::Reference(name.to_string()));
+        match *try!(pool.get(index)) {
+            ConstantPoolInfo::Integer(_) => Result::Ok(FrameType::Integer),
+            ConstantPoolInfo::Float(_) => Result::Ok(FrameType::Float),
+            ConstantPoolInfo::Long(_) => Result::Ok(FrameType::Long),
+            ConstantPoolInfo::Double(_) => Result::Ok(FrameType::Double),
+            ConstantPoolInfo::String(_) => class("java/lang/String"),
+            ConstantPoolInfo::Class(_) => class("java/lang/Class"),
+            ConstantPoolInfo::MethodType(_) => class("java/lang/invoke/MethodType"),
+            ConstantPoolInfo::MethodHandle(_, _) => class("java/lang/invoke/MethodHandle"),
+            ConstantPoolInfo::Dynamic(_, name_and_type) => {
+                let (_, descriptor) = try!(pool.name_and_type(name_and_type));
+                Result::Ok(FrameType::of(&try!(FieldType::parse(&descriptor))))
+            },
+            ref other => {
+                let message = format!("expected loadable constant at index {} but got {:?}", index, other);
+                Result::Err(ParseError::Format(message))
+            }
+        }
+    }
+
+    /// The type of an entry of a stack map frame. The class of an `Object` is
+    /// looked up in the pool, and an `UninitializedVariable` must be the pc of
+    /// a `new` in the code.
+    pub fn of_verification_type(verification_type: &VerificationType, pool: &ConstantPool, code: &[(u32, Instruction)])
+            -> ParseResult<FrameType> {
+        Result::Ok(match *verification_type {
+            VerificationType::Top => FrameType::Top,
+            VerificationType::Integer => FrameType::Integer,
+            VerificationType::Float => FrameType::Float,
+            VerificationType::Long => FrameType::Long,
+            VerificationType::Double => FrameType::Double,
+            VerificationType::Null => FrameType::Null,
+            VerificationType::UninitializedThis => FrameType::UninitializedThis,
+            VerificationType::Object { index } => FrameType::Reference(try!(pool.class_name(index))),
+            VerificationType::UninitializedVariable { offset } => {
+                match code.binary_search_by_key(&(offset as u32), |&(pc, _)| pc).map(|index| &code[index].1) {
+                    Result::Ok(&new(_)) => FrameType::Uninitialized(offset as u32),
+                    _ => {
+                        let message = format!("uninitialized type in stack map frame refers to {} which is not a new", offset);
+                        return Result::Err(ParseError::Decode(message));
+                    }
+                }
+            }
+        })
+    }
+
+    /// The type of an array of the class or array named as in a `Class`
+    /// constant.
+    pub fn array_of(name: &str) -> FrameType {
+        FrameType::Reference(array_of(name))
+    }
+
+    /// The type of the components of an array type, or none if this is not
+    /// one.
+    pub fn component(&self) -> Option<FrameType> {
+        match *self {
+            FrameType::Reference(ref name) if name.starts_with('[') => {
+                FieldType::parse(&name[1..]).ok().map(|component| FrameType::of(&component))
+            },
+            _ => Option::None
+        }
+    }
+
+    /// The number of slots, 2 for long and double.
+    pub fn size(&self) -> usize {
+        match *self {
+            FrameType::Long | FrameType::Double => 2,
+            _ => 1
+        }
+    }
+
+    /// Whether this is an object or null, initialized or not.
+    pub fn is_reference(&self) -> bool {
+        match *self {
+            FrameType::Null | FrameType::UninitializedThis | FrameType::Uninitialized(_) | FrameType::Reference(_) => true,
+            _ => false
+        }
+    }
+
+    /// The most specific type that both types are assignable to, which is
+    /// `Top` if they have nothing in common.
+    pub fn join(&self, other: &FrameType, hierarchy: &ClassHierarchy) -> ParseResult<FrameType> {
+        if self == other {
+            return Result::Ok(self.clone());
+        }
+        match (self, other) {
+            (&FrameType::Null, &FrameType::Reference(_)) => Result::Ok(other.clone()),
+            (&FrameType::Reference(_), &FrameType::Null) => Result::Ok(self.clone()),
+            (&FrameType::Reference(ref a), &FrameType::Reference(ref b)) => join_references(a, b, hierarchy).map(FrameType::Reference),
+            _ => Result::Ok(FrameType::Top)
+        }
+    }
+}
+
+impl fmt::Display for FrameType {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FrameType::Top => write!(fmt, "top"),
+            FrameType::Integer => write!(fmt, "int"),
+            FrameType::Float => write!(fmt, "float"),
+            FrameType::Long => write!(fmt, "long"),
+            FrameType::Double => write!(fmt, "double"),
+            FrameType::Null => write!(fmt, "null"),
+            FrameType::UninitializedThis => write!(fmt, "uninitializedThis"),
+            FrameType::Uninitialized(pc) => write!(fmt, "uninitialized({})", pc),
+            FrameType::Reference(ref name) => write!(fmt, "{}", name),
+            FrameType::ReturnAddress(pc) => write!(fmt, "returnAddress({})", pc)
+        }
+    }
+}
+
+/// The types of the local variables and the operand stack at some point in a
+/// method's code, one per slot.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub locals: Vec<FrameType>,
+    pub stack: Vec<FrameType>
+}
+
+impl Frame {
+    /// The frame on entry to a method of a class, holding `this`, if the
+    /// method is not static, and the parameters. In constructors other than
+    /// Object's, `this` is `UninitializedThis`.
+    pub fn initial(class_name: &str, is_static: bool, name: &str, descriptor: &MethodDescriptor) -> Frame {
+        let mut locals = Vec::new();
+        if !is_static {
+            if name == "<init>" && class_name != "java/lang/Object" {
+                locals.push(FrameType::UninitializedThis);
+            } else {
+                locals.push(FrameType::Reference(class_name.to_string()));
+            }
+        }
+        for parameter in &descriptor.parameters {
+            let parameter = FrameType::of(parameter);
+            if parameter.size() == 2 {
+                locals.push(parameter);
+                locals.push(FrameType::Top);
+            } else {
+                locals.push(parameter);
+            }
+        }
+        Frame { locals: locals, stack: Vec::new() }
+    }
+
+    /// Stores a value in a local variable, adding locals up to it as `Top`
+    /// and breaking up a long or double it overwrites half of.
+    pub fn store(&mut self, index: usize, value: FrameType) {
+        let size = value.size();
+        if self.locals.len() < index + size {
+            self.locals.resize(index + size, FrameType::Top);
+        }
+        if index > 0 && self.locals[index - 1].size() == 2 {
+            self.locals[index - 1] = FrameType::Top;
+        }
+        if size == 2 {
+            self.locals[index + 1] = FrameType::Top;
+        }
+        self.locals[index] = value;
+    }
+
+    /// The frame that an entry of a `StackMapTable` describes, given the
+    /// frame of the previous entry or the initial frame for the first one.
+    /// The locals are those the entry lists, without padding up to
+    /// `max_locals`.
+    pub fn apply(&self, frame: &StackMapFrame, pool: &ConstantPool, code: &[(u32, Instruction)]) -> ParseResult<Frame> {
+        let slots = |types: &[VerificationType]| -> ParseResult<Vec<FrameType>> {
+            let mut slots = Vec::with_capacity(types.len());
+            for verification_type in types {
+                let slot = try!(FrameType::of_verification_type(verification_type, pool, code));
+                let size = slot.size();
+                slots.push(slot);
+                if size == 2 {
+                    slots.push(FrameType::Top);
+                }
+            }
+            Result::Ok(slots)
+        };
+        let mut locals = self.locals.clone();
+        let stack = match *frame {
+            StackMapFrame::SameFrame { .. } | StackMapFrame::SameFrameExtended { .. } => Vec::new(),
+            StackMapFrame::SameLocals1StackItemFrame { ref stack, .. }
+                    | StackMapFrame::SameLocals1StackItemFrameExtended { ref stack, .. } => {
+                try!(slots(&[stack.clone()]))
+            },
+            StackMapFrame::ChopFrame { chopped, .. } => {
+                for _ in 0..chopped {
+                    match locals.pop() {
+                        Option::Some(FrameType::Top) if locals.last().map_or(false, |local| local.size() == 2) => {
+                            locals.pop();
+                        },
+                        Option::Some(_) => {},
+                        Option::None => {
+                            return Result::Err(ParseError::Decode("stack map frame chops more locals than there are".to_string()));
+                        }
+                    }
+                }
+                Vec::new()
+            },
+            StackMapFrame::AppendFrame { locals: ref appended, .. } => {
+                locals.extend(try!(slots(appended)));
+                Vec::new()
+            },
+            StackMapFrame::FullFrame { locals: ref full, ref stack, .. } => {
+                locals = try!(slots(full));
+                try!(slots(stack))
+            }
+        };
+        Result::Ok(Frame { locals: locals, stack: stack })
+    }
+
+    /// Joins the frames slot by slot, dropping locals that only one of them
+    /// has. Their stacks must be of the same height.
+    pub fn join(&self, other: &Frame, hierarchy: &ClassHierarchy) -> ParseResult<Frame> {
+        if self.stack.len() != other.stack.len() {
+            let message = format!("stack heights {} and {} differ", self.stack.len(), other.stack.len());
+            return Result::Err(ParseError::Decode(message));
+        }
+        let length = self.locals.len().min(other.locals.len());
+        let mut locals = Vec::with_capacity(length);
+        for slot in 0..length {
+            locals.push(try!(self.locals[slot].join(&other.locals[slot], hierarchy)));
+        }
+        // The second slot of a long or double means nothing on its own.
+        for slot in 0..length {
+            if locals[slot].size() == 2 && locals.get(slot + 1) != Option::Some(&FrameType::Top) {
+                locals[slot] = FrameType::Top;
+            }
+        }
+        let mut stack = Vec::with_capacity(self.stack.len());
+        for (a, b) in self.stack.iter().zip(other.stack.iter()) {
+            stack.push(try!(a.join(b, hierarchy)));
+        }
+        Result::Ok(Frame { locals: locals, stack: stack })
+    }
+}
+
+impl fmt::Display for Frame {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let list = |slots: &[FrameType]| entries(slots).iter().map(FrameType::to_string).collect::<Vec<_>>().join(", ");
+        write!(fmt, "locals: [{}], stack: [{}]", list(&self.locals), list(&self.stack))
+    }
+}
+
+struct Inferred {
+    frames: Vec<(u32, Frame)>,
+    max_stack: u16,
+    max_locals: u16
+}
+
+struct Interpreter<'a> {
+    pool: &'a ConstantPool,
+    hierarchy: &'a ClassHierarchy,
+    class_name: &'a str,
+    code: &'a [(u32, Instruction)]
+}
+
+impl<'a> Interpreter<'a> {
+    // Infers the frame at every pc that needs one, and the largest stack and
+    // number of locals used.
+    fn frames(&self, initial: &Frame, exception_table: &[Exception]) -> ParseResult<Inferred> {
+        let graph = try!(ControlFlowGraph::new(self.code, exception_table));
+        let mut catch_types = Vec::with_capacity(exception_table.len());
+        for exception in exception_table {
+            catch_types.push(FrameType::Reference(if exception.catch_type == 0 {
+                "java/lang/Throwable".to_string()
+            } else {
+                try!(self.pool.class_name(exception.catch_type))
+            }));
+        }
+
+        let mut max_stack = 0;
+        let mut max_locals = initial.locals.len();
+        let mut inputs: Vec<Option<Frame>> = vec![Option::None; graph.blocks.len()];
+        let mut work = Vec::new();
+        if !graph.blocks.is_empty() {
+            inputs[0] = Option::Some(initial.clone());
+            work.push(0);
+        }
+        while let Option::Some(block) = work.pop() {
+            let mut frame = inputs[block].clone().unwrap();
+            for i in graph.blocks[block].instructions.clone() {
+                let (pc, ref instruction) = self.code[i];
+                // Handlers must accept the locals both before and after each
+                // instruction they protect.
+                let handlers: Vec<usize> = (0..exception_table.len()).filter(|&k| {
+                    exception_table[k].start_pc as u32 <= pc && pc < exception_table[k].end_pc as u32
+                }).collect();
+                for &k in &handlers {
+                    let handler = graph.block_at(exception_table[k].handler_pc as u32).unwrap();
+                    let input = Frame { locals: frame.locals.clone(), stack: vec![catch_types[k].clone()] };
+                    if try!(self.merge(&mut inputs[handler], input, pc)) {
+                        work.push(handler);
+                    }
+                }
+                try!(self.execute(&mut frame, pc, instruction));
+                max_stack = max_stack.max(frame.stack.len());
+                max_locals = max_locals.max(frame.locals.len());
+                for &k in &handlers {
+                    let handler = graph.block_at(exception_table[k].handler_pc as u32).unwrap();
+                    let input = Frame { locals: frame.locals.clone(), stack: vec![catch_types[k].clone()] };
+                    if try!(self.merge(&mut inputs[handler], input, pc)) {
+                        work.push(handler);
+                    }
+                }
+            }
+            for edge in &graph.blocks[block].successors {
+                match edge.kind {
+                    EdgeKind::Exception(_) => continue,
+                    EdgeKind::Subroutine | EdgeKind::SubroutineReturn => unreachable!(),
+                    _ => {}
+                }
+                let pc = graph.blocks[edge.to].start_pc;
+                if try!(self.merge(&mut inputs[edge.to], frame.clone(), pc)) {
+                    work.push(edge.to);
+                }
+            }
+        }
+        if max_stack > 65535 || max_locals > 65535 {
+            return Result::Err(ParseError::Decode("more than 65535 locals or stack slots".to_string()));
+        }
+
+        let mut needed = vec![false; graph.blocks.len()];
+        for (block, data) in graph.blocks.iter().enumerate() {
+            for edge in &data.successors {
+                match edge.kind {
+                    EdgeKind::Branch | EdgeKind::Jump | EdgeKind::Switch | EdgeKind::Exception(_) => needed[edge.to] = true,
+                    _ => {}
+                }
+            }
+            let last = &self.code[data.instructions.end - 1].1;
+            if block + 1 < graph.blocks.len() && !last.falls_through() {
+                needed[block + 1] = true;
+            }
+        }
+        for exception in exception_table {
+            needed[graph.block_at(exception.handler_pc as u32).unwrap()] = true;
+        }
+        let mut frames = Vec::new();
+        for (block, input) in inputs.into_iter().enumerate() {
+            if !needed[block] {
+                continue;
+            }
+            match input {
+                Option::Some(frame) => frames.push((graph.blocks[block].start_pc, frame)),
+                Option::None => {
+                    let message = format!("unreachable code at {} needs a frame", graph.blocks[block].start_pc);
+                    return Result::Err(ParseError::Decode(message));
+                }
+            }
+        }
+        Result::Ok(Inferred { frames: frames, max_stack: max_stack as u16, max_locals: max_locals as u16 })
+    }
+
+    // Merges a frame into the input of a block, returning whether it changed.
+    fn merge(&self, input: &mut Option<Frame>, frame: Frame, pc: u32) -> ParseResult<bool> {
+        let merged = match *input {
+            Option::None => frame,
+            Option::Some(ref input) if input.stack.len() != frame.stack.len() => {
+                let message = format!("stack heights {} and {} meet at {}", input.stack.len(), frame.stack.len(), pc);
+                return Result::Err(ParseError::Decode(message));
+            },
+            Option::Some(ref input) => try!(input.join(&frame, self.hierarchy))
+        };
+        if input.as_ref() == Option::Some(&merged) {
+            return Result::Ok(false);
+        }
+        *input = Option::Some(merged);
+        Result::Ok(true)
+    }
+
+    fn execute(&self, frame: &mut Frame, pc: u32, instruction: &Instruction) -> ParseResult<()> {
+        let underflow = || Result::Err(ParseError::Decode(format!("stack underflow at {}", pc)));
+        macro_rules! pop {
+            ($slots:expr) => {
+                if frame.stack.len() < $slots {
+                    return underflow();
+                } else {
+                    let length = frame.stack.len() - $slots;
+                    frame.stack.split_off(length)
+                }
+            }
+        }
+        macro_rules! push {
+            ($frame_type:expr) => {{
+                let frame_type = $frame_type;
+                let size = frame_type.size();
+                frame.stack.push(frame_type);
+                if size == 2 {
+                    frame.stack.push(FrameType::Top);
+                }
+            }}
+        }
+
+        match *instruction {
+            nop | goto(_) | goto_w(_) | iinc(_, _) | iinc_w(_, _) | return_ => {},
+            aconst_null => push!(FrameType::Null),
+            iconst_m1 | iconst_0 | iconst_1 | iconst_2 | iconst_3 | iconst_4 | iconst_5 | bipush(_) | sipush(_) => {
+                push!(FrameType::Integer)
+            },
+            lconst_0 | lconst_1 => push!(FrameType::Long),
+            fconst_0 | fconst_1 | fconst_2 => push!(FrameType::Float),
+            dconst_0 | dconst_1 => push!(FrameType::Double),
+            ldc(index) => push!(try!(FrameType::of_constant(self.pool, index as u16))),
+            ldc_w(index) | ldc2_w(index) => push!(try!(FrameType::of_constant(self.pool, index))),
+
+            iload(_) | iload_0 | iload_1 | iload_2 | iload_3 | iload_w(_) => push!(FrameType::Integer),
+            lload(_) | lload_0 | lload_1 | lload_2 | lload_3 | lload_w(_) => push!(FrameType::Long),
+            fload(_) | fload_0 | fload_1 | fload_2 | fload_3 | fload_w(_) => push!(FrameType::Float),
+            dload(_) | dload_0 | dload_1 | dload_2 | dload_3 | dload_w(_) => push!(FrameType::Double),
+            aload(index) => push!(frame.locals.get(index as usize).cloned().unwrap_or(FrameType::Top)),
+            aload_w(index) => push!(frame.locals.get(index as usize).cloned().unwrap_or(FrameType::Top)),
+            aload_0 => push!(frame.locals.first().cloned().unwrap_or(FrameType::Top)),
+            aload_1 => push!(frame.locals.get(1).cloned().unwrap_or(FrameType::Top)),
+            aload_2 => push!(frame.locals.get(2).cloned().unwrap_or(FrameType::Top)),
+            aload_3 => push!(frame.locals.get(3).cloned().unwrap_or(FrameType::Top)),
+
+            istore(index) => { pop!(1); frame.store(index as usize, FrameType::Integer) },
+            istore_w(index) => { pop!(1); frame.store(index as usize, FrameType::Integer) },
+            istore_0 => { pop!(1); frame.store(0, FrameType::Integer) },
+            istore_1 => { pop!(1); frame.store(1, FrameType::Integer) },
+            istore_2 => { pop!(1); frame.store(2, FrameType::Integer) },
+            istore_3 => { pop!(1); frame.store(3, FrameType::Integer) },
+            lstore(index) => { pop!(2); frame.store(index as usize, FrameType::Long) },
+            lstore_w(index) => { pop!(2); frame.store(index as usize, FrameType::Long) },
+            lstore_0 => { pop!(2); frame.store(0, FrameType::Long) },
+            lstore_1 => { pop!(2); frame.store(1, FrameType::Long) },
+            lstore_2 => { pop!(2); frame.store(2, FrameType::Long) },
+            lstore_3 => { pop!(2); frame.store(3, FrameType::Long) },
+            fstore(index) => { pop!(1); frame.store(index as usize, FrameType::Float) },
+            fstore_w(index) => { pop!(1); frame.store(index as usize, FrameType::Float) },
+            fstore_0 => { pop!(1); frame.store(0, FrameType::Float) },
+            fstore_1 => { pop!(1); frame.store(1, FrameType::Float) },
+            fstore_2 => { pop!(1); frame.store(2, FrameType::Float) },
+            fstore_3 => { pop!(1); frame.store(3, FrameType::Float) },
+            dstore(index) => { pop!(2); frame.store(index as usize, FrameType::Double) },
+            dstore_w(index) => { pop!(2); frame.store(index as usize, FrameType::Double) },
+            dstore_0 => { pop!(2); frame.store(0, FrameType::Double) },
+            dstore_1 => { pop!(2); frame.store(1, FrameType::Double) },
+            dstore_2 => { pop!(2); frame.store(2, FrameType::Double) },
+            dstore_3 => { pop!(2); frame.store(3, FrameType::Double) },
+            astore(index) => { let value = pop!(1).remove(0); frame.store(index as usize, value) },
+            astore_w(index) => { let value = pop!(1).remove(0); frame.store(index as usize, value) },
+            astore_0 => { let value = pop!(1).remove(0); frame.store(0, value) },
+            astore_1 => { let value = pop!(1).remove(0); frame.store(1, value) },
+            astore_2 => { let value = pop!(1).remove(0); frame.store(2, value) },
+            astore_3 => { let value = pop!(1).remove(0); frame.store(3, value) },
+
+            iaload | baload | caload | saload => { pop!(2); push!(FrameType::Integer) },
+            laload => { pop!(2); push!(FrameType::Long) },
+            faload => { pop!(2); push!(FrameType::Float) },
+            daload => { pop!(2); push!(FrameType::Double) },
+            aaload => {
+                let array = pop!(2).remove(0);
+                push!(array.component().unwrap_or(FrameType::Null))
+            },
+            iastore | bastore | castore | sastore | fastore | aastore => { pop!(3); },
+            lastore | dastore => { pop!(4); },
+
+            pop => { pop!(1); },
+            pop2 => { pop!(2); },
+            dup => { let values = pop!(1); duplicate(frame, values, 0) },
+            dup_x1 => { let values = pop!(2); duplicate(frame, values, 1) },
+            dup_x2 => { let values = pop!(3); duplicate(frame, values, 2) },
+            dup2 => { let values = pop!(2); duplicate(frame, values, 0) },
+            dup2_x1 => { let values = pop!(3); duplicate(frame, values, 1) },
+            dup2_x2 => { let values = pop!(4); duplicate(frame, values, 2) },
+            swap => {
+                let mut values = pop!(2);
+                values.swap(0, 1);
+                frame.stack.extend(values);
+            },
+
+            iadd | isub | imul | idiv | irem | ishl | ishr | iushr | iand | ior | ixor => {
+                pop!(2);
+                push!(FrameType::Integer)
+            },
+            ladd | lsub | lmul | ldiv | lrem | land | lor | lxor => { pop!(4); push!(FrameType::Long) },
+            lshl | lshr | lushr => { pop!(3); push!(FrameType::Long) },
+            fadd | fsub | fmul | fdiv | frem => { pop!(2); push!(FrameType::Float) },
+            dadd | dsub | dmul | ddiv | drem => { pop!(4); push!(FrameType::Double) },
+            ineg | i2b | i2c | i2s | f2i => { pop!(1); push!(FrameType::Integer) },
+            lneg | d2l => { pop!(2); push!(FrameType::Long) },
+            fneg | i2f => { pop!(1); push!(FrameType::Float) },
+            dneg | l2d => { pop!(2); push!(FrameType::Double) },
+            i2l | f2l => { pop!(1); push!(FrameType::Long) },
+            i2d | f2d => { pop!(1); push!(FrameType::Double) },
+            l2i | d2i => { pop!(2); push!(FrameType::Integer) },
+            l2f | d2f => { pop!(2); push!(FrameType::Float) },
+            lcmp | dcmpl | dcmpg => { pop!(4); push!(FrameType::Integer) },
+            fcmpl | fcmpg => { pop!(2); push!(FrameType::Integer) },
+
+            ifeq(_) | ifne(_) | iflt(_) | ifge(_) | ifgt(_) | ifle(_) | ifnull(_) | ifnonnull(_) => { pop!(1); },
+            if_icmpeq(_) | if_icmpne(_) | if_icmplt(_) | if_icmpge(_) | if_icmpgt(_) | if_icmple(_)
+                    | if_acmpeq(_) | if_acmpne(_) => { pop!(2); },
+            tableswitch(_, _, _) | lookupswitch(_, _) => { pop!(1); },
+            ireturn | freturn | areturn | athrow | monitorenter | monitorexit => { pop!(1); },
+            lreturn | dreturn => { pop!(2); },
+            jsr(_) | jsr_w(_) | ret(_) | ret_w(_) => {
+                let message = format!("{:?} at {} is not allowed with stack maps", instruction, pc);
+                return Result::Err(ParseError::Decode(message));
+            },
+
+            getstatic(index) => push!(try!(self.field_type(index))),
+            putstatic(index) => { pop!(try!(self.field_type(index)).size()); },
+            getfield(index) => { pop!(1); push!(try!(self.field_type(index))) },
+            putfield(index) => { pop!(try!(self.field_type(index)).size() + 1); },
+            invokevirtual(index) | invokespecial(index) | invokestatic(index) | invokeinterface(index, _) => {
+                let member = try!(self.pool.member_ref(index));
+                let descriptor = try!(MethodDescriptor::parse(&member.descriptor));
+                pop!(descriptor.parameter_slots() as usize);
+                match *instruction {
+                    invokestatic(_) => {},
+                    invokespecial(_) if member.name == "<init>" => {
+                        let receiver = pop!(1).remove(0);
+                        let initialized = match receiver {
+                            FrameType::UninitializedThis => FrameType::Reference(self.class_name.to_string()),
+                            FrameType::Uninitialized(new_pc) => FrameType::Reference(try!(self.created_class(new_pc))),
+                            _ => {
+                                let message = format!("constructor called on {:?} at {}", receiver, pc);
+                                return Result::Err(ParseError::Decode(message));
+                            }
+                        };
+                        for slot in frame.locals.iter_mut().chain(frame.stack.iter_mut()) {
+                            if *slot == receiver {
+                                *slot = initialized.clone();
+                            }
+                        }
+                    },
+                    _ => { pop!(1); }
+                }
+                if let Option::Some(ref return_type) = descriptor.return_type {
+                    push!(FrameType::of(return_type))
+                }
+            },
+            invokedynamic(index) => {
+                let descriptor = match *try!(self.pool.get(index)) {
+                    ConstantPoolInfo::InvokeDynamic(_, name_and_type) => try!(self.pool.name_and_type(name_and_type)).1,
+                    ref other => {
+                        let message = format!("expected invoke dynamic at index {} but got {:?}", index, other);
+                        return Result::Err(ParseError::Format(message));
+                    }
+                };
+                let descriptor = try!(MethodDescriptor::parse(&descriptor));
+                pop!(descriptor.parameter_slots() as usize);
+                if let Option::Some(ref return_type) = descriptor.return_type {
+                    push!(FrameType::of(return_type))
+                }
+            },
+
+            new(_) => push!(FrameType::Uninitialized(pc)),
+            newarray(ref array_type) => {
+                pop!(1);
+                let descriptor = match *array_type {
+                    ArrayType::Boolean => "[Z",
+                    ArrayType::Char => "[C",
+                    ArrayType::Float => "[F",
+                    ArrayType::Double => "[D",
+                    ArrayType::Byte => "[B",
+                    ArrayType::Short => "[S",
+                    ArrayType::Int => "[I",
+                    ArrayType::Long => "[J"
+                };
+                push!(FrameType::Reference(descriptor.to_string()))
+            },
+            anewarray(index) => {
+                pop!(1);
+                push!(FrameType::array_of(&try!(self.pool.class_name(index))))
+            },
+            multianewarray(index, dimensions) => {
+                pop!(dimensions as usize);
+                push!(FrameType::Reference(try!(self.pool.class_name(index))))
+            },
+            arraylength | instanceof(_) => { pop!(1); push!(FrameType::Integer) },
+            checkcast(index) => { pop!(1); push!(FrameType::Reference(try!(self.pool.class_name(index)))) }
+        }
+        Result::Ok(())
+    }
+
+    fn field_type(&self, index: u16) -> ParseResult<FrameType> {
+        let member = try!(self.pool.member_ref(index));
+        Result::Ok(FrameType::of(&try!(FieldType::parse(&member.descriptor))))
+    }
+
+    // The class instantiated by the `new` at a pc.
+    fn created_class(&self, pc: u32) -> ParseResult<String> {
+        match self.code.binary_search_by_key(&pc, |&(pc, _)| pc).ok().map(|i| &self.code[i].1) {
+            Option::Some(&new(index)) => self.pool.class_name(index),
+            _ => Result::Err(ParseError::Decode(format!("uninitialized object refers to {} which is not a new", pc)))
+        }
+    }
+}
+
+// Pushes the values back with a copy of the top `values.len() - depth`
+// slots inserted `depth` slots down, as the `dup` instructions do.
+fn duplicate(frame: &mut Frame, values: Vec<FrameType>, depth: usize) {
+    let copied = values[depth..].to_vec();
+    frame.stack.extend(copied);
+    frame.stack.extend(values);
+}
+
+fn join_references(a: &str, b: &str, hierarchy: &ClassHierarchy) -> ParseResult<String> {
+    if a == b {
+        return Result::Ok(a.to_string());
+    }
+    match (a.starts_with('['), b.starts_with('[')) {
+        (false, false) => hierarchy.common_superclass(a, b),
+        (true, true) => {
+            // Arrays of references join by their components; any other arrays
+            // only have Object in common.
+            match (component_name(&a[1..]), component_name(&b[1..])) {
+                (Option::Some(a), Option::Some(b)) => join_references(a, b, hierarchy).map(|component| array_of(&component)),
+                _ => Result::Ok("java/lang/Object".to_string())
+            }
+        },
+        _ => Result::Ok("java/lang/Object".to_string())
+    }
+}
+
+// The name of an array's component type, if it is a reference, given the
+// descriptor after the `[`.
+fn component_name(component: &str) -> Option<&str> {
+    if component.starts_with('[') {
+        Option::Some(component)
+    } else if component.starts_with('L') && component.ends_with(';') {
+        Option::Some(&component[1..component.len() - 1])
+    } else {
+        Option::None
+    }
+}
+
+// The descriptor of an array of a class or array given as in a `Class`
+// constant.
+fn array_of(name: &str) -> String {
+    if name.starts_with('[') {
+        format!("[{}", name)
+    } else {
+        format!("[L{};", name)
+    }
+}
@@ -0,0 +1,223 @@
+use std::borrow::Cow;
+use std::char;
+use std::fmt;
+use std::str;
+
+use ::result::{ParseError, ParseResult};
+
+/// A string from the constant pool, stored exactly as the modified UTF-8 found
+/// in the class file. Unpaired surrogates and even malformed bytes are kept, so
+/// a string always encodes back to the bytes it was read from.
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct JavaString {
+    bytes: Vec<u8>
+}
+
+impl JavaString {
+
+    pub fn from_modified_utf8(bytes: Vec<u8>) -> JavaString {
+        JavaString { bytes: bytes }
+    }
+
+    pub fn from_utf16(units: &[u16]) -> JavaString {
+        let mut bytes = Vec::with_capacity(units.len());
+        for &unit in units {
+            push_modified_utf8(&mut bytes, unit);
+        }
+        JavaString { bytes: bytes }
+    }
+
+    /// The modified UTF-8 encoding, with NUL as `C0 80` and supplementary
+    /// characters as surrogate pairs.
+    pub fn as_modified_utf8(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn into_modified_utf8(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Decodes the string into UTF-16 code units as Java sees them. Fails only
+    /// on bytes that are not modified UTF-8.
+    pub fn to_utf16(&self) -> ParseResult<Vec<u16>> {
+        let mut units = Vec::with_capacity(self.bytes.len());
+        let mut i = 0;
+        while i < self.bytes.len() {
+            match decode_unit(&self.bytes, i) {
+                Option::Some((unit, length)) => {
+                    units.push(unit);
+                    i += length;
+                },
+                Option::None => {
+                    let message = format!("malformed modified utf8 byte {:#04x} at {}", self.bytes[i], i);
+                    return Result::Err(ParseError::Format(message));
+                }
+            }
+        }
+        Result::Ok(units)
+    }
+
+    /// Converts to a Rust string, failing on unpaired surrogates and malformed
+    /// bytes. Borrows whenever the bytes are also valid UTF-8.
+    pub fn try_to_str(&self) -> ParseResult<Cow<str>> {
+        if self.bytes.iter().all(|&b| b < 0xF0) {
+            if let Result::Ok(string) = str::from_utf8(&self.bytes) {
+                return Result::Ok(Cow::Borrowed(string));
+            }
+        }
+        let units = try!(self.to_utf16());
+        let mut string = String::with_capacity(self.bytes.len());
+        for c in char::decode_utf16(units.iter().cloned()) {
+            match c {
+                Result::Ok(c) => string.push(c),
+                Result::Err(e) => {
+                    let message = format!("unpaired surrogate {:x} in {:?}", e.unpaired_surrogate(), self.bytes);
+                    return Result::Err(ParseError::Format(message));
+                }
+            }
+        }
+        Result::Ok(Cow::Owned(string))
+    }
+
+    /// Converts to a Rust string, replacing unpaired surrogates and malformed
+    /// bytes with U+FFFD.
+    pub fn to_string_lossy(&self) -> Cow<str> {
+        if let Result::Ok(string) = self.try_to_str() {
+            return string;
+        }
+        let mut units = Vec::with_capacity(self.bytes.len());
+        let mut i = 0;
+        while i < self.bytes.len() {
+            match decode_unit(&self.bytes, i) {
+                Option::Some((unit, length)) => {
+                    units.push(unit);
+                    i += length;
+                },
+                Option::None => {
+                    info!("encountered malformed modified utf8 byte {:x}", self.bytes[i]);
+                    units.push(0xFFFD);
+                    i += 1;
+                }
+            }
+        }
+        Cow::Owned(char::decode_utf16(units.iter().cloned())
+            .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+            .collect())
+    }
+}
+
+impl<'a> From<&'a str> for JavaString {
+    fn from(string: &'a str) -> JavaString {
+        let mut bytes = Vec::with_capacity(string.len());
+        let mut units = [0; 2];
+        for c in string.chars() {
+            for &unit in c.encode_utf16(&mut units).iter() {
+                push_modified_utf8(&mut bytes, unit);
+            }
+        }
+        JavaString { bytes: bytes }
+    }
+}
+
+impl From<String> for JavaString {
+    fn from(string: String) -> JavaString {
+        JavaString::from(string.as_str())
+    }
+}
+
+impl PartialEq<str> for JavaString {
+    fn eq(&self, other: &str) -> bool {
+        if other.bytes().all(|b| b != 0 && b < 0xF0) {
+            self.bytes == other.as_bytes()
+        } else {
+            self.bytes == JavaString::from(other).bytes
+        }
+    }
+}
+
+impl<'a> PartialEq<&'a str> for JavaString {
+    fn eq(&self, other: &&'a str) -> bool {
+        *self == **other
+    }
+}
+
+impl PartialEq<String> for JavaString {
+    fn eq(&self, other: &String) -> bool {
+        *self == **other
+    }
+}
+
+impl PartialEq<JavaString> for str {
+    fn eq(&self, other: &JavaString) -> bool {
+        *other == *self
+    }
+}
+
+impl<'a> PartialEq<JavaString> for &'a str {
+    fn eq(&self, other: &JavaString) -> bool {
+        *other == **self
+    }
+}
+
+impl PartialEq<JavaString> for String {
+    fn eq(&self, other: &JavaString) -> bool {
+        *other == **self
+    }
+}
+
+impl fmt::Display for JavaString {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.to_string_lossy(), fmt)
+    }
+}
+
+impl fmt::Debug for JavaString {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self.try_to_str() {
+            Result::Ok(string) => fmt::Debug::fmt(&string, fmt),
+            Result::Err(_) => write!(fmt, "JavaString({:?})", self.bytes)
+        }
+    }
+}
+
+// Decodes the code unit starting at `i`, returning it with its encoded length.
+fn decode_unit(bytes: &[u8], i: usize) -> Option<(u16, usize)> {
+    let b0 = bytes[i] as u16;
+    let continuation = |offset: usize| match bytes.get(i + offset) {
+        Option::Some(&b) if b >> 6 == 0b10 => Option::Some(b as u16 & 0b0011_1111),
+        _ => Option::None
+    };
+    if b0 >> 7 == 0 {
+        Option::Some((b0, 1))
+    } else if b0 >> 5 == 0b110 {
+        continuation(1).map(|b1| (((b0 & 0b0001_1111) << 6) | b1, 2))
+    } else if b0 >> 4 == 0b1110 {
+        match (continuation(1), continuation(2)) {
+            (Option::Some(b1), Option::Some(b2)) => Option::Some((((b0 & 0b0000_1111) << 12) | (b1 << 6) | b2, 3)),
+            _ => Option::None
+        }
+    } else {
+        Option::None
+    }
+}
+
+fn push_modified_utf8(bytes: &mut Vec<u8>, unit: u16) {
+    if unit != 0 && unit <= 0x7F {
+        bytes.push(unit as u8);
+    } else if unit <= 0x7FF {
+        bytes.push((0b1100_0000 | (unit >> 6)) as u8);
+        bytes.push((0b1000_0000 | (unit & 0b0011_1111)) as u8);
+    } else {
+        bytes.push((0b1110_0000 | (unit >> 12)) as u8);
+        bytes.push((0b1000_0000 | ((unit >> 6) & 0b0011_1111)) as u8);
+        bytes.push((0b1000_0000 | (unit & 0b0011_1111)) as u8);
+    }
+}
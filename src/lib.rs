@@ -5,14 +5,51 @@ extern crate log;
 mod model;
 mod result;
 mod decode;
+mod encode;
+mod writer;
+mod module;
+mod pool;
+mod java_string;
+mod descriptor;
+mod signature;
+mod access;
+mod opcode;
+mod disasm;
+mod asm;
+mod cfg;
+mod dominators;
+mod loops;
+mod frames;
+mod verify;
+mod dataflow;
+mod code;
+mod debug;
 
-use std::char;
 use std::io::Read;
 use std::fs::File;
 
 pub use ::result::*;
 pub use ::model::*;
 pub use ::decode::*;
+pub use ::encode::*;
+pub use ::writer::*;
+pub use ::module::*;
+pub use ::java_string::*;
+pub use ::pool::*;
+pub use ::descriptor::*;
+pub use ::signature::*;
+pub use ::access::*;
+pub use ::opcode::*;
+pub use ::disasm::*;
+pub use ::asm::*;
+pub use ::cfg::*;
+pub use ::dominators::*;
+pub use ::loops::*;
+pub use ::frames::*;
+pub use ::verify::*;
+pub use ::dataflow::*;
+pub use ::code::*;
+pub use ::debug::*;
 
 pub struct ClassReader<'a> {
     reader: Box<Read + 'a>,
@@ -59,19 +96,12 @@ impl<'a> ClassReader<'a> {
         })
     }
 
-    fn read_attribute(self: &mut ClassReader<'a>, constant_pool: &Vec<ConstantPoolInfo>) -> ParseResult<Attribute> {
+    fn read_attribute(self: &mut ClassReader<'a>, constant_pool: &ConstantPool) -> ParseResult<Attribute> {
         let name_index = try!(self.read_u16());
         let length = try!(self.read_u32());
 
-        let name = match &constant_pool[name_index as usize - 1] {
-            &ConstantPoolInfo::Utf8(ref name) => { name },
-            i @ _ => {
-                let error_message = format!("expected utf8 at index {} but got {:?}", name_index, i);
-                return Result::Err(ParseError::Format(error_message));
-            }
-        };
-
-        let info = match name.as_str() {
+        let name = try!(constant_pool.utf8(name_index)).to_string_lossy();
+        let info = match &*name {
             "Code" => {
                 let max_stack = try!(self.read_u16());
                 let max_locals = try!(self.read_u16());
@@ -115,10 +145,13 @@ impl<'a> ClassReader<'a> {
                 for _ in 0..number_of_entries {
                     let frame_type = try!(self.read_u8());
                     let frame = match frame_type {
-                        0...63 => StackMapFrame::SameFrame,
+                        0...63 => StackMapFrame::SameFrame { offset_delta: frame_type },
                         64...127 => {
                             let info = try!(self.read_verification_type_info());
-                            StackMapFrame::SameLocals1StackItemFrame(info)
+                            StackMapFrame::SameLocals1StackItemFrame {
+                                offset_delta: frame_type - 64,
+                                stack: info
+                            }
                         },
                         128...246 => {
                             let message = format!("reserved frame type {} used", frame_type);
@@ -134,7 +167,10 @@ impl<'a> ClassReader<'a> {
                         },
                         248...250 => {
                             let offset_delta = try!(self.read_u16());
-                            StackMapFrame::ChopFrame { offset_delta: offset_delta }
+                            StackMapFrame::ChopFrame {
+                                offset_delta: offset_delta,
+                                chopped: 251 - frame_type
+                            }
                         },
                         251 => {
                             let offset_delta = try!(self.read_u16());
@@ -321,9 +357,110 @@ impl<'a> ClassReader<'a> {
                 }
                 Attribute::MethodParameters(parameters)
             },
+            "Module" => {
+                let module_name_index = try!(self.read_u16());
+                let module_flags = try!(self.read_u16());
+                let module_version_index = try!(self.read_u16());
+                let requires_count = try!(self.read_u16());
+                let mut requires = Vec::with_capacity(requires_count as usize);
+                for _ in 0..requires_count {
+                    let requires_index = try!(self.read_u16());
+                    let requires_flags = try!(self.read_u16());
+                    let requires_version_index = try!(self.read_u16());
+                    requires.push(ModuleRequires {
+                        requires_index: requires_index,
+                        requires_flags: requires_flags,
+                        requires_version_index: requires_version_index
+                    });
+                }
+                let exports_count = try!(self.read_u16());
+                let mut exports = Vec::with_capacity(exports_count as usize);
+                for _ in 0..exports_count {
+                    let exports_index = try!(self.read_u16());
+                    let exports_flags = try!(self.read_u16());
+                    let exports_to_index = try!(self.read_u16_table());
+                    exports.push(ModuleExports {
+                        exports_index: exports_index,
+                        exports_flags: exports_flags,
+                        exports_to_index: exports_to_index
+                    });
+                }
+                let opens_count = try!(self.read_u16());
+                let mut opens = Vec::with_capacity(opens_count as usize);
+                for _ in 0..opens_count {
+                    let opens_index = try!(self.read_u16());
+                    let opens_flags = try!(self.read_u16());
+                    let opens_to_index = try!(self.read_u16_table());
+                    opens.push(ModuleOpens {
+                        opens_index: opens_index,
+                        opens_flags: opens_flags,
+                        opens_to_index: opens_to_index
+                    });
+                }
+                let uses_index = try!(self.read_u16_table());
+                let provides_count = try!(self.read_u16());
+                let mut provides = Vec::with_capacity(provides_count as usize);
+                for _ in 0..provides_count {
+                    let provides_index = try!(self.read_u16());
+                    let provides_with_index = try!(self.read_u16_table());
+                    provides.push(ModuleProvides {
+                        provides_index: provides_index,
+                        provides_with_index: provides_with_index
+                    });
+                }
+                Attribute::Module {
+                    module_name_index: module_name_index,
+                    module_flags: module_flags,
+                    module_version_index: module_version_index,
+                    requires: requires,
+                    exports: exports,
+                    opens: opens,
+                    uses_index: uses_index,
+                    provides: provides
+                }
+            },
+            "ModulePackages" => {
+                let package_index = try!(self.read_u16_table());
+                Attribute::ModulePackages { package_index: package_index }
+            },
+            "ModuleMainClass" => {
+                let main_class_index = try!(self.read_u16());
+                Attribute::ModuleMainClass { main_class_index: main_class_index }
+            },
+            "NestHost" => {
+                let host_class_index = try!(self.read_u16());
+                Attribute::NestHost { host_class_index: host_class_index }
+            },
+            "NestMembers" => {
+                let classes = try!(self.read_u16_table());
+                Attribute::NestMembers { classes: classes }
+            },
+            "Record" => {
+                let components_count = try!(self.read_u16());
+                let mut components = Vec::with_capacity(components_count as usize);
+                for _ in 0..components_count {
+                    let name_index = try!(self.read_u16());
+                    let descriptor_index = try!(self.read_u16());
+                    let attributes = try!(self.read_attributes(constant_pool));
+                    let component = RecordComponent {
+                        name_index: name_index,
+                        descriptor_index: descriptor_index,
+                        attributes: attributes
+                    };
+                    components.push(component);
+                }
+                Attribute::Record(components)
+            },
+            "PermittedSubclasses" => {
+                let classes = try!(self.read_u16_table());
+                Attribute::PermittedSubclasses { classes: classes }
+            },
             _ => {
                 let info = try!(self.read_bytes(length));
-                Attribute::Unknown(info)
+                Attribute::Unknown {
+                    name_index: name_index,
+                    info: info
+                }
             }
         };
         Result::Ok(info)
@@ -364,7 +501,7 @@ impl<'a> ClassReader<'a> {
         Result::Ok(infos)
     }
 
-    fn read_type_annotations(self: &mut ClassReader<'a>, constant_pool: &Vec<ConstantPoolInfo>) -> ParseResult<Vec<TypeAnnotation>> {
+    fn read_type_annotations(self: &mut ClassReader<'a>, constant_pool: &ConstantPool) -> ParseResult<Vec<TypeAnnotation>> {
         let num_annotations = try!(self.read_u16());
         let mut annotations = Vec::with_capacity(num_annotations as usize);
         for _ in 0..num_annotations {
@@ -374,7 +511,7 @@ impl<'a> ClassReader<'a> {
         Result::Ok(annotations)
     }
 
-    fn read_type_annotation(self: &mut ClassReader<'a>, constant_pool: &Vec<ConstantPoolInfo>) -> ParseResult<TypeAnnotation> {
+    fn read_type_annotation(self: &mut ClassReader<'a>, constant_pool: &ConstantPool) -> ParseResult<TypeAnnotation> {
         let target_type_tag = try!(self.read_u8());
         let target_type = match target_type_tag {
             0x00 => TargetType::Type,
@@ -492,7 +629,7 @@ impl<'a> ClassReader<'a> {
         })
     }
 
-    fn read_parameter_annotations(self: &mut ClassReader<'a>, constant_pool: &Vec<ConstantPoolInfo>) -> ParseResult<Vec<Vec<Annotation>>> {
+    fn read_parameter_annotations(self: &mut ClassReader<'a>, constant_pool: &ConstantPool) -> ParseResult<Vec<Vec<Annotation>>> {
         let num_parameters = try!(self.read_u8());
         let mut parameter_annotations = Vec::with_capacity(num_parameters as usize);
         for _ in 0..num_parameters {
@@ -502,7 +639,7 @@ impl<'a> ClassReader<'a> {
         Result::Ok(parameter_annotations)
     }
 
-    fn read_annotations(self: &mut ClassReader<'a>, constant_pool: &Vec<ConstantPoolInfo>) -> ParseResult<Vec<Annotation>> {
+    fn read_annotations(self: &mut ClassReader<'a>, constant_pool: &ConstantPool) -> ParseResult<Vec<Annotation>> {
         let num_annotations = try!(self.read_u16());
         let mut annotations = Vec::with_capacity(num_annotations as usize);
         for _ in 0..num_annotations {
@@ -512,7 +649,7 @@ impl<'a> ClassReader<'a> {
         Result::Ok(annotations)
     }
 
-    fn read_annotation(self: &mut ClassReader<'a>, constant_pool: &Vec<ConstantPoolInfo>) -> ParseResult<Annotation> {
+    fn read_annotation(self: &mut ClassReader<'a>, constant_pool: &ConstantPool) -> ParseResult<Annotation> {
         let type_index = try!(self.read_u16());
         let element_value_pairs = try!(self.read_element_value_pairs(constant_pool));
         Result::Ok(Annotation {
@@ -521,7 +658,7 @@ impl<'a> ClassReader<'a> {
         })
     }
 
-    fn read_element_value_pairs(self: &mut ClassReader<'a>, constant_pool: &Vec<ConstantPoolInfo>) -> ParseResult<Vec<ElementValuePair>> {
+    fn read_element_value_pairs(self: &mut ClassReader<'a>, constant_pool: &ConstantPool) -> ParseResult<Vec<ElementValuePair>> {
         let num_evps = try!(self.read_u16());
         let mut element_value_pairs = Vec::with_capacity(num_evps as usize);
         for _ in 0..num_evps {
@@ -537,12 +674,15 @@ impl<'a> ClassReader<'a> {
         Result::Ok(element_value_pairs)
     }
 
-    fn read_element_value(self: &mut ClassReader<'a>, constant_pool: &Vec<ConstantPoolInfo>) -> ParseResult<ElementValue> {
+    fn read_element_value(self: &mut ClassReader<'a>, constant_pool: &ConstantPool) -> ParseResult<ElementValue> {
         let tag = try!(self.read_u8()) as char;
         let value = match tag {
             'B' | 'C' | 'D' | 'F' | 'I' | 'J' | 'S' | 'Z' | 's' => {
                 let const_value_index = try!(self.read_u16());
-                ElementValue::Constant { const_value_index: const_value_index }
+                ElementValue::Constant {
+                    tag: tag,
+                    const_value_index: const_value_index
+                }
             },
             'e' => {
                 let type_name_index = try!(self.read_u16());
@@ -577,7 +717,7 @@ impl<'a> ClassReader<'a> {
         Result::Ok(value)
     }
 
-    fn read_attributes(self: &mut ClassReader<'a>, constant_pool: &Vec<ConstantPoolInfo>) -> ParseResult<Vec<Attribute>> {
+    fn read_attributes(self: &mut ClassReader<'a>, constant_pool: &ConstantPool) -> ParseResult<Vec<Attribute>> {
         let attribute_count = try!(self.read_u16());
         let mut attributes = Vec::with_capacity(attribute_count as usize);
         for _ in 0..attribute_count {
@@ -587,7 +727,7 @@ impl<'a> ClassReader<'a> {
         Result::Ok(attributes)
     }
 
-    fn read_methods(self: &mut ClassReader<'a>, constant_pool: &Vec<ConstantPoolInfo>) -> ParseResult<Vec<Method>> {
+    fn read_methods(self: &mut ClassReader<'a>, constant_pool: &ConstantPool) -> ParseResult<Vec<Method>> {
         let method_count = try!(self.read_u16());
         let mut methods = Vec::with_capacity(method_count as usize);
         for _ in 0..method_count {
@@ -607,7 +747,7 @@ impl<'a> ClassReader<'a> {
         Result::Ok(methods)
     }
 
-    fn read_fields(self: &mut ClassReader<'a>, constant_pool: &Vec<ConstantPoolInfo>) -> ParseResult<Vec<Field>> {
+    fn read_fields(self: &mut ClassReader<'a>, constant_pool: &ConstantPool) -> ParseResult<Vec<Field>> {
         let field_count = try!(self.read_u16());
         let mut fields = Vec::with_capacity(field_count as usize);
         for _ in 0..field_count {
@@ -637,13 +777,23 @@ impl<'a> ClassReader<'a> {
         Result::Ok(interfaces)
     }
 
-    fn read_constant_pool(self: &mut ClassReader<'a>) -> ParseResult<Vec<ConstantPoolInfo>> {
-        let cp_count = try!(self.read_u16()) - 1;
+    fn read_u16_table(self: &mut ClassReader<'a>) -> ParseResult<Vec<u16>> {
+        let count = try!(self.read_u16());
+        let mut table = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let entry = try!(self.read_u16());
+            table.push(entry);
+        }
+        Result::Ok(table)
+    }
+
+    fn read_constant_pool(self: &mut ClassReader<'a>) -> ParseResult<ConstantPool> {
+        let cp_count = try!(self.read_u16()).saturating_sub(1);
         let mut cp: Vec<ConstantPoolInfo> = Vec::with_capacity(cp_count as usize);
 
         let mut i = 0;
         while i < cp_count {
-            let cp_info = try!(self.read_constant_pool_info());
+            let cp_info = try!(self.read_constant_pool_info(i + 1));
             let is_double_length = cp_info.is_double_length();
 
             cp.push(cp_info);
@@ -655,18 +805,18 @@ impl<'a> ClassReader<'a> {
             i += 1;
         }
 
-        Result::Ok(cp)
+        Result::Ok(ConstantPool::new(cp))
     }
 
-    fn read_constant_pool_info(self: &mut ClassReader<'a>) -> ParseResult<ConstantPoolInfo> {
+    fn read_constant_pool_info(self: &mut ClassReader<'a>, index: u16) -> ParseResult<ConstantPoolInfo> {
         let tag = try!(self.read_u8());
         debug!("read constant pool info tag {}", tag);
         let info = match tag {
             1 => {
                 let length = try!(self.read_u16());
                 let data = try!(self.read_bytes(length as u32));
-                let string = read_modified_utf8(&data.clone());
-                trace!("read {} utf8 bytes {:?} -> {}", length, data, string);
+                let string = JavaString::from_modified_utf8(data);
+                trace!("read {} utf8 bytes -> {:?}", length, string);
                 ConstantPoolInfo::Utf8(string)
             },
             3 => {
@@ -674,7 +824,7 @@ impl<'a> ClassReader<'a> {
                 ConstantPoolInfo::Integer(value)
             },
             4 => {
-                let value = try!(self.read_u32()) as f32;
+                let value = f32::from_bits(try!(self.read_u32()));
                 ConstantPoolInfo::Float(value)
             },
             5 => {
@@ -682,7 +832,7 @@ impl<'a> ClassReader<'a> {
                 ConstantPoolInfo::Long(value)
             },
             6 => {
-                let value = try!(self.read_u64()) as f64;
+                let value = f64::from_bits(try!(self.read_u64()));
                 ConstantPoolInfo::Double(value)
             },
             7 => {
@@ -722,13 +872,30 @@ impl<'a> ClassReader<'a> {
                 let descriptor_index = try!(self.read_u16());
                 ConstantPoolInfo::MethodType(descriptor_index)
             },
+            17 => {
+                let bootstrap_method_attr_index = try!(self.read_u16());
+                let name_and_type_index = try!(self.read_u16());
+                ConstantPoolInfo::Dynamic(bootstrap_method_attr_index, name_and_type_index)
+            },
             18 => {
                 let bootstrap_method_attr_index = try!(self.read_u16());
                 let name_and_type_index = try!(self.read_u16());
                 ConstantPoolInfo::InvokeDynamic(bootstrap_method_attr_index, name_and_type_index)
             },
+            19 => {
+                let name_index = try!(self.read_u16());
+                ConstantPoolInfo::Module(name_index)
+            },
+            20 => {
+                let name_index = try!(self.read_u16());
+                ConstantPoolInfo::Package(name_index)
+            },
+            2 | 13 | 14 => {
+                let message = format!("unused constant pool tag {} at index {}", tag, index);
+                return Result::Err(ParseError::Format(message));
+            },
             _ => {
-                let message = format!("unknown constant pool item with tag {}", tag);
+                let message = format!("unknown constant pool tag {} at index {}", tag, index);
                 return Result::Err(ParseError::Format(message));
             }
         };
@@ -780,66 +947,3 @@ impl<'a> ClassReader<'a> {
     }
 
 }
-
-fn read_modified_utf8(buf: &Vec<u8>) -> String {
-    let mut string = String::with_capacity(buf.len());
-
-    let mut i = 0;
-    while i < buf.len() {
-        let b0 = buf[i] as u32;
-        let decoded_char = if (b0 >> 7) == 0 {
-            trace!("read byte {:08.b}", b0);
-            char::from_u32(b0)
-        } else if (b0 >> 5) == 0b110 {
-            i += 1;
-            let b1 = buf[i] as u32; // assert that (b1 >> 6) == 0b10
-            let code_point = ((b0 & 0b0001_1111) << 6)
-                    + (b1 & 0b0011_1111);
-            trace!("read bytes {:08.b} {:08.b} -> {:032.b}", b0, b1, code_point);
-            char::from_u32(code_point)
-        } else if (b0 >> 4) == 0b1110 {
-            i += 1;
-            let b1 = buf[i] as u32; // assert that (b1 >> 6) == 0b10
-            i += 1;
-            let b2 = buf[i] as u32; // assert that (b1 >> 6) == 0b10
-            let check_for_surrogate = i < (buf.len() - 2);
-            if (b0 == 0b11101101) && ((b1 >> 4) == 0b1010) && check_for_surrogate && (buf[i+1] == 0b1110_1101) { // surrogate pair
-                i += 1;
-                let b3 = buf[i] as u32; // assert that b3 == 0b1110_1101
-                i += 1;
-                let b4 = buf[i] as u32; // assert that (b4 >> 4) == b1011
-                i += 1;
-                let b5 = buf[i] as u32; // assert that (b5 >> 6) == b10
-                let code_point = 0b1_0000_0000_0000_0000
-                        + ((b1 & 0b0000_1111) << 16)
-                        + ((b2 & 0b0011_1111) << 10)
-                        + ((b4 & 0b0000_1111) << 6)
-                        + (b5 & 0x0011_1111);
-                trace!("read bytes {:08.b} {:08.b} {:08.b} {:08.b} {:08.b} {:08.b} -> {:032.b}", b0, b1, b2, b3, b4, b5, code_point);
-                trace!(" -> {} {:x}", code_point, code_point);
-                char::from_u32(code_point)
-            } else {
-                let code_point = ((b0 & 0b0000_1111) << 12)
-                        + ((b1 & 0b0011_1111) << 6)
-                        + (b2 & 0b0011_1111);
-                if ((code_point >= 0xD800) && (code_point <= 0xDBFF))
-                        || ((code_point >= 0xDC00) && (code_point <= 0xDFFF)) {
-                    info!("encountered surrogate code point {:x} which is invalid for UTF-8", code_point);
-                    Option::None
-                } else {
-                    trace!("read bytes {:08.b} {:08.b} {:08.b} -> {:032.b}", b0, b1, b2, code_point);
-                    trace!(" -> {} {:x}", code_point, code_point);
-                    char::from_u32(code_point)
-                }
-            }
-        } else {
-            Option::None
-        };
-        match decoded_char {
-            Option::None => string.push('\u{FFFD}'),
-            Option::Some(c) => string.push(c)
-        }
-        i += 1;
-    }
-    string
-}
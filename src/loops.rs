@@ -0,0 +1,190 @@
+use ::cfg::ControlFlowGraph;
+use ::dominators::DominatorTree;
+
+/// The natural loops of a control-flow graph, nested into a forest, and the
+/// regions that are cyclic without being natural loops.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoopForest {
+    /// The loops in the order of their headers in a depth-first walk from the
+    /// entry, so every loop comes after the loops that contain it.
+    pub loops: Vec<Loop>,
+    pub irreducible_regions: Vec<IrreducibleRegion>,
+    innermost: Vec<Option<usize>>
+}
+
+/// A natural loop: the blocks that reach a back edge, an edge to a block that
+/// dominates its source, without passing the header the back edge goes to.
+/// Back edges to the same header make up one loop.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Loop {
+    pub header: usize,
+    /// The sources of the back edges.
+    pub latches: Vec<usize>,
+    /// The blocks of the loop in ascending order, including the header and
+    /// the blocks of nested loops.
+    pub blocks: Vec<usize>,
+    /// The index of the innermost loop containing this one.
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+    /// 1 for outermost loops.
+    pub depth: usize
+}
+
+/// A cycle that can be entered at more than one block, so that none of them
+/// dominates the others. Such regions are left when all back edges are
+/// removed; in a reducible graph there are none.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IrreducibleRegion {
+    /// The blocks of the region with predecessors outside of it, and the entry
+    /// of the method if it is part of the region.
+    pub entries: Vec<usize>,
+    /// The blocks of the region in ascending order.
+    pub blocks: Vec<usize>
+}
+
+impl LoopForest {
+    /// Finds the loops of a graph given its dominator tree.
+    pub fn new(graph: &ControlFlowGraph, dominators: &DominatorTree) -> LoopForest {
+        let count = graph.blocks.len();
+        let mut loops: Vec<Loop> = Vec::new();
+        for &header in graph.reverse_postorder().iter() {
+            let latches: Vec<usize> = graph.blocks[header].predecessors.iter()
+                .map(|edge| edge.from)
+                .filter(|&latch| dominators.dominates(header, latch))
+                .collect();
+            if latches.is_empty() {
+                continue;
+            }
+            let mut in_loop = vec![false; count];
+            in_loop[header] = true;
+            let mut stack = latches.clone();
+            while let Option::Some(block) = stack.pop() {
+                if in_loop[block] {
+                    continue;
+                }
+                in_loop[block] = true;
+                stack.extend(graph.blocks[block].predecessors.iter()
+                    .map(|edge| edge.from)
+                    .filter(|&predecessor| dominators.contains(predecessor)));
+            }
+            let mut latches = latches;
+            latches.sort();
+            latches.dedup();
+            loops.push(Loop {
+                header: header,
+                latches: latches,
+                blocks: (0..count).filter(|&block| in_loop[block]).collect(),
+                parent: Option::None,
+                children: Vec::new(),
+                depth: 1
+            });
+        }
+
+        // Natural loops are either nested or disjoint, and a loop's header
+        // comes after the headers of the loops around it.
+        let mut innermost: Vec<Option<usize>> = vec![Option::None; count];
+        for i in 0..loops.len() {
+            let parent = innermost[loops[i].header];
+            if let Option::Some(parent) = parent {
+                loops[parent].children.push(i);
+                loops[i].depth = loops[parent].depth + 1;
+            }
+            loops[i].parent = parent;
+            for &block in &loops[i].blocks {
+                innermost[block] = Option::Some(i);
+            }
+        }
+
+        let irreducible_regions = irreducible_regions(graph, dominators);
+        LoopForest { loops: loops, irreducible_regions: irreducible_regions, innermost: innermost }
+    }
+
+    /// The index of the innermost loop containing the block.
+    pub fn innermost_loop(&self, block: usize) -> Option<usize> {
+        self.innermost[block]
+    }
+
+    /// The number of loops containing the block.
+    pub fn depth(&self, block: usize) -> usize {
+        self.innermost[block].map_or(0, |index| self.loops[index].depth)
+    }
+
+    pub fn is_reducible(&self) -> bool {
+        self.irreducible_regions.is_empty()
+    }
+}
+
+// The strongly connected components that are left with more than one block
+// when back edges are removed, found with Tarjan's algorithm.
+fn irreducible_regions(graph: &ControlFlowGraph, dominators: &DominatorTree) -> Vec<IrreducibleRegion> {
+    let count = graph.blocks.len();
+    let successors: Vec<Vec<usize>> = graph.blocks.iter().enumerate().map(|(block, data)| {
+        data.successors.iter()
+            .map(|edge| edge.to)
+            .filter(|&successor| !dominators.dominates(successor, block))
+            .collect()
+    }).collect();
+
+    let mut regions = Vec::new();
+    let mut indices = vec![::std::usize::MAX; count];
+    let mut lowlinks = vec![0; count];
+    let mut on_stack = vec![false; count];
+    let mut stack = Vec::new();
+    let mut next = 0;
+    for start in (0..count).filter(|&block| dominators.contains(block)) {
+        if indices[start] != ::std::usize::MAX {
+            continue;
+        }
+        let mut calls = vec![(start, 0)];
+        indices[start] = next;
+        lowlinks[start] = next;
+        next += 1;
+        stack.push(start);
+        on_stack[start] = true;
+        while !calls.is_empty() {
+            let top = calls.len() - 1;
+            let (block, edge) = calls[top];
+            if let Option::Some(&successor) = successors[block].get(edge) {
+                calls[top].1 += 1;
+                if indices[successor] == ::std::usize::MAX {
+                    indices[successor] = next;
+                    lowlinks[successor] = next;
+                    next += 1;
+                    stack.push(successor);
+                    on_stack[successor] = true;
+                    calls.push((successor, 0));
+                } else if on_stack[successor] {
+                    lowlinks[block] = lowlinks[block].min(indices[successor]);
+                }
+                continue;
+            }
+            calls.pop();
+            if let Option::Some(&(caller, _)) = calls.last() {
+                lowlinks[caller] = lowlinks[caller].min(lowlinks[block]);
+            }
+            if lowlinks[block] != indices[block] {
+                continue;
+            }
+            let mut blocks = Vec::new();
+            loop {
+                let member = stack.pop().unwrap();
+                on_stack[member] = false;
+                blocks.push(member);
+                if member == block {
+                    break;
+                }
+            }
+            if blocks.len() > 1 {
+                blocks.sort();
+                let entries = blocks.iter().cloned().filter(|&member| {
+                    member == 0 || graph.blocks[member].predecessors.iter().any(|edge| {
+                        dominators.contains(edge.from) && blocks.binary_search(&edge.from).is_err()
+                    })
+                }).collect();
+                regions.push(IrreducibleRegion { entries: entries, blocks: blocks });
+            }
+        }
+    }
+    regions.sort_by_key(|region| region.blocks[0]);
+    regions
+}
@@ -1,3 +1,9 @@
+use ::descriptor::{FieldType, MethodDescriptor};
+use ::java_string::JavaString;
+use ::signature::{ClassSignature, MethodSignature, ReferenceTypeSignature};
+use ::pool::ConstantPool;
+use ::result::{ParseError, ParseResult};
+
 macro_rules! modifier_raw {
     ($name:ident, $field:ident, $mask:expr) => {
         pub fn $name(&self) -> bool {
@@ -14,12 +20,12 @@ macro_rules! modifier_inner {
     ($name:ident, $mask:expr) => { modifier_raw!($name, inner_class_access_flags, $mask); }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Class {
     pub magic: u32,
     pub minor_version: u16,
     pub major_version: u16,
-    pub constant_pool: Vec<ConstantPoolInfo>,
+    pub constant_pool: ConstantPool,
     pub access_flags: u16,
     pub this_class: u16,
     pub super_class: u16,
@@ -38,11 +44,123 @@ impl Class {
     modifier!(is_synthetic, 0x1000);
     modifier!(is_annotation, 0x2000);
     modifier!(is_enum, 0x4000);
+
+    /// The name of this class in internal form (`java/lang/Object`).
+    pub fn name(&self) -> ParseResult<String> {
+        self.constant_pool.class_name(self.this_class)
+    }
+
+    /// The name of the superclass, `None` for `java/lang/Object` and modules.
+    pub fn super_name(&self) -> ParseResult<Option<String>> {
+        if self.super_class == 0 {
+            Result::Ok(Option::None)
+        } else {
+            self.constant_pool.class_name(self.super_class).map(Option::Some)
+        }
+    }
+
+    pub fn interface_names(&self) -> ParseResult<Vec<String>> {
+        let mut names = Vec::with_capacity(self.interfaces.len());
+        for &index in &self.interfaces {
+            names.push(try!(self.constant_pool.class_name(index)));
+        }
+        Result::Ok(names)
+    }
+
+    pub fn find_method(&self, name: &str, descriptor: &str) -> Option<&Method> {
+        self.methods.iter().find(|method| {
+            is_utf8(&self.constant_pool, method.name_index, name)
+                && is_utf8(&self.constant_pool, method.descriptor_index, descriptor)
+        })
+    }
+
+    pub fn find_field(&self, name: &str) -> Option<&Field> {
+        self.fields.iter().find(|field| is_utf8(&self.constant_pool, field.name_index, name))
+    }
+
+    /// The parsed `Signature` attribute, `None` if the class is not generic.
+    pub fn signature(&self) -> ParseResult<Option<ClassSignature>> {
+        match signature_index(&self.attributes) {
+            Option::Some(index) => ClassSignature::parse(&try!(self.constant_pool.utf8_string(index))).map(Option::Some),
+            Option::None => Result::Ok(Option::None)
+        }
+    }
+
+    pub fn is_record(&self) -> bool {
+        self.record_components().is_some()
+    }
+
+    pub fn record_components(&self) -> Option<&[RecordComponent]> {
+        for attribute in &self.attributes {
+            if let Attribute::Record(ref components) = *attribute {
+                return Option::Some(components);
+            }
+        }
+        Option::None
+    }
+
+    /// Whether the class has a `PermittedSubclasses` attribute, i.e. is `sealed`.
+    pub fn is_sealed(&self) -> bool {
+        self.attributes.iter().any(|attribute| match *attribute {
+            Attribute::PermittedSubclasses { .. } => true,
+            _ => false
+        })
+    }
+
+    /// The names of the permitted subclasses, empty unless the class is sealed.
+    pub fn permitted_subclasses(&self) -> ParseResult<Vec<String>> {
+        self.class_names_of(|attribute| match *attribute {
+            Attribute::PermittedSubclasses { ref classes } => Option::Some(classes),
+            _ => Option::None
+        })
+    }
+
+    /// The name of the nest host, `None` if the class is not a nest member.
+    pub fn nest_host(&self) -> ParseResult<Option<String>> {
+        for attribute in &self.attributes {
+            if let Attribute::NestHost { host_class_index } = *attribute {
+                return self.constant_pool.class_name(host_class_index).map(Option::Some);
+            }
+        }
+        Result::Ok(Option::None)
+    }
+
+    /// The names of the nest members, empty unless the class is a nest host.
+    pub fn nest_members(&self) -> ParseResult<Vec<String>> {
+        self.class_names_of(|attribute| match *attribute {
+            Attribute::NestMembers { ref classes } => Option::Some(classes),
+            _ => Option::None
+        })
+    }
+
+    fn class_names_of<F>(&self, f: F) -> ParseResult<Vec<String>> where F: Fn(&Attribute) -> Option<&Vec<u16>> {
+        let mut names = Vec::new();
+        for classes in self.attributes.iter().filter_map(f) {
+            for &index in classes {
+                names.push(try!(self.constant_pool.class_name(index)));
+            }
+        }
+        Result::Ok(names)
+    }
 }
 
-#[derive(Debug)]
+fn signature_index(attributes: &[Attribute]) -> Option<u16> {
+    attributes.iter().filter_map(|attribute| match *attribute {
+        Attribute::Signature { signature_index } => Option::Some(signature_index),
+        _ => Option::None
+    }).next()
+}
+
+fn is_utf8(constant_pool: &ConstantPool, index: u16, string: &str) -> bool {
+    match constant_pool.utf8(index) {
+        Result::Ok(utf8) => *utf8 == *string,
+        Result::Err(_) => false
+    }
+}
+
+#[derive(Debug, Clone)]
 pub enum ConstantPoolInfo {
-    Utf8(String),                       // 1
+    Utf8(JavaString),                   // 1
     Integer(i32),                       // 3
     Float(f32),                         // 4
     Long(i64),                          // 5
@@ -55,11 +173,14 @@ pub enum ConstantPoolInfo {
     NameAndType(u16, u16),              // 12
     MethodHandle(u8, u16),              // 15
     MethodType(u16),                    // 16
+    Dynamic(u16, u16),                  // 17
     InvokeDynamic(u16, u16),            // 18
+    Module(u16),                        // 19
+    Package(u16),                       // 20
     Invalid
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Field {
     pub access_flags: u16,
     pub name_index: u16,
@@ -77,9 +198,72 @@ impl Field {
     modifier!(is_transient, 0x0080);
     modifier!(is_synthetic, 0x1000);
     modifier!(is_enum, 0x4000);
+
+    pub fn name(&self, constant_pool: &ConstantPool) -> ParseResult<String> {
+        constant_pool.utf8_string(self.name_index)
+    }
+
+    pub fn descriptor(&self, constant_pool: &ConstantPool) -> ParseResult<String> {
+        constant_pool.utf8_string(self.descriptor_index)
+    }
+
+    pub fn field_type(&self, constant_pool: &ConstantPool) -> ParseResult<FieldType> {
+        FieldType::parse(&try!(self.descriptor(constant_pool)))
+    }
+
+    pub fn signature(&self, constant_pool: &ConstantPool) -> ParseResult<Option<ReferenceTypeSignature>> {
+        match signature_index(&self.attributes) {
+            Option::Some(index) => ReferenceTypeSignature::parse(&try!(constant_pool.utf8_string(index))).map(Option::Some),
+            Option::None => Result::Ok(Option::None)
+        }
+    }
+
+    /// The value of the `ConstantValue` attribute, typed by the field descriptor.
+    pub fn constant_value(&self, constant_pool: &ConstantPool) -> ParseResult<Option<ConstantValue>> {
+        let constvalue_index = match self.attributes.iter().filter_map(|attribute| match *attribute {
+            Attribute::ConstantValue { constvalue_index } => Option::Some(constvalue_index),
+            _ => Option::None
+        }).next() {
+            Option::Some(index) => index,
+            Option::None => { return Result::Ok(Option::None); }
+        };
+        let descriptor = try!(constant_pool.utf8_string(self.descriptor_index));
+        let constant = try!(constant_pool.get(constvalue_index));
+        let value = match (descriptor.as_str(), constant) {
+            ("Z", &ConstantPoolInfo::Integer(value)) => ConstantValue::Boolean(value != 0),
+            ("B", &ConstantPoolInfo::Integer(value)) => ConstantValue::Byte(value as i8),
+            ("C", &ConstantPoolInfo::Integer(value)) => ConstantValue::Char(value as u16),
+            ("S", &ConstantPoolInfo::Integer(value)) => ConstantValue::Short(value as i16),
+            ("I", &ConstantPoolInfo::Integer(value)) => ConstantValue::Int(value),
+            ("J", &ConstantPoolInfo::Long(value)) => ConstantValue::Long(value),
+            ("F", &ConstantPoolInfo::Float(value)) => ConstantValue::Float(value),
+            ("D", &ConstantPoolInfo::Double(value)) => ConstantValue::Double(value),
+            ("Ljava/lang/String;", &ConstantPoolInfo::String(string_index)) => {
+                ConstantValue::String(try!(constant_pool.utf8(string_index)).clone())
+            },
+            (_, other) => {
+                let message = format!("constant value {:?} does not match field descriptor {}", other, descriptor);
+                return Result::Err(ParseError::Format(message));
+            }
+        };
+        Result::Ok(Option::Some(value))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ConstantValue {
+    Boolean(bool),
+    Byte(i8),
+    Char(u16),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    String(JavaString)
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Method {
     pub access_flags: u16,
     pub name_index: u16,
@@ -96,13 +280,32 @@ impl Method {
     modifier!(is_synchronized, 0x0020);
     modifier!(is_bridge, 0x0040);
     modifier!(is_varargs, 0x0080);
-    modifier!(is_native, 0x01000);
+    modifier!(is_native, 0x0100);
     modifier!(is_abstract, 0x0400);
     modifier!(is_strict, 0x0800);
     modifier!(is_synthetic, 0x1000);
+
+    pub fn name(&self, constant_pool: &ConstantPool) -> ParseResult<String> {
+        constant_pool.utf8_string(self.name_index)
+    }
+
+    pub fn descriptor(&self, constant_pool: &ConstantPool) -> ParseResult<String> {
+        constant_pool.utf8_string(self.descriptor_index)
+    }
+
+    pub fn method_descriptor(&self, constant_pool: &ConstantPool) -> ParseResult<MethodDescriptor> {
+        MethodDescriptor::parse(&try!(self.descriptor(constant_pool)))
+    }
+
+    pub fn signature(&self, constant_pool: &ConstantPool) -> ParseResult<Option<MethodSignature>> {
+        match signature_index(&self.attributes) {
+            Option::Some(index) => MethodSignature::parse(&try!(constant_pool.utf8_string(index))).map(Option::Some),
+            Option::None => Result::Ok(Option::None)
+        }
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Attribute {
     ConstantValue{ constvalue_index: u16 },
     Code {
@@ -133,10 +336,26 @@ pub enum Attribute {
     AnnotationDefault { element_value: ElementValue },
     BootstrapMethods(Vec<BootstrapMethod>),
     MethodParameters(Vec<MethodParameter>),
-    Unknown(Vec<u8>)
+    Module {
+        module_name_index: u16,
+        module_flags: u16,
+        module_version_index: u16,
+        requires: Vec<ModuleRequires>,
+        exports: Vec<ModuleExports>,
+        opens: Vec<ModuleOpens>,
+        uses_index: Vec<u16>,
+        provides: Vec<ModuleProvides>
+    },
+    ModulePackages { package_index: Vec<u16> },
+    ModuleMainClass { main_class_index: u16 },
+    NestHost { host_class_index: u16 },
+    NestMembers { classes: Vec<u16> },
+    Record(Vec<RecordComponent>),
+    PermittedSubclasses { classes: Vec<u16> },
+    Unknown { name_index: u16, info: Vec<u8> }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Exception {
     pub start_pc: u16,
     pub end_pc: u16,
@@ -144,22 +363,52 @@ pub struct Exception {
     pub catch_type: u16
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum StackMapFrame {
-    SameFrame,
-    SameLocals1StackItemFrame(VerificationType),
-    SameLocals1StackItemFrameExtended { offset_delta: u16, stack: VerificationType },
-    ChopFrame { offset_delta: u16 },
-    SameFrameExtended { offset_delta: u16 },
-    AppendFrame { offset_delta: u16, locals: Vec<VerificationType> },
-    FullFrame {
+    SameFrame { offset_delta: u8 },                                                     // 0-63
+    SameLocals1StackItemFrame { offset_delta: u8, stack: VerificationType },            // 64-127
+    SameLocals1StackItemFrameExtended { offset_delta: u16, stack: VerificationType },   // 247
+    ChopFrame { offset_delta: u16, chopped: u8 },                                       // 248-250
+    SameFrameExtended { offset_delta: u16 },                                            // 251
+    AppendFrame { offset_delta: u16, locals: Vec<VerificationType> },                   // 252-254
+    FullFrame {                                                                         // 255
         offset_delta: u16,
         locals: Vec<VerificationType>,
         stack: Vec<VerificationType>
     }
 }
 
-#[derive(Debug)]
+impl StackMapFrame {
+    /// The first byte of the frame in the class file. The offset delta of a
+    /// `SameFrame` is its frame type, and that of a
+    /// `SameLocals1StackItemFrame` its frame type less 64.
+    pub fn frame_type(&self) -> u8 {
+        match *self {
+            StackMapFrame::SameFrame { offset_delta } => offset_delta,
+            StackMapFrame::SameLocals1StackItemFrame { offset_delta, .. } => offset_delta.wrapping_add(64),
+            StackMapFrame::SameLocals1StackItemFrameExtended { .. } => 247,
+            StackMapFrame::ChopFrame { chopped, .. } => 251u8.wrapping_sub(chopped),
+            StackMapFrame::SameFrameExtended { .. } => 251,
+            StackMapFrame::AppendFrame { ref locals, .. } => 251u8.wrapping_add(locals.len() as u8),
+            StackMapFrame::FullFrame { .. } => 255
+        }
+    }
+
+    /// The pc of the frame for the first frame of a table, and the distance
+    /// to the previous frame's pc less one for the others.
+    pub fn offset_delta(&self) -> u16 {
+        match *self {
+            StackMapFrame::SameFrame { offset_delta } | StackMapFrame::SameLocals1StackItemFrame { offset_delta, .. } => {
+                offset_delta as u16
+            },
+            StackMapFrame::SameLocals1StackItemFrameExtended { offset_delta, .. } | StackMapFrame::ChopFrame { offset_delta, .. }
+                    | StackMapFrame::SameFrameExtended { offset_delta } | StackMapFrame::AppendFrame { offset_delta, .. }
+                    | StackMapFrame::FullFrame { offset_delta, .. } => offset_delta
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum VerificationType {
     Top,                                        // 0
     Integer,                                    // 1
@@ -172,7 +421,7 @@ pub enum VerificationType {
     UninitializedVariable { offset: u16 },      // 8
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct InnerClass {
     pub inner_class_info_index: u16,
     pub outer_class_info_index: u16,
@@ -193,13 +442,13 @@ impl InnerClass {
     modifier_inner!(is_enum, 0x4000);
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct LineNumber {
     pub start_pc: u16,
     pub line_number: u16
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct LocalVariable {
     pub start_pc: u16,
     pub length: u16,
@@ -208,13 +457,13 @@ pub struct LocalVariable {
     pub index: u16
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Annotation {
     pub type_index: u16,
     pub element_value_pairs: Vec<ElementValuePair>
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct TypeAnnotation {
     pub target_type: TargetType,
     pub target_info: TargetInfo,
@@ -223,22 +472,22 @@ pub struct TypeAnnotation {
     pub element_value_pairs: Vec<ElementValuePair>
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ElementValuePair {
     pub element_name_index: u16,
     pub value: ElementValue
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ElementValue {
-    Constant { const_value_index: u16 },
+    Constant { tag: char, const_value_index: u16 },
     EnumConstant { type_name_index: u16, const_name_index: u16 },
     Class { class_info_index: u16 },
     Annotation(Annotation),
     Array(Vec<ElementValue>)
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum TargetType {
     Type,                               // 0x00
     Method,                             // 0x01
@@ -264,7 +513,7 @@ pub enum TargetType {
     MethodReferenceArgument             // 0x4B
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum TargetInfo {
     TypeParameter { index: u8 },
     Supertype { index: u16 },
@@ -278,18 +527,18 @@ pub enum TargetInfo {
     TypeArgument { offset: u16, index: u8 }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct TypePath {
     pub path: Vec<PathElement>
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct PathElement {
     pub kind: TypePathKind,
     pub argument_index: u8
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum TypePathKind {
     Array,              // 0
     Nested,             // 1
@@ -297,26 +546,77 @@ pub enum TypePathKind {
     TypeArgument        // 3
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct LocalVariableTarget {
     pub start_pc: u16,
     pub length: u16,
     pub index: u16
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct BootstrapMethod {
     pub method_ref: u16,
     pub arguments: Vec<u16>
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct MethodParameter {
     pub name_index: u16,
     pub access_flags: u16
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordComponent {
+    pub name_index: u16,
+    pub descriptor_index: u16,
+    pub attributes: Vec<Attribute>
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModuleRequires {
+    pub requires_index: u16,
+    pub requires_flags: u16,
+    pub requires_version_index: u16
+}
+
+impl ModuleRequires {
+    modifier_raw!(is_transitive, requires_flags, 0x0020);
+    modifier_raw!(is_static_phase, requires_flags, 0x0040);
+    modifier_raw!(is_synthetic, requires_flags, 0x1000);
+    modifier_raw!(is_mandated, requires_flags, 0x8000);
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModuleExports {
+    pub exports_index: u16,
+    pub exports_flags: u16,
+    pub exports_to_index: Vec<u16>
+}
+
+impl ModuleExports {
+    modifier_raw!(is_synthetic, exports_flags, 0x1000);
+    modifier_raw!(is_mandated, exports_flags, 0x8000);
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModuleOpens {
+    pub opens_index: u16,
+    pub opens_flags: u16,
+    pub opens_to_index: Vec<u16>
+}
+
+impl ModuleOpens {
+    modifier_raw!(is_synthetic, opens_flags, 0x1000);
+    modifier_raw!(is_mandated, opens_flags, 0x8000);
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModuleProvides {
+    pub provides_index: u16,
+    pub provides_with_index: Vec<u16>
+}
+
+#[derive(Debug, Clone, PartialEq)]
 #[allow(non_camel_case_types)]
 pub enum Instruction {
     aaload,                                     //  50 (0x32)
@@ -375,7 +675,7 @@ pub enum Instruction {
     dup2_x1,                                    //  93 (0x5d)
     dup2_x2,                                    //  94 (0x5e)
     f2d,                                        // 141 (0x8d)
-    f2i,                                        // 193 (0x8b)
+    f2i,                                        // 139 (0x8b)
     f2l,                                        // 140 (0x8c)
     fadd,                                       //  98 (0x62)
     faload,                                     //  48 (0x30)
@@ -534,7 +834,7 @@ pub enum Instruction {
     iinc_w(u16, i16)                            // 196 (0xc4)
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ArrayType {
     Boolean,    //  4
     Char,       //  5
@@ -553,4 +853,66 @@ impl ConstantPoolInfo {
             _ => { false }
         }
     }
+
+    /// Whether the entry may be the operand of `ldc`, `ldc_w` or `ldc2_w` or a
+    /// static argument of a bootstrap method.
+    pub fn is_loadable(self: &ConstantPoolInfo) -> bool {
+        match *self {
+            ConstantPoolInfo::Integer(_) | ConstantPoolInfo::Float(_)
+                    | ConstantPoolInfo::Long(_) | ConstantPoolInfo::Double(_)
+                    | ConstantPoolInfo::Class(_) | ConstantPoolInfo::String(_)
+                    | ConstantPoolInfo::MethodHandle(_, _) | ConstantPoolInfo::MethodType(_)
+                    | ConstantPoolInfo::Dynamic(_, _) => { true },
+            _ => { false }
+        }
+    }
+}
+
+// Floats and doubles are compared by their bits so that NaN payloads and signed
+// zeros survive round trips and a constant is always equal to itself.
+impl PartialEq for ConstantPoolInfo {
+    fn eq(&self, other: &ConstantPoolInfo) -> bool {
+        use ::model::ConstantPoolInfo::*;
+        match (self, other) {
+            (&Utf8(ref a), &Utf8(ref b)) => a == b,
+            (&Integer(a), &Integer(b)) => a == b,
+            (&Float(a), &Float(b)) => a.to_bits() == b.to_bits(),
+            (&Long(a), &Long(b)) => a == b,
+            (&Double(a), &Double(b)) => a.to_bits() == b.to_bits(),
+            (&Class(a), &Class(b)) => a == b,
+            (&String(a), &String(b)) => a == b,
+            (&Fieldref(a1, a2), &Fieldref(b1, b2)) => a1 == b1 && a2 == b2,
+            (&Methodref(a1, a2), &Methodref(b1, b2)) => a1 == b1 && a2 == b2,
+            (&InterfaceMethodref(a1, a2), &InterfaceMethodref(b1, b2)) => a1 == b1 && a2 == b2,
+            (&NameAndType(a1, a2), &NameAndType(b1, b2)) => a1 == b1 && a2 == b2,
+            (&MethodHandle(a1, a2), &MethodHandle(b1, b2)) => a1 == b1 && a2 == b2,
+            (&MethodType(a), &MethodType(b)) => a == b,
+            (&Dynamic(a1, a2), &Dynamic(b1, b2)) => a1 == b1 && a2 == b2,
+            (&InvokeDynamic(a1, a2), &InvokeDynamic(b1, b2)) => a1 == b1 && a2 == b2,
+            (&Module(a), &Module(b)) => a == b,
+            (&Package(a), &Package(b)) => a == b,
+            (&Invalid, &Invalid) => true,
+            _ => false
+        }
+    }
+}
+
+// Like the constant pool entries they come from, float and double values are
+// compared bit by bit.
+impl PartialEq for ConstantValue {
+    fn eq(&self, other: &ConstantValue) -> bool {
+        use ::model::ConstantValue::*;
+        match (self, other) {
+            (&Boolean(a), &Boolean(b)) => a == b,
+            (&Byte(a), &Byte(b)) => a == b,
+            (&Char(a), &Char(b)) => a == b,
+            (&Short(a), &Short(b)) => a == b,
+            (&Int(a), &Int(b)) => a == b,
+            (&Long(a), &Long(b)) => a == b,
+            (&Float(a), &Float(b)) => a.to_bits() == b.to_bits(),
+            (&Double(a), &Double(b)) => a.to_bits() == b.to_bits(),
+            (&String(ref a), &String(ref b)) => a == b,
+            _ => false
+        }
+    }
 }
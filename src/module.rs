@@ -0,0 +1,150 @@
+use ::model::*;
+use ::pool::ConstantPool;
+use ::result::ParseResult;
+
+/// The module declaration of a `module-info.class` with all names resolved.
+/// Packages and classes are given in internal form (`java/lang`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModuleDescriptor {
+    pub name: String,
+    pub flags: u16,
+    pub version: Option<String>,
+    pub requires: Vec<ModuleDependency>,
+    pub exports: Vec<PackageExport>,
+    pub opens: Vec<PackageExport>,
+    pub uses: Vec<String>,
+    pub provides: Vec<ServiceProvider>,
+    pub packages: Vec<String>,
+    pub main_class: Option<String>
+}
+
+impl ModuleDescriptor {
+    pub fn is_open(&self) -> bool {
+        self.flags & 0x0020 == 0x0020
+    }
+
+    /// The packages exported to every module.
+    pub fn exported_packages(&self) -> Vec<&str> {
+        self.exports.iter()
+            .filter(|export| export.to.is_empty())
+            .map(|export| export.package.as_str())
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModuleDependency {
+    pub name: String,
+    pub flags: u16,
+    pub version: Option<String>
+}
+
+impl ModuleDependency {
+    pub fn is_transitive(&self) -> bool {
+        self.flags & 0x0020 == 0x0020
+    }
+
+    pub fn is_static_phase(&self) -> bool {
+        self.flags & 0x0040 == 0x0040
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackageExport {
+    pub package: String,
+    pub flags: u16,
+    pub to: Vec<String>
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServiceProvider {
+    pub service: String,
+    pub implementations: Vec<String>
+}
+
+impl Class {
+    /// Resolves the `Module`, `ModulePackages` and `ModuleMainClass` attributes.
+    /// Returns `None` if the class is not a module declaration.
+    pub fn module(&self) -> ParseResult<Option<ModuleDescriptor>> {
+        let cp = &self.constant_pool;
+        let mut descriptor = Option::None;
+        let mut packages = Vec::new();
+        let mut main_class = Option::None;
+        for attribute in &self.attributes {
+            match *attribute {
+                Attribute::Module { module_name_index, module_flags, module_version_index,
+                        ref requires, ref exports, ref opens, ref uses_index, ref provides } => {
+                    let mut dependencies = Vec::with_capacity(requires.len());
+                    for entry in requires {
+                        dependencies.push(ModuleDependency {
+                            name: try!(cp.module_name(entry.requires_index)),
+                            flags: entry.requires_flags,
+                            version: try!(cp.optional_utf8(entry.requires_version_index))
+                        });
+                    }
+                    let mut exported = Vec::with_capacity(exports.len());
+                    for entry in exports {
+                        exported.push(try!(package_export(cp, entry.exports_index, entry.exports_flags, &entry.exports_to_index)));
+                    }
+                    let mut opened = Vec::with_capacity(opens.len());
+                    for entry in opens {
+                        opened.push(try!(package_export(cp, entry.opens_index, entry.opens_flags, &entry.opens_to_index)));
+                    }
+                    let mut uses = Vec::with_capacity(uses_index.len());
+                    for &index in uses_index {
+                        uses.push(try!(cp.class_name(index)));
+                    }
+                    let mut providers = Vec::with_capacity(provides.len());
+                    for entry in provides {
+                        let mut implementations = Vec::with_capacity(entry.provides_with_index.len());
+                        for &index in &entry.provides_with_index {
+                            implementations.push(try!(cp.class_name(index)));
+                        }
+                        providers.push(ServiceProvider {
+                            service: try!(cp.class_name(entry.provides_index)),
+                            implementations: implementations
+                        });
+                    }
+                    descriptor = Option::Some(ModuleDescriptor {
+                        name: try!(cp.module_name(module_name_index)),
+                        flags: module_flags,
+                        version: try!(cp.optional_utf8(module_version_index)),
+                        requires: dependencies,
+                        exports: exported,
+                        opens: opened,
+                        uses: uses,
+                        provides: providers,
+                        packages: Vec::new(),
+                        main_class: Option::None
+                    });
+                },
+                Attribute::ModulePackages { ref package_index } => {
+                    for &index in package_index {
+                        packages.push(try!(cp.package_name(index)));
+                    }
+                },
+                Attribute::ModuleMainClass { main_class_index } => {
+                    main_class = Option::Some(try!(cp.class_name(main_class_index)));
+                },
+                _ => {}
+            }
+        }
+        Result::Ok(descriptor.map(|mut descriptor| {
+            descriptor.packages = packages;
+            descriptor.main_class = main_class;
+            descriptor
+        }))
+    }
+}
+
+fn package_export(cp: &ConstantPool, index: u16, flags: u16, to_index: &[u16]) -> ParseResult<PackageExport> {
+    let mut to = Vec::with_capacity(to_index.len());
+    for &module_index in to_index {
+        to.push(try!(cp.module_name(module_index)));
+    }
+    Result::Ok(PackageExport {
+        package: try!(cp.package_name(index)),
+        flags: flags,
+        to: to
+    })
+}
@@ -0,0 +1,723 @@
+use std::fmt;
+
+use ::descriptor::{FieldType, MethodDescriptor};
+use ::model::{ConstantPoolInfo, Instruction};
+use ::model::Instruction::*;
+use ::pool::ConstantPool;
+use ::result::{ParseError, ParseResult};
+
+/// The operation of an instruction as encoded in its first byte. The wide
+/// forms of loads, stores, `iinc` and `ret` all have the opcode `wide`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[allow(non_camel_case_types)]
+pub enum Opcode {
+    nop = 0x00,
+    aconst_null = 0x01,
+    iconst_m1 = 0x02,
+    iconst_0 = 0x03,
+    iconst_1 = 0x04,
+    iconst_2 = 0x05,
+    iconst_3 = 0x06,
+    iconst_4 = 0x07,
+    iconst_5 = 0x08,
+    lconst_0 = 0x09,
+    lconst_1 = 0x0a,
+    fconst_0 = 0x0b,
+    fconst_1 = 0x0c,
+    fconst_2 = 0x0d,
+    dconst_0 = 0x0e,
+    dconst_1 = 0x0f,
+    bipush = 0x10,
+    sipush = 0x11,
+    ldc = 0x12,
+    ldc_w = 0x13,
+    ldc2_w = 0x14,
+    iload = 0x15,
+    lload = 0x16,
+    fload = 0x17,
+    dload = 0x18,
+    aload = 0x19,
+    iload_0 = 0x1a,
+    iload_1 = 0x1b,
+    iload_2 = 0x1c,
+    iload_3 = 0x1d,
+    lload_0 = 0x1e,
+    lload_1 = 0x1f,
+    lload_2 = 0x20,
+    lload_3 = 0x21,
+    fload_0 = 0x22,
+    fload_1 = 0x23,
+    fload_2 = 0x24,
+    fload_3 = 0x25,
+    dload_0 = 0x26,
+    dload_1 = 0x27,
+    dload_2 = 0x28,
+    dload_3 = 0x29,
+    aload_0 = 0x2a,
+    aload_1 = 0x2b,
+    aload_2 = 0x2c,
+    aload_3 = 0x2d,
+    iaload = 0x2e,
+    laload = 0x2f,
+    faload = 0x30,
+    daload = 0x31,
+    aaload = 0x32,
+    baload = 0x33,
+    caload = 0x34,
+    saload = 0x35,
+    istore = 0x36,
+    lstore = 0x37,
+    fstore = 0x38,
+    dstore = 0x39,
+    astore = 0x3a,
+    istore_0 = 0x3b,
+    istore_1 = 0x3c,
+    istore_2 = 0x3d,
+    istore_3 = 0x3e,
+    lstore_0 = 0x3f,
+    lstore_1 = 0x40,
+    lstore_2 = 0x41,
+    lstore_3 = 0x42,
+    fstore_0 = 0x43,
+    fstore_1 = 0x44,
+    fstore_2 = 0x45,
+    fstore_3 = 0x46,
+    dstore_0 = 0x47,
+    dstore_1 = 0x48,
+    dstore_2 = 0x49,
+    dstore_3 = 0x4a,
+    astore_0 = 0x4b,
+    astore_1 = 0x4c,
+    astore_2 = 0x4d,
+    astore_3 = 0x4e,
+    iastore = 0x4f,
+    lastore = 0x50,
+    fastore = 0x51,
+    dastore = 0x52,
+    aastore = 0x53,
+    bastore = 0x54,
+    castore = 0x55,
+    sastore = 0x56,
+    pop = 0x57,
+    pop2 = 0x58,
+    dup = 0x59,
+    dup_x1 = 0x5a,
+    dup_x2 = 0x5b,
+    dup2 = 0x5c,
+    dup2_x1 = 0x5d,
+    dup2_x2 = 0x5e,
+    swap = 0x5f,
+    iadd = 0x60,
+    ladd = 0x61,
+    fadd = 0x62,
+    dadd = 0x63,
+    isub = 0x64,
+    lsub = 0x65,
+    fsub = 0x66,
+    dsub = 0x67,
+    imul = 0x68,
+    lmul = 0x69,
+    fmul = 0x6a,
+    dmul = 0x6b,
+    idiv = 0x6c,
+    ldiv = 0x6d,
+    fdiv = 0x6e,
+    ddiv = 0x6f,
+    irem = 0x70,
+    lrem = 0x71,
+    frem = 0x72,
+    drem = 0x73,
+    ineg = 0x74,
+    lneg = 0x75,
+    fneg = 0x76,
+    dneg = 0x77,
+    ishl = 0x78,
+    lshl = 0x79,
+    ishr = 0x7a,
+    lshr = 0x7b,
+    iushr = 0x7c,
+    lushr = 0x7d,
+    iand = 0x7e,
+    land = 0x7f,
+    ior = 0x80,
+    lor = 0x81,
+    ixor = 0x82,
+    lxor = 0x83,
+    iinc = 0x84,
+    i2l = 0x85,
+    i2f = 0x86,
+    i2d = 0x87,
+    l2i = 0x88,
+    l2f = 0x89,
+    l2d = 0x8a,
+    f2i = 0x8b,
+    f2l = 0x8c,
+    f2d = 0x8d,
+    d2i = 0x8e,
+    d2l = 0x8f,
+    d2f = 0x90,
+    i2b = 0x91,
+    i2c = 0x92,
+    i2s = 0x93,
+    lcmp = 0x94,
+    fcmpl = 0x95,
+    fcmpg = 0x96,
+    dcmpl = 0x97,
+    dcmpg = 0x98,
+    ifeq = 0x99,
+    ifne = 0x9a,
+    iflt = 0x9b,
+    ifge = 0x9c,
+    ifgt = 0x9d,
+    ifle = 0x9e,
+    if_icmpeq = 0x9f,
+    if_icmpne = 0xa0,
+    if_icmplt = 0xa1,
+    if_icmpge = 0xa2,
+    if_icmpgt = 0xa3,
+    if_icmple = 0xa4,
+    if_acmpeq = 0xa5,
+    if_acmpne = 0xa6,
+    goto = 0xa7,
+    jsr = 0xa8,
+    ret = 0xa9,
+    tableswitch = 0xaa,
+    lookupswitch = 0xab,
+    ireturn = 0xac,
+    lreturn = 0xad,
+    freturn = 0xae,
+    dreturn = 0xaf,
+    areturn = 0xb0,
+    return_ = 0xb1,
+    getstatic = 0xb2,
+    putstatic = 0xb3,
+    getfield = 0xb4,
+    putfield = 0xb5,
+    invokevirtual = 0xb6,
+    invokespecial = 0xb7,
+    invokestatic = 0xb8,
+    invokeinterface = 0xb9,
+    invokedynamic = 0xba,
+    new = 0xbb,
+    newarray = 0xbc,
+    anewarray = 0xbd,
+    arraylength = 0xbe,
+    athrow = 0xbf,
+    checkcast = 0xc0,
+    instanceof = 0xc1,
+    monitorenter = 0xc2,
+    monitorexit = 0xc3,
+    wide = 0xc4,
+    multianewarray = 0xc5,
+    ifnull = 0xc6,
+    ifnonnull = 0xc7,
+    goto_w = 0xc8,
+    jsr_w = 0xc9
+}
+
+static OPCODES: [Opcode; 202] = [
+    Opcode::nop, Opcode::aconst_null, Opcode::iconst_m1, Opcode::iconst_0, Opcode::iconst_1, Opcode::iconst_2,
+    Opcode::iconst_3, Opcode::iconst_4, Opcode::iconst_5, Opcode::lconst_0, Opcode::lconst_1, Opcode::fconst_0,
+    Opcode::fconst_1, Opcode::fconst_2, Opcode::dconst_0, Opcode::dconst_1, Opcode::bipush, Opcode::sipush,
+    Opcode::ldc, Opcode::ldc_w, Opcode::ldc2_w, Opcode::iload, Opcode::lload, Opcode::fload,
+    Opcode::dload, Opcode::aload, Opcode::iload_0, Opcode::iload_1, Opcode::iload_2, Opcode::iload_3,
+    Opcode::lload_0, Opcode::lload_1, Opcode::lload_2, Opcode::lload_3, Opcode::fload_0, Opcode::fload_1,
+    Opcode::fload_2, Opcode::fload_3, Opcode::dload_0, Opcode::dload_1, Opcode::dload_2, Opcode::dload_3,
+    Opcode::aload_0, Opcode::aload_1, Opcode::aload_2, Opcode::aload_3, Opcode::iaload, Opcode::laload,
+    Opcode::faload, Opcode::daload, Opcode::aaload, Opcode::baload, Opcode::caload, Opcode::saload,
+    Opcode::istore, Opcode::lstore, Opcode::fstore, Opcode::dstore, Opcode::astore, Opcode::istore_0,
+    Opcode::istore_1, Opcode::istore_2, Opcode::istore_3, Opcode::lstore_0, Opcode::lstore_1, Opcode::lstore_2,
+    Opcode::lstore_3, Opcode::fstore_0, Opcode::fstore_1, Opcode::fstore_2, Opcode::fstore_3, Opcode::dstore_0,
+    Opcode::dstore_1, Opcode::dstore_2, Opcode::dstore_3, Opcode::astore_0, Opcode::astore_1, Opcode::astore_2,
+    Opcode::astore_3, Opcode::iastore, Opcode::lastore, Opcode::fastore, Opcode::dastore, Opcode::aastore,
+    Opcode::bastore, Opcode::castore, Opcode::sastore, Opcode::pop, Opcode::pop2, Opcode::dup,
+    Opcode::dup_x1, Opcode::dup_x2, Opcode::dup2, Opcode::dup2_x1, Opcode::dup2_x2, Opcode::swap,
+    Opcode::iadd, Opcode::ladd, Opcode::fadd, Opcode::dadd, Opcode::isub, Opcode::lsub,
+    Opcode::fsub, Opcode::dsub, Opcode::imul, Opcode::lmul, Opcode::fmul, Opcode::dmul,
+    Opcode::idiv, Opcode::ldiv, Opcode::fdiv, Opcode::ddiv, Opcode::irem, Opcode::lrem,
+    Opcode::frem, Opcode::drem, Opcode::ineg, Opcode::lneg, Opcode::fneg, Opcode::dneg,
+    Opcode::ishl, Opcode::lshl, Opcode::ishr, Opcode::lshr, Opcode::iushr, Opcode::lushr,
+    Opcode::iand, Opcode::land, Opcode::ior, Opcode::lor, Opcode::ixor, Opcode::lxor,
+    Opcode::iinc, Opcode::i2l, Opcode::i2f, Opcode::i2d, Opcode::l2i, Opcode::l2f,
+    Opcode::l2d, Opcode::f2i, Opcode::f2l, Opcode::f2d, Opcode::d2i, Opcode::d2l,
+    Opcode::d2f, Opcode::i2b, Opcode::i2c, Opcode::i2s, Opcode::lcmp, Opcode::fcmpl,
+    Opcode::fcmpg, Opcode::dcmpl, Opcode::dcmpg, Opcode::ifeq, Opcode::ifne, Opcode::iflt,
+    Opcode::ifge, Opcode::ifgt, Opcode::ifle, Opcode::if_icmpeq, Opcode::if_icmpne, Opcode::if_icmplt,
+    Opcode::if_icmpge, Opcode::if_icmpgt, Opcode::if_icmple, Opcode::if_acmpeq, Opcode::if_acmpne, Opcode::goto,
+    Opcode::jsr, Opcode::ret, Opcode::tableswitch, Opcode::lookupswitch, Opcode::ireturn, Opcode::lreturn,
+    Opcode::freturn, Opcode::dreturn, Opcode::areturn, Opcode::return_, Opcode::getstatic, Opcode::putstatic,
+    Opcode::getfield, Opcode::putfield, Opcode::invokevirtual, Opcode::invokespecial, Opcode::invokestatic, Opcode::invokeinterface,
+    Opcode::invokedynamic, Opcode::new, Opcode::newarray, Opcode::anewarray, Opcode::arraylength, Opcode::athrow,
+    Opcode::checkcast, Opcode::instanceof, Opcode::monitorenter, Opcode::monitorexit, Opcode::wide, Opcode::multianewarray,
+    Opcode::ifnull, Opcode::ifnonnull, Opcode::goto_w, Opcode::jsr_w
+];
+
+static MNEMONICS: [&str; 202] = [
+    "nop", "aconst_null", "iconst_m1", "iconst_0", "iconst_1", "iconst_2", "iconst_3", "iconst_4",
+    "iconst_5", "lconst_0", "lconst_1", "fconst_0", "fconst_1", "fconst_2", "dconst_0", "dconst_1",
+    "bipush", "sipush", "ldc", "ldc_w", "ldc2_w", "iload", "lload", "fload",
+    "dload", "aload", "iload_0", "iload_1", "iload_2", "iload_3", "lload_0", "lload_1",
+    "lload_2", "lload_3", "fload_0", "fload_1", "fload_2", "fload_3", "dload_0", "dload_1",
+    "dload_2", "dload_3", "aload_0", "aload_1", "aload_2", "aload_3", "iaload", "laload",
+    "faload", "daload", "aaload", "baload", "caload", "saload", "istore", "lstore",
+    "fstore", "dstore", "astore", "istore_0", "istore_1", "istore_2", "istore_3", "lstore_0",
+    "lstore_1", "lstore_2", "lstore_3", "fstore_0", "fstore_1", "fstore_2", "fstore_3", "dstore_0",
+    "dstore_1", "dstore_2", "dstore_3", "astore_0", "astore_1", "astore_2", "astore_3", "iastore",
+    "lastore", "fastore", "dastore", "aastore", "bastore", "castore", "sastore", "pop",
+    "pop2", "dup", "dup_x1", "dup_x2", "dup2", "dup2_x1", "dup2_x2", "swap",
+    "iadd", "ladd", "fadd", "dadd", "isub", "lsub", "fsub", "dsub",
+    "imul", "lmul", "fmul", "dmul", "idiv", "ldiv", "fdiv", "ddiv",
+    "irem", "lrem", "frem", "drem", "ineg", "lneg", "fneg", "dneg",
+    "ishl", "lshl", "ishr", "lshr", "iushr", "lushr", "iand", "land",
+    "ior", "lor", "ixor", "lxor", "iinc", "i2l", "i2f", "i2d",
+    "l2i", "l2f", "l2d", "f2i", "f2l", "f2d", "d2i", "d2l",
+    "d2f", "i2b", "i2c", "i2s", "lcmp", "fcmpl", "fcmpg", "dcmpl",
+    "dcmpg", "ifeq", "ifne", "iflt", "ifge", "ifgt", "ifle", "if_icmpeq",
+    "if_icmpne", "if_icmplt", "if_icmpge", "if_icmpgt", "if_icmple", "if_acmpeq", "if_acmpne", "goto",
+    "jsr", "ret", "tableswitch", "lookupswitch", "ireturn", "lreturn", "freturn", "dreturn",
+    "areturn", "return", "getstatic", "putstatic", "getfield", "putfield", "invokevirtual", "invokespecial",
+    "invokestatic", "invokeinterface", "invokedynamic", "new", "newarray", "anewarray", "arraylength", "athrow",
+    "checkcast", "instanceof", "monitorenter", "monitorexit", "wide", "multianewarray", "ifnull", "ifnonnull",
+    "goto_w", "jsr_w"
+];
+
+// Encoded length by opcode. Zero marks the instructions of variable length,
+// tableswitch, lookupswitch and wide.
+static LENGTHS: [u8; 202] = [
+//  0  1  2  3  4  5  6  7  8  9  a  b  c  d  e  f
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,     // 0x00
+    2, 3, 2, 3, 3, 2, 2, 2, 2, 2, 1, 1, 1, 1, 1, 1,     // 0x10
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,     // 0x20
+    1, 1, 1, 1, 1, 1, 2, 2, 2, 2, 2, 1, 1, 1, 1, 1,     // 0x30
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,     // 0x40
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,     // 0x50
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,     // 0x60
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,     // 0x70
+    1, 1, 1, 1, 3, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,     // 0x80
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 3, 3, 3, 3, 3, 3, 3,     // 0x90
+    3, 3, 3, 3, 3, 3, 3, 3, 3, 2, 0, 0, 1, 1, 1, 1,     // 0xa0
+    1, 1, 3, 3, 3, 3, 3, 3, 3, 5, 5, 3, 2, 3, 1, 1,     // 0xb0
+    3, 3, 1, 1, 0, 4, 3, 3, 5, 5                        // 0xc0
+];
+
+impl Opcode {
+    /// The opcode encoded as this byte, or none for the unassigned ones and
+    /// those reserved for debuggers and the JVM itself.
+    pub fn from_byte(byte: u8) -> Option<Opcode> {
+        OPCODES.get(byte as usize).cloned()
+    }
+
+    pub fn mnemonic(&self) -> &'static str {
+        MNEMONICS[*self as usize]
+    }
+
+    /// The encoded length of instructions with this opcode, or none for
+    /// `tableswitch` and `lookupswitch`, whose length depends on their pc and
+    /// entries, and `wide`, whose length depends on the instruction it widens.
+    pub fn length(&self) -> Option<u32> {
+        match LENGTHS[*self as usize] {
+            0 => Option::None,
+            length => Option::Some(length as u32)
+        }
+    }
+}
+
+impl fmt::Display for Opcode {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str(self.mnemonic())
+    }
+}
+
+/// The number of operand stack slots an instruction pops and then pushes,
+/// with longs and doubles taking two. The `dup` and `swap` instructions count
+/// the values they copy or move as popped and pushed again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StackEffect {
+    pub pops: u16,
+    pub pushes: u16
+}
+
+impl Instruction {
+    pub fn opcode(&self) -> Opcode {
+        match *self {
+            aaload => Opcode::aaload,
+            aastore => Opcode::aastore,
+            aconst_null => Opcode::aconst_null,
+            aload(_) => Opcode::aload,
+            aload_0 => Opcode::aload_0,
+            aload_1 => Opcode::aload_1,
+            aload_2 => Opcode::aload_2,
+            aload_3 => Opcode::aload_3,
+            anewarray(_) => Opcode::anewarray,
+            areturn => Opcode::areturn,
+            arraylength => Opcode::arraylength,
+            astore(_) => Opcode::astore,
+            astore_0 => Opcode::astore_0,
+            astore_1 => Opcode::astore_1,
+            astore_2 => Opcode::astore_2,
+            astore_3 => Opcode::astore_3,
+            athrow => Opcode::athrow,
+            baload => Opcode::baload,
+            bastore => Opcode::bastore,
+            bipush(_) => Opcode::bipush,
+            caload => Opcode::caload,
+            castore => Opcode::castore,
+            checkcast(_) => Opcode::checkcast,
+            d2f => Opcode::d2f,
+            d2i => Opcode::d2i,
+            d2l => Opcode::d2l,
+            dadd => Opcode::dadd,
+            daload => Opcode::daload,
+            dastore => Opcode::dastore,
+            dcmpg => Opcode::dcmpg,
+            dcmpl => Opcode::dcmpl,
+            dconst_0 => Opcode::dconst_0,
+            dconst_1 => Opcode::dconst_1,
+            ddiv => Opcode::ddiv,
+            dload(_) => Opcode::dload,
+            dload_0 => Opcode::dload_0,
+            dload_1 => Opcode::dload_1,
+            dload_2 => Opcode::dload_2,
+            dload_3 => Opcode::dload_3,
+            dmul => Opcode::dmul,
+            dneg => Opcode::dneg,
+            drem => Opcode::drem,
+            dreturn => Opcode::dreturn,
+            dstore(_) => Opcode::dstore,
+            dstore_0 => Opcode::dstore_0,
+            dstore_1 => Opcode::dstore_1,
+            dstore_2 => Opcode::dstore_2,
+            dstore_3 => Opcode::dstore_3,
+            dsub => Opcode::dsub,
+            dup => Opcode::dup,
+            dup_x1 => Opcode::dup_x1,
+            dup_x2 => Opcode::dup_x2,
+            dup2 => Opcode::dup2,
+            dup2_x1 => Opcode::dup2_x1,
+            dup2_x2 => Opcode::dup2_x2,
+            f2d => Opcode::f2d,
+            f2i => Opcode::f2i,
+            f2l => Opcode::f2l,
+            fadd => Opcode::fadd,
+            faload => Opcode::faload,
+            fastore => Opcode::fastore,
+            fcmpg => Opcode::fcmpg,
+            fcmpl => Opcode::fcmpl,
+            fconst_0 => Opcode::fconst_0,
+            fconst_1 => Opcode::fconst_1,
+            fconst_2 => Opcode::fconst_2,
+            fdiv => Opcode::fdiv,
+            fload(_) => Opcode::fload,
+            fload_0 => Opcode::fload_0,
+            fload_1 => Opcode::fload_1,
+            fload_2 => Opcode::fload_2,
+            fload_3 => Opcode::fload_3,
+            fmul => Opcode::fmul,
+            fneg => Opcode::fneg,
+            frem => Opcode::frem,
+            freturn => Opcode::freturn,
+            fstore(_) => Opcode::fstore,
+            fstore_0 => Opcode::fstore_0,
+            fstore_1 => Opcode::fstore_1,
+            fstore_2 => Opcode::fstore_2,
+            fstore_3 => Opcode::fstore_3,
+            fsub => Opcode::fsub,
+            getfield(_) => Opcode::getfield,
+            getstatic(_) => Opcode::getstatic,
+            goto(_) => Opcode::goto,
+            goto_w(_) => Opcode::goto_w,
+            i2b => Opcode::i2b,
+            i2c => Opcode::i2c,
+            i2d => Opcode::i2d,
+            i2f => Opcode::i2f,
+            i2l => Opcode::i2l,
+            i2s => Opcode::i2s,
+            iadd => Opcode::iadd,
+            iaload => Opcode::iaload,
+            iand => Opcode::iand,
+            iastore => Opcode::iastore,
+            iconst_m1 => Opcode::iconst_m1,
+            iconst_0 => Opcode::iconst_0,
+            iconst_1 => Opcode::iconst_1,
+            iconst_2 => Opcode::iconst_2,
+            iconst_3 => Opcode::iconst_3,
+            iconst_4 => Opcode::iconst_4,
+            iconst_5 => Opcode::iconst_5,
+            idiv => Opcode::idiv,
+            if_acmpeq(_) => Opcode::if_acmpeq,
+            if_acmpne(_) => Opcode::if_acmpne,
+            if_icmpeq(_) => Opcode::if_icmpeq,
+            if_icmpne(_) => Opcode::if_icmpne,
+            if_icmplt(_) => Opcode::if_icmplt,
+            if_icmpge(_) => Opcode::if_icmpge,
+            if_icmpgt(_) => Opcode::if_icmpgt,
+            if_icmple(_) => Opcode::if_icmple,
+            ifeq(_) => Opcode::ifeq,
+            ifne(_) => Opcode::ifne,
+            iflt(_) => Opcode::iflt,
+            ifge(_) => Opcode::ifge,
+            ifgt(_) => Opcode::ifgt,
+            ifle(_) => Opcode::ifle,
+            ifnonnull(_) => Opcode::ifnonnull,
+            ifnull(_) => Opcode::ifnull,
+            iinc(_, _) => Opcode::iinc,
+            iload(_) => Opcode::iload,
+            iload_0 => Opcode::iload_0,
+            iload_1 => Opcode::iload_1,
+            iload_2 => Opcode::iload_2,
+            iload_3 => Opcode::iload_3,
+            imul => Opcode::imul,
+            ineg => Opcode::ineg,
+            instanceof(_) => Opcode::instanceof,
+            invokedynamic(_) => Opcode::invokedynamic,
+            invokeinterface(_, _) => Opcode::invokeinterface,
+            invokespecial(_) => Opcode::invokespecial,
+            invokestatic(_) => Opcode::invokestatic,
+            invokevirtual(_) => Opcode::invokevirtual,
+            ior => Opcode::ior,
+            irem => Opcode::irem,
+            ireturn => Opcode::ireturn,
+            ishl => Opcode::ishl,
+            ishr => Opcode::ishr,
+            istore(_) => Opcode::istore,
+            istore_0 => Opcode::istore_0,
+            istore_1 => Opcode::istore_1,
+            istore_2 => Opcode::istore_2,
+            istore_3 => Opcode::istore_3,
+            isub => Opcode::isub,
+            iushr => Opcode::iushr,
+            ixor => Opcode::ixor,
+            jsr(_) => Opcode::jsr,
+            jsr_w(_) => Opcode::jsr_w,
+            l2d => Opcode::l2d,
+            l2f => Opcode::l2f,
+            l2i => Opcode::l2i,
+            ladd => Opcode::ladd,
+            laload => Opcode::laload,
+            land => Opcode::land,
+            lastore => Opcode::lastore,
+            lcmp => Opcode::lcmp,
+            lconst_0 => Opcode::lconst_0,
+            lconst_1 => Opcode::lconst_1,
+            ldc(_) => Opcode::ldc,
+            ldc_w(_) => Opcode::ldc_w,
+            ldc2_w(_) => Opcode::ldc2_w,
+            ldiv => Opcode::ldiv,
+            lload(_) => Opcode::lload,
+            lload_0 => Opcode::lload_0,
+            lload_1 => Opcode::lload_1,
+            lload_2 => Opcode::lload_2,
+            lload_3 => Opcode::lload_3,
+            lmul => Opcode::lmul,
+            lneg => Opcode::lneg,
+            lookupswitch(_, _) => Opcode::lookupswitch,
+            lor => Opcode::lor,
+            lrem => Opcode::lrem,
+            lreturn => Opcode::lreturn,
+            lshl => Opcode::lshl,
+            lshr => Opcode::lshr,
+            lstore(_) => Opcode::lstore,
+            lstore_0 => Opcode::lstore_0,
+            lstore_1 => Opcode::lstore_1,
+            lstore_2 => Opcode::lstore_2,
+            lstore_3 => Opcode::lstore_3,
+            lsub => Opcode::lsub,
+            lushr => Opcode::lushr,
+            lxor => Opcode::lxor,
+            monitorenter => Opcode::monitorenter,
+            monitorexit => Opcode::monitorexit,
+            multianewarray(_, _) => Opcode::multianewarray,
+            new(_) => Opcode::new,
+            newarray(_) => Opcode::newarray,
+            nop => Opcode::nop,
+            pop => Opcode::pop,
+            pop2 => Opcode::pop2,
+            putfield(_) => Opcode::putfield,
+            putstatic(_) => Opcode::putstatic,
+            ret(_) => Opcode::ret,
+            return_ => Opcode::return_,
+            saload => Opcode::saload,
+            sastore => Opcode::sastore,
+            sipush(_) => Opcode::sipush,
+            swap => Opcode::swap,
+            tableswitch(_, _, _) => Opcode::tableswitch,
+            iload_w(_) | fload_w(_) | aload_w(_) | lload_w(_) | dload_w(_) | istore_w(_) | fstore_w(_) | astore_w(_) | lstore_w(_) | dstore_w(_) | ret_w(_) | iinc_w(_, _) => Opcode::wide
+        }
+    }
+
+    /// The mnemonic of the opcode, or of the opcode with `_w` appended for the
+    /// wide forms, as javap prints them.
+    pub fn mnemonic(&self) -> &'static str {
+        match *self {
+            iload_w(_) => "iload_w",
+            fload_w(_) => "fload_w",
+            aload_w(_) => "aload_w",
+            lload_w(_) => "lload_w",
+            dload_w(_) => "dload_w",
+            istore_w(_) => "istore_w",
+            fstore_w(_) => "fstore_w",
+            astore_w(_) => "astore_w",
+            lstore_w(_) => "lstore_w",
+            dstore_w(_) => "dstore_w",
+            ret_w(_) => "ret_w",
+            iinc_w(_, _) => "iinc_w",
+            _ => self.opcode().mnemonic()
+        }
+    }
+
+    /// The number of bytes the instruction is encoded in at a pc, which
+    /// decides the padding of a switch.
+    pub fn length(&self, pc: u32) -> u32 {
+        let padding = 3 - pc % 4;
+        match *self {
+            tableswitch(_, _, ref offsets) => 13 + padding + 4 * offsets.len() as u32,
+            lookupswitch(_, ref pairs) => 9 + padding + 8 * pairs.len() as u32,
+            iinc_w(_, _) => 6,
+            iload_w(_) | fload_w(_) | aload_w(_) | lload_w(_) | dload_w(_) | istore_w(_) | fstore_w(_)
+                    | astore_w(_) | lstore_w(_) | dstore_w(_) | ret_w(_) => 4,
+            _ => LENGTHS[self.opcode() as usize] as u32
+        }
+    }
+
+    /// The stack slots the instruction pops and pushes. Those of field and
+    /// method instructions follow from the descriptor of their constant.
+    pub fn stack_effect(&self, pool: &ConstantPool) -> ParseResult<StackEffect> {
+        let effect = |pops: u16, pushes: u16| Result::Ok(StackEffect { pops: pops, pushes: pushes });
+        match *self {
+            nop | iinc(_, _) | iinc_w(_, _) | goto(_) | goto_w(_) | ret(_) | ret_w(_) | return_ => effect(0, 0),
+            aconst_null | iconst_m1 | iconst_0 | iconst_1 | iconst_2 | iconst_3 | iconst_4 | iconst_5
+                    | fconst_0 | fconst_1 | fconst_2 | bipush(_) | sipush(_) | ldc(_) | ldc_w(_)
+                    | iload(_) | iload_w(_) | iload_0 | iload_1 | iload_2 | iload_3
+                    | fload(_) | fload_w(_) | fload_0 | fload_1 | fload_2 | fload_3
+                    | aload(_) | aload_w(_) | aload_0 | aload_1 | aload_2 | aload_3
+                    | jsr(_) | jsr_w(_) | new(_) => effect(0, 1),
+            lconst_0 | lconst_1 | dconst_0 | dconst_1 | ldc2_w(_)
+                    | lload(_) | lload_w(_) | lload_0 | lload_1 | lload_2 | lload_3
+                    | dload(_) | dload_w(_) | dload_0 | dload_1 | dload_2 | dload_3 => effect(0, 2),
+            istore(_) | istore_w(_) | istore_0 | istore_1 | istore_2 | istore_3
+                    | fstore(_) | fstore_w(_) | fstore_0 | fstore_1 | fstore_2 | fstore_3
+                    | astore(_) | astore_w(_) | astore_0 | astore_1 | astore_2 | astore_3
+                    | pop | ifeq(_) | ifne(_) | iflt(_) | ifge(_) | ifgt(_) | ifle(_) | ifnull(_) | ifnonnull(_)
+                    | tableswitch(_, _, _) | lookupswitch(_, _) | ireturn | freturn | areturn | athrow
+                    | monitorenter | monitorexit => effect(1, 0),
+            lstore(_) | lstore_w(_) | lstore_0 | lstore_1 | lstore_2 | lstore_3
+                    | dstore(_) | dstore_w(_) | dstore_0 | dstore_1 | dstore_2 | dstore_3
+                    | pop2 | if_icmpeq(_) | if_icmpne(_) | if_icmplt(_) | if_icmpge(_) | if_icmpgt(_) | if_icmple(_)
+                    | if_acmpeq(_) | if_acmpne(_) | lreturn | dreturn => effect(2, 0),
+            iastore | fastore | aastore | bastore | castore | sastore => effect(3, 0),
+            lastore | dastore => effect(4, 0),
+            iaload | faload | aaload | baload | caload | saload => effect(2, 1),
+            laload | daload => effect(2, 2),
+            dup => effect(1, 2),
+            dup_x1 => effect(2, 3),
+            dup_x2 => effect(3, 4),
+            dup2 => effect(2, 4),
+            dup2_x1 => effect(3, 5),
+            dup2_x2 => effect(4, 6),
+            swap => effect(2, 2),
+            iadd | isub | imul | idiv | irem | ishl | ishr | iushr | iand | ior | ixor
+                    | fadd | fsub | fmul | fdiv | frem | fcmpl | fcmpg => effect(2, 1),
+            ladd | lsub | lmul | ldiv | lrem | land | lor | lxor
+                    | dadd | dsub | dmul | ddiv | drem => effect(4, 2),
+            lshl | lshr | lushr => effect(3, 2),
+            lcmp | dcmpl | dcmpg => effect(4, 1),
+            ineg | fneg | i2f | i2b | i2c | i2s | f2i | newarray(_) | anewarray(_) | arraylength
+                    | checkcast(_) | instanceof(_) => effect(1, 1),
+            lneg | dneg | l2d | d2l => effect(2, 2),
+            i2l | i2d | f2l | f2d => effect(1, 2),
+            l2i | l2f | d2i | d2f => effect(2, 1),
+            multianewarray(_, dimensions) => effect(dimensions as u16, 1),
+            getstatic(index) | putstatic(index) | getfield(index) | putfield(index) => {
+                let size = try!(FieldType::parse(&try!(pool.member_ref(index)).descriptor)).size();
+                match *self {
+                    getstatic(_) => effect(0, size),
+                    putstatic(_) => effect(size, 0),
+                    getfield(_) => effect(1, size),
+                    _ => effect(size + 1, 0)
+                }
+            },
+            invokevirtual(index) | invokespecial(index) | invokestatic(index) | invokeinterface(index, _) => {
+                let descriptor = try!(MethodDescriptor::parse(&try!(pool.member_ref(index)).descriptor));
+                let receiver = match *self {
+                    invokestatic(_) => 0,
+                    _ => 1
+                };
+                effect(descriptor.parameter_slots() + receiver, returned_slots(&descriptor))
+            },
+            invokedynamic(index) => {
+                let descriptor = match *try!(pool.get(index)) {
+                    ConstantPoolInfo::InvokeDynamic(_, name_and_type) => try!(pool.name_and_type(name_and_type)).1,
+                    ref other => {
+                        let message = format!("expected invoke dynamic at index {} but got {:?}", index, other);
+                        return Result::Err(ParseError::Format(message));
+                    }
+                };
+                let descriptor = try!(MethodDescriptor::parse(&descriptor));
+                effect(descriptor.parameter_slots(), returned_slots(&descriptor))
+            }
+        }
+    }
+
+    /// Whether the instruction may go to a pc given by its operands: the
+    /// conditional branches, `goto`, `jsr` and the switches.
+    pub fn is_branch(&self) -> bool {
+        match *self {
+            if_acmpeq(_) | if_acmpne(_) | if_icmpeq(_) | if_icmpne(_) | if_icmplt(_) | if_icmpge(_)
+                    | if_icmpgt(_) | if_icmple(_) | ifeq(_) | ifne(_) | iflt(_) | ifge(_) | ifgt(_) | ifle(_)
+                    | ifnonnull(_) | ifnull(_) | goto(_) | goto_w(_) | jsr(_) | jsr_w(_)
+                    | tableswitch(_, _, _) | lookupswitch(_, _) => true,
+            _ => false
+        }
+    }
+
+    /// Whether execution may go on with the next instruction. It does not
+    /// after `goto`, the switches, the returns, `athrow` and `ret`, nor after
+    /// `jsr`, whose next instruction is reached by returning from the
+    /// subroutine.
+    pub fn falls_through(&self) -> bool {
+        match *self {
+            goto(_) | goto_w(_) | jsr(_) | jsr_w(_) | tableswitch(_, _, _) | lookupswitch(_, _)
+                    | ireturn | lreturn | freturn | dreturn | areturn | return_ | athrow | ret(_) | ret_w(_) => false,
+            _ => true
+        }
+    }
+
+    /// Whether the instruction is the last of its basic block, because it
+    /// branches or does not fall through.
+    pub fn ends_block(&self) -> bool {
+        self.is_branch() || !self.falls_through()
+    }
+
+    /// The pcs a branch at a pc goes to, other than by falling through. The
+    /// targets of a switch are those of its entries in order followed by the
+    /// default, so a pc may be listed more than once. Fails if a target lies
+    /// before the start of the code.
+    pub fn branch_targets(&self, pc: u32) -> ParseResult<Vec<u32>> {
+        let offsets: Vec<i32> = match *self {
+            if_acmpeq(offset) | if_acmpne(offset) | if_icmpeq(offset) | if_icmpne(offset) | if_icmplt(offset)
+                    | if_icmpge(offset) | if_icmpgt(offset) | if_icmple(offset) | ifeq(offset) | ifne(offset)
+                    | iflt(offset) | ifge(offset) | ifgt(offset) | ifle(offset) | ifnonnull(offset)
+                    | ifnull(offset) | goto(offset) | jsr(offset) => vec![offset as i32],
+            goto_w(offset) | jsr_w(offset) => vec![offset],
+            tableswitch(default, _, ref offsets) => offsets.iter().cloned().chain(::std::iter::once(default)).collect(),
+            lookupswitch(default, ref pairs) => {
+                pairs.iter().map(|&(_, offset)| offset).chain(::std::iter::once(default)).collect()
+            },
+            _ => Vec::new()
+        };
+        let mut targets = Vec::with_capacity(offsets.len());
+        for offset in offsets {
+            let target = pc as i64 + offset as i64;
+            if target < 0 || target > ::std::u32::MAX as i64 {
+                let message = format!("branch at {} targets {} which is not the start of an instruction", pc, target);
+                return Result::Err(ParseError::Decode(message));
+            }
+            targets.push(target as u32);
+        }
+        Result::Ok(targets)
+    }
+}
+
+fn returned_slots(descriptor: &MethodDescriptor) -> u16 {
+    descriptor.return_type.as_ref().map_or(0, FieldType::size)
+}
@@ -0,0 +1,178 @@
+use std::slice;
+
+use ::java_string::JavaString;
+use ::model::ConstantPoolInfo;
+use ::result::{ParseError, ParseResult};
+
+/// The constant pool of a class, indexed from 1 as in the class file. The
+/// second slot of a long or double holds `ConstantPoolInfo::Invalid`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ConstantPool {
+    entries: Vec<ConstantPoolInfo>
+}
+
+/// A resolved `Fieldref`, `Methodref` or `InterfaceMethodref`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemberRef {
+    pub class_name: String,
+    pub name: String,
+    pub descriptor: String
+}
+
+impl ConstantPool {
+
+    pub fn new(entries: Vec<ConstantPoolInfo>) -> ConstantPool {
+        ConstantPool { entries: entries }
+    }
+
+    /// The number of slots, one less than `constant_pool_count`.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn entries(&self) -> &[ConstantPoolInfo] {
+        &self.entries
+    }
+
+    pub fn into_entries(self) -> Vec<ConstantPoolInfo> {
+        self.entries
+    }
+
+    pub fn iter(&self) -> slice::Iter<ConstantPoolInfo> {
+        self.entries.iter()
+    }
+
+    /// Appends an entry, followed by an `Invalid` slot for longs and doubles,
+    /// and returns its index. A class file holds at most 65534 slots, so an
+    /// entry that does not fit is an error.
+    pub fn push(&mut self, info: ConstantPoolInfo) -> ParseResult<u16> {
+        let double_length = info.is_double_length();
+        if self.entries.len() + if double_length { 2 } else { 1 } > 65534 {
+            return Result::Err(ParseError::Encode("too many constants".to_string()));
+        }
+        self.entries.push(info);
+        let index = self.entries.len() as u16;
+        if double_length {
+            self.entries.push(ConstantPoolInfo::Invalid);
+        }
+        Result::Ok(index)
+    }
+
+    pub fn get(&self, index: u16) -> ParseResult<&ConstantPoolInfo> {
+        if index == 0 || index as usize > self.entries.len() {
+            let message = format!("constant pool index {} out of range", index);
+            return Result::Err(ParseError::Format(message));
+        }
+        match self.entries[index as usize - 1] {
+            ConstantPoolInfo::Invalid => {
+                let message = format!("constant pool index {} is the second slot of a long or double", index);
+                Result::Err(ParseError::Format(message))
+            },
+            ref info => Result::Ok(info)
+        }
+    }
+
+    pub fn utf8(&self, index: u16) -> ParseResult<&JavaString> {
+        match try!(self.get(index)) {
+            &ConstantPoolInfo::Utf8(ref string) => Result::Ok(string),
+            other => Result::Err(unexpected(index, "utf8", other))
+        }
+    }
+
+    /// Like `utf8`, but fails if the string is not valid Unicode.
+    pub fn utf8_string(&self, index: u16) -> ParseResult<String> {
+        self.utf8(index).and_then(|string| string.try_to_str().map(|string| string.into_owned()))
+    }
+
+    /// Like `utf8_string`, but index 0 resolves to `None`.
+    pub fn optional_utf8(&self, index: u16) -> ParseResult<Option<String>> {
+        if index == 0 {
+            Result::Ok(Option::None)
+        } else {
+            self.utf8_string(index).map(Option::Some)
+        }
+    }
+
+    pub fn class_name(&self, index: u16) -> ParseResult<String> {
+        match try!(self.get(index)) {
+            &ConstantPoolInfo::Class(name_index) => self.utf8_string(name_index),
+            other => Result::Err(unexpected(index, "class", other))
+        }
+    }
+
+    pub fn module_name(&self, index: u16) -> ParseResult<String> {
+        match try!(self.get(index)) {
+            &ConstantPoolInfo::Module(name_index) => self.utf8_string(name_index),
+            other => Result::Err(unexpected(index, "module", other))
+        }
+    }
+
+    pub fn package_name(&self, index: u16) -> ParseResult<String> {
+        match try!(self.get(index)) {
+            &ConstantPoolInfo::Package(name_index) => self.utf8_string(name_index),
+            other => Result::Err(unexpected(index, "package", other))
+        }
+    }
+
+    /// Resolves a `NameAndType` to its name and descriptor.
+    pub fn name_and_type(&self, index: u16) -> ParseResult<(String, String)> {
+        match try!(self.get(index)) {
+            &ConstantPoolInfo::NameAndType(name_index, descriptor_index) => {
+                Result::Ok((try!(self.utf8_string(name_index)), try!(self.utf8_string(descriptor_index))))
+            },
+            other => Result::Err(unexpected(index, "name and type", other))
+        }
+    }
+
+    pub fn member_ref(&self, index: u16) -> ParseResult<MemberRef> {
+        match try!(self.get(index)) {
+            &ConstantPoolInfo::Fieldref(class_index, name_and_type_index)
+                    | &ConstantPoolInfo::Methodref(class_index, name_and_type_index)
+                    | &ConstantPoolInfo::InterfaceMethodref(class_index, name_and_type_index) => {
+                let (name, descriptor) = try!(self.name_and_type(name_and_type_index));
+                Result::Ok(MemberRef {
+                    class_name: try!(self.class_name(class_index)),
+                    name: name,
+                    descriptor: descriptor
+                })
+            },
+            other => Result::Err(unexpected(index, "member reference", other))
+        }
+    }
+
+    /// The index of the first utf8 entry equal to `string`.
+    pub fn find_utf8(&self, string: &str) -> Option<u16> {
+        self.entries.iter().position(|info| match *info {
+            ConstantPoolInfo::Utf8(ref utf8) => utf8 == string,
+            _ => false
+        }).map(|i| i as u16 + 1)
+    }
+
+    /// The index of the first class entry whose name is at `name_index`.
+    pub fn find_class(&self, name_index: u16) -> Option<u16> {
+        self.entries.iter().position(|info| *info == ConstantPoolInfo::Class(name_index)).map(|i| i as u16 + 1)
+    }
+}
+
+impl From<Vec<ConstantPoolInfo>> for ConstantPool {
+    fn from(entries: Vec<ConstantPoolInfo>) -> ConstantPool {
+        ConstantPool::new(entries)
+    }
+}
+
+impl<'a> IntoIterator for &'a ConstantPool {
+    type Item = &'a ConstantPoolInfo;
+    type IntoIter = slice::Iter<'a, ConstantPoolInfo>;
+
+    fn into_iter(self) -> slice::Iter<'a, ConstantPoolInfo> {
+        self.entries.iter()
+    }
+}
+
+fn unexpected(index: u16, expected: &str, actual: &ConstantPoolInfo) -> ParseError {
+    ParseError::Format(format!("expected {} at index {} but got {:?}", expected, index, actual))
+}
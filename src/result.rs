@@ -10,6 +10,7 @@ pub enum ParseError {
     Io(io::Error),
     Format(String),
     Decode(String),
+    Encode(String),
     Generic
 }
 
@@ -25,7 +26,8 @@ impl error::Error for ParseError {
             ParseError::Io(_) => "I/O error",
             ParseError::Format(ref msg) => msg,
             ParseError::Generic => "unknown error",
-            ParseError::Decode(ref msg) => msg
+            ParseError::Decode(ref msg) => msg,
+            ParseError::Encode(ref msg) => msg
         }
     }
     fn cause(&self) -> Option<&error::Error> {
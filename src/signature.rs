@@ -0,0 +1,553 @@
+use std::fmt;
+
+use ::descriptor::BaseType;
+use ::result::{ParseError, ParseResult};
+
+/// A type in a generic signature: a base type or a reference type.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum JavaTypeSignature {
+    Base(BaseType),
+    Reference(ReferenceTypeSignature)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ReferenceTypeSignature {
+    Class(ClassTypeSignature),
+    TypeVariable(String),
+    Array(Box<JavaTypeSignature>)
+}
+
+/// A class type such as `Ljava/util/Map<TK;TV;>.Entry<TK;TV;>;`. The package
+/// is in internal form and empty for the unnamed package.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ClassTypeSignature {
+    pub package: String,
+    pub class: SimpleClassTypeSignature,
+    pub inner: Vec<SimpleClassTypeSignature>
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SimpleClassTypeSignature {
+    pub name: String,
+    pub type_arguments: Vec<TypeArgument>
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TypeArgument {
+    Any,                                // *
+    Exact(ReferenceTypeSignature),
+    Extends(ReferenceTypeSignature),    // +
+    Super(ReferenceTypeSignature)       // -
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TypeParameter {
+    pub name: String,
+    pub class_bound: Option<ReferenceTypeSignature>,
+    pub interface_bounds: Vec<ReferenceTypeSignature>
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ClassSignature {
+    pub type_parameters: Vec<TypeParameter>,
+    pub superclass: ClassTypeSignature,
+    pub interfaces: Vec<ClassTypeSignature>
+}
+
+/// A method signature. A `return_type` of `None` stands for `void`; thrown
+/// types are class types or type variables.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MethodSignature {
+    pub type_parameters: Vec<TypeParameter>,
+    pub parameters: Vec<JavaTypeSignature>,
+    pub return_type: Option<JavaTypeSignature>,
+    pub throws: Vec<ReferenceTypeSignature>
+}
+
+impl ClassSignature {
+    pub fn parse(signature: &str) -> ParseResult<ClassSignature> {
+        let mut parser = Parser::new(signature);
+        let type_parameters = try!(parser.type_parameters());
+        let superclass = try!(parser.class_type());
+        let mut interfaces = Vec::new();
+        while !parser.at_end() {
+            interfaces.push(try!(parser.class_type()));
+        }
+        Result::Ok(ClassSignature {
+            type_parameters: type_parameters,
+            superclass: superclass,
+            interfaces: interfaces
+        })
+    }
+
+    /// Renders the signature as in a Java class declaration, e.g.
+    /// `<T> extends java.lang.Object implements java.util.List<T>`.
+    pub fn to_java_source(&self) -> String {
+        let mut source = type_parameters_to_java_source(&self.type_parameters);
+        if !source.is_empty() {
+            source.push(' ');
+        }
+        source.push_str("extends ");
+        source.push_str(&self.superclass.to_java_source());
+        if !self.interfaces.is_empty() {
+            let interfaces: Vec<String> = self.interfaces.iter().map(ClassTypeSignature::to_java_source).collect();
+            source.push_str(" implements ");
+            source.push_str(&interfaces.join(", "));
+        }
+        source
+    }
+}
+
+impl MethodSignature {
+    pub fn parse(signature: &str) -> ParseResult<MethodSignature> {
+        let mut parser = Parser::new(signature);
+        let type_parameters = try!(parser.type_parameters());
+        try!(parser.expect('('));
+        let mut parameters = Vec::new();
+        while try!(parser.peek()) != ')' {
+            parameters.push(try!(parser.java_type()));
+        }
+        try!(parser.expect(')'));
+        let return_type = if try!(parser.peek()) == 'V' {
+            parser.position += 1;
+            Option::None
+        } else {
+            Option::Some(try!(parser.java_type()))
+        };
+        let mut throws = Vec::new();
+        while !parser.at_end() {
+            try!(parser.expect('^'));
+            throws.push(match try!(parser.peek()) {
+                'L' => ReferenceTypeSignature::Class(try!(parser.class_type())),
+                'T' => try!(parser.type_variable()),
+                _ => { return parser.error("a class type or type variable"); }
+            });
+        }
+        Result::Ok(MethodSignature {
+            type_parameters: type_parameters,
+            parameters: parameters,
+            return_type: return_type,
+            throws: throws
+        })
+    }
+
+    /// Renders the signature as a Java method declaration, e.g.
+    /// `<T> void sort(java.util.List<T>)`.
+    pub fn to_java_source(&self, name: &str) -> String {
+        let mut source = type_parameters_to_java_source(&self.type_parameters);
+        if !source.is_empty() {
+            source.push(' ');
+        }
+        match self.return_type {
+            Option::Some(ref return_type) => source.push_str(&return_type.to_java_source()),
+            Option::None => source.push_str("void")
+        }
+        let parameters: Vec<String> = self.parameters.iter().map(JavaTypeSignature::to_java_source).collect();
+        source.push_str(&format!(" {}({})", name, parameters.join(", ")));
+        if !self.throws.is_empty() {
+            let throws: Vec<String> = self.throws.iter().map(ReferenceTypeSignature::to_java_source).collect();
+            source.push_str(" throws ");
+            source.push_str(&throws.join(", "));
+        }
+        source
+    }
+}
+
+impl ReferenceTypeSignature {
+    /// Parses a field signature.
+    pub fn parse(signature: &str) -> ParseResult<ReferenceTypeSignature> {
+        let mut parser = Parser::new(signature);
+        let reference_type = try!(parser.reference_type());
+        if !parser.at_end() {
+            return parser.error("end of input");
+        }
+        Result::Ok(reference_type)
+    }
+
+    pub fn to_java_source(&self) -> String {
+        match *self {
+            ReferenceTypeSignature::Class(ref class_type) => class_type.to_java_source(),
+            ReferenceTypeSignature::TypeVariable(ref name) => name.clone(),
+            ReferenceTypeSignature::Array(ref component) => format!("{}[]", component.to_java_source())
+        }
+    }
+}
+
+impl JavaTypeSignature {
+    pub fn to_java_source(&self) -> String {
+        match *self {
+            JavaTypeSignature::Base(base_type) => base_type.java_name().to_string(),
+            JavaTypeSignature::Reference(ref reference_type) => reference_type.to_java_source()
+        }
+    }
+}
+
+impl ClassTypeSignature {
+    /// Renders the type with a qualified name, e.g. `java.util.List<? extends T>`.
+    pub fn to_java_source(&self) -> String {
+        let mut source = self.package.replace('/', ".");
+        if !source.is_empty() {
+            source.push('.');
+        }
+        source.push_str(&self.class.to_java_source());
+        for inner in &self.inner {
+            source.push('.');
+            source.push_str(&inner.to_java_source());
+        }
+        source
+    }
+}
+
+impl SimpleClassTypeSignature {
+    pub fn to_java_source(&self) -> String {
+        if self.type_arguments.is_empty() {
+            return self.name.clone();
+        }
+        let type_arguments: Vec<String> = self.type_arguments.iter().map(TypeArgument::to_java_source).collect();
+        format!("{}<{}>", self.name, type_arguments.join(", "))
+    }
+}
+
+impl TypeArgument {
+    pub fn to_java_source(&self) -> String {
+        match *self {
+            TypeArgument::Any => "?".to_string(),
+            TypeArgument::Exact(ref bound) => bound.to_java_source(),
+            TypeArgument::Extends(ref bound) => format!("? extends {}", bound.to_java_source()),
+            TypeArgument::Super(ref bound) => format!("? super {}", bound.to_java_source())
+        }
+    }
+}
+
+impl TypeParameter {
+    pub fn to_java_source(&self) -> String {
+        let bounds: Vec<String> = self.class_bound.iter().chain(self.interface_bounds.iter())
+            .map(ReferenceTypeSignature::to_java_source)
+            .collect();
+        if bounds.is_empty() {
+            self.name.clone()
+        } else {
+            format!("{} extends {}", self.name, bounds.join(" & "))
+        }
+    }
+}
+
+fn type_parameters_to_java_source(type_parameters: &[TypeParameter]) -> String {
+    if type_parameters.is_empty() {
+        return String::new();
+    }
+    let type_parameters: Vec<String> = type_parameters.iter().map(TypeParameter::to_java_source).collect();
+    format!("<{}>", type_parameters.join(", "))
+}
+
+impl fmt::Display for JavaTypeSignature {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            JavaTypeSignature::Base(base_type) => write!(fmt, "{}", base_type.descriptor_char()),
+            JavaTypeSignature::Reference(ref reference_type) => write!(fmt, "{}", reference_type)
+        }
+    }
+}
+
+impl fmt::Display for ReferenceTypeSignature {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ReferenceTypeSignature::Class(ref class_type) => write!(fmt, "{}", class_type),
+            ReferenceTypeSignature::TypeVariable(ref name) => write!(fmt, "T{};", name),
+            ReferenceTypeSignature::Array(ref component) => write!(fmt, "[{}", component)
+        }
+    }
+}
+
+impl fmt::Display for ClassTypeSignature {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(fmt, "L"));
+        if !self.package.is_empty() {
+            try!(write!(fmt, "{}/", self.package));
+        }
+        try!(write!(fmt, "{}", self.class));
+        for inner in &self.inner {
+            try!(write!(fmt, ".{}", inner));
+        }
+        write!(fmt, ";")
+    }
+}
+
+impl fmt::Display for SimpleClassTypeSignature {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(fmt, "{}", self.name));
+        if !self.type_arguments.is_empty() {
+            try!(write!(fmt, "<"));
+            for type_argument in &self.type_arguments {
+                try!(write!(fmt, "{}", type_argument));
+            }
+            try!(write!(fmt, ">"));
+        }
+        Result::Ok(())
+    }
+}
+
+impl fmt::Display for TypeArgument {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TypeArgument::Any => write!(fmt, "*"),
+            TypeArgument::Exact(ref bound) => write!(fmt, "{}", bound),
+            TypeArgument::Extends(ref bound) => write!(fmt, "+{}", bound),
+            TypeArgument::Super(ref bound) => write!(fmt, "-{}", bound)
+        }
+    }
+}
+
+impl fmt::Display for TypeParameter {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(fmt, "{}:", self.name));
+        if let Option::Some(ref class_bound) = self.class_bound {
+            try!(write!(fmt, "{}", class_bound));
+        }
+        for interface_bound in &self.interface_bounds {
+            try!(write!(fmt, ":{}", interface_bound));
+        }
+        Result::Ok(())
+    }
+}
+
+fn fmt_type_parameters(fmt: &mut fmt::Formatter, type_parameters: &[TypeParameter]) -> fmt::Result {
+    if !type_parameters.is_empty() {
+        try!(write!(fmt, "<"));
+        for type_parameter in type_parameters {
+            try!(write!(fmt, "{}", type_parameter));
+        }
+        try!(write!(fmt, ">"));
+    }
+    Result::Ok(())
+}
+
+impl fmt::Display for ClassSignature {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        try!(fmt_type_parameters(fmt, &self.type_parameters));
+        try!(write!(fmt, "{}", self.superclass));
+        for interface in &self.interfaces {
+            try!(write!(fmt, "{}", interface));
+        }
+        Result::Ok(())
+    }
+}
+
+impl fmt::Display for MethodSignature {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        try!(fmt_type_parameters(fmt, &self.type_parameters));
+        try!(write!(fmt, "("));
+        for parameter in &self.parameters {
+            try!(write!(fmt, "{}", parameter));
+        }
+        try!(write!(fmt, ")"));
+        match self.return_type {
+            Option::Some(ref return_type) => try!(write!(fmt, "{}", return_type)),
+            Option::None => try!(write!(fmt, "V"))
+        }
+        for throws in &self.throws {
+            try!(write!(fmt, "^{}", throws));
+        }
+        Result::Ok(())
+    }
+}
+
+// Type arguments nest by recursion, so their depth is bounded to keep
+// malicious signatures from overflowing the stack.
+const MAX_TYPE_ARGUMENT_DEPTH: usize = 64;
+
+struct Parser<'a> {
+    input: &'a str,
+    position: usize,
+    // The number of enclosing type argument lists.
+    depth: usize
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Parser<'a> {
+        Parser { input: input, position: 0, depth: 0 }
+    }
+
+    fn error<T>(&self, expected: &str) -> ParseResult<T> {
+        let found = match self.input[self.position..].chars().next() {
+            Option::Some(c) => format!("{:?}", c),
+            Option::None => "end of input".to_string()
+        };
+        let message = format!("invalid signature {:?} at position {}: expected {} but found {}",
+                self.input, self.position, expected, found);
+        Result::Err(ParseError::Format(message))
+    }
+
+    fn at_end(&self) -> bool {
+        self.position == self.input.len()
+    }
+
+    fn peek(&self) -> ParseResult<char> {
+        match self.input[self.position..].chars().next() {
+            Option::Some(c) => Result::Ok(c),
+            Option::None => self.error("a type")
+        }
+    }
+
+    fn expect(&mut self, c: char) -> ParseResult<()> {
+        if self.input[self.position..].starts_with(c) {
+            self.position += c.len_utf8();
+            Result::Ok(())
+        } else {
+            self.error(&format!("{:?}", c))
+        }
+    }
+
+    fn identifier(&mut self) -> ParseResult<&'a str> {
+        let start = self.position;
+        for c in self.input[start..].chars() {
+            match c {
+                '.' | ';' | '[' | '/' | '<' | '>' | ':' => break,
+                _ => self.position += c.len_utf8()
+            }
+        }
+        if self.position == start {
+            return self.error("an identifier");
+        }
+        Result::Ok(&self.input[start..self.position])
+    }
+
+    fn type_parameters(&mut self) -> ParseResult<Vec<TypeParameter>> {
+        let mut type_parameters = Vec::new();
+        if try!(self.peek()) != '<' {
+            return Result::Ok(type_parameters);
+        }
+        self.position += 1;
+        loop {
+            let name = try!(self.identifier()).to_string();
+            try!(self.expect(':'));
+            let class_bound = match try!(self.peek()) {
+                'L' | 'T' | '[' => Option::Some(try!(self.reference_type())),
+                _ => Option::None
+            };
+            let mut interface_bounds = Vec::new();
+            while try!(self.peek()) == ':' {
+                self.position += 1;
+                interface_bounds.push(try!(self.reference_type()));
+            }
+            type_parameters.push(TypeParameter {
+                name: name,
+                class_bound: class_bound,
+                interface_bounds: interface_bounds
+            });
+            if try!(self.peek()) == '>' {
+                self.position += 1;
+                return Result::Ok(type_parameters);
+            }
+        }
+    }
+
+    fn java_type(&mut self) -> ParseResult<JavaTypeSignature> {
+        let c = try!(self.peek());
+        match BaseType::from_char(c) {
+            Option::Some(base_type) => {
+                self.position += 1;
+                Result::Ok(JavaTypeSignature::Base(base_type))
+            },
+            Option::None => self.reference_type().map(JavaTypeSignature::Reference)
+        }
+    }
+
+    fn reference_type(&mut self) -> ParseResult<ReferenceTypeSignature> {
+        match try!(self.peek()) {
+            'L' => self.class_type().map(ReferenceTypeSignature::Class),
+            'T' => self.type_variable(),
+            '[' => {
+                let start = self.position;
+                let mut dimensions = 0;
+                while self.input[self.position..].starts_with('[') {
+                    if dimensions == 255 {
+                        self.position = start;
+                        return self.error("at most 255 array dimensions");
+                    }
+                    dimensions += 1;
+                    self.position += 1;
+                }
+                let mut array = ReferenceTypeSignature::Array(Box::new(try!(self.java_type())));
+                for _ in 1..dimensions {
+                    array = ReferenceTypeSignature::Array(Box::new(JavaTypeSignature::Reference(array)));
+                }
+                Result::Ok(array)
+            },
+            _ => self.error("a reference type")
+        }
+    }
+
+    fn type_variable(&mut self) -> ParseResult<ReferenceTypeSignature> {
+        try!(self.expect('T'));
+        let name = try!(self.identifier()).to_string();
+        try!(self.expect(';'));
+        Result::Ok(ReferenceTypeSignature::TypeVariable(name))
+    }
+
+    fn class_type(&mut self) -> ParseResult<ClassTypeSignature> {
+        try!(self.expect('L'));
+        let start = self.position;
+        let mut name = try!(self.identifier());
+        while try!(self.peek()) == '/' {
+            self.position += 1;
+            name = try!(self.identifier());
+        }
+        let package_end = self.position - name.len();
+        let package = if package_end > start {
+            self.input[start..package_end - 1].to_string()
+        } else {
+            String::new()
+        };
+        let class = try!(self.simple_class_type(name));
+        let mut inner = Vec::new();
+        while try!(self.peek()) == '.' {
+            self.position += 1;
+            let name = try!(self.identifier());
+            inner.push(try!(self.simple_class_type(name)));
+        }
+        try!(self.expect(';'));
+        Result::Ok(ClassTypeSignature {
+            package: package,
+            class: class,
+            inner: inner
+        })
+    }
+
+    fn simple_class_type(&mut self, name: &str) -> ParseResult<SimpleClassTypeSignature> {
+        let mut type_arguments = Vec::new();
+        if try!(self.peek()) == '<' {
+            if self.depth == MAX_TYPE_ARGUMENT_DEPTH {
+                return self.error(&format!("at most {} levels of type arguments", MAX_TYPE_ARGUMENT_DEPTH));
+            }
+            self.depth += 1;
+            self.position += 1;
+            loop {
+                type_arguments.push(match try!(self.peek()) {
+                    '*' => {
+                        self.position += 1;
+                        TypeArgument::Any
+                    },
+                    '+' => {
+                        self.position += 1;
+                        TypeArgument::Extends(try!(self.reference_type()))
+                    },
+                    '-' => {
+                        self.position += 1;
+                        TypeArgument::Super(try!(self.reference_type()))
+                    },
+                    _ => TypeArgument::Exact(try!(self.reference_type()))
+                });
+                if try!(self.peek()) == '>' {
+                    self.position += 1;
+                    break;
+                }
+            }
+            self.depth -= 1;
+        }
+        Result::Ok(SimpleClassTypeSignature {
+            name: name.to_string(),
+            type_arguments: type_arguments
+        })
+    }
+}
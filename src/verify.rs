@@ -0,0 +1,1112 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use ::descriptor::{FieldType, MethodDescriptor};
+use ::frames::{ClassHierarchy, Frame, FrameType};
+use ::model::{ArrayType, Attribute, Class, ConstantPoolInfo, Exception, Instruction, Method, StackMapFrame};
+use ::model::Instruction::*;
+use ::pool::ConstantPool;
+use ::result::ParseError;
+
+/// Why the code of a method failed verification.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerifyError {
+    /// The method's name followed by its descriptor, or empty if the class
+    /// itself could not be read.
+    pub method: String,
+    /// The pc of the instruction that failed.
+    pub pc: u32,
+    pub message: String,
+    /// The frame required there, by a stack map or an earlier path, if the
+    /// failure is a mismatch with one.
+    pub expected: Option<Frame>,
+    /// The frame that reached the instruction.
+    pub actual: Option<Frame>
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(fmt, "{} at {}: {}", self.method, self.pc, self.message));
+        if let Option::Some(ref expected) = self.expected {
+            try!(write!(fmt, "\n  expected {}", expected));
+        }
+        if let Option::Some(ref actual) = self.actual {
+            try!(write!(fmt, "\n  actual {}", actual));
+        }
+        Result::Ok(())
+    }
+}
+
+impl Class {
+    /// Verifies the code of every method as the JVM does when loading the
+    /// class. From version 50 the code is type checked against its
+    /// `StackMapTable`; before that, and when version 50 code fails to check,
+    /// the types are inferred instead.
+    ///
+    /// Other classes are only known through the hierarchy, so the checks that
+    /// need to load them, such as access to protected members, are left to the
+    /// JVM. Returns the first failure of every method that fails.
+    pub fn verify(&self, hierarchy: &ClassHierarchy) -> Result<(), Vec<VerifyError>> {
+        let names = self.name().and_then(|name| self.super_name().map(|super_name| (name, super_name)));
+        let (class_name, super_name) = match names {
+            Result::Ok(names) => names,
+            Result::Err(error) => {
+                return Result::Err(vec![VerifyError {
+                    method: String::new(),
+                    pc: 0,
+                    message: describe(&error),
+                    expected: Option::None,
+                    actual: Option::None
+                }]);
+            }
+        };
+        let errors: Vec<VerifyError> = self.methods.iter().filter_map(|method| {
+            self.verify_method(method, &class_name, super_name.as_ref().map(String::as_str), hierarchy)
+        }).collect();
+        if errors.is_empty() {
+            Result::Ok(())
+        } else {
+            Result::Err(errors)
+        }
+    }
+
+    // Why the method fails verification, if it does.
+    fn verify_method(&self, method: &Method, class_name: &str, super_name: Option<&str>, hierarchy: &ClassHierarchy)
+            -> Option<VerifyError> {
+        let pool = &self.constant_pool;
+        let names = method.name(pool).and_then(|name| method.descriptor(pool).map(|descriptor| (name, descriptor)));
+        let (name, descriptor) = match names {
+            Result::Ok(names) => names,
+            Result::Err(error) => {
+                return Option::Some(VerifyError {
+                    method: String::new(),
+                    pc: 0,
+                    message: describe(&error),
+                    expected: Option::None,
+                    actual: Option::None
+                });
+            }
+        };
+        let method_name = format!("{}{}", name, descriptor);
+        let error = |failure: Box<Failure>| {
+            let failure = *failure;
+            Option::Some(VerifyError {
+                method: method_name.clone(),
+                pc: failure.pc,
+                message: failure.message,
+                expected: failure.expected.map(trim),
+                actual: failure.actual.map(trim)
+            })
+        };
+        let parsed = match MethodDescriptor::parse(&descriptor) {
+            Result::Ok(parsed) => parsed,
+            Result::Err(e) => return error(Failure::at(0, describe(&e)))
+        };
+
+        let code = method.attributes.iter().filter_map(|attribute| match *attribute {
+            Attribute::Code { max_stack, max_locals, ref code, ref exception_table, ref attributes } => {
+                Option::Some((max_stack, max_locals, code, exception_table, attributes))
+            },
+            _ => Option::None
+        }).next();
+        let (max_stack, max_locals, code, exception_table, attributes) = match (code, method.is_abstract() || method.is_native()) {
+            (Option::None, true) => return Option::None,
+            (Option::None, false) => return error(Failure::at(0, "method has no code".to_string())),
+            (Option::Some(_), true) => return error(Failure::at(0, "abstract or native method has code".to_string())),
+            (Option::Some(code), false) => code
+        };
+        let table: &[StackMapFrame] = attributes.iter().filter_map(|attribute| match *attribute {
+            Attribute::StackMapTable(ref frames) => Option::Some(frames.as_slice()),
+            _ => Option::None
+        }).next().unwrap_or(&[]);
+
+        let verifier = Verifier {
+            pool: pool,
+            hierarchy: hierarchy,
+            class_name: class_name,
+            super_name: super_name,
+            name: &name,
+            descriptor: &parsed,
+            is_static: method.is_static(),
+            max_stack: max_stack as usize,
+            max_locals: max_locals as usize,
+            code: code,
+            exception_table: exception_table
+        };
+        let result = if self.major_version < 50 {
+            verifier.infer()
+        } else {
+            match verifier.check(table) {
+                Result::Err(_) if self.major_version == 50 => verifier.infer(),
+                result => result
+            }
+        };
+        result.err().and_then(error)
+    }
+}
+
+struct Failure {
+    pc: u32,
+    message: String,
+    expected: Option<Frame>,
+    actual: Option<Frame>
+}
+
+impl Failure {
+    fn new(pc: u32, message: String, expected: Option<Frame>, actual: Option<Frame>) -> Box<Failure> {
+        Box::new(Failure { pc: pc, message: message, expected: expected, actual: actual })
+    }
+
+    fn at(pc: u32, message: String) -> Box<Failure> {
+        Failure::new(pc, message, Option::None, Option::None)
+    }
+}
+
+// An exception table entry with its range and handler as instruction indices.
+struct Handler {
+    start: usize,
+    end: usize,
+    index: usize,
+    catch_type: FrameType
+}
+
+struct Verifier<'a> {
+    pool: &'a ConstantPool,
+    hierarchy: &'a ClassHierarchy,
+    class_name: &'a str,
+    super_name: Option<&'a str>,
+    name: &'a str,
+    descriptor: &'a MethodDescriptor,
+    is_static: bool,
+    max_stack: usize,
+    max_locals: usize,
+    code: &'a [(u32, Instruction)],
+    exception_table: &'a [Exception]
+}
+
+impl<'a> Verifier<'a> {
+    // Type checking (JVMS 4.10.1): a single pass over the code, where the
+    // frame after an instruction must be assignable to the stack map frame of
+    // every instruction it can go to.
+    fn check(&self, table: &[StackMapFrame]) -> Result<(), Box<Failure>> {
+        let initial = try!(self.initial());
+        let maps = try!(self.stack_maps(table));
+        let handlers = try!(self.handlers());
+
+        let mut current = Option::Some(initial);
+        for (i, &(pc, ref instruction)) in self.code.iter().enumerate() {
+            let mut frame = match (current.take(), maps[i].clone()) {
+                (Option::Some(frame), Option::Some(map)) => {
+                    if !try!(self.is_frame_assignable(pc, &frame, &map)) {
+                        let message = "frame does not match the stack map frame".to_string();
+                        return Result::Err(Failure::new(pc, message, Option::Some(map), Option::Some(frame)));
+                    }
+                    map
+                },
+                (Option::None, Option::Some(map)) => map,
+                (Option::Some(frame), Option::None) => frame,
+                (Option::None, Option::None) => {
+                    let message = "no stack map frame after an unconditional transfer of control".to_string();
+                    return Result::Err(Failure::at(pc, message));
+                }
+            };
+            match *instruction {
+                jsr(_) | jsr_w(_) | ret(_) | ret_w(_) => {
+                    let message = "jsr and ret are not allowed in class files with stack maps".to_string();
+                    return Result::Err(Failure::new(pc, message, Option::None, Option::Some(frame)));
+                },
+                _ => {}
+            }
+            for handler in handlers.iter().filter(|handler| handler.start <= i && i < handler.end) {
+                let input = Frame { locals: frame.locals.clone(), stack: vec![handler.catch_type.clone()] };
+                try!(self.check_target(pc, &input, handler.index, &maps, "exception handler"));
+            }
+
+            let before = frame.clone();
+            if let Result::Err(message) = self.execute(&mut frame, i) {
+                return Result::Err(Failure::new(pc, message, Option::None, Option::Some(before)));
+            }
+            for target in try!(self.branch_targets(i)) {
+                try!(self.check_target(pc, &frame, target, &maps, "branch target"));
+            }
+            if instruction.falls_through() {
+                current = Option::Some(frame);
+            }
+        }
+        match current {
+            Option::Some(frame) => {
+                let message = "execution falls off the end of the code".to_string();
+                Result::Err(Failure::new(self.code[self.code.len() - 1].0, message, Option::None, Option::Some(frame)))
+            },
+            Option::None => Result::Ok(())
+        }
+    }
+
+    fn check_target(&self, pc: u32, frame: &Frame, target: usize, maps: &[Option<Frame>], kind: &str) -> Result<(), Box<Failure>> {
+        let target_pc = self.code[target].0;
+        match maps[target] {
+            Option::Some(ref map) if try!(self.is_frame_assignable(pc, frame, map)) => Result::Ok(()),
+            Option::Some(ref map) => {
+                let message = format!("frame does not match the stack map frame of the {} at {}", kind, target_pc);
+                Result::Err(Failure::new(pc, message, Option::Some(map.clone()), Option::Some(frame.clone())))
+            },
+            Option::None => {
+                let message = format!("{} at {} has no stack map frame", kind, target_pc);
+                Result::Err(Failure::new(pc, message, Option::None, Option::Some(frame.clone())))
+            }
+        }
+    }
+
+    // Expands the stack map frames, indexed by instruction.
+    fn stack_maps(&self, table: &[StackMapFrame]) -> Result<Vec<Option<Frame>>, Box<Failure>> {
+        let mut maps = vec![Option::None; self.code.len()];
+        let mut frame = Frame::initial(self.class_name, self.is_static, self.name, self.descriptor);
+        let mut previous: Option<u32> = Option::None;
+        for entry in table {
+            let offset_delta = entry.offset_delta() as u32;
+            let pc = previous.map_or(offset_delta, |previous| previous + offset_delta + 1);
+            previous = Option::Some(pc);
+            let index = match self.index_of(pc) {
+                Option::Some(index) => index,
+                Option::None => {
+                    let message = format!("stack map frame at {} is not at the start of an instruction", pc);
+                    return Result::Err(Failure::at(pc, message));
+                }
+            };
+            frame = try!(frame.apply(entry, self.pool, self.code).map_err(|e| Failure::at(pc, describe(&e))));
+            if frame.locals.len() > self.max_locals || frame.stack.len() > self.max_stack {
+                return Result::Err(Failure::at(pc, "stack map frame exceeds max_locals or max_stack".to_string()));
+            }
+            let mut padded = frame.clone();
+            padded.locals.resize(self.max_locals, FrameType::Top);
+            maps[index] = Option::Some(padded);
+        }
+        Result::Ok(maps)
+    }
+
+    // Type inference (JVMS 4.10.2): the frame at every instruction is the
+    // merge of the frames of all paths to it, found by iterating until nothing
+    // changes. A subroutine returns to each of its `jsr`s with the locals it
+    // stores to and the caller's other locals.
+    fn infer(&self) -> Result<(), Box<Failure>> {
+        let initial = try!(self.initial());
+        let handlers = try!(self.handlers());
+
+        let mut inputs: Vec<Option<Frame>> = vec![Option::None; self.code.len()];
+        inputs[0] = Option::Some(initial);
+        let mut work = vec![0];
+        let mut callers: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+        let mut returns: BTreeMap<usize, Frame> = BTreeMap::new();
+        let mut stores: BTreeMap<usize, Vec<bool>> = BTreeMap::new();
+        while let Option::Some(i) = work.pop() {
+            let (pc, ref instruction) = self.code[i];
+            let frame = inputs[i].clone().unwrap();
+            for handler in handlers.iter().filter(|handler| handler.start <= i && i < handler.end) {
+                let input = Frame { locals: frame.locals.clone(), stack: vec![handler.catch_type.clone()] };
+                try!(self.merge(&mut inputs, &mut work, handler.index, input));
+            }
+
+            let mut next = frame.clone();
+            if let Result::Err(message) = self.execute(&mut next, i) {
+                return Result::Err(Failure::new(pc, message, Option::None, Option::Some(frame)));
+            }
+            let subroutine = match *instruction {
+                jsr(_) | jsr_w(_) => Option::Some(try!(self.branch_targets(i))[0]),
+                _ => Option::None
+            };
+            if let Option::Some(entry) = subroutine {
+                try!(self.merge(&mut inputs, &mut work, entry, next));
+                if i + 1 == self.code.len() {
+                    return Result::Err(Failure::at(pc, "jsr at the end of the code".to_string()));
+                }
+                let sites = callers.entry(entry).or_insert_with(Vec::new);
+                if !sites.contains(&i) {
+                    sites.push(i);
+                }
+                if let Option::Some(returned) = returns.get(&entry) {
+                    let stored = stores.entry(entry).or_insert_with(|| self.stored_locals(entry, &handlers));
+                    try!(self.merge(&mut inputs, &mut work, i + 1, after_return(returned, &frame, stored)));
+                }
+                continue;
+            }
+            let local = match *instruction {
+                ret(index) => Option::Some(index as usize),
+                ret_w(index) => Option::Some(index as usize),
+                _ => Option::None
+            };
+            if let Option::Some(local) = local {
+                let entry = match frame.locals[local] {
+                    FrameType::ReturnAddress(entry_pc) => self.index_of(entry_pc).unwrap(),
+                    _ => unreachable!()
+                };
+                let returned = match returns.get(&entry) {
+                    Option::Some(returned) => match returned.join(&next, self.hierarchy) {
+                        Result::Ok(joined) => joined,
+                        Result::Err(error) => {
+                            let expected = Option::Some(returned.clone());
+                            return Result::Err(Failure::new(pc, describe(&error), expected, Option::Some(next)));
+                        }
+                    },
+                    Option::None => next
+                };
+                if returns.get(&entry) == Option::Some(&returned) {
+                    continue;
+                }
+                let stored = stores.entry(entry).or_insert_with(|| self.stored_locals(entry, &handlers));
+                for &site in callers.get(&entry).map_or(&[][..], Vec::as_slice) {
+                    let caller = inputs[site].clone().unwrap();
+                    try!(self.merge(&mut inputs, &mut work, site + 1, after_return(&returned, &caller, stored)));
+                }
+                returns.insert(entry, returned);
+                continue;
+            }
+
+            for target in try!(self.branch_targets(i)) {
+                try!(self.merge(&mut inputs, &mut work, target, next.clone()));
+            }
+            if instruction.falls_through() {
+                if i + 1 == self.code.len() {
+                    let message = "execution falls off the end of the code".to_string();
+                    return Result::Err(Failure::new(pc, message, Option::None, Option::Some(next)));
+                }
+                try!(self.merge(&mut inputs, &mut work, i + 1, next));
+            }
+        }
+        Result::Ok(())
+    }
+
+    // Merges a frame into the input of an instruction, queueing it if that
+    // changed. Values on the stack must merge to something other than top.
+    fn merge(&self, inputs: &mut [Option<Frame>], work: &mut Vec<usize>, target: usize, frame: Frame) -> Result<(), Box<Failure>> {
+        let pc = self.code[target].0;
+        let merged = match inputs[target] {
+            Option::None => frame,
+            Option::Some(ref input) => {
+                let merged = input.join(&frame, self.hierarchy);
+                let conflict = match merged {
+                    Result::Ok(ref merged) => merged.stack.iter().zip(input.stack.iter()).any(|(merged, input)| {
+                        *merged == FrameType::Top && *input != FrameType::Top
+                    }),
+                    Result::Err(_) => true
+                };
+                if conflict {
+                    let message = match merged {
+                        Result::Err(ParseError::Decode(ref message)) if input.stack.len() != frame.stack.len() => message.clone(),
+                        Result::Err(ref error) => describe(error),
+                        Result::Ok(_) => "incompatible types on the stack".to_string()
+                    };
+                    return Result::Err(Failure::new(pc, message, Option::Some(input.clone()), Option::Some(frame)));
+                }
+                merged.unwrap()
+            }
+        };
+        if inputs[target].as_ref() != Option::Some(&merged) {
+            inputs[target] = Option::Some(merged);
+            work.push(target);
+        }
+        Result::Ok(())
+    }
+
+    // The locals that the subroutine at an instruction may store to, following
+    // nested subroutines.
+    fn stored_locals(&self, entry: usize, handlers: &[Handler]) -> Vec<bool> {
+        let mut stored = vec![false; self.max_locals];
+        let mut visited = vec![false; self.code.len()];
+        let mut stack = vec![entry];
+        while let Option::Some(i) = stack.pop() {
+            if visited[i] {
+                continue;
+            }
+            visited[i] = true;
+            let instruction = &self.code[i].1;
+            match instruction.local_access() {
+                Option::Some(ref access) if access.writes => {
+                    for slot in stored.iter_mut().skip(access.index as usize).take(access.size as usize) {
+                        *slot = true;
+                    }
+                },
+                _ => {}
+            }
+            stack.extend(handlers.iter().filter(|handler| handler.start <= i && i < handler.end).map(|handler| handler.index));
+            stack.extend(self.branch_targets(i).unwrap_or_default());
+            let next = match *instruction {
+                // Nested subroutines return to the next instruction.
+                jsr(_) | jsr_w(_) => true,
+                _ => instruction.falls_through()
+            };
+            if next && i + 1 < self.code.len() {
+                stack.push(i + 1);
+            }
+        }
+        stored
+    }
+
+    fn initial(&self) -> Result<Frame, Box<Failure>> {
+        if self.code.is_empty() {
+            return Result::Err(Failure::at(0, "code is empty".to_string()));
+        }
+        let mut frame = Frame::initial(self.class_name, self.is_static, self.name, self.descriptor);
+        if frame.locals.len() > self.max_locals {
+            return Result::Err(Failure::at(0, "max_locals is too small for the parameters".to_string()));
+        }
+        frame.locals.resize(self.max_locals, FrameType::Top);
+        Result::Ok(frame)
+    }
+
+    fn handlers(&self) -> Result<Vec<Handler>, Box<Failure>> {
+        let last = self.code[self.code.len() - 1].0;
+        let mut handlers = Vec::with_capacity(self.exception_table.len());
+        for exception in self.exception_table {
+            let start = self.index_of(exception.start_pc as u32);
+            let end = match self.index_of(exception.end_pc as u32) {
+                Option::None if exception.end_pc as u32 > last => Option::Some(self.code.len()),
+                end => end
+            };
+            let index = self.index_of(exception.handler_pc as u32);
+            let (start, end, index) = match (start, end, index) {
+                (Option::Some(start), Option::Some(end), Option::Some(index)) if start < end => (start, end, index),
+                _ => {
+                    let message = format!("exception table entry {}-{} handled at {} does not fit the code",
+                        exception.start_pc, exception.end_pc, exception.handler_pc);
+                    return Result::Err(Failure::at(exception.start_pc as u32, message));
+                }
+            };
+            let name = if exception.catch_type == 0 {
+                "java/lang/Throwable".to_string()
+            } else {
+                try!(self.pool.class_name(exception.catch_type).map_err(|e| Failure::at(exception.handler_pc as u32, describe(&e))))
+            };
+            let throwable = self.is_class_assignable(&name, "java/lang/Throwable");
+            if !try!(throwable.map_err(|message| Failure::at(exception.handler_pc as u32, message))) {
+                let message = format!("exception handler at {} catches {} which is not a Throwable", exception.handler_pc, name);
+                return Result::Err(Failure::at(exception.handler_pc as u32, message));
+            }
+            handlers.push(Handler { start: start, end: end, index: index, catch_type: FrameType::Reference(name) });
+        }
+        Result::Ok(handlers)
+    }
+
+    fn index_of(&self, pc: u32) -> Option<usize> {
+        self.code.binary_search_by_key(&pc, |&(pc, _)| pc).ok()
+    }
+
+    // The distinct targets of a branch, switch or `jsr`, other than falling
+    // through.
+    fn branch_targets(&self, i: usize) -> Result<Vec<usize>, Box<Failure>> {
+        let pc = self.code[i].0;
+        let mut targets = Vec::new();
+        for target in try!(self.code[i].1.branch_targets(pc).map_err(|error| Failure::at(pc, describe(&error)))) {
+            match self.index_of(target) {
+                Option::Some(index) if !targets.contains(&index) => targets.push(index),
+                Option::Some(_) => {},
+                Option::None => {
+                    return Result::Err(Failure::at(pc, format!("branch targets {} which is not the start of an instruction", target)));
+                }
+            }
+        }
+        Result::Ok(targets)
+    }
+
+    fn is_frame_assignable(&self, pc: u32, from: &Frame, to: &Frame) -> Result<bool, Box<Failure>> {
+        if from.locals.len() != to.locals.len() || from.stack.len() != to.stack.len() {
+            return Result::Ok(false);
+        }
+        for (from, to) in from.locals.iter().zip(to.locals.iter()).chain(from.stack.iter().zip(to.stack.iter())) {
+            if !try!(self.is_assignable(from, to).map_err(|message| Failure::at(pc, message))) {
+                return Result::Ok(false);
+            }
+        }
+        // A constructor may not get to a frame that claims `this` has been
+        // initialized without calling another constructor.
+        let uninitialized_this = |frame: &Frame| frame.locals.contains(&FrameType::UninitializedThis);
+        Result::Ok(!uninitialized_this(from) || uninitialized_this(to))
+    }
+
+    fn is_assignable(&self, from: &FrameType, to: &FrameType) -> Result<bool, String> {
+        if from == to || *to == FrameType::Top {
+            return Result::Ok(true);
+        }
+        match (from, to) {
+            (&FrameType::Null, &FrameType::Reference(_)) => Result::Ok(true),
+            (&FrameType::Reference(ref from), &FrameType::Reference(ref to)) => self.is_class_assignable(from, to),
+            _ => Result::Ok(false)
+        }
+    }
+
+    // Whether a class or array, named as in a `Class` constant, can be used
+    // where another is expected. Interfaces accept any object.
+    fn is_class_assignable(&self, from: &str, to: &str) -> Result<bool, String> {
+        if from == to || to == "java/lang/Object" {
+            return Result::Ok(true);
+        }
+        match (from.starts_with('['), to.starts_with('[')) {
+            (true, true) => {
+                let from = FrameType::Reference(from.to_string()).component();
+                let to = FrameType::Reference(to.to_string()).component();
+                match (from, to) {
+                    (Option::Some(FrameType::Reference(ref from)), Option::Some(FrameType::Reference(ref to))) => {
+                        self.is_class_assignable(from, to)
+                    },
+                    _ => Result::Ok(false)
+                }
+            },
+            (true, false) => Result::Ok(to == "java/lang/Cloneable" || to == "java/io/Serializable"),
+            (false, true) => Result::Ok(false),
+            (false, false) => {
+                if try!(self.hierarchy.is_interface(to).map_err(|e| describe(&e))) {
+                    return Result::Ok(true);
+                }
+                self.hierarchy.common_superclass(from, to).map(|common| common == to).map_err(|e| describe(&e))
+            }
+        }
+    }
+
+    // Simulates an instruction, checking the types of its operands.
+    fn execute(&self, frame: &mut Frame, i: usize) -> Result<(), String> {
+        let (pc, ref instruction) = self.code[i];
+        macro_rules! pop {
+            ($frame_type:expr) => { try!(self.pop(frame, &$frame_type)) }
+        }
+        macro_rules! push {
+            ($frame_type:expr) => { try!(self.push(frame, $frame_type)) }
+        }
+        let object = || FrameType::Reference("java/lang/Object".to_string());
+
+        match *instruction {
+            nop | goto(_) | goto_w(_) => {},
+            aconst_null => push!(FrameType::Null),
+            iconst_m1 | iconst_0 | iconst_1 | iconst_2 | iconst_3 | iconst_4 | iconst_5 | bipush(_) | sipush(_) => {
+                push!(FrameType::Integer)
+            },
+            lconst_0 | lconst_1 => push!(FrameType::Long),
+            fconst_0 | fconst_1 | fconst_2 => push!(FrameType::Float),
+            dconst_0 | dconst_1 => push!(FrameType::Double),
+            ldc(index) => push!(try!(self.constant(index as u16, 1))),
+            ldc_w(index) => push!(try!(self.constant(index, 1))),
+            ldc2_w(index) => push!(try!(self.constant(index, 2))),
+
+            iload(index) => try!(self.load(frame, index as usize, FrameType::Integer)),
+            iload_w(index) => try!(self.load(frame, index as usize, FrameType::Integer)),
+            iload_0 => try!(self.load(frame, 0, FrameType::Integer)),
+            iload_1 => try!(self.load(frame, 1, FrameType::Integer)),
+            iload_2 => try!(self.load(frame, 2, FrameType::Integer)),
+            iload_3 => try!(self.load(frame, 3, FrameType::Integer)),
+            lload(index) => try!(self.load(frame, index as usize, FrameType::Long)),
+            lload_w(index) => try!(self.load(frame, index as usize, FrameType::Long)),
+            lload_0 => try!(self.load(frame, 0, FrameType::Long)),
+            lload_1 => try!(self.load(frame, 1, FrameType::Long)),
+            lload_2 => try!(self.load(frame, 2, FrameType::Long)),
+            lload_3 => try!(self.load(frame, 3, FrameType::Long)),
+            fload(index) => try!(self.load(frame, index as usize, FrameType::Float)),
+            fload_w(index) => try!(self.load(frame, index as usize, FrameType::Float)),
+            fload_0 => try!(self.load(frame, 0, FrameType::Float)),
+            fload_1 => try!(self.load(frame, 1, FrameType::Float)),
+            fload_2 => try!(self.load(frame, 2, FrameType::Float)),
+            fload_3 => try!(self.load(frame, 3, FrameType::Float)),
+            dload(index) => try!(self.load(frame, index as usize, FrameType::Double)),
+            dload_w(index) => try!(self.load(frame, index as usize, FrameType::Double)),
+            dload_0 => try!(self.load(frame, 0, FrameType::Double)),
+            dload_1 => try!(self.load(frame, 1, FrameType::Double)),
+            dload_2 => try!(self.load(frame, 2, FrameType::Double)),
+            dload_3 => try!(self.load(frame, 3, FrameType::Double)),
+            aload(index) => try!(self.load_reference(frame, index as usize)),
+            aload_w(index) => try!(self.load_reference(frame, index as usize)),
+            aload_0 => try!(self.load_reference(frame, 0)),
+            aload_1 => try!(self.load_reference(frame, 1)),
+            aload_2 => try!(self.load_reference(frame, 2)),
+            aload_3 => try!(self.load_reference(frame, 3)),
+
+            istore(index) => { pop!(FrameType::Integer); try!(self.store(frame, index as usize, FrameType::Integer)) },
+            istore_w(index) => { pop!(FrameType::Integer); try!(self.store(frame, index as usize, FrameType::Integer)) },
+            istore_0 => { pop!(FrameType::Integer); try!(self.store(frame, 0, FrameType::Integer)) },
+            istore_1 => { pop!(FrameType::Integer); try!(self.store(frame, 1, FrameType::Integer)) },
+            istore_2 => { pop!(FrameType::Integer); try!(self.store(frame, 2, FrameType::Integer)) },
+            istore_3 => { pop!(FrameType::Integer); try!(self.store(frame, 3, FrameType::Integer)) },
+            lstore(index) => { pop!(FrameType::Long); try!(self.store(frame, index as usize, FrameType::Long)) },
+            lstore_w(index) => { pop!(FrameType::Long); try!(self.store(frame, index as usize, FrameType::Long)) },
+            lstore_0 => { pop!(FrameType::Long); try!(self.store(frame, 0, FrameType::Long)) },
+            lstore_1 => { pop!(FrameType::Long); try!(self.store(frame, 1, FrameType::Long)) },
+            lstore_2 => { pop!(FrameType::Long); try!(self.store(frame, 2, FrameType::Long)) },
+            lstore_3 => { pop!(FrameType::Long); try!(self.store(frame, 3, FrameType::Long)) },
+            fstore(index) => { pop!(FrameType::Float); try!(self.store(frame, index as usize, FrameType::Float)) },
+            fstore_w(index) => { pop!(FrameType::Float); try!(self.store(frame, index as usize, FrameType::Float)) },
+            fstore_0 => { pop!(FrameType::Float); try!(self.store(frame, 0, FrameType::Float)) },
+            fstore_1 => { pop!(FrameType::Float); try!(self.store(frame, 1, FrameType::Float)) },
+            fstore_2 => { pop!(FrameType::Float); try!(self.store(frame, 2, FrameType::Float)) },
+            fstore_3 => { pop!(FrameType::Float); try!(self.store(frame, 3, FrameType::Float)) },
+            dstore(index) => { pop!(FrameType::Double); try!(self.store(frame, index as usize, FrameType::Double)) },
+            dstore_w(index) => { pop!(FrameType::Double); try!(self.store(frame, index as usize, FrameType::Double)) },
+            dstore_0 => { pop!(FrameType::Double); try!(self.store(frame, 0, FrameType::Double)) },
+            dstore_1 => { pop!(FrameType::Double); try!(self.store(frame, 1, FrameType::Double)) },
+            dstore_2 => { pop!(FrameType::Double); try!(self.store(frame, 2, FrameType::Double)) },
+            dstore_3 => { pop!(FrameType::Double); try!(self.store(frame, 3, FrameType::Double)) },
+            astore(index) => try!(self.store_reference(frame, index as usize)),
+            astore_w(index) => try!(self.store_reference(frame, index as usize)),
+            astore_0 => try!(self.store_reference(frame, 0)),
+            astore_1 => try!(self.store_reference(frame, 1)),
+            astore_2 => try!(self.store_reference(frame, 2)),
+            astore_3 => try!(self.store_reference(frame, 3)),
+            iinc(index, _) => try!(self.local(frame, index as usize, &FrameType::Integer)),
+            iinc_w(index, _) => try!(self.local(frame, index as usize, &FrameType::Integer)),
+
+            iaload => { pop!(FrameType::Integer); try!(self.pop_array(frame, &["[I"])); push!(FrameType::Integer) },
+            baload => { pop!(FrameType::Integer); try!(self.pop_array(frame, &["[B", "[Z"])); push!(FrameType::Integer) },
+            caload => { pop!(FrameType::Integer); try!(self.pop_array(frame, &["[C"])); push!(FrameType::Integer) },
+            saload => { pop!(FrameType::Integer); try!(self.pop_array(frame, &["[S"])); push!(FrameType::Integer) },
+            laload => { pop!(FrameType::Integer); try!(self.pop_array(frame, &["[J"])); push!(FrameType::Long) },
+            faload => { pop!(FrameType::Integer); try!(self.pop_array(frame, &["[F"])); push!(FrameType::Float) },
+            daload => { pop!(FrameType::Integer); try!(self.pop_array(frame, &["[D"])); push!(FrameType::Double) },
+            aaload => {
+                pop!(FrameType::Integer);
+                let array = try!(self.pop_array(frame, &["[L", "[["]));
+                push!(array.component().unwrap_or(FrameType::Null))
+            },
+            iastore => { pop!(FrameType::Integer); pop!(FrameType::Integer); try!(self.pop_array(frame, &["[I"])); },
+            bastore => { pop!(FrameType::Integer); pop!(FrameType::Integer); try!(self.pop_array(frame, &["[B", "[Z"])); },
+            castore => { pop!(FrameType::Integer); pop!(FrameType::Integer); try!(self.pop_array(frame, &["[C"])); },
+            sastore => { pop!(FrameType::Integer); pop!(FrameType::Integer); try!(self.pop_array(frame, &["[S"])); },
+            lastore => { pop!(FrameType::Long); pop!(FrameType::Integer); try!(self.pop_array(frame, &["[J"])); },
+            fastore => { pop!(FrameType::Float); pop!(FrameType::Integer); try!(self.pop_array(frame, &["[F"])); },
+            dastore => { pop!(FrameType::Double); pop!(FrameType::Integer); try!(self.pop_array(frame, &["[D"])); },
+            aastore => { pop!(object()); pop!(FrameType::Integer); try!(self.pop_array(frame, &["[L", "[["])); },
+
+            pop => { try!(self.take(frame, &[1])); },
+            pop2 => { try!(self.take(frame, &[2])); },
+            dup => { let values = try!(self.take(frame, &[1])); try!(self.duplicate(frame, values, 0)) },
+            dup_x1 => { let values = try!(self.take(frame, &[1, 2])); try!(self.duplicate(frame, values, 1)) },
+            dup_x2 => { let values = try!(self.take(frame, &[1, 3])); try!(self.duplicate(frame, values, 2)) },
+            dup2 => { let values = try!(self.take(frame, &[2])); try!(self.duplicate(frame, values, 0)) },
+            dup2_x1 => { let values = try!(self.take(frame, &[2, 3])); try!(self.duplicate(frame, values, 1)) },
+            dup2_x2 => { let values = try!(self.take(frame, &[2, 4])); try!(self.duplicate(frame, values, 2)) },
+            swap => {
+                let mut values = try!(self.take(frame, &[1, 2]));
+                values.swap(0, 1);
+                frame.stack.extend(values);
+            },
+
+            iadd | isub | imul | idiv | irem | ishl | ishr | iushr | iand | ior | ixor => {
+                pop!(FrameType::Integer);
+                pop!(FrameType::Integer);
+                push!(FrameType::Integer)
+            },
+            ladd | lsub | lmul | ldiv | lrem | land | lor | lxor => {
+                pop!(FrameType::Long);
+                pop!(FrameType::Long);
+                push!(FrameType::Long)
+            },
+            lshl | lshr | lushr => { pop!(FrameType::Integer); pop!(FrameType::Long); push!(FrameType::Long) },
+            fadd | fsub | fmul | fdiv | frem => { pop!(FrameType::Float); pop!(FrameType::Float); push!(FrameType::Float) },
+            dadd | dsub | dmul | ddiv | drem => { pop!(FrameType::Double); pop!(FrameType::Double); push!(FrameType::Double) },
+            ineg | i2b | i2c | i2s => { pop!(FrameType::Integer); push!(FrameType::Integer) },
+            lneg => { pop!(FrameType::Long); push!(FrameType::Long) },
+            fneg => { pop!(FrameType::Float); push!(FrameType::Float) },
+            dneg => { pop!(FrameType::Double); push!(FrameType::Double) },
+            i2l => { pop!(FrameType::Integer); push!(FrameType::Long) },
+            i2f => { pop!(FrameType::Integer); push!(FrameType::Float) },
+            i2d => { pop!(FrameType::Integer); push!(FrameType::Double) },
+            l2i => { pop!(FrameType::Long); push!(FrameType::Integer) },
+            l2f => { pop!(FrameType::Long); push!(FrameType::Float) },
+            l2d => { pop!(FrameType::Long); push!(FrameType::Double) },
+            f2i => { pop!(FrameType::Float); push!(FrameType::Integer) },
+            f2l => { pop!(FrameType::Float); push!(FrameType::Long) },
+            f2d => { pop!(FrameType::Float); push!(FrameType::Double) },
+            d2i => { pop!(FrameType::Double); push!(FrameType::Integer) },
+            d2l => { pop!(FrameType::Double); push!(FrameType::Long) },
+            d2f => { pop!(FrameType::Double); push!(FrameType::Float) },
+            lcmp => { pop!(FrameType::Long); pop!(FrameType::Long); push!(FrameType::Integer) },
+            fcmpl | fcmpg => { pop!(FrameType::Float); pop!(FrameType::Float); push!(FrameType::Integer) },
+            dcmpl | dcmpg => { pop!(FrameType::Double); pop!(FrameType::Double); push!(FrameType::Integer) },
+
+            ifeq(_) | ifne(_) | iflt(_) | ifge(_) | ifgt(_) | ifle(_) => { pop!(FrameType::Integer); },
+            if_icmpeq(_) | if_icmpne(_) | if_icmplt(_) | if_icmpge(_) | if_icmpgt(_) | if_icmple(_) => {
+                pop!(FrameType::Integer);
+                pop!(FrameType::Integer);
+            },
+            ifnull(_) | ifnonnull(_) => { try!(self.pop_reference(frame)); },
+            if_acmpeq(_) | if_acmpne(_) => { try!(self.pop_reference(frame)); try!(self.pop_reference(frame)); },
+            tableswitch(_, _, _) => { pop!(FrameType::Integer); },
+            lookupswitch(_, ref pairs) => {
+                if pairs.windows(2).any(|pair| pair[0].0 >= pair[1].0) {
+                    return Result::Err("lookupswitch keys are not sorted".to_string());
+                }
+                pop!(FrameType::Integer);
+            },
+            jsr(offset) => push!(FrameType::ReturnAddress((pc as i64 + offset as i64) as u32)),
+            jsr_w(offset) => push!(FrameType::ReturnAddress((pc as i64 + offset as i64) as u32)),
+            ret(index) => try!(self.return_address(frame, index as usize)),
+            ret_w(index) => try!(self.return_address(frame, index as usize)),
+
+            ireturn => try!(self.value_return(frame, &FrameType::Integer)),
+            lreturn => try!(self.value_return(frame, &FrameType::Long)),
+            freturn => try!(self.value_return(frame, &FrameType::Float)),
+            dreturn => try!(self.value_return(frame, &FrameType::Double)),
+            areturn => {
+                match self.descriptor.return_type {
+                    Option::Some(ref return_type) if return_type.is_reference() => { pop!(FrameType::of(return_type)); },
+                    _ => return Result::Err("areturn from a method that does not return a reference".to_string())
+                }
+            },
+            return_ => {
+                if self.descriptor.return_type.is_some() {
+                    return Result::Err("return from a method that returns a value".to_string());
+                }
+                if self.name == "<init>" && frame.locals.contains(&FrameType::UninitializedThis) {
+                    return Result::Err("constructor returns without calling another constructor".to_string());
+                }
+            },
+            athrow => { pop!(FrameType::Reference("java/lang/Throwable".to_string())); },
+            monitorenter | monitorexit => { pop!(object()); },
+
+            getstatic(index) => push!(try!(self.field_type(index))),
+            putstatic(index) => { pop!(try!(self.field_type(index))); },
+            getfield(index) => {
+                let member = try!(self.pool.member_ref(index).map_err(|e| describe(&e)));
+                pop!(FrameType::Reference(member.class_name));
+                push!(try!(self.field_type(index)))
+            },
+            putfield(index) => {
+                let member = try!(self.pool.member_ref(index).map_err(|e| describe(&e)));
+                pop!(try!(self.field_type(index)));
+                // Constructors may set their own class's fields before calling
+                // another constructor.
+                if frame.stack.last() == Option::Some(&FrameType::UninitializedThis) && member.class_name == self.class_name {
+                    frame.stack.pop();
+                } else {
+                    pop!(FrameType::Reference(member.class_name));
+                }
+            },
+            invokevirtual(index) | invokespecial(index) | invokestatic(index) | invokeinterface(index, _) => {
+                let member = try!(self.pool.member_ref(index).map_err(|e| describe(&e)));
+                let descriptor = try!(MethodDescriptor::parse(&member.descriptor).map_err(|e| describe(&e)));
+                let constructor = member.name == "<init>";
+                match *instruction {
+                    invokespecial(_) if member.name == "<clinit>" => {
+                        return Result::Err("invokespecial of a class initializer".to_string());
+                    },
+                    invokespecial(_) => {},
+                    _ if member.name.starts_with('<') => {
+                        return Result::Err(format!("{:?} of {}", instruction, member.name));
+                    },
+                    invokeinterface(_, count) if count as u16 != descriptor.parameter_slots() + 1 => {
+                        return Result::Err(format!("invokeinterface count {} does not match the arguments", count));
+                    },
+                    _ => {}
+                }
+                for parameter in descriptor.parameters.iter().rev() {
+                    pop!(FrameType::of(parameter));
+                }
+                match *instruction {
+                    invokestatic(_) => {},
+                    invokeinterface(_, _) => { pop!(object()); },
+                    invokespecial(_) if constructor => {
+                        if descriptor.return_type.is_some() {
+                            return Result::Err("constructor returns a value".to_string());
+                        }
+                        try!(self.initialize(frame, &member.class_name));
+                    },
+                    invokespecial(_) => { pop!(FrameType::Reference(self.class_name.to_string())); },
+                    _ => { pop!(FrameType::Reference(member.class_name.clone())); }
+                }
+                if let Option::Some(ref return_type) = descriptor.return_type {
+                    push!(FrameType::of(return_type))
+                }
+            },
+            invokedynamic(index) => {
+                let descriptor = match *try!(self.pool.get(index).map_err(|e| describe(&e))) {
+                    ConstantPoolInfo::InvokeDynamic(_, name_and_type) => {
+                        try!(self.pool.name_and_type(name_and_type).map_err(|e| describe(&e))).1
+                    },
+                    ref other => return Result::Err(format!("expected invoke dynamic at index {} but got {:?}", index, other))
+                };
+                let descriptor = try!(MethodDescriptor::parse(&descriptor).map_err(|e| describe(&e)));
+                for parameter in descriptor.parameters.iter().rev() {
+                    pop!(FrameType::of(parameter));
+                }
+                if let Option::Some(ref return_type) = descriptor.return_type {
+                    push!(FrameType::of(return_type))
+                }
+            },
+
+            new(index) => {
+                let name = try!(self.pool.class_name(index).map_err(|e| describe(&e)));
+                if name.starts_with('[') {
+                    return Result::Err(format!("new of array type {}", name));
+                }
+                let created = FrameType::Uninitialized(pc);
+                if frame.stack.contains(&created) {
+                    return Result::Err("new while its previous object is still uninitialized on the stack".to_string());
+                }
+                for local in frame.locals.iter_mut().filter(|local| **local == created) {
+                    *local = FrameType::Top;
+                }
+                push!(created)
+            },
+            newarray(ref array_type) => {
+                pop!(FrameType::Integer);
+                let descriptor = match *array_type {
+                    ArrayType::Boolean => "[Z",
+                    ArrayType::Char => "[C",
+                    ArrayType::Float => "[F",
+                    ArrayType::Double => "[D",
+                    ArrayType::Byte => "[B",
+                    ArrayType::Short => "[S",
+                    ArrayType::Int => "[I",
+                    ArrayType::Long => "[J"
+                };
+                push!(FrameType::Reference(descriptor.to_string()))
+            },
+            anewarray(index) => {
+                pop!(FrameType::Integer);
+                let name = try!(self.pool.class_name(index).map_err(|e| describe(&e)));
+                if dimensions(&name) >= 255 {
+                    return Result::Err(format!("anewarray of {} has more than 255 dimensions", name));
+                }
+                push!(FrameType::array_of(&name))
+            },
+            multianewarray(index, count) => {
+                let name = try!(self.pool.class_name(index).map_err(|e| describe(&e)));
+                if count == 0 || dimensions(&name) < count as usize {
+                    return Result::Err(format!("multianewarray of {} with {} dimensions", name, count));
+                }
+                for _ in 0..count {
+                    pop!(FrameType::Integer);
+                }
+                push!(FrameType::Reference(name))
+            },
+            arraylength => { try!(self.pop_array(frame, &["["])); push!(FrameType::Integer) },
+            instanceof(_) => { pop!(object()); push!(FrameType::Integer) },
+            checkcast(index) => {
+                pop!(object());
+                push!(FrameType::Reference(try!(self.pool.class_name(index).map_err(|e| describe(&e)))))
+            }
+        }
+        Result::Ok(())
+    }
+
+    fn pop(&self, frame: &mut Frame, expected: &FrameType) -> Result<FrameType, String> {
+        let size = expected.size();
+        if frame.stack.len() < size {
+            return Result::Err(format!("expected {} but the stack is empty", expected));
+        }
+        let length = frame.stack.len() - size;
+        let value = frame.stack[length].clone();
+        if !try!(self.is_assignable(&value, expected)) {
+            return Result::Err(format!("expected {} on the stack but found {}", expected, value));
+        }
+        frame.stack.truncate(length);
+        Result::Ok(value)
+    }
+
+    // Pops an object or null, initialized or not.
+    fn pop_reference(&self, frame: &mut Frame) -> Result<FrameType, String> {
+        match frame.stack.pop() {
+            Option::Some(ref value) if value.is_reference() => Result::Ok(value.clone()),
+            Option::Some(value) => Result::Err(format!("expected a reference on the stack but found {}", value)),
+            Option::None => Result::Err("expected a reference but the stack is empty".to_string())
+        }
+    }
+
+    // Pops null or an array whose descriptor starts with one of the prefixes.
+    fn pop_array(&self, frame: &mut Frame, prefixes: &[&str]) -> Result<FrameType, String> {
+        let value = match frame.stack.pop() {
+            Option::Some(value) => value,
+            Option::None => return Result::Err("expected an array but the stack is empty".to_string())
+        };
+        let valid = match value {
+            FrameType::Null => true,
+            FrameType::Reference(ref name) => prefixes.iter().any(|prefix| name.starts_with(prefix)),
+            _ => false
+        };
+        if !valid {
+            return Result::Err(format!("expected an array of {} on the stack but found {}", prefixes.join(" or "), value));
+        }
+        Result::Ok(value)
+    }
+
+    // Pops the top slots, given as the depths that must not split a long or
+    // double, the deepest last.
+    fn take(&self, frame: &mut Frame, depths: &[usize]) -> Result<Vec<FrameType>, String> {
+        let slots = depths[depths.len() - 1];
+        if frame.stack.len() < slots {
+            return Result::Err(format!("expected {} values but the stack has {}", slots, frame.stack.len()));
+        }
+        for &depth in depths {
+            let position = frame.stack.len() - depth;
+            if position > 0 && frame.stack[position - 1].size() == 2 {
+                return Result::Err("instruction splits a long or double on the stack".to_string());
+            }
+        }
+        let length = frame.stack.len() - slots;
+        Result::Ok(frame.stack.split_off(length))
+    }
+
+    // Pushes the values back with a copy of the top `values.len() - depth`
+    // slots inserted `depth` slots down, as the `dup` instructions do.
+    fn duplicate(&self, frame: &mut Frame, values: Vec<FrameType>, depth: usize) -> Result<(), String> {
+        let copied = values[depth..].to_vec();
+        frame.stack.extend(copied);
+        frame.stack.extend(values);
+        if frame.stack.len() > self.max_stack {
+            return Result::Err(format!("stack grows beyond max_stack {}", self.max_stack));
+        }
+        Result::Ok(())
+    }
+
+    fn push(&self, frame: &mut Frame, value: FrameType) -> Result<(), String> {
+        let size = value.size();
+        if frame.stack.len() + size > self.max_stack {
+            return Result::Err(format!("stack grows beyond max_stack {}", self.max_stack));
+        }
+        frame.stack.push(value);
+        if size == 2 {
+            frame.stack.push(FrameType::Top);
+        }
+        Result::Ok(())
+    }
+
+    // Checks that a local holds a value of a type.
+    fn local(&self, frame: &Frame, index: usize, expected: &FrameType) -> Result<(), String> {
+        if index + expected.size() > frame.locals.len() {
+            return Result::Err(format!("local {} is beyond max_locals {}", index, self.max_locals));
+        }
+        let value = &frame.locals[index];
+        if !try!(self.is_assignable(value, expected)) {
+            return Result::Err(format!("expected {} in local {} but found {}", expected, index, value));
+        }
+        Result::Ok(())
+    }
+
+    fn load(&self, frame: &mut Frame, index: usize, value: FrameType) -> Result<(), String> {
+        try!(self.local(frame, index, &value));
+        self.push(frame, value)
+    }
+
+    fn load_reference(&self, frame: &mut Frame, index: usize) -> Result<(), String> {
+        match frame.locals.get(index).cloned() {
+            Option::Some(ref value) if value.is_reference() => self.push(frame, value.clone()),
+            Option::Some(value) => Result::Err(format!("expected a reference in local {} but found {}", index, value)),
+            Option::None => Result::Err(format!("local {} is beyond max_locals {}", index, self.max_locals))
+        }
+    }
+
+    fn store(&self, frame: &mut Frame, index: usize, value: FrameType) -> Result<(), String> {
+        if index + value.size() > self.max_locals {
+            return Result::Err(format!("local {} is beyond max_locals {}", index, self.max_locals));
+        }
+        frame.store(index, value);
+        Result::Ok(())
+    }
+
+    // Stores an object, null or return address.
+    fn store_reference(&self, frame: &mut Frame, index: usize) -> Result<(), String> {
+        match frame.stack.pop() {
+            Option::Some(value) => match value {
+                FrameType::ReturnAddress(_) => self.store(frame, index, value),
+                _ if value.is_reference() => self.store(frame, index, value),
+                _ => Result::Err(format!("expected a reference or return address on the stack but found {}", value))
+            },
+            Option::None => Result::Err("expected a reference but the stack is empty".to_string())
+        }
+    }
+
+    fn return_address(&self, frame: &Frame, index: usize) -> Result<(), String> {
+        match frame.locals.get(index) {
+            Option::Some(&FrameType::ReturnAddress(_)) => Result::Ok(()),
+            Option::Some(value) => Result::Err(format!("expected a return address in local {} but found {}", index, value)),
+            Option::None => Result::Err(format!("local {} is beyond max_locals {}", index, self.max_locals))
+        }
+    }
+
+    fn value_return(&self, frame: &mut Frame, value: &FrameType) -> Result<(), String> {
+        match self.descriptor.return_type {
+            Option::Some(ref return_type) if FrameType::of(return_type) == *value => {
+                try!(self.pop(frame, value));
+                Result::Ok(())
+            },
+            _ => Result::Err(format!("return of {} from a method that does not return it", value))
+        }
+    }
+
+    // Calls a constructor on the object below the arguments, after which every
+    // copy of it is initialized.
+    fn initialize(&self, frame: &mut Frame, class_name: &str) -> Result<(), String> {
+        let receiver = try!(self.pop_reference(frame));
+        let initialized = match receiver {
+            FrameType::UninitializedThis => {
+                if class_name != self.class_name && Option::Some(class_name) != self.super_name {
+                    return Result::Err(format!("constructor of {} called on this", class_name));
+                }
+                FrameType::Reference(self.class_name.to_string())
+            },
+            FrameType::Uninitialized(new_pc) => {
+                let created = match self.index_of(new_pc).map(|index| &self.code[index].1) {
+                    Option::Some(&new(index)) => try!(self.pool.class_name(index).map_err(|e| describe(&e))),
+                    _ => return Result::Err(format!("uninitialized object refers to {} which is not a new", new_pc))
+                };
+                if created != class_name {
+                    return Result::Err(format!("constructor of {} called on a new {}", class_name, created));
+                }
+                FrameType::Reference(created)
+            },
+            _ => return Result::Err(format!("constructor called on {}", receiver))
+        };
+        for slot in frame.locals.iter_mut().chain(frame.stack.iter_mut()) {
+            if *slot == receiver {
+                *slot = initialized.clone();
+            }
+        }
+        Result::Ok(())
+    }
+
+    // The type a `ldc` pushes, checking that the instruction fits its size.
+    fn constant(&self, index: u16, size: usize) -> Result<FrameType, String> {
+        let value = try!(FrameType::of_constant(self.pool, index).map_err(|e| describe(&e)));
+        match (value.size(), size) {
+            (2, 1) => Result::Err(format!("ldc of the long or double at index {}", index)),
+            (1, 2) => Result::Err(format!("ldc2_w of the constant at index {} which is not a long or double", index)),
+            _ => Result::Ok(value)
+        }
+    }
+
+    fn field_type(&self, index: u16) -> Result<FrameType, String> {
+        let member = try!(self.pool.member_ref(index).map_err(|e| describe(&e)));
+        FieldType::parse(&member.descriptor).map(|field_type| FrameType::of(&field_type)).map_err(|e| describe(&e))
+    }
+}
+
+// The frame at the instruction after a `jsr` once its subroutine returns,
+// which has the caller's locals where the subroutine did not store to them.
+fn after_return(returned: &Frame, caller: &Frame, stored: &[bool]) -> Frame {
+    let mut locals: Vec<FrameType> = (0..caller.locals.len()).map(|slot| {
+        if stored[slot] { returned.locals[slot].clone() } else { caller.locals[slot].clone() }
+    }).collect();
+    for slot in 0..locals.len() {
+        if locals[slot].size() == 2 && locals.get(slot + 1) != Option::Some(&FrameType::Top) {
+            locals[slot] = FrameType::Top;
+        }
+    }
+    Frame { locals: locals, stack: returned.stack.clone() }
+}
+
+// The number of dimensions of an array named as in a `Class` constant.
+fn dimensions(name: &str) -> usize {
+    name.chars().take_while(|&c| c == '[').count()
+}
+
+// Drops the locals past the last one that is set, leaving the second slot of
+// a long or double.
+fn trim(mut frame: Frame) -> Frame {
+    while frame.locals.last() == Option::Some(&FrameType::Top)
+            && !(frame.locals.len() > 1 && frame.locals[frame.locals.len() - 2].size() == 2) {
+        frame.locals.pop();
+    }
+    frame
+}
+
+fn describe(error: &ParseError) -> String {
+    match *error {
+        ParseError::Io(ref error) => error.to_string(),
+        ParseError::Format(ref message) | ParseError::Decode(ref message) | ParseError::Encode(ref message) => message.clone(),
+        ParseError::Generic => "unknown error".to_string()
+    }
+}
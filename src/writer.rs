@@ -0,0 +1,729 @@
+use std::io::Write;
+use std::fs::File;
+
+use ::model::*;
+use ::pool::ConstantPool;
+use ::result::{ParseError, ParseResult};
+use ::encode::{encode_code, relocate_code, PcMap};
+
+pub struct ClassWriter<'a> {
+    writer: Box<Write + 'a>,
+    position: usize
+}
+
+impl<'a> ClassWriter<'a> {
+
+    pub fn write_to_path(class: &Class, path: &str) -> ParseResult<()> {
+        let mut file = match File::create(path) {
+            Result::Ok(f) => f,
+            Result::Err(e) => { return Result::Err(ParseError::Io(e)); }
+        };
+        ClassWriter::write_to_writer(class, &mut file)
+    }
+
+    pub fn write_to_writer<T: Write + 'a>(class: &Class, writer: &mut T) -> ParseResult<()> {
+        let mut cw = ClassWriter { writer: Box::new(writer), position: 0 };
+
+        try!(cw.write_u32(class.magic));
+        try!(cw.write_u16(class.minor_version));
+        try!(cw.write_u16(class.major_version));
+        try!(cw.write_constant_pool(&class.constant_pool));
+        try!(cw.write_u16(class.access_flags));
+        try!(cw.write_u16(class.this_class));
+        try!(cw.write_u16(class.super_class));
+        try!(cw.write_interfaces(&class.interfaces));
+        try!(cw.write_fields(&class.fields, &class.constant_pool));
+        try!(cw.write_methods(&class.methods, &class.constant_pool));
+        try!(cw.write_attributes(&class.attributes, &class.constant_pool));
+
+        try!(cw.writer.flush());
+        Result::Ok(())
+    }
+
+    fn write_attribute(self: &mut ClassWriter<'a>, attribute: &Attribute, constant_pool: &ConstantPool) -> ParseResult<()> {
+        let name_index = match *attribute {
+            Attribute::Unknown { name_index, .. } => name_index,
+            _ => try!(find_utf8(constant_pool, attribute_name(attribute)))
+        };
+
+        let mut info = Vec::new();
+        {
+            let mut cw = ClassWriter { writer: Box::new(&mut info), position: 0 };
+            try!(cw.write_attribute_info(attribute, constant_pool));
+        }
+
+        try!(self.write_u16(name_index));
+        try!(self.write_u32(info.len() as u32));
+        self.write_bytes(&info)
+    }
+
+    fn write_attribute_info(self: &mut ClassWriter<'a>, attribute: &Attribute, constant_pool: &ConstantPool) -> ParseResult<()> {
+        match *attribute {
+            Attribute::Code { max_stack, max_locals, ref code, ref exception_table, ref attributes } => {
+                try!(self.write_u16(max_stack));
+                try!(self.write_u16(max_locals));
+                let (code, map) = try!(relocate_code(code));
+                let bytes = try!(encode_code(&code));
+                try!(self.write_u32(bytes.len() as u32));
+                try!(self.write_bytes(&bytes));
+                try!(self.write_length(exception_table.len()));
+                for exception in exception_table {
+                    let exception = if map.is_unchanged() { exception.clone() } else { try!(map.exception(exception)) };
+                    try!(self.write_u16(exception.start_pc));
+                    try!(self.write_u16(exception.end_pc));
+                    try!(self.write_u16(exception.handler_pc));
+                    try!(self.write_u16(exception.catch_type));
+                }
+                if map.is_unchanged() {
+                    try!(self.write_attributes(attributes, constant_pool));
+                } else {
+                    try!(self.write_attributes(&try!(relocate_code_attributes(attributes, &map)), constant_pool));
+                }
+            },
+            Attribute::ConstantValue { constvalue_index } => {
+                try!(self.write_u16(constvalue_index));
+            },
+            Attribute::StackMapTable(ref entries) => {
+                try!(self.write_length(entries.len()));
+                for frame in entries {
+                    try!(self.write_stack_map_frame(frame));
+                }
+            },
+            Attribute::Exceptions { ref exception_index_table } => {
+                try!(self.write_length(exception_index_table.len()));
+                for &exception_index in exception_index_table {
+                    try!(self.write_u16(exception_index));
+                }
+            },
+            Attribute::InnerClasses { ref classes } => {
+                try!(self.write_length(classes.len()));
+                for class in classes {
+                    try!(self.write_u16(class.inner_class_info_index));
+                    try!(self.write_u16(class.outer_class_info_index));
+                    try!(self.write_u16(class.inner_name_index));
+                    try!(self.write_u16(class.inner_class_access_flags));
+                }
+            },
+            Attribute::EnclosingMethod { class_index, method_index } => {
+                try!(self.write_u16(class_index));
+                try!(self.write_u16(method_index));
+            },
+            Attribute::Synthetic | Attribute::Deprecated => {},
+            Attribute::Signature { signature_index } => {
+                try!(self.write_u16(signature_index));
+            },
+            Attribute::SourceFile { sourcefile_index } => {
+                try!(self.write_u16(sourcefile_index));
+            },
+            Attribute::SourceDebugExtension(ref data) => {
+                try!(self.write_bytes(data));
+            },
+            Attribute::LineNumberTable(ref entries) => {
+                try!(self.write_length(entries.len()));
+                for entry in entries {
+                    try!(self.write_u16(entry.start_pc));
+                    try!(self.write_u16(entry.line_number));
+                }
+            },
+            Attribute::LocalVariableTable(ref entries) | Attribute::LocalVariableTypeTable(ref entries) => {
+                try!(self.write_length(entries.len()));
+                for entry in entries {
+                    try!(self.write_u16(entry.start_pc));
+                    try!(self.write_u16(entry.length));
+                    try!(self.write_u16(entry.name_index));
+                    try!(self.write_u16(entry.descriptor_or_signature_index));
+                    try!(self.write_u16(entry.index));
+                }
+            },
+            Attribute::RuntimeVisibleAnnotations(ref annotations)
+                    | Attribute::RuntimeInvisibleAnnotations(ref annotations) => {
+                try!(self.write_annotations(annotations));
+            },
+            Attribute::RuntimeVisibleParameterAnnotations(ref parameter_annotations)
+                    | Attribute::RuntimeInvisibleParameterAnnotations(ref parameter_annotations) => {
+                try!(self.write_parameter_annotations(parameter_annotations));
+            },
+            Attribute::RuntimeVisibleTypeAnnotations(ref type_annotations)
+                    | Attribute::RuntimeInvisibleTypeAnnotations(ref type_annotations) => {
+                try!(self.write_type_annotations(type_annotations));
+            },
+            Attribute::AnnotationDefault { ref element_value } => {
+                try!(self.write_element_value(element_value));
+            },
+            Attribute::BootstrapMethods(ref bootstrap_methods) => {
+                try!(self.write_length(bootstrap_methods.len()));
+                for bootstrap_method in bootstrap_methods {
+                    try!(self.write_u16(bootstrap_method.method_ref));
+                    try!(self.write_length(bootstrap_method.arguments.len()));
+                    for &argument in &bootstrap_method.arguments {
+                        try!(self.write_u16(argument));
+                    }
+                }
+            },
+            Attribute::MethodParameters(ref parameters) => {
+                try!(self.write_short_length(parameters.len()));
+                for parameter in parameters {
+                    try!(self.write_u16(parameter.name_index));
+                    try!(self.write_u16(parameter.access_flags));
+                }
+            },
+            Attribute::Module { module_name_index, module_flags, module_version_index,
+                    ref requires, ref exports, ref opens, ref uses_index, ref provides } => {
+                try!(self.write_u16(module_name_index));
+                try!(self.write_u16(module_flags));
+                try!(self.write_u16(module_version_index));
+                try!(self.write_length(requires.len()));
+                for entry in requires {
+                    try!(self.write_u16(entry.requires_index));
+                    try!(self.write_u16(entry.requires_flags));
+                    try!(self.write_u16(entry.requires_version_index));
+                }
+                try!(self.write_length(exports.len()));
+                for entry in exports {
+                    try!(self.write_u16(entry.exports_index));
+                    try!(self.write_u16(entry.exports_flags));
+                    try!(self.write_u16_table(&entry.exports_to_index));
+                }
+                try!(self.write_length(opens.len()));
+                for entry in opens {
+                    try!(self.write_u16(entry.opens_index));
+                    try!(self.write_u16(entry.opens_flags));
+                    try!(self.write_u16_table(&entry.opens_to_index));
+                }
+                try!(self.write_u16_table(uses_index));
+                try!(self.write_length(provides.len()));
+                for entry in provides {
+                    try!(self.write_u16(entry.provides_index));
+                    try!(self.write_u16_table(&entry.provides_with_index));
+                }
+            },
+            Attribute::ModulePackages { ref package_index } => {
+                try!(self.write_u16_table(package_index));
+            },
+            Attribute::ModuleMainClass { main_class_index } => {
+                try!(self.write_u16(main_class_index));
+            },
+            Attribute::NestHost { host_class_index } => {
+                try!(self.write_u16(host_class_index));
+            },
+            Attribute::NestMembers { ref classes } | Attribute::PermittedSubclasses { ref classes } => {
+                try!(self.write_u16_table(classes));
+            },
+            Attribute::Record(ref components) => {
+                try!(self.write_length(components.len()));
+                for component in components {
+                    try!(self.write_u16(component.name_index));
+                    try!(self.write_u16(component.descriptor_index));
+                    try!(self.write_attributes(&component.attributes, constant_pool));
+                }
+            },
+            Attribute::Unknown { ref info, .. } => {
+                try!(self.write_bytes(info));
+            }
+        }
+        Result::Ok(())
+    }
+
+    fn write_stack_map_frame(self: &mut ClassWriter<'a>, frame: &StackMapFrame) -> ParseResult<()> {
+        match *frame {
+            StackMapFrame::SameFrame { offset_delta } => {
+                if offset_delta > 63 {
+                    let message = format!("offset delta {} too large for same frame", offset_delta);
+                    return Result::Err(ParseError::Encode(message));
+                }
+                try!(self.write_u8(offset_delta));
+            },
+            StackMapFrame::SameLocals1StackItemFrame { offset_delta, ref stack } => {
+                if offset_delta > 63 {
+                    let message = format!("offset delta {} too large for same locals 1 stack item frame", offset_delta);
+                    return Result::Err(ParseError::Encode(message));
+                }
+                try!(self.write_u8(64 + offset_delta));
+                try!(self.write_verification_type_info(stack));
+            },
+            StackMapFrame::SameLocals1StackItemFrameExtended { offset_delta, ref stack } => {
+                try!(self.write_u8(247));
+                try!(self.write_u16(offset_delta));
+                try!(self.write_verification_type_info(stack));
+            },
+            StackMapFrame::ChopFrame { offset_delta, chopped } => {
+                if chopped < 1 || chopped > 3 {
+                    let message = format!("cannot chop {} locals in a chop frame", chopped);
+                    return Result::Err(ParseError::Encode(message));
+                }
+                try!(self.write_u8(251 - chopped));
+                try!(self.write_u16(offset_delta));
+            },
+            StackMapFrame::SameFrameExtended { offset_delta } => {
+                try!(self.write_u8(251));
+                try!(self.write_u16(offset_delta));
+            },
+            StackMapFrame::AppendFrame { offset_delta, ref locals } => {
+                if locals.is_empty() || locals.len() > 3 {
+                    let message = format!("cannot append {} locals in an append frame", locals.len());
+                    return Result::Err(ParseError::Encode(message));
+                }
+                try!(self.write_u8(251 + locals.len() as u8));
+                try!(self.write_u16(offset_delta));
+                try!(self.write_verification_type_infos(locals));
+            },
+            StackMapFrame::FullFrame { offset_delta, ref locals, ref stack } => {
+                try!(self.write_u8(255));
+                try!(self.write_u16(offset_delta));
+                try!(self.write_length(locals.len()));
+                try!(self.write_verification_type_infos(locals));
+                try!(self.write_length(stack.len()));
+                try!(self.write_verification_type_infos(stack));
+            }
+        }
+        Result::Ok(())
+    }
+
+    fn write_verification_type_info(self: &mut ClassWriter<'a>, info: &VerificationType) -> ParseResult<()> {
+        match *info {
+            VerificationType::Top => self.write_u8(0),
+            VerificationType::Integer => self.write_u8(1),
+            VerificationType::Float => self.write_u8(2),
+            VerificationType::Double => self.write_u8(3),
+            VerificationType::Long => self.write_u8(4),
+            VerificationType::Null => self.write_u8(5),
+            VerificationType::UninitializedThis => self.write_u8(6),
+            VerificationType::Object { index } => {
+                try!(self.write_u8(7));
+                self.write_u16(index)
+            },
+            VerificationType::UninitializedVariable { offset } => {
+                try!(self.write_u8(8));
+                self.write_u16(offset)
+            }
+        }
+    }
+
+    fn write_verification_type_infos(self: &mut ClassWriter<'a>, infos: &[VerificationType]) -> ParseResult<()> {
+        for info in infos {
+            try!(self.write_verification_type_info(info));
+        }
+        Result::Ok(())
+    }
+
+    fn write_type_annotations(self: &mut ClassWriter<'a>, annotations: &[TypeAnnotation]) -> ParseResult<()> {
+        try!(self.write_length(annotations.len()));
+        for annotation in annotations {
+            try!(self.write_type_annotation(annotation));
+        }
+        Result::Ok(())
+    }
+
+    fn write_type_annotation(self: &mut ClassWriter<'a>, annotation: &TypeAnnotation) -> ParseResult<()> {
+        let target_type_tag = match annotation.target_type {
+            TargetType::Type => 0x00,
+            TargetType::Method => 0x01,
+            TargetType::Supertype => 0x10,
+            TargetType::TypeBound => 0x11,
+            TargetType::MethodBound => 0x12,
+            TargetType::Field => 0x13,
+            TargetType::MethodReturnType => 0x14,
+            TargetType::ReceiverType => 0x15,
+            TargetType::Parameter => 0x16,
+            TargetType::Throws => 0x17,
+            TargetType::LocalVariableDeclaration => 0x40,
+            TargetType::ResourceVariableDeclaration => 0x41,
+            TargetType::ExceptionParameterDeclaration => 0x42,
+            TargetType::Instanceof => 0x43,
+            TargetType::New => 0x44,
+            TargetType::MethodReferenceNew => 0x45,
+            TargetType::MethodReference => 0x46,
+            TargetType::Cast => 0x47,
+            TargetType::ConstructorArgument => 0x48,
+            TargetType::MethodArgument => 0x49,
+            TargetType::MethodReferenceNewArgument => 0x4A,
+            TargetType::MethodReferenceArgument => 0x4B
+        };
+        try!(self.write_u8(target_type_tag));
+        match annotation.target_info {
+            TargetInfo::TypeParameter { index } => {
+                try!(self.write_u8(index));
+            },
+            TargetInfo::Supertype { index } => {
+                try!(self.write_u16(index));
+            },
+            TargetInfo::TypeParameterBound { index, bound_index } => {
+                try!(self.write_u8(index));
+                try!(self.write_u8(bound_index));
+            },
+            TargetInfo::Empty => {},
+            TargetInfo::MethodFormalParameter { index } => {
+                try!(self.write_u8(index));
+            },
+            TargetInfo::Throws { type_index } => {
+                try!(self.write_u16(type_index));
+            },
+            TargetInfo::Localvar(ref table) => {
+                try!(self.write_length(table.len()));
+                for entry in table {
+                    try!(self.write_u16(entry.start_pc));
+                    try!(self.write_u16(entry.length));
+                    try!(self.write_u16(entry.index));
+                }
+            },
+            TargetInfo::Catch { exception_table_index } => {
+                try!(self.write_u16(exception_table_index));
+            },
+            TargetInfo::Offset(offset) => {
+                try!(self.write_u16(offset));
+            },
+            TargetInfo::TypeArgument { offset, index } => {
+                try!(self.write_u16(offset));
+                try!(self.write_u8(index));
+            }
+        }
+        try!(self.write_short_length(annotation.type_path.path.len()));
+        for path_element in &annotation.type_path.path {
+            let type_path_kind_tag = match path_element.kind {
+                TypePathKind::Array => 0,
+                TypePathKind::Nested => 1,
+                TypePathKind::WildcardBound => 2,
+                TypePathKind::TypeArgument => 3
+            };
+            try!(self.write_u8(type_path_kind_tag));
+            try!(self.write_u8(path_element.argument_index));
+        }
+        try!(self.write_u16(annotation.type_index));
+        self.write_element_value_pairs(&annotation.element_value_pairs)
+    }
+
+    fn write_parameter_annotations(self: &mut ClassWriter<'a>, parameter_annotations: &[Vec<Annotation>]) -> ParseResult<()> {
+        try!(self.write_short_length(parameter_annotations.len()));
+        for annotations in parameter_annotations {
+            try!(self.write_annotations(annotations));
+        }
+        Result::Ok(())
+    }
+
+    fn write_annotations(self: &mut ClassWriter<'a>, annotations: &[Annotation]) -> ParseResult<()> {
+        try!(self.write_length(annotations.len()));
+        for annotation in annotations {
+            try!(self.write_annotation(annotation));
+        }
+        Result::Ok(())
+    }
+
+    fn write_annotation(self: &mut ClassWriter<'a>, annotation: &Annotation) -> ParseResult<()> {
+        try!(self.write_u16(annotation.type_index));
+        self.write_element_value_pairs(&annotation.element_value_pairs)
+    }
+
+    fn write_element_value_pairs(self: &mut ClassWriter<'a>, element_value_pairs: &[ElementValuePair]) -> ParseResult<()> {
+        try!(self.write_length(element_value_pairs.len()));
+        for element_value_pair in element_value_pairs {
+            try!(self.write_u16(element_value_pair.element_name_index));
+            try!(self.write_element_value(&element_value_pair.value));
+        }
+        Result::Ok(())
+    }
+
+    fn write_element_value(self: &mut ClassWriter<'a>, element_value: &ElementValue) -> ParseResult<()> {
+        match *element_value {
+            ElementValue::Constant { tag, const_value_index } => {
+                match tag {
+                    'B' | 'C' | 'D' | 'F' | 'I' | 'J' | 'S' | 'Z' | 's' => {},
+                    _ => {
+                        let message = format!("invalid constant element value tag {}", tag);
+                        return Result::Err(ParseError::Encode(message));
+                    }
+                }
+                try!(self.write_u8(tag as u8));
+                try!(self.write_u16(const_value_index));
+            },
+            ElementValue::EnumConstant { type_name_index, const_name_index } => {
+                try!(self.write_u8(b'e'));
+                try!(self.write_u16(type_name_index));
+                try!(self.write_u16(const_name_index));
+            },
+            ElementValue::Class { class_info_index } => {
+                try!(self.write_u8(b'c'));
+                try!(self.write_u16(class_info_index));
+            },
+            ElementValue::Annotation(ref annotation) => {
+                try!(self.write_u8(b'@'));
+                try!(self.write_annotation(annotation));
+            },
+            ElementValue::Array(ref element_values) => {
+                try!(self.write_u8(b'['));
+                try!(self.write_length(element_values.len()));
+                for array_value in element_values {
+                    try!(self.write_element_value(array_value));
+                }
+            }
+        }
+        Result::Ok(())
+    }
+
+    fn write_attributes(self: &mut ClassWriter<'a>, attributes: &[Attribute], constant_pool: &ConstantPool) -> ParseResult<()> {
+        try!(self.write_length(attributes.len()));
+        for attribute in attributes {
+            try!(self.write_attribute(attribute, constant_pool));
+        }
+        Result::Ok(())
+    }
+
+    fn write_methods(self: &mut ClassWriter<'a>, methods: &[Method], constant_pool: &ConstantPool) -> ParseResult<()> {
+        try!(self.write_length(methods.len()));
+        for method in methods {
+            try!(self.write_u16(method.access_flags));
+            try!(self.write_u16(method.name_index));
+            try!(self.write_u16(method.descriptor_index));
+            try!(self.write_attributes(&method.attributes, constant_pool));
+        }
+        Result::Ok(())
+    }
+
+    fn write_fields(self: &mut ClassWriter<'a>, fields: &[Field], constant_pool: &ConstantPool) -> ParseResult<()> {
+        try!(self.write_length(fields.len()));
+        for field in fields {
+            try!(self.write_u16(field.access_flags));
+            try!(self.write_u16(field.name_index));
+            try!(self.write_u16(field.descriptor_index));
+            try!(self.write_attributes(&field.attributes, constant_pool));
+        }
+        Result::Ok(())
+    }
+
+    fn write_interfaces(self: &mut ClassWriter<'a>, interfaces: &[u16]) -> ParseResult<()> {
+        try!(self.write_length(interfaces.len()));
+        for &interface in interfaces {
+            try!(self.write_u16(interface));
+        }
+        Result::Ok(())
+    }
+
+    fn write_u16_table(self: &mut ClassWriter<'a>, table: &[u16]) -> ParseResult<()> {
+        try!(self.write_length(table.len()));
+        for &entry in table {
+            try!(self.write_u16(entry));
+        }
+        Result::Ok(())
+    }
+
+    fn write_constant_pool(self: &mut ClassWriter<'a>, constant_pool: &ConstantPool) -> ParseResult<()> {
+        try!(self.write_length(constant_pool.len() + 1));
+        for cp_info in constant_pool {
+            try!(self.write_constant_pool_info(cp_info));
+        }
+        Result::Ok(())
+    }
+
+    fn write_constant_pool_info(self: &mut ClassWriter<'a>, cp_info: &ConstantPoolInfo) -> ParseResult<()> {
+        match *cp_info {
+            ConstantPoolInfo::Utf8(ref string) => {
+                let data = string.as_modified_utf8();
+                try!(self.write_u8(1));
+                try!(self.write_length(data.len()));
+                try!(self.write_bytes(data));
+            },
+            ConstantPoolInfo::Integer(value) => {
+                try!(self.write_u8(3));
+                try!(self.write_u32(value as u32));
+            },
+            ConstantPoolInfo::Float(value) => {
+                try!(self.write_u8(4));
+                try!(self.write_u32(value.to_bits()));
+            },
+            ConstantPoolInfo::Long(value) => {
+                try!(self.write_u8(5));
+                try!(self.write_u64(value as u64));
+            },
+            ConstantPoolInfo::Double(value) => {
+                try!(self.write_u8(6));
+                try!(self.write_u64(value.to_bits()));
+            },
+            ConstantPoolInfo::Class(name_index) => {
+                try!(self.write_u8(7));
+                try!(self.write_u16(name_index));
+            },
+            ConstantPoolInfo::String(string_index) => {
+                try!(self.write_u8(8));
+                try!(self.write_u16(string_index));
+            },
+            ConstantPoolInfo::Fieldref(class_index, name_and_type_index) => {
+                try!(self.write_u8(9));
+                try!(self.write_u16(class_index));
+                try!(self.write_u16(name_and_type_index));
+            },
+            ConstantPoolInfo::Methodref(class_index, name_and_type_index) => {
+                try!(self.write_u8(10));
+                try!(self.write_u16(class_index));
+                try!(self.write_u16(name_and_type_index));
+            },
+            ConstantPoolInfo::InterfaceMethodref(class_index, name_and_type_index) => {
+                try!(self.write_u8(11));
+                try!(self.write_u16(class_index));
+                try!(self.write_u16(name_and_type_index));
+            },
+            ConstantPoolInfo::NameAndType(name_index, descriptor_index) => {
+                try!(self.write_u8(12));
+                try!(self.write_u16(name_index));
+                try!(self.write_u16(descriptor_index));
+            },
+            ConstantPoolInfo::MethodHandle(reference_kind, reference_index) => {
+                try!(self.write_u8(15));
+                try!(self.write_u8(reference_kind));
+                try!(self.write_u16(reference_index));
+            },
+            ConstantPoolInfo::MethodType(descriptor_index) => {
+                try!(self.write_u8(16));
+                try!(self.write_u16(descriptor_index));
+            },
+            ConstantPoolInfo::Dynamic(bootstrap_method_attr_index, name_and_type_index) => {
+                try!(self.write_u8(17));
+                try!(self.write_u16(bootstrap_method_attr_index));
+                try!(self.write_u16(name_and_type_index));
+            },
+            ConstantPoolInfo::InvokeDynamic(bootstrap_method_attr_index, name_and_type_index) => {
+                try!(self.write_u8(18));
+                try!(self.write_u16(bootstrap_method_attr_index));
+                try!(self.write_u16(name_and_type_index));
+            },
+            ConstantPoolInfo::Module(name_index) => {
+                try!(self.write_u8(19));
+                try!(self.write_u16(name_index));
+            },
+            ConstantPoolInfo::Package(name_index) => {
+                try!(self.write_u8(20));
+                try!(self.write_u16(name_index));
+            },
+            ConstantPoolInfo::Invalid => {
+                // second slot of a long or double, it is not written
+            }
+        }
+        Result::Ok(())
+    }
+
+    fn write_length(self: &mut ClassWriter<'a>, length: usize) -> ParseResult<()> {
+        if length > 0xFFFF {
+            let message = format!("length {} does not fit into an u2", length);
+            return Result::Err(ParseError::Encode(message));
+        }
+        self.write_u16(length as u16)
+    }
+
+    fn write_short_length(self: &mut ClassWriter<'a>, length: usize) -> ParseResult<()> {
+        if length > 0xFF {
+            let message = format!("length {} does not fit into an u1", length);
+            return Result::Err(ParseError::Encode(message));
+        }
+        self.write_u8(length as u8)
+    }
+
+    fn write_bytes(self: &mut ClassWriter<'a>, bytes: &[u8]) -> ParseResult<()> {
+        try!(self.writer.write_all(bytes));
+
+        self.position += bytes.len();
+        Result::Ok(())
+    }
+
+    fn write_u64(self: &mut ClassWriter<'a>, val: u64) -> ParseResult<()> {
+        self.write_bytes(&[(val >> 56) as u8, (val >> 48) as u8, (val >> 40) as u8, (val >> 32) as u8,
+                (val >> 24) as u8, (val >> 16) as u8, (val >> 8) as u8, val as u8])
+    }
+
+    fn write_u32(self: &mut ClassWriter<'a>, val: u32) -> ParseResult<()> {
+        self.write_bytes(&[(val >> 24) as u8, (val >> 16) as u8, (val >> 8) as u8, val as u8])
+    }
+
+    fn write_u16(self: &mut ClassWriter<'a>, val: u16) -> ParseResult<()> {
+        self.write_bytes(&[(val >> 8) as u8, val as u8])
+    }
+
+    fn write_u8(self: &mut ClassWriter<'a>, val: u8) -> ParseResult<()> {
+        self.write_bytes(&[val])
+    }
+
+}
+
+// Moves the pcs in the attributes of a `Code` attribute whose instructions
+// were moved by `relocate_code`. A stack map cannot be moved without
+// recomputing its frames, so it is an error rather than written stale.
+fn relocate_code_attributes(attributes: &[Attribute], map: &PcMap) -> ParseResult<Vec<Attribute>> {
+    let mut relocated = Vec::with_capacity(attributes.len());
+    for attribute in attributes {
+        relocated.push(match *attribute {
+            Attribute::StackMapTable(_) => {
+                return Result::Err(ParseError::Encode("cannot write a StackMapTable for code whose instructions moved".to_string()));
+            },
+            Attribute::LineNumberTable(ref entries) => {
+                let mut moved = Vec::with_capacity(entries.len());
+                for entry in entries {
+                    moved.push(try!(map.line_number(entry)));
+                }
+                Attribute::LineNumberTable(moved)
+            },
+            Attribute::LocalVariableTable(ref entries) | Attribute::LocalVariableTypeTable(ref entries) => {
+                let mut moved = Vec::with_capacity(entries.len());
+                for entry in entries {
+                    moved.push(try!(map.local_variable(entry)));
+                }
+                match *attribute {
+                    Attribute::LocalVariableTable(_) => Attribute::LocalVariableTable(moved),
+                    _ => Attribute::LocalVariableTypeTable(moved)
+                }
+            },
+            Attribute::RuntimeVisibleTypeAnnotations(ref annotations)
+                    | Attribute::RuntimeInvisibleTypeAnnotations(ref annotations) => {
+                let mut moved = Vec::with_capacity(annotations.len());
+                for annotation in annotations {
+                    moved.push(try!(map.type_annotation(annotation)));
+                }
+                match *attribute {
+                    Attribute::RuntimeVisibleTypeAnnotations(_) => Attribute::RuntimeVisibleTypeAnnotations(moved),
+                    _ => Attribute::RuntimeInvisibleTypeAnnotations(moved)
+                }
+            },
+            ref other => other.clone()
+        });
+    }
+    Result::Ok(relocated)
+}
+
+fn attribute_name(attribute: &Attribute) -> &'static str {
+    match *attribute {
+        Attribute::ConstantValue { .. } => "ConstantValue",
+        Attribute::Code { .. } => "Code",
+        Attribute::StackMapTable(_) => "StackMapTable",
+        Attribute::Exceptions { .. } => "Exceptions",
+        Attribute::InnerClasses { .. } => "InnerClasses",
+        Attribute::EnclosingMethod { .. } => "EnclosingMethod",
+        Attribute::Synthetic => "Synthetic",
+        Attribute::Signature { .. } => "Signature",
+        Attribute::SourceFile { .. } => "SourceFile",
+        Attribute::SourceDebugExtension(_) => "SourceDebugExtension",
+        Attribute::LineNumberTable(_) => "LineNumberTable",
+        Attribute::LocalVariableTable(_) => "LocalVariableTable",
+        Attribute::LocalVariableTypeTable(_) => "LocalVariableTypeTable",
+        Attribute::Deprecated => "Deprecated",
+        Attribute::RuntimeVisibleAnnotations(_) => "RuntimeVisibleAnnotations",
+        Attribute::RuntimeInvisibleAnnotations(_) => "RuntimeInvisibleAnnotations",
+        Attribute::RuntimeVisibleParameterAnnotations(_) => "RuntimeVisibleParameterAnnotations",
+        Attribute::RuntimeInvisibleParameterAnnotations(_) => "RuntimeInvisibleParameterAnnotations",
+        Attribute::RuntimeVisibleTypeAnnotations(_) => "RuntimeVisibleTypeAnnotations",
+        Attribute::RuntimeInvisibleTypeAnnotations(_) => "RuntimeInvisibleTypeAnnotations",
+        Attribute::AnnotationDefault { .. } => "AnnotationDefault",
+        Attribute::BootstrapMethods(_) => "BootstrapMethods",
+        Attribute::MethodParameters(_) => "MethodParameters",
+        Attribute::Module { .. } => "Module",
+        Attribute::ModulePackages { .. } => "ModulePackages",
+        Attribute::ModuleMainClass { .. } => "ModuleMainClass",
+        Attribute::NestHost { .. } => "NestHost",
+        Attribute::NestMembers { .. } => "NestMembers",
+        Attribute::Record(_) => "Record",
+        Attribute::PermittedSubclasses { .. } => "PermittedSubclasses",
+        Attribute::Unknown { .. } => "Unknown"
+    }
+}
+
+fn find_utf8(constant_pool: &ConstantPool, name: &str) -> ParseResult<u16> {
+    constant_pool.find_utf8(name).ok_or_else(|| {
+        let message = format!("no utf8 constant pool entry for attribute name {}", name);
+        ParseError::Encode(message)
+    })
+}
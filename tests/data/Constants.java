@@ -0,0 +1,11 @@
+public class Constants {
+    static final boolean FLAG = true;
+    static final byte BYTE = -3;
+    static final char CHAR = 'x';
+    static final short SHORT = 1000;
+    static final int INT = 123456;
+    static final long LONG = -1L;
+    static final float FLOAT = 1.5f;
+    static final double NEGATIVE_ZERO = -0.0;
+    static final String STRING = "constant";
+}
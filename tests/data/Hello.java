@@ -0,0 +1,27 @@
+public class Hello {
+    private static final long COUNT = 3L;
+
+    private final String greeting;
+
+    public Hello(String greeting) {
+        this.greeting = greeting;
+    }
+
+    @Deprecated
+    public String greet(String name, int times) {
+        StringBuilder sb = new StringBuilder();
+        for (int i = 0; i < times; i++) {
+            sb.append(greeting).append(", ").append(name).append('é');
+        }
+        return sb.toString();
+    }
+
+    public static void main(String[] args) {
+        switch (args.length) {
+            case 0: System.out.println(new Hello("Hello").greet("world", (int) COUNT)); break;
+            case 1: System.out.println(args[0]); break;
+            case 2: System.out.println(args[1]); break;
+            default: System.out.println("\0 too many");
+        }
+    }
+}
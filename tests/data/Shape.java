@@ -0,0 +1,13 @@
+import java.util.List;
+
+public sealed interface Shape permits Shape.Circle, Shape.Square {
+    record Circle(@Deprecated double radius, List<String> tags) implements Shape {}
+
+    final class Square implements Shape {
+        private final int side;
+
+        Square(int side) {
+            this.side = side;
+        }
+    }
+}
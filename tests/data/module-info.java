@@ -0,0 +1,9 @@
+module com.example.app {
+    requires transitive java.logging;
+    requires static java.sql;
+    exports com.example.app;
+    exports com.example.internal to java.base;
+    opens com.example.internal;
+    uses java.lang.Runnable;
+    provides java.lang.Runnable with com.example.app.Main;
+}
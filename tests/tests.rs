@@ -0,0 +1,1643 @@
+extern crate classreader;
+
+use classreader::*;
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::Read;
+
+fn read_fixture(name: &str) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let mut file = File::open(format!("tests/data/{}", name)).unwrap();
+    file.read_to_end(&mut bytes).unwrap();
+    bytes
+}
+
+#[test]
+fn test_write_round_trip() {
+    let bytes = read_fixture("Hello.class");
+    let class = ClassReader::new_from_reader(&mut &bytes[..]).unwrap();
+
+    let mut written = Vec::new();
+    ClassWriter::write_to_writer(&class, &mut written).unwrap();
+
+    assert_eq!(bytes, written);
+    assert_eq!(class, ClassReader::new_from_reader(&mut &written[..]).unwrap());
+}
+
+fn method_code(class: &Class, name: &str) -> Vec<(u32, Instruction)> {
+    for method in class.methods.iter().filter(|method| method.name(&class.constant_pool).unwrap() == name) {
+        for attribute in &method.attributes {
+            if let Attribute::Code { ref code, .. } = *attribute {
+                return code.clone();
+            }
+        }
+    }
+    panic!("no code for method {}", name);
+}
+
+#[test]
+fn test_encode_relocates_branches() {
+    let bytes = read_fixture("Hello.class");
+    let class = ClassReader::new_from_reader(&mut &bytes[..]).unwrap();
+    let mut code = method_code(&class, "main");
+    code.insert(1, (1, Instruction::nop));
+
+    let decoded = decode_code(&encode_code(&code).unwrap()).unwrap();
+    assert_eq!(code.len(), decoded.len());
+
+    let new_pc = |old_pc: i32| code.iter().position(|&(pc, _)| pc as i32 == old_pc).map(|i| decoded[i].0 as i32);
+    for (&(old_pc, ref old), &(pc, ref new)) in code.iter().zip(decoded.iter()) {
+        match (old, new) {
+            (&Instruction::tableswitch(old_default, old_low, ref old_offsets),
+                    &Instruction::tableswitch(default, low, ref offsets)) => {
+                assert_eq!(old_low, low);
+                assert_eq!(new_pc(old_pc as i32 + old_default), Some(pc as i32 + default));
+                for (&old_offset, &offset) in old_offsets.iter().zip(offsets.iter()) {
+                    assert_eq!(new_pc(old_pc as i32 + old_offset), Some(pc as i32 + offset));
+                }
+            },
+            (&Instruction::goto(old_offset), &Instruction::goto(offset)) => {
+                assert_eq!(new_pc(old_pc as i32 + old_offset as i32), Some(pc as i32 + offset as i32));
+            },
+            _ => assert_eq!(old, new)
+        }
+    }
+
+    let (_, map) = relocate_code(&method_code(&class, "main")).unwrap();
+    assert!(map.is_unchanged());
+    let (relocated, map) = relocate_code(&code).unwrap();
+    assert!(!map.is_unchanged());
+    assert_eq!(map.pc(0), Some(0));
+    assert_eq!(map.pc(1), Some(relocated[1].0));
+    let &(last_pc, ref last) = code.last().unwrap();
+    let &(new_last_pc, _) = relocated.last().unwrap();
+    assert_eq!(map.pc(last_pc + last.length(last_pc)), Some(new_last_pc + last.length(new_last_pc)));
+
+    let exception = Exception { start_pc: 0, end_pc: 1, handler_pc: 1, catch_type: 0 };
+    let moved = map.exception(&exception).unwrap();
+    assert_eq!((moved.start_pc, moved.end_pc, moved.handler_pc), (0, 1, 1));
+    let line_number = LineNumber { start_pc: code[3].0 as u16, line_number: 7 };
+    assert_eq!(map.line_number(&line_number).unwrap().start_pc as u32, relocated[3].0);
+    let inside = code.iter().position(|&(pc, ref instruction)| instruction.length(pc) > 1).unwrap();
+    let line_number = LineNumber { start_pc: code[inside].0 as u16 + 1, line_number: 7 };
+    assert!(map.line_number(&line_number).is_err());
+}
+
+#[test]
+fn test_write_relocates_tables() {
+    let bytes = read_fixture("Hello.class");
+    let mut class = ClassReader::new_from_reader(&mut &bytes[..]).unwrap();
+    let greet = class.methods.iter().position(|method| method.name(&class.constant_pool).unwrap() == "greet").unwrap();
+    match class.methods[greet].attributes[0] {
+        Attribute::Code { ref mut code, ref mut exception_table, .. } => {
+            // A nop after astore_3, before the try block that starts at 8.
+            code.insert(4, (7, Instruction::nop));
+            exception_table.push(Exception { start_pc: 8, end_pc: 47, handler_pc: 47, catch_type: 0 });
+        },
+        ref other => panic!("{:?}", other)
+    }
+
+    let mut written = Vec::new();
+    match ClassWriter::write_to_writer(&class, &mut written) {
+        Result::Err(ParseError::Encode(_)) => {},
+        other => panic!("{:?}", other)
+    }
+
+    if let Attribute::Code { ref mut attributes, .. } = class.methods[greet].attributes[0] {
+        attributes.retain(|attribute| match *attribute { Attribute::StackMapTable(_) => false, _ => true });
+    }
+    let mut written = Vec::new();
+    ClassWriter::write_to_writer(&class, &mut written).unwrap();
+    let class = ClassReader::new_from_reader(&mut &written[..]).unwrap();
+    match class.methods[greet].attributes[0] {
+        Attribute::Code { ref code, ref exception_table, ref attributes, .. } => {
+            assert_eq!((8, Instruction::nop), code[4]);
+            assert_eq!((45, Instruction::goto(-33)), code[22]);
+            assert_eq!((9, 48, 48), (exception_table[0].start_pc, exception_table[0].end_pc, exception_table[0].handler_pc));
+            for attribute in attributes {
+                match *attribute {
+                    Attribute::LineNumberTable(ref entries) => {
+                        let pcs: Vec<u16> = entries.iter().map(|entry| entry.start_pc).collect();
+                        assert_eq!(vec![0, 9, 18, 42, 48], pcs);
+                    },
+                    Attribute::LocalVariableTable(ref entries) => {
+                        let ranges: Vec<(u16, u16)> = entries.iter().map(|entry| (entry.start_pc, entry.length)).collect();
+                        assert_eq!(vec![(12, 36), (0, 53), (0, 53), (0, 53), (9, 44)], ranges);
+                    },
+                    ref other => panic!("{:?}", other)
+                }
+            }
+        },
+        ref other => panic!("{:?}", other)
+    }
+}
+
+fn class_bytes(constant_pool: &[u8], constant_pool_count: u16) -> Vec<u8> {
+    let mut bytes = vec![0xCA, 0xFE, 0xBA, 0xBE, 0, 0, 0, 55];
+    bytes.push((constant_pool_count >> 8) as u8);
+    bytes.push(constant_pool_count as u8);
+    bytes.extend_from_slice(constant_pool);
+    bytes.extend_from_slice(&[0, 0x21, 0, 2, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0]);
+    bytes
+}
+
+#[test]
+fn test_constant_pool_dynamic_module_package() {
+    let constant_pool = [
+        1, 0, 1, b'A',                                          // #1 Utf8 A
+        7, 0, 1,                                                // #2 Class #1
+        1, 0, 16, b'j', b'a', b'v', b'a', b'/', b'l', b'a', b'n',
+        b'g', b'/', b'O', b'b', b'j', b'e', b'c', b't',        // #3 Utf8 java/lang/Object
+        7, 0, 3,                                                // #4 Class #3
+        17, 0, 0, 0, 6,                                         // #5 Dynamic #0:#6
+        12, 0, 7, 0, 8,                                         // #6 NameAndType #7:#8
+        1, 0, 1, b'x',                                          // #7 Utf8 x
+        1, 0, 1, b'I',                                          // #8 Utf8 I
+        19, 0, 7,                                               // #9 Module #7
+        20, 0, 7                                                // #10 Package #7
+    ];
+    let bytes = class_bytes(&constant_pool, 11);
+    let class = ClassReader::new_from_reader(&mut &bytes[..]).unwrap();
+
+    assert_eq!(ConstantPoolInfo::Dynamic(0, 6), *class.constant_pool.get(5).unwrap());
+    assert_eq!(ConstantPoolInfo::Module(7), *class.constant_pool.get(9).unwrap());
+    assert_eq!(ConstantPoolInfo::Package(7), *class.constant_pool.get(10).unwrap());
+    assert!(class.constant_pool.get(5).unwrap().is_loadable());
+    assert!(!class.constant_pool.get(9).unwrap().is_loadable());
+
+    let mut written = Vec::new();
+    ClassWriter::write_to_writer(&class, &mut written).unwrap();
+    assert_eq!(bytes, written);
+}
+
+#[test]
+fn test_constant_pool_unknown_tag() {
+    let bytes = class_bytes(&[1, 0, 1, b'A', 21, 0, 1], 3);
+    match ClassReader::new_from_reader(&mut &bytes[..]) {
+        Result::Err(ParseError::Format(message)) => assert_eq!("unknown constant pool tag 21 at index 2", message),
+        other => panic!("unexpected result {:?}", other)
+    }
+}
+
+#[test]
+fn test_module_descriptor() {
+    let bytes = read_fixture("module-info.class");
+    let class = ClassReader::new_from_reader(&mut &bytes[..]).unwrap();
+    let module = class.module().unwrap().unwrap();
+
+    assert_eq!("com.example.app", module.name);
+    assert_eq!(Some("1.2".to_string()), module.version);
+    assert!(!module.is_open());
+
+    let requires: Vec<&str> = module.requires.iter().map(|r| r.name.as_str()).collect();
+    assert_eq!(vec!["java.base", "java.logging", "java.sql"], requires);
+    assert!(module.requires[1].is_transitive());
+    assert!(module.requires[2].is_static_phase());
+
+    assert_eq!(vec!["com/example/app"], module.exported_packages());
+    assert_eq!(vec!["java.base".to_string()], module.exports[1].to);
+    assert_eq!("com/example/internal", module.opens[0].package);
+    assert_eq!(vec!["java/lang/Runnable".to_string()], module.uses);
+    assert_eq!("java/lang/Runnable", module.provides[0].service);
+    assert_eq!(vec!["com/example/app/Main".to_string()], module.provides[0].implementations);
+    assert_eq!(vec!["com/example/app".to_string(), "com/example/internal".to_string()], module.packages);
+    assert_eq!(Some("com/example/app/Main".to_string()), module.main_class);
+
+    let mut written = Vec::new();
+    ClassWriter::write_to_writer(&class, &mut written).unwrap();
+    assert_eq!(bytes, written);
+}
+
+#[test]
+fn test_sealed_nest_host() {
+    let class = ClassReader::new_from_path("tests/data/Shape.class").unwrap();
+    assert!(class.is_sealed());
+    assert!(!class.is_record());
+    assert_eq!(vec!["Shape$Circle".to_string(), "Shape$Square".to_string()], class.permitted_subclasses().unwrap());
+    assert_eq!(vec!["Shape$Square".to_string(), "Shape$Circle".to_string()], class.nest_members().unwrap());
+    assert_eq!(None, class.nest_host().unwrap());
+}
+
+#[test]
+fn test_record() {
+    let class = ClassReader::new_from_path("tests/data/Shape$Circle.class").unwrap();
+    assert!(class.is_record());
+    assert!(!class.is_sealed());
+    assert_eq!(Some("Shape".to_string()), class.nest_host().unwrap());
+
+    let components = class.record_components().unwrap();
+    assert_eq!(2, components.len());
+    match *class.constant_pool.get(components[1].name_index).unwrap() {
+        ConstantPoolInfo::Utf8(ref name) => assert_eq!("tags", name),
+        ref other => panic!("unexpected constant {:?}", other)
+    }
+    match components[1].attributes[0] {
+        Attribute::Signature { signature_index } => {
+            assert_eq!(ConstantPoolInfo::Utf8(JavaString::from("Ljava/util/List<Ljava/lang/String;>;")),
+                    *class.constant_pool.get(signature_index).unwrap());
+        },
+        ref other => panic!("unexpected attribute {:?}", other)
+    }
+}
+
+fn opcode_corpus() -> Vec<(&'static [u8], Instruction)> {
+    use classreader::Instruction::*;
+    vec![
+        (&[0x32], aaload),
+        (&[0x53], aastore),
+        (&[0x01], aconst_null),
+        (&[0x19, 0xfa], aload(250)),
+        (&[0x2a], aload_0),
+        (&[0x2b], aload_1),
+        (&[0x2c], aload_2),
+        (&[0x2d], aload_3),
+        (&[0xbd, 0x12, 0x34], anewarray(0x1234)),
+        (&[0xb0], areturn),
+        (&[0xbe], arraylength),
+        (&[0x3a, 0xfa], astore(250)),
+        (&[0x4b], astore_0),
+        (&[0x4c], astore_1),
+        (&[0x4d], astore_2),
+        (&[0x4e], astore_3),
+        (&[0xbf], athrow),
+        (&[0x33], baload),
+        (&[0x54], bastore),
+        (&[0x10, 0xfe], bipush(-2)),
+        (&[0x34], caload),
+        (&[0x55], castore),
+        (&[0xc0, 0x12, 0x34], checkcast(0x1234)),
+        (&[0x90], d2f),
+        (&[0x8e], d2i),
+        (&[0x8f], d2l),
+        (&[0x63], dadd),
+        (&[0x31], daload),
+        (&[0x52], dastore),
+        (&[0x98], dcmpg),
+        (&[0x97], dcmpl),
+        (&[0x0e], dconst_0),
+        (&[0x0f], dconst_1),
+        (&[0x6f], ddiv),
+        (&[0x18, 0xfa], dload(250)),
+        (&[0x26], dload_0),
+        (&[0x27], dload_1),
+        (&[0x28], dload_2),
+        (&[0x29], dload_3),
+        (&[0x6b], dmul),
+        (&[0x77], dneg),
+        (&[0x73], drem),
+        (&[0xaf], dreturn),
+        (&[0x39, 0xfa], dstore(250)),
+        (&[0x47], dstore_0),
+        (&[0x48], dstore_1),
+        (&[0x49], dstore_2),
+        (&[0x4a], dstore_3),
+        (&[0x67], dsub),
+        (&[0x59], dup),
+        (&[0x5a], dup_x1),
+        (&[0x5b], dup_x2),
+        (&[0x5c], dup2),
+        (&[0x5d], dup2_x1),
+        (&[0x5e], dup2_x2),
+        (&[0x8d], f2d),
+        (&[0x8b], f2i),
+        (&[0x8c], f2l),
+        (&[0x62], fadd),
+        (&[0x30], faload),
+        (&[0x51], fastore),
+        (&[0x96], fcmpg),
+        (&[0x95], fcmpl),
+        (&[0x0b], fconst_0),
+        (&[0x0c], fconst_1),
+        (&[0x0d], fconst_2),
+        (&[0x6e], fdiv),
+        (&[0x17, 0xfa], fload(250)),
+        (&[0x22], fload_0),
+        (&[0x23], fload_1),
+        (&[0x24], fload_2),
+        (&[0x25], fload_3),
+        (&[0x6a], fmul),
+        (&[0x76], fneg),
+        (&[0x72], frem),
+        (&[0xae], freturn),
+        (&[0x38, 0xfa], fstore(250)),
+        (&[0x43], fstore_0),
+        (&[0x44], fstore_1),
+        (&[0x45], fstore_2),
+        (&[0x46], fstore_3),
+        (&[0x66], fsub),
+        (&[0xb4, 0x12, 0x34], getfield(0x1234)),
+        (&[0xb2, 0x12, 0x34], getstatic(0x1234)),
+        (&[0xa7, 0, 0], goto(0)),
+        (&[0xc8, 0, 0, 0, 0], goto_w(0)),
+        (&[0x91], i2b),
+        (&[0x92], i2c),
+        (&[0x87], i2d),
+        (&[0x86], i2f),
+        (&[0x85], i2l),
+        (&[0x93], i2s),
+        (&[0x60], iadd),
+        (&[0x2e], iaload),
+        (&[0x7e], iand),
+        (&[0x4f], iastore),
+        (&[0x02], iconst_m1),
+        (&[0x03], iconst_0),
+        (&[0x04], iconst_1),
+        (&[0x05], iconst_2),
+        (&[0x06], iconst_3),
+        (&[0x07], iconst_4),
+        (&[0x08], iconst_5),
+        (&[0x6c], idiv),
+        (&[0xa5, 0, 0], if_acmpeq(0)),
+        (&[0xa6, 0, 0], if_acmpne(0)),
+        (&[0x9f, 0, 0], if_icmpeq(0)),
+        (&[0xa0, 0, 0], if_icmpne(0)),
+        (&[0xa1, 0, 0], if_icmplt(0)),
+        (&[0xa2, 0, 0], if_icmpge(0)),
+        (&[0xa3, 0, 0], if_icmpgt(0)),
+        (&[0xa4, 0, 0], if_icmple(0)),
+        (&[0x99, 0, 0], ifeq(0)),
+        (&[0x9a, 0, 0], ifne(0)),
+        (&[0x9b, 0, 0], iflt(0)),
+        (&[0x9c, 0, 0], ifge(0)),
+        (&[0x9d, 0, 0], ifgt(0)),
+        (&[0x9e, 0, 0], ifle(0)),
+        (&[0xc7, 0, 0], ifnonnull(0)),
+        (&[0xc6, 0, 0], ifnull(0)),
+        (&[0x84, 7, 0xff], iinc(7, -1)),
+        (&[0x15, 0xfa], iload(250)),
+        (&[0x1a], iload_0),
+        (&[0x1b], iload_1),
+        (&[0x1c], iload_2),
+        (&[0x1d], iload_3),
+        (&[0x68], imul),
+        (&[0x74], ineg),
+        (&[0xc1, 0x12, 0x34], instanceof(0x1234)),
+        (&[0xba, 0x12, 0x34, 0, 0], invokedynamic(0x1234)),
+        (&[0xb9, 0x12, 0x34, 2, 0], invokeinterface(0x1234, 2)),
+        (&[0xb7, 0x12, 0x34], invokespecial(0x1234)),
+        (&[0xb8, 0x12, 0x34], invokestatic(0x1234)),
+        (&[0xb6, 0x12, 0x34], invokevirtual(0x1234)),
+        (&[0x80], ior),
+        (&[0x70], irem),
+        (&[0xac], ireturn),
+        (&[0x78], ishl),
+        (&[0x7a], ishr),
+        (&[0x36, 0xfa], istore(250)),
+        (&[0x3b], istore_0),
+        (&[0x3c], istore_1),
+        (&[0x3d], istore_2),
+        (&[0x3e], istore_3),
+        (&[0x64], isub),
+        (&[0x7c], iushr),
+        (&[0x82], ixor),
+        (&[0xa8, 0, 0], jsr(0)),
+        (&[0xc9, 0, 0, 0, 0], jsr_w(0)),
+        (&[0x8a], l2d),
+        (&[0x89], l2f),
+        (&[0x88], l2i),
+        (&[0x61], ladd),
+        (&[0x2f], laload),
+        (&[0x7f], land),
+        (&[0x50], lastore),
+        (&[0x94], lcmp),
+        (&[0x09], lconst_0),
+        (&[0x0a], lconst_1),
+        (&[0x12, 0xfa], ldc(250)),
+        (&[0x13, 0x12, 0x34], ldc_w(0x1234)),
+        (&[0x14, 0x12, 0x34], ldc2_w(0x1234)),
+        (&[0x6d], ldiv),
+        (&[0x16, 0xfa], lload(250)),
+        (&[0x1e], lload_0),
+        (&[0x1f], lload_1),
+        (&[0x20], lload_2),
+        (&[0x21], lload_3),
+        (&[0x69], lmul),
+        (&[0x75], lneg),
+        (&[0xab, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0xff, 0xff, 0xff, 0xfe, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0], lookupswitch(0, vec![(-2, 0), (1, 0)].into_boxed_slice())),
+        (&[0x81], lor),
+        (&[0x71], lrem),
+        (&[0xad], lreturn),
+        (&[0x79], lshl),
+        (&[0x7b], lshr),
+        (&[0x37, 0xfa], lstore(250)),
+        (&[0x3f], lstore_0),
+        (&[0x40], lstore_1),
+        (&[0x41], lstore_2),
+        (&[0x42], lstore_3),
+        (&[0x65], lsub),
+        (&[0x7d], lushr),
+        (&[0x83], lxor),
+        (&[0xc2], monitorenter),
+        (&[0xc3], monitorexit),
+        (&[0xc5, 0x12, 0x34, 3], multianewarray(0x1234, 3)),
+        (&[0xbb, 0x12, 0x34], new(0x1234)),
+        (&[0xbc, 10], newarray(ArrayType::Int)),
+        (&[0x00], nop),
+        (&[0x57], pop),
+        (&[0x58], pop2),
+        (&[0xb5, 0x12, 0x34], putfield(0x1234)),
+        (&[0xb3, 0x12, 0x34], putstatic(0x1234)),
+        (&[0xa9, 0xfa], ret(250)),
+        (&[0xb1], return_),
+        (&[0x35], saload),
+        (&[0x56], sastore),
+        (&[0x11, 0xfe, 0xdc], sipush(-292)),
+        (&[0x5f], swap),
+        (&[0xaa, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0], tableswitch(0, -1, vec![0, 0].into_boxed_slice())),
+        (&[0xc4, 0x15, 0x12, 0x34], iload_w(0x1234)),
+        (&[0xc4, 0x16, 0x12, 0x34], lload_w(0x1234)),
+        (&[0xc4, 0x17, 0x12, 0x34], fload_w(0x1234)),
+        (&[0xc4, 0x18, 0x12, 0x34], dload_w(0x1234)),
+        (&[0xc4, 0x19, 0x12, 0x34], aload_w(0x1234)),
+        (&[0xc4, 0x36, 0x12, 0x34], istore_w(0x1234)),
+        (&[0xc4, 0x37, 0x12, 0x34], lstore_w(0x1234)),
+        (&[0xc4, 0x38, 0x12, 0x34], fstore_w(0x1234)),
+        (&[0xc4, 0x39, 0x12, 0x34], dstore_w(0x1234)),
+        (&[0xc4, 0x3a, 0x12, 0x34], astore_w(0x1234)),
+        (&[0xc4, 0xa9, 0x12, 0x34], ret_w(0x1234)),
+        (&[0xc4, 0x84, 0x12, 0x34, 0xff, 0x00], iinc_w(0x1234, -256))
+    ]
+}
+
+#[test]
+fn test_decode_every_opcode() {
+    for (bytes, instruction) in opcode_corpus() {
+        assert_eq!(vec![(0, instruction)], decode_code(&bytes.to_vec()).unwrap());
+        assert_eq!(bytes.to_vec(), encode_code(&decode_code(&bytes.to_vec()).unwrap()).unwrap());
+    }
+}
+
+#[test]
+fn test_decode_instruction_lengths() {
+    let mut bytes = Vec::new();
+    let mut expected = Vec::new();
+    for (encoded, instruction) in opcode_corpus() {
+        match instruction {
+            Instruction::tableswitch(..) | Instruction::lookupswitch(..) => continue,
+            _ => {}
+        }
+        expected.push((bytes.len() as u32, instruction));
+        bytes.extend_from_slice(encoded);
+    }
+    assert_eq!(expected, decode_code(&bytes).unwrap());
+}
+
+#[test]
+fn test_decode_reserved_bytes() {
+    assert!(decode_code(&vec![0xb9, 0, 1, 1, 1]).is_err());
+    assert!(decode_code(&vec![0xb9, 0, 1, 0, 0]).is_err());
+    assert!(decode_code(&vec![0xba, 0, 1, 0, 1]).is_err());
+    assert_eq!(vec![(0, Instruction::invokedynamic(1)), (5, Instruction::return_)],
+            decode_code(&vec![0xba, 0, 1, 0, 0, 0xb1]).unwrap());
+}
+
+#[test]
+fn test_decode_truncated_instructions() {
+    for (bytes, _) in opcode_corpus() {
+        for length in 1..bytes.len() {
+            match decode_code(&bytes[..length].to_vec()) {
+                Result::Err(ParseError::Decode(message)) => assert_eq!("truncated instruction at 0", message),
+                other => panic!("unexpected result {:?} for {:?}", other, &bytes[..length])
+            }
+        }
+    }
+}
+
+#[test]
+fn test_decode_malformed_switches() {
+    // tableswitch at 1 with low 1 and high 0
+    let bytes = vec![0x00, 0xaa, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0];
+    match decode_code(&bytes) {
+        Result::Err(ParseError::Decode(message)) => assert_eq!("tableswitch at 1 with low 1 greater than high 0", message),
+        other => panic!("unexpected result {:?}", other)
+    }
+    // tableswitch claiming 2^32 offsets and lookupswitch claiming 2^31 - 1 pairs
+    assert!(decode_code(&vec![0xaa, 0, 0, 0, 0, 0, 0, 0, 0x80, 0, 0, 0, 0x7f, 0xff, 0xff, 0xff]).is_err());
+    assert!(decode_code(&vec![0xab, 0, 0, 0, 0, 0, 0, 0, 0x7f, 0xff, 0xff, 0xff]).is_err());
+    assert!(decode_code(&vec![0xab, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, 0xff, 0xff]).is_err());
+}
+
+#[test]
+fn test_decode_random_bytes() {
+    let mut seed: u32 = 0x2545_f491;
+    for _ in 0..2000 {
+        let mut bytes = Vec::new();
+        for _ in 0..(seed % 48) {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            bytes.push((seed >> 24) as u8);
+        }
+        let _ = decode_code(&bytes);
+        seed = seed.wrapping_add(1);
+    }
+}
+
+#[test]
+fn test_float_and_double_bits() {
+    let constant_pool = [
+        1, 0, 1, b'A',                                          // #1 Utf8 A
+        7, 0, 1,                                                // #2 Class #1
+        4, 0x7f, 0xc0, 0x00, 0x01,                              // #3 Float NaN with payload
+        7, 0, 1,                                                // #4 Class #1
+        4, 0x3f, 0xc0, 0x00, 0x00,                              // #5 Float 1.5
+        6, 0x80, 0, 0, 0, 0, 0, 0, 0,                           // #6 Double -0.0
+        6, 0x7f, 0xf0, 0, 0, 0, 0, 0, 0x2a                      // #8 Double NaN with payload
+    ];
+    let bytes = class_bytes(&constant_pool, 10);
+    let class = ClassReader::new_from_reader(&mut &bytes[..]).unwrap();
+
+    match *class.constant_pool.get(3).unwrap() {
+        ConstantPoolInfo::Float(value) => assert_eq!(0x7fc0_0001, value.to_bits()),
+        ref other => panic!("unexpected constant {:?}", other)
+    }
+    assert_eq!(ConstantPoolInfo::Float(1.5), *class.constant_pool.get(5).unwrap());
+    assert_eq!(ConstantPoolInfo::Double(-0.0), *class.constant_pool.get(6).unwrap());
+    assert!(ConstantPoolInfo::Double(0.0) != *class.constant_pool.get(6).unwrap());
+    match *class.constant_pool.get(8).unwrap() {
+        ConstantPoolInfo::Double(value) => assert_eq!(0x7ff0_0000_0000_002a, value.to_bits()),
+        ref other => panic!("unexpected constant {:?}", other)
+    }
+
+    let mut written = Vec::new();
+    ClassWriter::write_to_writer(&class, &mut written).unwrap();
+    assert_eq!(bytes, written);
+    assert_eq!(class, ClassReader::new_from_reader(&mut &written[..]).unwrap());
+}
+
+#[test]
+fn test_field_constant_values() {
+    let class = ClassReader::new_from_path("tests/data/Constants.class").unwrap();
+    let values: Vec<ConstantValue> = class.fields.iter()
+        .map(|field| field.constant_value(&class.constant_pool).unwrap().unwrap())
+        .collect();
+    assert_eq!(vec![
+        ConstantValue::Boolean(true),
+        ConstantValue::Byte(-3),
+        ConstantValue::Char('x' as u16),
+        ConstantValue::Short(1000),
+        ConstantValue::Int(123456),
+        ConstantValue::Long(-1),
+        ConstantValue::Float(1.5),
+        ConstantValue::Double(-0.0),
+        ConstantValue::String(JavaString::from("constant"))
+    ], values);
+    assert!(ConstantValue::Double(0.0) != values[7]);
+    assert_eq!(ConstantValue::Float(std::f32::NAN), ConstantValue::Float(std::f32::NAN));
+    assert!(ConstantValue::Float(std::f32::NAN) != ConstantValue::Float(-std::f32::NAN));
+}
+
+#[test]
+fn test_java_string() {
+    let nul = JavaString::from("a\u{0}b");
+    assert_eq!(&[b'a', 0xC0, 0x80, b'b'], nul.as_modified_utf8());
+    assert_eq!("a\u{0}b", nul.try_to_str().unwrap());
+
+    let emoji = JavaString::from("\u{1F600}");
+    assert_eq!(&[0xED, 0xA0, 0xBD, 0xED, 0xB8, 0x80], emoji.as_modified_utf8());
+    assert_eq!(vec![0xD83D, 0xDE00], emoji.to_utf16().unwrap());
+    assert_eq!("\u{1F600}", emoji.try_to_str().unwrap());
+    assert_eq!(emoji, JavaString::from_utf16(&[0xD83D, 0xDE00]));
+
+    let unpaired = JavaString::from_utf16(&[b'x' as u16, 0xD800]);
+    assert_eq!(&[b'x', 0xED, 0xA0, 0x80], unpaired.as_modified_utf8());
+    assert!(unpaired.try_to_str().is_err());
+    assert_eq!("x\u{FFFD}", unpaired.to_string_lossy());
+
+    let truncated = JavaString::from_modified_utf8(vec![b'y', 0xE2, 0x82]);
+    assert!(truncated.to_utf16().is_err());
+    assert_eq!("y\u{FFFD}\u{FFFD}", truncated.to_string_lossy());
+
+    let ascii = JavaString::from("java/lang/Object");
+    assert_eq!("java/lang/Object", ascii);
+    assert_eq!("java/lang/Object", ascii.to_string());
+}
+
+#[test]
+fn test_java_string_round_trip() {
+    let constant_pool = [
+        1, 0, 1, b'A',                                          // #1 Utf8 A
+        7, 0, 1,                                                // #2 Class #1
+        1, 0, 4, b'x', 0xED, 0xB0, 0x80,                        // #3 Utf8 x with unpaired low surrogate
+        7, 0, 3,                                                // #4 Class #3
+        1, 0, 2, 0xC0, 0x80                                     // #5 Utf8 NUL
+    ];
+    let bytes = class_bytes(&constant_pool, 6);
+    let class = ClassReader::new_from_reader(&mut &bytes[..]).unwrap();
+
+    assert_eq!(ConstantPoolInfo::Utf8(JavaString::from_utf16(&[b'x' as u16, 0xDC00])), *class.constant_pool.get(3).unwrap());
+    assert_eq!(ConstantPoolInfo::Utf8(JavaString::from("\u{0}")), *class.constant_pool.get(5).unwrap());
+
+    let mut written = Vec::new();
+    ClassWriter::write_to_writer(&class, &mut written).unwrap();
+    assert_eq!(bytes, written);
+}
+
+#[test]
+fn test_constant_pool_resolution() {
+    let class = ClassReader::new_from_path("tests/data/Hello.class").unwrap();
+    let pool = &class.constant_pool;
+
+    assert_eq!(MemberRef {
+        class_name: "java/lang/Object".to_string(),
+        name: "<init>".to_string(),
+        descriptor: "()V".to_string()
+    }, pool.member_ref(1).unwrap());
+    assert_eq!(MemberRef {
+        class_name: "Hello".to_string(),
+        name: "greeting".to_string(),
+        descriptor: "Ljava/lang/String;".to_string()
+    }, pool.member_ref(7).unwrap());
+    assert_eq!(("<init>".to_string(), "()V".to_string()), pool.name_and_type(3).unwrap());
+    assert_eq!("java/lang/Object", pool.class_name(2).unwrap());
+    assert_eq!("<init>", *pool.utf8(5).unwrap());
+    assert_eq!(Some(5), pool.find_utf8("<init>"));
+
+    assert!(pool.get(0).is_err());
+    assert!(pool.get(pool.len() as u16 + 1).is_err());
+    assert!(pool.get(pool.len() as u16).is_ok());
+    assert!(pool.utf8(1).is_err());
+    assert!(pool.member_ref(3).is_err());
+    assert!(pool.class_name(5).is_err());
+}
+
+#[test]
+fn test_constant_pool_long_second_slot() {
+    let mut pool = ConstantPool::default();
+    assert_eq!(1, pool.push(ConstantPoolInfo::Long(3)).unwrap());
+    assert_eq!(3, pool.push(ConstantPoolInfo::Integer(4)).unwrap());
+    assert_eq!(3, pool.len());
+    assert_eq!(ConstantPoolInfo::Long(3), *pool.get(1).unwrap());
+    assert!(pool.get(2).is_err());
+    assert_eq!(ConstantPoolInfo::Integer(4), *pool.get(3).unwrap());
+
+    while pool.len() < 65533 {
+        pool.push(ConstantPoolInfo::Integer(5)).unwrap();
+    }
+    assert!(pool.push(ConstantPoolInfo::Long(6)).is_err());
+    assert_eq!(65534, pool.push(ConstantPoolInfo::Integer(6)).unwrap());
+    assert!(pool.push(ConstantPoolInfo::Integer(7)).is_err());
+    assert_eq!(65534, pool.len());
+}
+
+#[test]
+fn test_resolved_names() {
+    let class = ClassReader::new_from_path("tests/data/Hello.class").unwrap();
+    assert_eq!("Hello", class.name().unwrap());
+    assert_eq!(Some("java/lang/Object".to_string()), class.super_name().unwrap());
+    assert!(class.interface_names().unwrap().is_empty());
+
+    let greet = class.find_method("greet", "(Ljava/lang/String;I)Ljava/lang/String;").unwrap();
+    assert_eq!("greet", greet.name(&class.constant_pool).unwrap());
+    assert_eq!("(Ljava/lang/String;I)Ljava/lang/String;", greet.descriptor(&class.constant_pool).unwrap());
+    assert!(class.find_method("greet", "()V").is_none());
+    assert!(class.find_method("main", "([Ljava/lang/String;)V").unwrap().is_static());
+
+    let count = class.find_field("COUNT").unwrap();
+    assert_eq!("J", count.descriptor(&class.constant_pool).unwrap());
+    assert!(class.find_field("missing").is_none());
+
+    let shape = ClassReader::new_from_path("tests/data/Shape$Circle.class").unwrap();
+    assert_eq!(vec!["Shape".to_string()], shape.interface_names().unwrap());
+
+    let module = ClassReader::new_from_path("tests/data/module-info.class").unwrap();
+    assert_eq!(None, module.super_name().unwrap());
+}
+
+#[test]
+fn test_descriptors() {
+    let descriptor = MethodDescriptor::parse("([Ljava/lang/String;JI[[D)V").unwrap();
+    assert_eq!(vec![
+        FieldType::Array(Box::new(FieldType::Object("java/lang/String".to_string()))),
+        FieldType::Base(BaseType::Long),
+        FieldType::Base(BaseType::Int),
+        FieldType::Array(Box::new(FieldType::Array(Box::new(FieldType::Base(BaseType::Double)))))
+    ], descriptor.parameters);
+    assert_eq!(None, descriptor.return_type);
+    assert_eq!(5, descriptor.parameter_slots());
+    assert_eq!(0, descriptor.return_slots());
+    assert_eq!("([Ljava/lang/String;JI[[D)V", descriptor.to_string());
+    assert_eq!("void main(java.lang.String[], long, int, double[][])", descriptor.to_java_source("main"));
+
+    let field_type = FieldType::parse("Ljava/util/Map$Entry;").unwrap();
+    assert_eq!("java.util.Map$Entry", field_type.to_java_source());
+    assert_eq!(2, FieldType::parse("D").unwrap().size());
+    assert_eq!(2, MethodDescriptor::parse("()J").unwrap().return_slots());
+
+    for &(invalid, position) in &[("", 0), ("V", 0), ("Q", 0), ("L;", 1), ("Ljava//Object;", 6),
+            ("Ljava/lang/Object", 17), ("II", 1), ("[", 1), ("La.b;", 2)] {
+        let message = format!("{:?}", FieldType::parse(invalid).unwrap_err());
+        assert!(message.contains(&format!("at position {}:", position)), "{} {}", invalid, message);
+    }
+    for &(invalid, position) in &[("V", 0), ("()", 2), ("(V)V", 1), ("(I)VV", 4), ("(I", 2)] {
+        let message = format!("{:?}", MethodDescriptor::parse(invalid).unwrap_err());
+        assert!(message.contains(&format!("at position {}:", position)), "{} {}", invalid, message);
+    }
+    assert_eq!(255, FieldType::parse(&format!("{}I", "[".repeat(255))).unwrap().dimensions());
+    assert!(FieldType::parse(&format!("{}I", "[".repeat(256))).is_err());
+    assert!(FieldType::parse(&format!("{}I", "[".repeat(60000))).is_err());
+    assert!(MethodDescriptor::parse(&format!("({}I)V", "[".repeat(60000))).is_err());
+    assert_eq!(254, MethodDescriptor::parse(&format!("({})V", "J".repeat(127))).unwrap().parameter_slots());
+    assert!(MethodDescriptor::parse(&format!("({}I)V", "J".repeat(128))).is_err());
+    assert!(MethodDescriptor::parse(&format!("({})V", "J".repeat(40000))).is_err());
+
+    let class = ClassReader::new_from_path("tests/data/Hello.class").unwrap();
+    let greet = class.find_method("greet", "(Ljava/lang/String;I)Ljava/lang/String;").unwrap();
+    assert_eq!(2, greet.method_descriptor(&class.constant_pool).unwrap().parameter_slots());
+    let count = class.find_field("COUNT").unwrap();
+    assert_eq!(FieldType::Base(BaseType::Long), count.field_type(&class.constant_pool).unwrap());
+}
+
+#[test]
+fn test_signatures() {
+    let signature = "<K:Ljava/lang/Object;V::Ljava/lang/Comparable<-TV;>;>Ljava/util/AbstractMap<TK;TV;>;Ljava/io/Serializable;";
+    let class_signature = ClassSignature::parse(signature).unwrap();
+    assert_eq!(signature, class_signature.to_string());
+    assert_eq!(2, class_signature.type_parameters.len());
+    assert_eq!(None, class_signature.type_parameters[1].class_bound);
+    assert_eq!("java/util", class_signature.superclass.package);
+    assert_eq!("<K extends java.lang.Object, V extends java.lang.Comparable<? super V>> \
+            extends java.util.AbstractMap<K, V> implements java.io.Serializable",
+            class_signature.to_java_source());
+
+    let signature = "<T:Ljava/lang/Object;E:Ljava/lang/Exception;>(Ljava/util/List<+TT;>;[I)Ljava/util/Map$Entry<*TT;>;^TE;^Ljava/io/IOException;";
+    let method_signature = MethodSignature::parse(signature).unwrap();
+    assert_eq!(signature, method_signature.to_string());
+    assert_eq!(2, method_signature.throws.len());
+    assert_eq!("<T extends java.lang.Object, E extends java.lang.Exception> java.util.Map$Entry<?, T> \
+            copy(java.util.List<? extends T>, int[]) throws E, java.io.IOException",
+            method_signature.to_java_source("copy"));
+
+    let signature = "Lpkg/Outer<Ljava/lang/String;>.Inner<[TT;>.Deepest;";
+    let field_signature = ReferenceTypeSignature::parse(signature).unwrap();
+    assert_eq!(signature, field_signature.to_string());
+    assert_eq!("pkg.Outer<java.lang.String>.Inner<T[]>.Deepest", field_signature.to_java_source());
+    match field_signature {
+        ReferenceTypeSignature::Class(ref class_type) => {
+            assert_eq!("Outer", class_type.class.name);
+            assert_eq!(vec!["Inner", "Deepest"], class_type.inner.iter().map(|inner| inner.name.as_str()).collect::<Vec<_>>());
+        },
+        ref other => panic!("unexpected signature {:?}", other)
+    }
+    assert_eq!(ReferenceTypeSignature::TypeVariable("T".to_string()), ReferenceTypeSignature::parse("TT;").unwrap());
+
+    for invalid in &["", "I", "Ljava/lang/Object", "TT", "L;", "Ljava//Object;", "Ljava/util/List<>;", "TT;TT;"] {
+        assert!(ReferenceTypeSignature::parse(invalid).is_err(), "{}", invalid);
+    }
+    for invalid in &["<>Ljava/lang/Object;", "<T>Ljava/lang/Object;", "Ljava/lang/Object;I"] {
+        assert!(ClassSignature::parse(invalid).is_err(), "{}", invalid);
+    }
+    for invalid in &["()", "(V)V", "()V^I", "<T:I>()V"] {
+        assert!(MethodSignature::parse(invalid).is_err(), "{}", invalid);
+    }
+    let message = format!("{:?}", MethodSignature::parse("(Ljava/util/List<>;)V").unwrap_err());
+    assert!(message.contains("at position 17:"), "{}", message);
+
+    let array = ReferenceTypeSignature::parse(&format!("{}I", "[".repeat(255))).unwrap();
+    assert_eq!(format!("int{}", "[]".repeat(255)), array.to_java_source());
+    assert!(ReferenceTypeSignature::parse(&format!("{}I", "[".repeat(256))).is_err());
+    assert!(MethodSignature::parse(&format!("({}I)V", "[".repeat(60000))).is_err());
+    let nested = |depth: usize| format!("Ljava/lang/Object;{}Ljava/lang/String;{}", "Ljava/util/List<".repeat(depth), ">;".repeat(depth));
+    assert!(ClassSignature::parse(&nested(64)).is_ok());
+    assert!(ClassSignature::parse(&nested(65)).is_err());
+    assert!(ClassSignature::parse(&nested(60000)).is_err());
+
+    let class = ClassReader::new_from_path("tests/data/Shape$Circle.class").unwrap();
+    let record = class.record_components().unwrap();
+    let tags = &record[1];
+    assert_eq!("java.util.List<java.lang.String>", tags.attributes.iter().filter_map(|attribute| match *attribute {
+        Attribute::Signature { signature_index } => Some(ReferenceTypeSignature::parse(&class.constant_pool.utf8_string(signature_index).unwrap()).unwrap()),
+        _ => None
+    }).next().unwrap().to_java_source());
+    let field = class.find_field("tags").unwrap();
+    assert!(field.signature(&class.constant_pool).unwrap().is_some());
+    assert_eq!(None, class.signature().unwrap());
+}
+
+#[test]
+fn test_access_flags() {
+    let native = Method { access_flags: 0x0101, name_index: 0, descriptor_index: 0, attributes: vec![] };
+    assert!(native.is_native());
+    assert!(!native.is_synthetic());
+    let synthetic = Method { access_flags: 0x1000, name_index: 0, descriptor_index: 0, attributes: vec![] };
+    assert!(!synthetic.is_native());
+    assert_eq!(MethodAccess::PUBLIC | MethodAccess::NATIVE, native.access());
+
+    let access = FieldAccess::from_bits(0x0019);
+    assert_eq!("public static final", access.to_string());
+    assert_eq!(vec![FieldAccess::PUBLIC, FieldAccess::STATIC, FieldAccess::FINAL], access.iter().collect::<Vec<_>>());
+    assert_eq!("FieldAccess(PUBLIC | STATIC | FINAL)", format!("{:?}", access));
+    assert!(access.contains(FieldAccess::STATIC | FieldAccess::FINAL));
+    assert!(!access.contains(FieldAccess::PRIVATE | FieldAccess::FINAL));
+    assert_eq!(FieldAccess::PUBLIC | FieldAccess::FINAL, access - FieldAccess::STATIC);
+
+    let unknown = ClassAccess::from_bits(0x0021 | 0x0100);
+    assert_eq!(0x0100, unknown.unknown_bits());
+    assert_eq!(0x0121, unknown.bits());
+    assert_eq!("public", unknown.to_string());
+    assert_eq!("ClassAccess(PUBLIC | SUPER | 0x0100)", format!("{:?}", unknown));
+    assert_eq!("abstract static", (MethodAccess::STATIC | MethodAccess::ABSTRACT | MethodAccess::SYNTHETIC).to_string());
+    assert_eq!("transitive static", (RequiresAccess::STATIC_PHASE | RequiresAccess::TRANSITIVE).to_string());
+
+    assert!(ClassAccess::from_bits(0x0601).check().is_ok());
+    assert!(ClassAccess::from_bits(0x0201).check().is_err());
+    assert!(ClassAccess::from_bits(0x0411).check().is_err());
+    assert!(ClassAccess::from_bits(0x2001).check().is_err());
+    assert!(ClassAccess::from_bits(0x8001).check().is_err());
+    assert!(ClassAccess::MODULE.check().is_ok());
+    assert!(FieldAccess::from_bits(0x0003).check(false).is_err());
+    assert!(FieldAccess::from_bits(0x0050).check(false).is_err());
+    assert!(FieldAccess::from_bits(0x0019).check(true).is_ok());
+    assert!(FieldAccess::from_bits(0x0009).check(true).is_err());
+    assert!(MethodAccess::from_bits(0x0401).check(false, 52).is_ok());
+    assert!(MethodAccess::from_bits(0x0409).check(false, 52).is_err());
+    assert!(MethodAccess::from_bits(0x0C01).check(false, 52).is_err());
+    assert!(MethodAccess::from_bits(0x0C01).check(false, 61).is_ok());
+    assert!(MethodAccess::from_bits(0x0009).check(true, 52).is_ok());
+    assert!(MethodAccess::from_bits(0x0009).check(true, 51).is_err());
+    assert!(MethodAccess::from_bits(0x0011).check(true, 52).is_err());
+    assert!(InnerClassAccess::from_bits(0x000C).check().is_ok());
+    assert!(InnerClassAccess::from_bits(0x0006).check().is_err());
+
+    for name in &["Hello.class", "Shape.class", "Shape$Circle.class", "module-info.class"] {
+        let class = ClassReader::new_from_path(&format!("tests/data/{}", name)).unwrap();
+        class.check_access().unwrap();
+    }
+    let class = ClassReader::new_from_path("tests/data/Hello.class").unwrap();
+    assert_eq!("public", class.access().to_string());
+    let count = class.find_field("COUNT").unwrap();
+    assert_eq!("private static final", count.access().to_string());
+    let module = ClassReader::new_from_path("tests/data/module-info.class").unwrap().module().unwrap().unwrap();
+    assert!(module.requires.iter().any(|requires| requires.access().contains(RequiresAccess::MANDATED)));
+}
+
+#[test]
+fn test_disassemble() {
+    // Expected output is from javap -c -v -p without the Classfile, Last
+    // modified and checksum lines.
+    for name in &["Hello", "Constants", "Shape$Circle", "module-info"] {
+        let class = ClassReader::new_from_path(&format!("tests/data/{}.class", name)).unwrap();
+        let mut expected = String::new();
+        File::open(format!("tests/data/{}.javap", name)).unwrap().read_to_string(&mut expected).unwrap();
+        assert_eq!(expected, disassemble(&class).unwrap(), "{}", name);
+    }
+
+    // Constants that refer to themselves are errors rather than endless
+    // recursion.
+    let class = ClassReader::new_from_path("tests/data/Hello.class").unwrap();
+    for &is_string in &[false, true] {
+        let mut entries = class.constant_pool.clone().into_entries();
+        let index = entries.iter().position(|info| match *info {
+            ConstantPoolInfo::Methodref(_, _) => !is_string,
+            ConstantPoolInfo::String(_) => is_string,
+            _ => false
+        }).unwrap();
+        let own_index = index as u16 + 1;
+        entries[index] = match entries[index] {
+            ConstantPoolInfo::Methodref(_, name_and_type_index) => ConstantPoolInfo::Methodref(own_index, name_and_type_index),
+            _ => ConstantPoolInfo::String(own_index)
+        };
+        let mut cyclic = class.clone();
+        cyclic.constant_pool = ConstantPool::new(entries);
+        match disassemble(&cyclic) {
+            Result::Err(ParseError::Format(_)) => {},
+            other => panic!("{:?}", other)
+        }
+    }
+}
+
+#[test]
+fn test_assemble_disassembly() {
+    for name in &["Hello", "Constants", "Shape$Circle", "module-info"] {
+        let class = ClassReader::new_from_path(&format!("tests/data/{}.class", name)).unwrap();
+        let text = disassemble(&class).unwrap();
+        let assembled = assemble(&text).unwrap();
+        assert_eq!(text, disassemble(&assembled).unwrap(), "{}", name);
+        // javap drops the trailing space of the ", " in Hello.
+        if *name != "Hello" {
+            assert_eq!(class, assembled, "{}", name);
+        }
+    }
+}
+
+#[test]
+fn test_assemble() {
+    let source = "
+public class Count
+{
+  public static void main(java.lang.String[]);
+    descriptor: ([Ljava/lang/String;)V
+    Code:
+      stack=3, locals=2
+             iconst_0
+             istore_1
+      Loop:  iload_1
+             aload_0
+             arraylength
+             if_icmpge     Done
+      Try:   getstatic     Field java/lang/System.out:Ljava/io/PrintStream;
+             aload_0
+             iload_1
+             aaload
+             invokestatic  Method java/lang/Integer.parseInt:(Ljava/lang/String;)I
+             invokevirtual Method java/io/PrintStream.println:(I)V
+      End:   goto          Next
+      Catch: pop
+      Next:  iinc          1, 1
+             goto          Loop
+      Done:  return
+      Exception table:
+         from    to  target type
+          Try   End   Catch Class java/lang/NumberFormatException
+";
+    let class = assemble(source).unwrap();
+    assert_eq!(52, class.major_version);
+    assert_eq!("Count", class.name().unwrap());
+    assert_eq!("java/lang/Object", class.super_name().unwrap().unwrap());
+
+    let mut bytes = Vec::new();
+    ClassWriter::write_to_writer(&class, &mut bytes).unwrap();
+    let class = ClassReader::new_from_reader(&mut &bytes[..]).unwrap();
+    let main = class.find_method("main", "([Ljava/lang/String;)V").unwrap();
+    assert_eq!(0x0009, main.access_flags);
+    match main.attributes[0] {
+        Attribute::Code { max_stack, max_locals, ref code, ref exception_table, .. } => {
+            assert_eq!((3, 2), (max_stack, max_locals));
+            assert_eq!((5, Instruction::if_icmpge(25)), code[5]);
+            assert_eq!((27, Instruction::goto(-25)), code[15]);
+            assert_eq!(1, exception_table.len());
+            assert_eq!((8, 20, 23), (exception_table[0].start_pc, exception_table[0].end_pc, exception_table[0].handler_pc));
+            let pool = &class.constant_pool;
+            assert_eq!("java/lang/NumberFormatException", pool.class_name(exception_table[0].catch_type).unwrap());
+            match code[11].1 {
+                Instruction::invokevirtual(index) => assert_eq!("println", pool.member_ref(index).unwrap().name),
+                ref other => panic!("{:?}", other)
+            }
+        },
+        ref other => panic!("{:?}", other)
+    }
+
+    let error = assemble("public class A\n{\n  void f();\n    descriptor: ()V\n    Code:\n      stack=0, locals=1\n        goto Nowhere\n}\n");
+    match error {
+        Result::Err(ParseError::Format(message)) => assert_eq!("line 7: unknown label Nowhere", message),
+        other => panic!("{:?}", other)
+    }
+}
+
+#[test]
+fn test_control_flow_graph() {
+    let class = ClassReader::new_from_path("tests/data/Hello.class").unwrap();
+    let code = |name: &str| match class.methods.iter().find(|method| method.name(&class.constant_pool).unwrap() == name).unwrap().attributes[0] {
+        Attribute::Code { ref code, ref exception_table, .. } => ControlFlowGraph::new(code, exception_table).unwrap(),
+        ref other => panic!("{:?}", other)
+    };
+
+    let greet = code("greet");
+    assert_eq!(vec![0, 11, 17, 47], greet.blocks.iter().map(|block| block.start_pc).collect::<Vec<_>>());
+    assert_eq!(vec![Edge { from: 1, to: 2, kind: EdgeKind::FallThrough }, Edge { from: 1, to: 3, kind: EdgeKind::Branch }],
+               greet.blocks[1].successors);
+    assert_eq!(vec![Edge { from: 0, to: 1, kind: EdgeKind::FallThrough }, Edge { from: 2, to: 1, kind: EdgeKind::Jump }],
+               greet.blocks[1].predecessors);
+    assert_eq!(Option::Some(2), greet.block_at(40));
+    assert_eq!(vec![0, 1, 3, 2], greet.reverse_postorder());
+
+    let main = code("main");
+    assert_eq!(vec![0, 28, 52, 64, 76, 84], main.blocks.iter().map(|block| block.start_pc).collect::<Vec<_>>());
+    assert_eq!(vec![(1, EdgeKind::Switch), (2, EdgeKind::Switch), (3, EdgeKind::Switch), (4, EdgeKind::Switch)],
+               main.blocks[0].successors.iter().map(|edge| (edge.to, edge.kind)).collect::<Vec<_>>());
+    assert_eq!(4, main.blocks[5].predecessors.len());
+
+    let class = assemble("
+class Finally
+{
+  static void f(boolean);
+    descriptor: (Z)V
+    Code:
+      stack=1, locals=2
+      Try:     jsr           Finally
+               iload_0
+               ifeq          Done
+               jsr           Finally
+      Done:    return
+      Catch:   astore_1
+               jsr           Finally
+               aload_1
+               athrow
+      Finally: astore_1
+               ret           1
+      Exception table:
+         from    to  target type
+          Try  Done  Catch   any
+}
+").unwrap();
+    let graph = match class.methods[0].attributes[0] {
+        Attribute::Code { ref code, ref exception_table, .. } => ControlFlowGraph::new(code, exception_table).unwrap(),
+        ref other => panic!("{:?}", other)
+    };
+    assert_eq!(7, graph.blocks.len());
+    let successors = |block: usize| graph.blocks[block].successors.iter().map(|edge| (edge.to, edge.kind)).collect::<Vec<_>>();
+    assert_eq!(vec![(6, EdgeKind::Subroutine), (4, EdgeKind::Exception(0))], successors(0));
+    assert_eq!(vec![(2, EdgeKind::FallThrough), (3, EdgeKind::Branch), (4, EdgeKind::Exception(0))], successors(1));
+    assert_eq!(vec![(6, EdgeKind::Subroutine)], successors(4));
+    assert!(successors(5).is_empty());
+    assert_eq!(vec![(1, EdgeKind::SubroutineReturn), (3, EdgeKind::SubroutineReturn), (5, EdgeKind::SubroutineReturn)],
+               successors(6));
+}
+
+#[test]
+fn test_dominators_and_loops() {
+    let class = ClassReader::new_from_path("tests/data/Hello.class").unwrap();
+    let greet = match class.find_method("greet", "(Ljava/lang/String;I)Ljava/lang/String;").unwrap().attributes[0] {
+        Attribute::Code { ref code, ref exception_table, .. } => ControlFlowGraph::new(code, exception_table).unwrap(),
+        ref other => panic!("{:?}", other)
+    };
+    let dominators = DominatorTree::new(&greet);
+    assert_eq!(&[0], dominators.roots());
+    assert_eq!(vec![Option::None, Option::Some(0), Option::Some(1), Option::Some(1)],
+               (0..4).map(|block| dominators.immediate_dominator(block)).collect::<Vec<_>>());
+    assert_eq!(&[2, 3], dominators.children(1));
+    assert!(dominators.dominates(0, 3) && dominators.dominates(2, 2) && !dominators.dominates(2, 3));
+    assert_eq!(vec![vec![], vec![1], vec![1], vec![]], dominators.frontiers(&greet));
+
+    let post_dominators = DominatorTree::post_dominators(&greet);
+    assert!(post_dominators.is_post_dominator_tree());
+    assert_eq!(&[3], post_dominators.roots());
+    assert_eq!(vec![Option::Some(1), Option::Some(3), Option::Some(1), Option::None],
+               (0..4).map(|block| post_dominators.immediate_dominator(block)).collect::<Vec<_>>());
+    assert_eq!(vec![1], post_dominators.frontiers(&greet)[2]);
+
+    let loops = LoopForest::new(&greet, &dominators);
+    assert_eq!(1, loops.loops.len());
+    assert_eq!((1, vec![2], vec![1, 2]), (loops.loops[0].header, loops.loops[0].latches.clone(), loops.loops[0].blocks.clone()));
+    assert_eq!((Option::None, 1), (loops.loops[0].parent, loops.loops[0].depth));
+    assert_eq!(vec![0, 1, 1, 0], (0..4).map(|block| loops.depth(block)).collect::<Vec<_>>());
+    assert_eq!(Option::Some(0), loops.innermost_loop(2));
+    assert!(loops.is_reducible());
+
+    let class = assemble("
+class Irreducible
+{
+  static void f(int);
+    descriptor: (I)V
+    Code:
+      stack=1, locals=1
+               iload_0
+               ifeq          B
+      A:       iinc          0, -1
+               iload_0
+               ifeq          Done
+      B:       iinc          0, 2
+               goto          A
+      Done:    return
+}
+").unwrap();
+    let graph = match class.methods[0].attributes[0] {
+        Attribute::Code { ref code, ref exception_table, .. } => ControlFlowGraph::new(code, exception_table).unwrap(),
+        ref other => panic!("{:?}", other)
+    };
+    let dominators = DominatorTree::new(&graph);
+    assert_eq!(vec![Option::None, Option::Some(0), Option::Some(0), Option::Some(1)],
+               (0..4).map(|block| dominators.immediate_dominator(block)).collect::<Vec<_>>());
+    let loops = LoopForest::new(&graph, &dominators);
+    assert!(loops.loops.is_empty());
+    assert!(!loops.is_reducible());
+    assert_eq!(vec![IrreducibleRegion { entries: vec![1, 2], blocks: vec![1, 2] }], loops.irreducible_regions);
+}
+
+#[test]
+fn test_compute_frames() {
+    let hierarchy = |a: &str, b: &str| -> ParseResult<String> {
+        match (a, b) {
+            ("java/lang/Integer", "java/lang/Long") | ("java/lang/Long", "java/lang/Integer") => Result::Ok("java/lang/Number".to_string()),
+            _ => Result::Ok("java/lang/Object".to_string())
+        }
+    };
+
+    let original = ClassReader::new_from_path("tests/data/Hello.class").unwrap();
+    let mut class = original.clone();
+    class.compute_frames(&hierarchy).unwrap();
+    assert_eq!(original.constant_pool, class.constant_pool);
+    assert_eq!(original.methods[0], class.methods[0]);
+    assert_eq!(original.methods[2], class.methods[2]);
+    // javac drops the loop variable once the loop is done, but it is still
+    // there.
+    match class.methods[1].attributes[0] {
+        Attribute::Code { ref attributes, .. } => {
+            assert_eq!(Attribute::StackMapTable(vec![
+                StackMapFrame::AppendFrame { offset_delta: 11, locals: vec![VerificationType::Object { index: 13 }, VerificationType::Integer] },
+                StackMapFrame::SameFrame { offset_delta: 35 }
+            ]), attributes[2]);
+        },
+        ref other => panic!("{:?}", other)
+    }
+
+    let mut class = assemble("
+class Frames
+{
+  static void f(int);
+    descriptor: (I)V
+    Code:
+      stack=1, locals=1
+               iload_0
+               ifeq          Skip
+               lconst_1
+               lstore_1
+               iconst_2
+               istore_3
+      Loop:    iinc          3, -1
+               iload_3
+               ifgt          Loop
+      Skip:    iload_0
+               ifeq          Zero
+               iload_0
+               invokestatic  Method java/lang/Integer.valueOf:(I)Ljava/lang/Integer;
+               goto          Done
+      Zero:    lconst_0
+               invokestatic  Method java/lang/Long.valueOf:(J)Ljava/lang/Long;
+      Done:    pop
+               return
+}
+").unwrap();
+    class.compute_frames(&hierarchy).unwrap();
+    let mut bytes = Vec::new();
+    ClassWriter::write_to_writer(&class, &mut bytes).unwrap();
+    let class = ClassReader::new_from_reader(&mut &bytes[..]).unwrap();
+    match class.methods[0].attributes[0] {
+        Attribute::Code { max_stack, max_locals, ref attributes, .. } => {
+            assert_eq!((2, 4), (max_stack, max_locals));
+            let number = class.constant_pool.iter().position(|info| *info == ConstantPoolInfo::Class(class.constant_pool.find_utf8("java/lang/Number").unwrap())).unwrap() as u16 + 1;
+            assert_eq!(vec![Attribute::StackMapTable(vec![
+                StackMapFrame::AppendFrame { offset_delta: 8, locals: vec![VerificationType::Long, VerificationType::Integer] },
+                StackMapFrame::ChopFrame { offset_delta: 6, chopped: 2 },
+                StackMapFrame::SameFrame { offset_delta: 10 },
+                StackMapFrame::SameLocals1StackItemFrame { offset_delta: 3, stack: VerificationType::Object { index: number } }
+            ])], *attributes);
+        },
+        ref other => panic!("{:?}", other)
+    }
+
+    let method = |code: &str| {
+        let class = assemble(&format!("class A\n{{\n  static void f();\n    descriptor: ()V\n    Code:\n      stack=1, locals=1\n{}\n}}\n", code)).unwrap();
+        let mut pool = class.constant_pool.clone();
+        class.methods[0].compute_frames("A", &mut pool, &hierarchy)
+    };
+    match method("        return\n        return") {
+        Result::Err(ParseError::Decode(message)) => assert_eq!("unreachable code at 1 needs a frame", message),
+        other => panic!("{:?}", other)
+    }
+    match method("        jsr      Sub\n        return\n        Sub: astore_0\n        ret      0") {
+        Result::Err(ParseError::Decode(_)) => {},
+        other => panic!("{:?}", other)
+    }
+}
+
+#[test]
+fn test_verify() {
+    let hierarchy = |_: &str, _: &str| -> ParseResult<String> { Result::Ok("java/lang/Object".to_string()) };
+
+    let class = ClassReader::new_from_path("tests/data/Hello.class").unwrap();
+    assert_eq!(Result::Ok(()), class.verify(&hierarchy));
+
+    let source = "
+class Finally
+  major version: 49
+{
+  static void f(boolean);
+    descriptor: (Z)V
+    Code:
+      stack=1, locals=3
+      Try:     jsr           Finally
+               iload_0
+               ifeq          Done
+               jsr           Finally
+      Done:    return
+      Catch:   astore_1
+               jsr           Finally
+               aload_1
+               athrow
+      Finally: astore_2
+               ret           2
+      Exception table:
+         from    to  target type
+          Try  Done  Catch   any
+}
+";
+    let mut class = assemble(source).unwrap();
+    assert_eq!(Result::Ok(()), class.verify(&hierarchy));
+    // A subroutine that overwrites the exception the handler rethrows.
+    let clobbered = assemble(&source.replace("astore_2", "astore_1").replace("ret           2", "ret           1")).unwrap();
+    let errors = clobbered.verify(&hierarchy).unwrap_err();
+    assert_eq!((15, "expected a reference in local 1 but found returnAddress(17)"), (errors[0].pc, errors[0].message.as_str()));
+    class.major_version = 51;
+    match class.verify(&hierarchy) {
+        Result::Err(errors) => {
+            assert_eq!(1, errors.len());
+            assert_eq!(("f(Z)V", 0), (errors[0].method.as_str(), errors[0].pc));
+        },
+        other => panic!("{:?}", other)
+    }
+
+    let mut class = assemble("
+class Locals
+{
+  static void f(int);
+    descriptor: (I)V
+    Code:
+      stack=1, locals=2
+               iconst_0
+               istore_1
+               iload_0
+               ifeq          Skip
+               iconst_1
+               istore_1
+      Skip:    return
+}
+").unwrap();
+    assert_eq!(1, class.verify(&hierarchy).unwrap_err().len());
+    class.compute_frames(&hierarchy).unwrap();
+    assert_eq!(Result::Ok(()), class.verify(&hierarchy));
+    if let Attribute::Code { ref mut code, .. } = class.methods[0].attributes[0] {
+        code[4].1 = Instruction::fconst_1;
+        code[5].1 = Instruction::fstore_1;
+    }
+    let errors = class.verify(&hierarchy).unwrap_err();
+    assert_eq!(VerifyError {
+        method: "f(I)V".to_string(),
+        pc: 8,
+        message: "frame does not match the stack map frame".to_string(),
+        expected: Option::Some(Frame { locals: vec![FrameType::Integer, FrameType::Integer], stack: Vec::new() }),
+        actual: Option::Some(Frame { locals: vec![FrameType::Integer, FrameType::Float], stack: Vec::new() })
+    }, errors[0]);
+    assert_eq!("f(I)V at 8: frame does not match the stack map frame\n  expected locals: [int, int], stack: []\n  actual locals: [int, float], stack: []",
+               errors[0].to_string());
+    if let Attribute::Code { ref mut code, .. } = class.methods[0].attributes[0] {
+        code[5].1 = Instruction::istore_1;
+    }
+    let errors = class.verify(&hierarchy).unwrap_err();
+    assert_eq!((7, "expected int on the stack but found float"), (errors[0].pc, errors[0].message.as_str()));
+}
+
+#[test]
+fn test_data_flow() {
+    let class = assemble("
+class Flow
+{
+  static int f(int, boolean);
+    descriptor: (IZ)I
+    Code:
+      stack=2, locals=4
+               iconst_0
+               istore_2
+      Try:     iload_1
+               ifeq          Skip
+               iload_0
+               istore_2
+               iload_0
+               iconst_0
+               idiv
+               istore_3
+      Skip:    iload_2
+               ireturn
+      Catch:   pop
+               iload_2
+               ireturn
+      Exception table:
+         from    to  target type
+          Try  Skip  Catch   any
+}
+").unwrap();
+    let (code, exception_table) = match class.methods[0].attributes[0] {
+        Attribute::Code { ref code, ref exception_table, .. } => (code, exception_table),
+        ref other => panic!("{:?}", other)
+    };
+
+    let live = DataFlow::new(&LiveLocals, code, exception_table).unwrap();
+    assert_eq!(vec![0, 1], live.before[0].iter().cloned().collect::<Vec<_>>());
+    // The handler reads local 2, so it stays live while the try block
+    // overwrites it.
+    assert_eq!(vec![0, 2], live.before[5].iter().cloned().collect::<Vec<_>>());
+    assert_eq!(vec![2], live.after[9].iter().cloned().collect::<Vec<_>>());
+    assert!(live.before[14].is_empty());
+
+    let reaching = DataFlow::new(&ReachingDefinitions { parameter_slots: 2 }, code, exception_table).unwrap();
+    let definitions = |definitions: &[(u16, Option<u32>)]| {
+        definitions.iter().map(|&(local, pc)| Definition { local: local, pc: pc }).collect::<BTreeSet<_>>()
+    };
+    assert_eq!(definitions(&[(0, Option::None), (1, Option::None), (2, Option::Some(1)), (2, Option::Some(7)), (3, Option::Some(11))]),
+               reaching.before[10]);
+    // An instruction that throws has not stored anything yet.
+    assert_eq!(definitions(&[(0, Option::None), (1, Option::None), (2, Option::Some(1)), (2, Option::Some(7))]),
+               reaching.before[12]);
+    // Overwriting either half of a long destroys the whole value.
+    let analysis = ReachingDefinitions { parameter_slots: 2 };
+    let long = analysis.transfer(0, &Instruction::lstore(2), &analysis.boundary());
+    assert_eq!(definitions(&[(0, Option::None), (1, Option::None), (2, Option::Some(0)), (3, Option::Some(0))]), long);
+    assert_eq!(definitions(&[(0, Option::None), (1, Option::None), (3, Option::Some(1))]),
+               analysis.transfer(1, &Instruction::istore(3), &long));
+    assert_eq!(definitions(&[(0, Option::None), (1, Option::Some(1)), (2, Option::Some(1))]),
+               analysis.transfer(1, &Instruction::dstore(1), &long));
+    // The halves of a wide parameter are not known to belong together.
+    let analysis = ReachingDefinitions { parameter_slots: 3 };
+    assert_eq!(definitions(&[(0, Option::None), (1, Option::Some(0)), (2, Option::None)]),
+               analysis.transfer(0, &Instruction::istore(1), &analysis.boundary()));
+
+    assert_eq!(Option::Some(LocalAccess { index: 5, size: 2, reads: false, writes: true }), Instruction::dstore(5).local_access());
+    assert_eq!(Option::Some(LocalAccess { index: 1, size: 1, reads: true, writes: true }), Instruction::iinc(1, -1).local_access());
+    assert_eq!(Option::None, Instruction::iadd.local_access());
+}
+
+#[test]
+fn test_instruction_metadata() {
+    for (bytes, instruction) in opcode_corpus() {
+        assert_eq!(bytes[0], instruction.opcode() as u8);
+        assert_eq!(Option::Some(instruction.opcode()), Opcode::from_byte(bytes[0]));
+        assert_eq!(bytes.len() as u32, instruction.length(0));
+    }
+    assert_eq!(Option::None, Opcode::from_byte(0xca));
+    assert_eq!(Option::Some(3), Opcode::goto.length());
+    assert_eq!(Option::None, Opcode::tableswitch.length());
+    assert_eq!("return", Instruction::return_.mnemonic());
+    assert_eq!("iinc_w", Instruction::iinc_w(300, 1).mnemonic());
+    assert_eq!("wide", Instruction::iinc_w(300, 1).opcode().to_string());
+
+    let switch = Instruction::tableswitch(20, 0, vec![8, 12, 8].into_boxed_slice());
+    assert_eq!(28, switch.length(0));
+    assert_eq!(25, switch.length(3));
+    assert_eq!(vec![18, 22, 18, 30], switch.branch_targets(10).unwrap());
+    let lookup = Instruction::lookupswitch(4, vec![(1, 4), (7, -2)].into_boxed_slice());
+    assert_eq!(vec![6, 0, 6], lookup.branch_targets(2).unwrap());
+    assert_eq!(vec![7], Instruction::jsr_w(5).branch_targets(2).unwrap());
+    assert!(Instruction::ifeq(-3).branch_targets(2).is_err());
+    assert!(Instruction::iadd.branch_targets(2).unwrap().is_empty());
+
+    let flow = |instruction: Instruction| (instruction.is_branch(), instruction.falls_through(), instruction.ends_block());
+    assert_eq!((true, true, true), flow(Instruction::ifnull(3)));
+    assert_eq!((true, false, true), flow(Instruction::goto(3)));
+    assert_eq!((true, false, true), flow(Instruction::jsr(3)));
+    assert_eq!((false, false, true), flow(Instruction::athrow));
+    assert_eq!((false, false, true), flow(Instruction::ret(1)));
+    assert_eq!((false, true, false), flow(Instruction::invokestatic(1)));
+
+    let class = assemble("
+class Effects
+{
+  long value;
+    descriptor: J
+
+  double f(Effects);
+    descriptor: (LEffects;)D
+    Code:
+      stack=6, locals=2
+         aload_1
+         aload_0
+         getfield      Field Effects.value:J
+         putfield      Field Effects.value:J
+         aload_0
+         iconst_1
+         lconst_1
+         invokevirtual Method Effects.g:(IJ)D
+         invokestatic  Method java/lang/Math.abs:(D)D
+         dreturn
+}
+").unwrap();
+    let code = match class.methods[0].attributes[0] {
+        Attribute::Code { ref code, .. } => code,
+        ref other => panic!("{:?}", other)
+    };
+    let effects: Vec<(u16, u16)> = code.iter().map(|&(_, ref instruction)| {
+        let effect = instruction.stack_effect(&class.constant_pool).unwrap();
+        (effect.pops, effect.pushes)
+    }).collect();
+    assert_eq!(vec![(0, 1), (0, 1), (1, 2), (3, 0), (0, 1), (0, 1), (0, 2), (4, 2), (2, 2), (2, 0)], effects);
+    assert_eq!(StackEffect { pops: 3, pushes: 5 }, Instruction::dup2_x1.stack_effect(&class.constant_pool).unwrap());
+}
+
+#[test]
+fn test_code_body() {
+    let class = assemble("
+class Body
+{
+  static int f(int);
+    descriptor: (I)I
+    Code:
+      stack=1, locals=1
+      Loop:    iload_0
+               ifeq          Done
+               iinc          0, -1
+               goto          Loop
+      Done:    iload_0
+               ireturn
+      Exception table:
+         from    to  target type
+         Loop  Done    Done   any
+      LineNumberTable:
+        line 3: Loop
+        line 4: Done
+      LocalVariableTable:
+        Start  Length  Slot  Name   Signature
+         Loop    Done     0     n   I
+}
+").unwrap();
+    let attribute = &class.methods[0].attributes[0];
+    let mut body = CodeBody::from_attribute(attribute).unwrap();
+    assert_eq!(attribute, &body.to_attribute().unwrap());
+    let (start, end) = (body.line_numbers[0].start, body.line_numbers[1].start);
+    assert_eq!(vec![CodeElement::Label(start), CodeElement::Instruction(Instruction::iload_0), CodeElement::Branch(Opcode::ifeq, end)],
+               body.elements[..3].to_vec());
+    assert_eq!((start, end, end), (body.exception_table[0].start, body.exception_table[0].end, body.exception_table[0].handler));
+    assert_eq!(Option::Some(5), body.position(end));
+
+    // The StackMapTable is kept while nothing moves, and type annotations move
+    // along with their instructions.
+    let type_annotation = |target_info: TargetInfo| TypeAnnotation {
+        target_type: TargetType::Cast,
+        target_info: target_info,
+        type_path: TypePath { path: vec![] },
+        type_index: 1,
+        element_value_pairs: vec![]
+    };
+    let mut annotated = attribute.clone();
+    if let Attribute::Code { ref mut attributes, .. } = annotated {
+        attributes.push(Attribute::StackMapTable(vec![StackMapFrame::SameFrame { offset_delta: 10 }]));
+        attributes.push(Attribute::RuntimeVisibleTypeAnnotations(vec![
+            type_annotation(TargetInfo::Offset(4)),
+            type_annotation(TargetInfo::Localvar(vec![LocalVariableTarget { start_pc: 0, length: 10, index: 0 }]))
+        ]));
+    }
+    let mut edited = CodeBody::from_attribute(&annotated).unwrap();
+    assert_eq!(annotated, edited.to_attribute().unwrap());
+    edited.elements.insert(0, CodeElement::Instruction(Instruction::nop));
+    let iinc = edited.elements.iter().position(|element| *element == CodeElement::Instruction(Instruction::iinc(0, -1))).unwrap();
+    match edited.to_attribute().unwrap() {
+        Attribute::Code { ref attributes, .. } => assert_eq!(Attribute::RuntimeVisibleTypeAnnotations(vec![
+            type_annotation(TargetInfo::Offset(5)),
+            type_annotation(TargetInfo::Localvar(vec![LocalVariableTarget { start_pc: 1, length: 10, index: 0 }]))
+        ]), attributes[2]),
+        ref other => panic!("{:?}", other)
+    }
+    // Removing the cast and its label drops the annotation on it.
+    edited.elements.drain(iinc - 1..iinc + 1);
+    match edited.to_attribute().unwrap() {
+        Attribute::Code { ref attributes, .. } => assert_eq!(Attribute::RuntimeVisibleTypeAnnotations(vec![
+            type_annotation(TargetInfo::Localvar(vec![LocalVariableTarget { start_pc: 1, length: 7, index: 0 }]))
+        ]), attributes[2]),
+        ref other => panic!("{:?}", other)
+    }
+
+    // Both branches now span more than 32767 bytes.
+    let nops = vec![CodeElement::Instruction(Instruction::nop); 40000];
+    body.elements.splice(4..4, nops);
+    let (code, exception_table, attributes) = match body.to_attribute().unwrap() {
+        Attribute::Code { code, exception_table, attributes, .. } => (code, exception_table, attributes),
+        other => panic!("{:?}", other)
+    };
+    assert_eq!(vec![(0, Instruction::iload_0), (1, Instruction::ifne(8)), (4, Instruction::goto_w(40013)), (9, Instruction::iinc(0, -1))],
+               code[..4].to_vec());
+    assert_eq!(vec![(40012, Instruction::goto_w(-40012)), (40017, Instruction::iload_0), (40018, Instruction::ireturn)],
+               code[code.len() - 3..].to_vec());
+    assert_eq!(vec![Exception { start_pc: 0, end_pc: 40017, handler_pc: 40017, catch_type: 0 }], exception_table);
+    assert_eq!(Attribute::LineNumberTable(vec![LineNumber { start_pc: 0, line_number: 3 }, LineNumber { start_pc: 40017, line_number: 4 }]),
+               attributes[0]);
+    match attributes[1] {
+        Attribute::LocalVariableTable(ref variables) => assert_eq!((0, 40017), (variables[0].start_pc, variables[0].length)),
+        ref other => panic!("{:?}", other)
+    }
+
+    let mut body = CodeBody::new(0, 0);
+    let label = body.new_label();
+    body.elements.push(CodeElement::Branch(Opcode::goto, label));
+    match body.to_attribute() {
+        Result::Err(ParseError::Encode(message)) => assert_eq!(format!("{:?} is not placed", label), message),
+        other => panic!("{:?}", other)
+    }
+    body.elements = vec![CodeElement::Instruction(Instruction::goto(0))];
+    match body.to_attribute() {
+        Result::Err(ParseError::Encode(message)) => assert_eq!("goto at 0 must be a branch to a label", message),
+        other => panic!("{:?}", other)
+    }
+}
+
+#[test]
+fn test_stack_map_frames() {
+    assert_eq!((63, 63), (StackMapFrame::SameFrame { offset_delta: 63 }.frame_type(), StackMapFrame::SameFrame { offset_delta: 63 }.offset_delta()));
+    let frame = StackMapFrame::SameLocals1StackItemFrame { offset_delta: 5, stack: VerificationType::Integer };
+    assert_eq!((69, 5), (frame.frame_type(), frame.offset_delta()));
+    let frame = StackMapFrame::ChopFrame { offset_delta: 300, chopped: 2 };
+    assert_eq!((249, 300), (frame.frame_type(), frame.offset_delta()));
+    let frame = StackMapFrame::AppendFrame { offset_delta: 7, locals: vec![VerificationType::Long] };
+    assert_eq!((252, 7), (frame.frame_type(), frame.offset_delta()));
+
+    let class = ClassReader::new_from_path("tests/data/Hello.class").unwrap();
+    let string = FrameType::Reference("java/lang/String".to_string());
+    let builder = FrameType::Reference("java/lang/StringBuilder".to_string());
+    let locals = vec![FrameType::Reference("Hello".to_string()), string, FrameType::Integer, builder];
+    let mut appended = locals.clone();
+    appended.push(FrameType::Integer);
+    assert_eq!(vec![
+        (11, Frame { locals: appended, stack: vec![] }),
+        (47, Frame { locals: locals, stack: vec![] })
+    ], class.methods[1].stack_map_frames("Hello", &class.constant_pool).unwrap());
+
+    let hierarchy = |_: &str, _: &str| -> ParseResult<String> { Result::Ok("java/lang/Object".to_string()) };
+    let mut class = assemble("
+class Frames
+{
+  static void f(long);
+    descriptor: (J)V
+    Code:
+      stack=4, locals=2
+               new           class java/lang/Integer
+               dup
+               lload_0
+               lconst_0
+               lcmp
+               ifeq          Zero
+               iconst_1
+               goto          Done
+      Zero:    iconst_0
+      Done:    invokespecial Method java/lang/Integer.\"<init>\":(I)V
+               pop
+               return
+}
+").unwrap();
+    class.compute_frames(&hierarchy).unwrap();
+    let uninitialized = FrameType::Uninitialized(0);
+    assert_eq!(vec![
+        (14, Frame { locals: vec![FrameType::Long, FrameType::Top], stack: vec![uninitialized.clone(), uninitialized.clone()] }),
+        (15, Frame { locals: vec![FrameType::Long, FrameType::Top], stack: vec![uninitialized.clone(), uninitialized, FrameType::Integer] })
+    ], class.methods[0].stack_map_frames("Frames", &class.constant_pool).unwrap());
+
+    if let Attribute::Code { ref mut attributes, .. } = class.methods[0].attributes[0] {
+        attributes[0] = Attribute::StackMapTable(vec![
+            StackMapFrame::SameLocals1StackItemFrame { offset_delta: 14, stack: VerificationType::UninitializedVariable { offset: 3 } }
+        ]);
+    }
+    match class.methods[0].stack_map_frames("Frames", &class.constant_pool) {
+        Result::Err(ParseError::Decode(message)) => assert_eq!("uninitialized type in stack map frame refers to 3 which is not a new", message),
+        other => panic!("{:?}", other)
+    }
+}
+
+#[test]
+fn test_debug_info() {
+    let class = ClassReader::new_from_path("tests/data/Hello.class").unwrap();
+    let greet = &class.methods[1];
+    let debug_info = DebugInfo::from_attribute(&greet.attributes[0], &class.constant_pool).unwrap();
+    assert_eq!((Option::Some(12), Option::Some(13), Option::Some(13), Option::Some(16)),
+               (debug_info.line_for_pc(0), debug_info.line_for_pc(14), debug_info.line_for_pc(44), debug_info.line_for_pc(51)));
+    assert_eq!(Option::None, debug_info.line_for_pc(52));
+    assert_eq!(vec![8, 41], debug_info.pcs_for_line(13));
+    assert!(debug_info.pcs_for_line(15).is_empty());
+    assert_eq!(Option::None, debug_info.variable_at(4, 10));
+    let i = debug_info.variable_at(4, 11).unwrap();
+    assert_eq!(("i", 11, 47, "I", Option::None), (i.name.as_str(), i.start_pc, i.end_pc, i.descriptor.as_str(), i.signature.clone()));
+    assert_eq!(Option::None, debug_info.variable_at(4, 47));
+    assert_eq!(5, debug_info.variables().len());
+    assert_eq!(vec![Option::Some("name".to_string()), Option::Some("times".to_string())], greet.parameter_names(&class.constant_pool).unwrap());
+
+    let class = ClassReader::new_from_path("tests/data/Shape$Circle.class").unwrap();
+    let constructor = class.methods.iter().find(|method| method.name(&class.constant_pool).unwrap() == "<init>").unwrap();
+    assert_eq!(vec![Option::Some("radius".to_string()), Option::Some("tags".to_string())],
+               constructor.parameter_names(&class.constant_pool).unwrap());
+
+    let mut class = assemble("
+class Debug
+{
+  static int size(long, java.util.List<java.lang.String>);
+    descriptor: (JLjava/util/List;)I
+    Code:
+      stack=1, locals=3
+      Begin:   aload_2
+               invokeinterface InterfaceMethod java/util/List.size:()I, 1
+      End:     ireturn
+      LocalVariableTable:
+        Start  Length  Slot  Name   Signature
+         Begin    End     0 count   J
+         Begin    End     2 names   Ljava/util/List;
+      LocalVariableTypeTable:
+        Start  Length  Slot  Name   Signature
+         Begin    End     2 names   Ljava/util/List<Ljava/lang/String;>;
+}
+").unwrap();
+    let debug_info = DebugInfo::from_attribute(&class.methods[0].attributes[0], &class.constant_pool).unwrap();
+    assert_eq!(Option::None, debug_info.line_for_pc(0));
+    let names = debug_info.variable_at(2, 5).unwrap();
+    assert_eq!(("Ljava/util/List;", Option::Some("Ljava/util/List<Ljava/lang/String;>;")),
+               (names.descriptor.as_str(), names.signature.as_ref().map(|signature| signature.as_str())));
+    assert_eq!(Option::None, debug_info.variable_at(0, 0).unwrap().signature);
+    match names.type_signature().unwrap() {
+        Option::Some(ReferenceTypeSignature::Class(ref class)) => assert_eq!(("List", 1), (class.class.name.as_str(), class.class.type_arguments.len())),
+        other => panic!("{:?}", other)
+    }
+
+    // An unnamed entry of MethodParameters falls back to the variable table.
+    let names = class.constant_pool.find_utf8("names").unwrap();
+    class.methods[0].attributes.push(Attribute::MethodParameters(vec![
+        MethodParameter { name_index: 0, access_flags: 0 },
+        MethodParameter { name_index: names, access_flags: 0 }
+    ]));
+    assert_eq!(vec![Option::Some("count".to_string()), Option::Some("names".to_string())],
+               class.methods[0].parameter_names(&class.constant_pool).unwrap());
+    class.methods[0].attributes.remove(0);
+    assert_eq!(vec![Option::None, Option::Some("names".to_string())], class.methods[0].parameter_names(&class.constant_pool).unwrap());
+}